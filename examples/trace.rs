@@ -73,14 +73,14 @@ async fn run(
 
     let login_scope = "";
 
-    let dclient = client.authenticate(&[&login_scope]).await?;
+    let dclient = client.authenticate(&[login_scope]).await?;
     let manifest = dclient.get_manifest(&image, &version).await?;
 
     let layers_digests = manifest.layers_digests(None)?;
     println!("{} -> got {} layer(s)", &image, layers_digests.len(),);
 
     for layer_digest in &layers_digests {
-        let blob = dclient.get_blob(&image, &layer_digest).await?;
+        let blob = dclient.get_blob(&image, layer_digest).await?;
         println!("Layer {}, got {} bytes.\n", layer_digest, blob.len());
     }
 
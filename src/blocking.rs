@@ -0,0 +1,276 @@
+//! Synchronous facade over the asynchronous `v2::Client`.
+//!
+//! Requests are driven by a runtime owned by the client, so that callers do
+//! not need one. The methods of this client must not be called from within
+//! an asynchronous runtime.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! # extern crate dkregistry;
+//! # fn run() -> dkregistry::errors::Result<()> {
+//! use dkregistry::blocking::Client;
+//! use dkregistry::v2::Config;
+//!
+//! let dclient = Client::new(Config::default().registry("quay.io"))?;
+//! let tags = dclient.get_all_tags("coreos/etcd", None)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::errors::Result;
+use crate::mediatypes::MediaTypes;
+use crate::v2::manifest::{
+    ConfigBlob, ImageIndex, ImageSizes, Manifest, ManifestBytes, ManifestHead, Platform,
+    RawManifest,
+};
+use crate::v2::{self, Referrer, ReferrerFilter};
+use futures::stream::TryStreamExt;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::sync::Arc;
+use tokio::runtime::Runtime;
+
+/// A synchronous client to make outgoing API requests to a registry.
+///
+/// Clones share the same runtime, and bearer tokens.
+#[derive(Clone, Debug)]
+pub struct Client {
+    inner: v2::Client,
+    runtime: Arc<Runtime>,
+}
+
+impl Client {
+    /// Build a client from a `v2::Config`.
+    pub fn new(config: v2::Config) -> Result<Self> {
+        Self::from_async(config.build()?)
+    }
+
+    /// Wrap an asynchronous client.
+    pub fn from_async(client: v2::Client) -> Result<Self> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        Ok(Self {
+            inner: client,
+            runtime: Arc::new(runtime),
+        })
+    }
+
+    /// Get the wrapped asynchronous client.
+    pub fn as_async(&self) -> &v2::Client {
+        &self.inner
+    }
+
+    /// Check whether remote registry supports v2 API.
+    pub fn is_v2_supported(&self) -> Result<bool> {
+        self.runtime.block_on(self.inner.is_v2_supported())
+    }
+
+    /// Perform registry authentication, see `v2::Client::authenticate`.
+    pub fn authenticate(self, scopes: &[&str]) -> Result<Self> {
+        let inner = self.runtime.block_on(self.inner.authenticate(scopes))?;
+        Ok(Self {
+            inner,
+            runtime: self.runtime,
+        })
+    }
+
+    /// List repositories of the registry.
+    pub fn get_catalog(&self, paginate: Option<u32>) -> Result<Vec<String>> {
+        self.runtime
+            .block_on(self.inner.get_catalog(paginate).try_collect())
+    }
+
+    /// List existing tags for an image.
+    pub fn get_all_tags(&self, name: &str, paginate: Option<u32>) -> Result<Vec<String>> {
+        self.runtime
+            .block_on(self.inner.get_all_tags(name, paginate))
+    }
+
+    /// Fetch an image manifest.
+    pub fn get_manifest(&self, name: &str, reference: &str) -> Result<Manifest> {
+        self.runtime
+            .block_on(self.inner.get_manifest(name, reference))
+    }
+
+    /// Fetch an image manifest and return it with its digest.
+    pub fn get_manifest_and_ref(
+        &self,
+        name: &str,
+        reference: &str,
+    ) -> Result<(Manifest, Option<String>)> {
+        self.runtime
+            .block_on(self.inner.get_manifest_and_ref(name, reference))
+    }
+
+    /// Fetch an image manifest along with the bytes it was served as.
+    pub fn get_raw_manifest(&self, name: &str, reference: &str) -> Result<RawManifest> {
+        self.runtime
+            .block_on(self.inner.get_raw_manifest(name, reference))
+    }
+
+    /// Fetch the image configuration referenced by a manifest.
+    pub fn get_image_config(&self, name: &str, manifest: &Manifest) -> Result<ConfigBlob> {
+        self.runtime
+            .block_on(self.inner.get_image_config(name, manifest))
+    }
+
+    /// Fetch the labels of an image, selecting the image for `platform`
+    /// in image indexes.
+    pub fn get_image_labels(
+        &self,
+        name: &str,
+        reference: &str,
+        platform: &Platform,
+    ) -> Result<BTreeMap<String, String>> {
+        self.runtime
+            .block_on(self.inner.get_image_labels(name, reference, platform))
+    }
+
+    /// Get the sizes of an image, or of the images of an index, without
+    /// downloading their layers.
+    pub fn get_image_size(&self, name: &str, reference: &str) -> Result<ImageSizes> {
+        self.runtime
+            .block_on(self.inner.get_image_size(name, reference))
+    }
+
+    /// Fetch content digest for a particular tag.
+    pub fn get_manifestref(&self, name: &str, reference: &str) -> Result<Option<String>> {
+        self.runtime
+            .block_on(self.inner.get_manifestref(name, reference))
+    }
+
+    /// Probe an image manifest without fetching it.
+    pub fn head_manifest(&self, name: &str, reference: &str) -> Result<ManifestHead> {
+        self.runtime
+            .block_on(self.inner.head_manifest(name, reference))
+    }
+
+    /// Check if an image manifest exists.
+    pub fn has_manifest(
+        &self,
+        name: &str,
+        reference: &str,
+        mediatypes: Option<&[&str]>,
+    ) -> Result<Option<MediaTypes>> {
+        self.runtime
+            .block_on(self.inner.has_manifest(name, reference, mediatypes))
+    }
+
+    /// Fetch an image index (manifest list or OCI image index).
+    pub fn get_image_index(&self, name: &str, reference: &str) -> Result<ImageIndex> {
+        self.runtime
+            .block_on(self.inner.get_image_index(name, reference))
+    }
+
+    /// Fetch the image manifest for a platform, and return it with its digest.
+    pub fn get_platform_manifest(
+        &self,
+        name: &str,
+        reference: &str,
+        os: &str,
+        arch: &str,
+        variant: Option<&str>,
+    ) -> Result<(Manifest, Option<String>)> {
+        self.runtime.block_on(
+            self.inner
+                .get_platform_manifest(name, reference, os, arch, variant),
+        )
+    }
+
+    /// Upload an image manifest, returning its digest.
+    pub fn put_manifest(
+        &self,
+        name: &str,
+        reference: &str,
+        media_type: &str,
+        body: Vec<u8>,
+    ) -> Result<String> {
+        self.runtime
+            .block_on(self.inner.put_manifest(name, reference, media_type, body))
+    }
+
+    /// Upload a serialized manifest, returning its digest.
+    pub fn put_manifest_bytes(
+        &self,
+        name: &str,
+        reference: &str,
+        manifest: &ManifestBytes,
+    ) -> Result<String> {
+        self.runtime
+            .block_on(self.inner.put_manifest_bytes(name, reference, manifest))
+    }
+
+    /// Delete a manifest by digest.
+    pub fn delete_manifest(&self, name: &str, digest: &str) -> Result<()> {
+        self.runtime
+            .block_on(self.inner.delete_manifest(name, digest))
+    }
+
+    /// List manifests referring to manifest `digest`.
+    pub fn get_referrers(
+        &self,
+        name: &str,
+        digest: &str,
+        artifact_type: Option<&str>,
+    ) -> Result<Vec<Referrer>> {
+        self.runtime
+            .block_on(self.inner.get_referrers(name, digest, artifact_type))
+    }
+
+    /// List manifests referring to manifest `digest` which match `filter`.
+    pub fn get_referrers_filtered(
+        &self,
+        name: &str,
+        digest: &str,
+        filter: &ReferrerFilter,
+    ) -> Result<Vec<Referrer>> {
+        self.runtime
+            .block_on(self.inner.get_referrers_filtered(name, digest, filter))
+    }
+
+    /// Upload a manifest referring to another one, by digest.
+    pub fn put_referrer(&self, name: &str, manifest: &ManifestBytes) -> Result<String> {
+        self.runtime
+            .block_on(self.inner.put_referrer(name, manifest))
+    }
+
+    /// Check if a blob exists.
+    pub fn has_blob(&self, name: &str, digest: &str) -> Result<bool> {
+        self.runtime.block_on(self.inner.has_blob(name, digest))
+    }
+
+    /// Check if a blob exists, returning its size.
+    pub fn get_blob_size(&self, name: &str, digest: &str) -> Result<Option<u64>> {
+        self.runtime
+            .block_on(self.inner.get_blob_size(name, digest))
+    }
+
+    /// Retrieve blob.
+    pub fn get_blob(&self, name: &str, digest: &str) -> Result<Vec<u8>> {
+        self.runtime.block_on(self.inner.get_blob(name, digest))
+    }
+
+    /// Write a blob to `writer` as it is received, returning its size.
+    ///
+    /// The digest is verified once the whole blob has been written.
+    pub fn copy_blob<W: Write>(&self, name: &str, digest: &str, writer: &mut W) -> Result<u64> {
+        self.runtime.block_on(async {
+            let (stream, _) = self.inner.get_blob_stream(name, digest).await?;
+            let mut stream = Box::pin(stream);
+            let mut size = 0;
+            while let Some(chunk) = stream.try_next().await? {
+                writer.write_all(&chunk)?;
+                size += chunk.len() as u64;
+            }
+            Ok(size)
+        })
+    }
+
+    /// Upload a blob, returning its digest as computed by the registry.
+    pub fn push_blob(&self, name: &str, digest: &str, blob: Vec<u8>) -> Result<String> {
+        self.runtime
+            .block_on(self.inner.push_blob(name, digest, blob))
+    }
+}
@@ -0,0 +1,144 @@
+//! Docker credential helpers.
+//!
+//! Credential helpers are external programs named `docker-credential-<helper>`,
+//! which store registry credentials (e.g. in a system keychain) or obtain
+//! them from a cloud provider (e.g. ECR or GCR). They are configured in
+//! `~/.docker/config.json`, through `credHelpers` for specific registries
+//! and `credsStore` for all of them.
+//!
+//! Protocol is at https://github.com/docker/docker-credential-helpers.
+
+use crate::errors::{Error, Result};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Message of helpers which have no credentials for a registry.
+const NOT_FOUND_MESSAGE: &str = "credentials not found in native keychain";
+
+/// Credentials exchanged with helpers.
+#[derive(Debug, Deserialize, Serialize)]
+struct HelperCredentials {
+    #[serde(rename = "ServerURL", default)]
+    server_url: String,
+    #[serde(rename = "Username")]
+    username: String,
+    #[serde(rename = "Secret")]
+    secret: String,
+}
+
+/// Get the credentials for registry `server_url` from credential `helper`.
+///
+/// Returns `Error::AuthInfoMissing` if the helper has no such credentials.
+pub fn get(helper: &str, server_url: &str) -> Result<(Option<String>, Option<String>)> {
+    get_with(&program(helper), server_url)
+}
+
+/// Store credentials for registry `server_url` with credential `helper`.
+pub fn store(helper: &str, server_url: &str, username: &str, secret: &str) -> Result<()> {
+    let creds = HelperCredentials {
+        server_url: server_url.to_string(),
+        username: username.to_string(),
+        secret: secret.to_string(),
+    };
+    run(&program(helper), "store", &serde_json::to_vec(&creds)?)?;
+    Ok(())
+}
+
+fn program(helper: &str) -> String {
+    format!("docker-credential-{}", helper)
+}
+
+fn get_with(program: &str, server_url: &str) -> Result<(Option<String>, Option<String>)> {
+    let output = match run(program, "get", server_url.as_bytes()) {
+        Err(Error::CredentialHelper(_, message)) if message.contains(NOT_FOUND_MESSAGE) => {
+            return Err(Error::AuthInfoMissing(server_url.to_string()))
+        }
+        res => res?,
+    };
+    let creds: HelperCredentials = serde_json::from_slice(&output)?;
+    trace!(
+        "Found credentials for user={:?} on {} with {}",
+        creds.username,
+        server_url,
+        program
+    );
+    let non_empty = |s: String| Some(s).filter(|s| !s.is_empty());
+    Ok((non_empty(creds.username), non_empty(creds.secret)))
+}
+
+/// Run `program` with `action`, writing `input` to its standard input, and
+/// return its standard output.
+fn run(program: &str, action: &str, input: &[u8]) -> Result<Vec<u8>> {
+    let helper_error = |message: String| Error::CredentialHelper(program.to_string(), message);
+
+    let mut child = Command::new(program)
+        .arg(action)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| helper_error(e.to_string()))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // Helpers may exit without reading their input, e.g. on errors.
+        match stdin.write_all(input) {
+            Err(e) if e.kind() != std::io::ErrorKind::BrokenPipe => {
+                return Err(helper_error(e.to_string()))
+            }
+            _ => {}
+        }
+    }
+    let output = child
+        .wait_with_output()
+        .map_err(|e| helper_error(e.to_string()))?;
+    if !output.status.success() {
+        // Helpers report errors on standard output.
+        let message = if output.stdout.is_empty() {
+            String::from_utf8_lossy(&output.stderr)
+        } else {
+            String::from_utf8_lossy(&output.stdout)
+        };
+        return Err(helper_error(message.trim().to_string()));
+    }
+    Ok(output.stdout)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    fn helper(dir: &tempfile::TempDir, name: &str, script: &str) -> String {
+        let path = dir.path().join(format!("docker-credential-{}", name));
+        std::fs::write(&path, format!("#!/bin/sh\n{}\n", script)).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn helper_credentials_are_parsed() {
+        let dir = tempfile::tempdir().unwrap();
+        let program = helper(
+            &dir,
+            "test",
+            r#"read url; echo "{\"ServerURL\":\"$url\",\"Username\":\"user\",\"Secret\":\"secret\"}""#,
+        );
+        let creds = get_with(&program, "registry.example.com").unwrap();
+        assert_eq!(creds, (Some("user".into()), Some("secret".into())));
+    }
+
+    #[test]
+    fn helper_errors_are_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        let program = helper(
+            &dir,
+            "missing",
+            "echo 'credentials not found in native keychain'; exit 1",
+        );
+        let res = get_with(&program, "registry.example.com");
+        assert!(matches!(res, Err(Error::AuthInfoMissing(_))));
+
+        let program = helper(&dir, "locked", "echo 'keychain locked'; exit 1");
+        let res = get_with(&program, "registry.example.com");
+        assert!(matches!(res, Err(Error::CredentialHelper(_, m)) if m == "keychain locked"));
+    }
+}
@@ -0,0 +1,224 @@
+//! Parsing of the Docker client configuration (`~/.docker/config.json`).
+
+use crate::credential_helper;
+use crate::errors::{Error, Result};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// Key of Docker Hub credentials, for historical reasons.
+const DOCKER_HUB_KEY: &str = "https://index.docker.io/v1/";
+
+/// Docker client configuration, limited to registry credentials.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub(crate) struct DockerConfig {
+    #[serde(default)]
+    auths: HashMap<String, AuthEntry>,
+    #[serde(rename = "credHelpers", default)]
+    cred_helpers: HashMap<String, String>,
+    #[serde(rename = "credsStore", default)]
+    creds_store: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+struct AuthEntry {
+    /// Base64 encoded `username:password`.
+    #[serde(default)]
+    auth: Option<String>,
+    #[serde(default)]
+    username: Option<String>,
+    #[serde(default)]
+    password: Option<String>,
+    #[serde(rename = "identitytoken", default)]
+    identity_token: Option<String>,
+}
+
+/// Credentials for a registry.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct RegistryCredentials {
+    pub(crate) username: Option<String>,
+    pub(crate) password: Option<String>,
+    /// OAuth2 refresh token, used in place of a password.
+    pub(crate) identity_token: Option<String>,
+}
+
+impl DockerConfig {
+    /// Path of the configuration file, in `$DOCKER_CONFIG` or `~/.docker`.
+    pub(crate) fn default_path() -> Option<PathBuf> {
+        if let Some(dir) = std::env::var_os("DOCKER_CONFIG") {
+            return Some(PathBuf::from(dir).join("config.json"));
+        }
+        std::env::var_os("HOME")
+            .or_else(|| std::env::var_os("USERPROFILE"))
+            .map(|home| PathBuf::from(home).join(".docker").join("config.json"))
+    }
+
+    /// Get the credentials for registry `index`, from its credential
+    /// helper if any, or else from its `auths` entry.
+    ///
+    /// Helper failures are logged, and only returned if there is no
+    /// `auths` entry to fall back to.
+    pub(crate) fn credentials(&self, index: &str) -> Result<RegistryCredentials> {
+        let host = normalize_registry(index);
+        let helper = find_registry(&self.cred_helpers, &host).or(self.creds_store.as_ref());
+        let server_url = match host.as_str() {
+            "docker.io" => DOCKER_HUB_KEY,
+            _ => index,
+        };
+        let mut helper_error = None;
+        if let Some(helper) = helper {
+            match credential_helper::get(helper, server_url) {
+                Err(Error::AuthInfoMissing(_)) => {}
+                // Fall back to `auths`, e.g. if the helper is not installed.
+                Err(e) => {
+                    warn!("credential helper for {} failed: {}", index, e);
+                    helper_error = Some(e);
+                }
+                // Helpers return identity tokens for this special user name.
+                Ok((Some(user), token)) if user == "<token>" => {
+                    return Ok(RegistryCredentials {
+                        identity_token: token,
+                        ..Default::default()
+                    })
+                }
+                Ok((username, password)) => {
+                    return Ok(RegistryCredentials {
+                        username,
+                        password,
+                        identity_token: None,
+                    });
+                }
+            }
+        }
+
+        let entry = find_registry(&self.auths, &host).ok_or_else(|| {
+            helper_error.unwrap_or_else(|| Error::AuthInfoMissing(server_url.to_string()))
+        })?;
+        let (username, password) = match &entry.auth {
+            Some(auth) if !auth.is_empty() => decode_auth(auth)?,
+            _ => (entry.username.clone(), entry.password.clone()),
+        };
+        trace!("Found credentials for user={:?} on {}", username, index);
+        Ok(RegistryCredentials {
+            username,
+            password,
+            identity_token: entry.identity_token.clone().filter(|t| !t.is_empty()),
+        })
+    }
+}
+
+/// Find the value of the first key, in sorted order, matching registry
+/// `host`, since several keys may be aliases of the same registry.
+fn find_registry<'a, V>(map: &'a HashMap<String, V>, host: &str) -> Option<&'a V> {
+    map.iter()
+        .filter(|(registry, _)| normalize_registry(registry) == host)
+        .min_by_key(|(registry, _)| registry.as_str())
+        .map(|(_, value)| value)
+}
+
+/// Decode a base64 encoded `username:password` pair.
+fn decode_auth(auth: &str) -> Result<(Option<String>, Option<String>)> {
+    let s = String::from_utf8(base64::decode(auth)?)?;
+    let creds: Vec<&str> = s.splitn(2, ':').collect();
+    let up = match (creds.first(), creds.get(1)) {
+        (Some(&""), Some(p)) => (None, Some(p.to_string())),
+        (Some(u), Some(&"")) => (Some(u.to_string()), None),
+        (Some(u), Some(p)) => (Some(u.to_string()), Some(p.to_string())),
+        (_, _) => (None, None),
+    };
+    Ok(up)
+}
+
+/// Reduce a registry, as written in configuration keys, to its host.
+///
+/// Keys may be URLs (e.g. `https://quay.io/v1/`), and Docker Hub has
+/// several aliases.
+fn normalize_registry(registry: &str) -> String {
+    let host = registry
+        .split_once("://")
+        .map_or(registry, |(_, rest)| rest)
+        .split('/')
+        .next()
+        .unwrap_or_default()
+        .to_lowercase();
+    match host.as_str() {
+        "index.docker.io" | "registry-1.docker.io" | "registry.hub.docker.com" => {
+            "docker.io".to_string()
+        }
+        _ => host,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("quay.io" => "quay.io"; "host")]
+    #[test_case("https://Quay.io/v1/" => "quay.io"; "url")]
+    #[test_case("localhost:5000" => "localhost:5000"; "port")]
+    #[test_case("https://index.docker.io/v1/" => "docker.io"; "docker hub key")]
+    #[test_case("registry-1.docker.io" => "docker.io"; "docker hub registry")]
+    fn registries_are_normalized(registry: &str) -> String {
+        normalize_registry(registry)
+    }
+
+    #[test]
+    fn credentials_are_selected_by_host() {
+        let config: DockerConfig = serde_json::from_str(
+            r#"{"auths": {
+                "https://index.docker.io/v1/": {"auth": "dXNlcjpwYXNz"},
+                "quay.io": {"username": "quser", "password": "qpass"},
+                "https://registry.example.com/": {"identitytoken": "token"}
+            }}"#,
+        )
+        .unwrap();
+
+        let creds = config.credentials("registry-1.docker.io").unwrap();
+        assert_eq!(creds.username.as_deref(), Some("user"));
+        assert_eq!(creds.password.as_deref(), Some("pass"));
+        let creds = config.credentials("quay.io").unwrap();
+        assert_eq!(creds.username.as_deref(), Some("quser"));
+        let creds = config.credentials("registry.example.com").unwrap();
+        assert_eq!(creds.identity_token.as_deref(), Some("token"));
+        assert!(matches!(
+            config.credentials("gcr.io"),
+            Err(Error::AuthInfoMissing(_))
+        ));
+    }
+
+    #[test]
+    fn credentials_fall_back_to_auths_if_helper_fails() {
+        let config: DockerConfig = serde_json::from_str(
+            r#"{
+                "auths": {"quay.io": {"username": "quser", "password": "qpass"}},
+                "credHelpers": {
+                    "quay.io": "dkregistry-missing",
+                    "gcr.io": "dkregistry-missing"
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let creds = config.credentials("quay.io").unwrap();
+        assert_eq!(creds.username.as_deref(), Some("quser"));
+        assert!(matches!(
+            config.credentials("gcr.io"),
+            Err(Error::CredentialHelper(..))
+        ));
+    }
+
+    #[test]
+    fn aliased_keys_are_selected_in_sorted_order() {
+        let config: DockerConfig = serde_json::from_str(
+            r#"{"auths": {
+                "registry-1.docker.io": {"username": "second"},
+                "https://index.docker.io/v1/": {"username": "first"},
+                "index.docker.io": {"username": "third"}
+            }}"#,
+        )
+        .unwrap();
+
+        let creds = config.credentials("docker.io").unwrap();
+        assert_eq!(creds.username.as_deref(), Some("first"));
+    }
+}
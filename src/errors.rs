@@ -7,6 +7,8 @@ pub enum Error {
     Base64Decode(#[from] base64::DecodeError),
     #[error("header parse error")]
     HeaderParse(#[from] http::header::ToStrError),
+    #[error("I/O error")]
+    Io(#[from] std::io::Error),
     #[error("json error")]
     Json(#[from] serde_json::Error),
     #[error("http transport error: {0}")]
@@ -15,10 +17,12 @@ pub enum Error {
     Uri(#[from] url::ParseError),
     #[error("input is not UTF-8")]
     Ut8Parse(#[from] std::string::FromUtf8Error),
-    #[error("strum error")]
-    StrumParse(#[from] strum::ParseError),
     #[error("authentication information missing for index {0}")]
     AuthInfoMissing(String),
+    #[error("credential helper {0} failed: {1}")]
+    CredentialHelper(String, String),
+    #[error("{0} credentials provider failed: {1}")]
+    CredentialsProvider(String, String),
     #[error("unknown media type {0:?}")]
     UnknownMimeType(mime::Mime),
     #[error("unknown media type {0:?}")]
@@ -27,6 +31,10 @@ pub enum Error {
     MimeParse(#[from] mime::FromStrError),
     #[error("missing authentication header {0}")]
     MissingAuthHeader(&'static str),
+    #[error("missing response header {0}")]
+    MissingHeader(&'static str),
+    #[error("invalid response header {0}: {1:?}")]
+    InvalidHeader(&'static str, String),
     #[error("unexpected HTTP status {0}")]
     UnexpectedHttpStatus(http::StatusCode),
     #[error("invalid auth token '{0}'")]
@@ -41,16 +49,190 @@ pub enum Error {
     Client { status: http::StatusCode },
     #[error("request failed with status {status}")]
     Server { status: http::StatusCode },
+    #[error("request failed with status {status}: {}", first_error(.errors))]
+    Registry {
+        status: http::StatusCode,
+        errors: Vec<crate::v2::ApiError>,
+    },
     #[error("content digest error")]
-    ContentDigestParse(#[from] crate::v2::ContentDigestError),
+    ContentDigestParse(crate::v2::ContentDigestError),
+    #[error("digest mismatch: expected {expected}, got {got}")]
+    DigestMismatch { expected: String, got: String },
+    #[error("size mismatch: expected {expected} bytes, got {got}")]
+    SizeMismatch { expected: u64, got: u64 },
     #[error("no header Content-Type given and no workaround to apply")]
     MediaTypeSniff,
+    #[error("invalid schema 1 manifest signature: {0}")]
+    Schema1Signature(String),
+    #[error("invalid eStargz layer: {0}")]
+    Estargz(String),
+    #[error("file {0} not found in eStargz layer")]
+    EstargzFileNotFound(String),
     #[error("manifest error")]
     Manifest(#[from] crate::v2::manifest::ManifestError),
     #[error("reference is invalid")]
     ReferenceParse(#[from] crate::reference::ReferenceParseError),
+    #[error("registry does not support deleting tag {0}, only manifests by digest")]
+    TagDeletionUnsupported(String),
+    #[error("manifest {0} not found")]
+    ManifestNotFound(String),
+    #[error("no manifest for platform {0} in image index")]
+    PlatformNotFound(String),
+    #[error("registry does not support deletions")]
+    DeletionUnsupported,
+    #[error("rate limited by registry: {0:?}")]
+    RateLimited(crate::v2::RateLimit),
+    #[error("operation deadline of {0:?} exceeded")]
+    DeadlineExceeded(std::time::Duration),
     #[error("requested operation requires that credentials are available")]
     NoCredentials,
+    #[error("foreign layer {0} refused by policy")]
+    ForeignLayer(String),
+}
+
+impl From<crate::v2::ContentDigestError> for Error {
+    fn from(e: crate::v2::ContentDigestError) -> Self {
+        match e {
+            crate::v2::ContentDigestError::Verify { expected, got } => {
+                Error::DigestMismatch { expected, got }
+            }
+            e => Error::ContentDigestParse(e),
+        }
+    }
+}
+
+/// Broad category of an `Error`, see `Error::kind`.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// Network failure, timeout, rate limiting or server error, which may
+    /// not happen again.
+    Transient,
+    /// Missing or rejected credentials, or denied access.
+    Auth,
+    /// Missing repository, manifest, blob or platform.
+    NotFound,
+    /// Operation not supported by the registry.
+    Unsupported,
+    /// Invalid or unexpected response from the registry.
+    Protocol,
+    /// Invalid input, or local failure.
+    Other,
+}
+
+impl Error {
+    /// Classify this error, e.g. to decide whether to retry an operation.
+    pub fn kind(&self) -> ErrorKind {
+        use crate::v2::ErrorCode;
+        match self {
+            Error::Reqwest(e) => match e.status() {
+                Some(status) => status_kind(status),
+                None if e.is_timeout()
+                    || crate::v2::is_connect(e)
+                    || e.is_request()
+                    || e.is_body() =>
+                {
+                    ErrorKind::Transient
+                }
+                None if e.is_decode() => ErrorKind::Protocol,
+                None => ErrorKind::Other,
+            },
+            Error::RateLimited(_) | Error::DeadlineExceeded(_) => ErrorKind::Transient,
+            Error::AuthInfoMissing(_)
+            | Error::CredentialHelper(..)
+            | Error::CredentialsProvider(..)
+            | Error::MissingAuthHeader(_)
+            | Error::InvalidAuthToken(_)
+            | Error::LoginReturnedBadToken
+            | Error::NoCredentials => ErrorKind::Auth,
+            Error::ManifestNotFound(_)
+            | Error::PlatformNotFound(_)
+            | Error::EstargzFileNotFound(_)
+            | Error::Manifest(crate::v2::manifest::ManifestError::MissingPlatform(_)) => {
+                ErrorKind::NotFound
+            }
+            Error::V2NotSupported
+            | Error::UnsupportedMediaType(_)
+            | Error::TagDeletionUnsupported(_)
+            | Error::DeletionUnsupported
+            | Error::ForeignLayer(_) => ErrorKind::Unsupported,
+            Error::Registry { status, errors } => match errors.first().map(|e| &e.code) {
+                Some(ErrorCode::Unauthorized | ErrorCode::Denied) => ErrorKind::Auth,
+                Some(
+                    ErrorCode::BlobUnknown
+                    | ErrorCode::BlobUploadUnknown
+                    | ErrorCode::ManifestUnknown
+                    | ErrorCode::NameUnknown,
+                ) => ErrorKind::NotFound,
+                Some(ErrorCode::Unsupported) => ErrorKind::Unsupported,
+                Some(ErrorCode::TooManyRequests) => ErrorKind::Transient,
+                _ => status_kind(*status),
+            },
+            Error::Client { status }
+            | Error::Server { status }
+            | Error::UnexpectedHttpStatus(status) => status_kind(*status),
+            Error::HeaderParse(_)
+            | Error::Json(_)
+            | Error::Ut8Parse(_)
+            | Error::UnknownMimeType(_)
+            | Error::MimeParse(_)
+            | Error::MissingHeader(_)
+            | Error::InvalidHeader(..)
+            | Error::Www(_)
+            | Error::DigestMismatch { .. }
+            | Error::SizeMismatch { .. }
+            | Error::Schema1Signature(_)
+            | Error::Estargz(_)
+            | Error::MediaTypeSniff => ErrorKind::Protocol,
+            Error::Base64Decode(_)
+            | Error::Io(_)
+            | Error::Uri(_)
+            | Error::ContentDigestParse(_)
+            | Error::Manifest(_)
+            | Error::ReferenceParse(_) => ErrorKind::Other,
+        }
+    }
+
+    /// HTTP status of the failed response, if this error comes from one.
+    pub fn status(&self) -> Option<http::StatusCode> {
+        match self {
+            Error::Reqwest(e) => e.status(),
+            Error::Client { status }
+            | Error::Server { status }
+            | Error::Registry { status, .. }
+            | Error::UnexpectedHttpStatus(status) => Some(*status),
+            _ => None,
+        }
+    }
+
+    /// Whether the operation which failed with this error may succeed if
+    /// attempted again.
+    pub fn is_retryable(&self) -> bool {
+        self.kind() == ErrorKind::Transient
+    }
+}
+
+/// Describe the first error reported by a registry.
+fn first_error(errors: &[crate::v2::ApiError]) -> String {
+    errors
+        .first()
+        .map_or_else(|| "no error details".to_string(), ToString::to_string)
+}
+
+/// Classify an HTTP error status.
+fn status_kind(status: http::StatusCode) -> ErrorKind {
+    match status {
+        http::StatusCode::UNAUTHORIZED | http::StatusCode::FORBIDDEN => ErrorKind::Auth,
+        http::StatusCode::NOT_FOUND => ErrorKind::NotFound,
+        http::StatusCode::METHOD_NOT_ALLOWED | http::StatusCode::NOT_IMPLEMENTED => {
+            ErrorKind::Unsupported
+        }
+        http::StatusCode::REQUEST_TIMEOUT | http::StatusCode::TOO_MANY_REQUESTS => {
+            ErrorKind::Transient
+        }
+        s if s.is_server_error() => ErrorKind::Transient,
+        _ => ErrorKind::Protocol,
+    }
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -63,4 +245,36 @@ mod tests {
         fn check_bounds<T: Send + Sync + 'static>() {}
         check_bounds::<Error>();
     }
+
+    #[test]
+    fn test_error_kind() {
+        let kind = |status: u16| {
+            let status = http::StatusCode::from_u16(status).unwrap();
+            Error::Client { status }.kind()
+        };
+        assert_eq!(kind(401), ErrorKind::Auth);
+        assert_eq!(kind(404), ErrorKind::NotFound);
+        assert_eq!(kind(405), ErrorKind::Unsupported);
+        assert_eq!(kind(400), ErrorKind::Protocol);
+        let server = Error::Server {
+            status: http::StatusCode::BAD_GATEWAY,
+        };
+        assert!(server.is_retryable());
+        assert!(!Error::ManifestNotFound("latest".into()).is_retryable());
+        assert_eq!(Error::NoCredentials.kind(), ErrorKind::Auth);
+    }
+
+    #[test]
+    fn test_registry_error_without_details() {
+        let e = Error::Registry {
+            status: http::StatusCode::NOT_FOUND,
+            errors: Vec::new(),
+        };
+        assert_eq!(e.kind(), ErrorKind::NotFound);
+        assert_eq!(e.status(), Some(http::StatusCode::NOT_FOUND));
+        assert_eq!(
+            e.to_string(),
+            "request failed with status 404 Not Found: no error details"
+        );
+    }
 }
@@ -40,9 +40,14 @@ extern crate log;
 #[macro_use]
 extern crate strum_macros;
 
+#[cfg(feature = "blocking")]
+pub mod blocking;
+pub mod credential_helper;
+mod docker_config;
 pub mod errors;
 pub mod mediatypes;
 pub mod reference;
+#[cfg(feature = "render")]
 pub mod render;
 pub mod v2;
 
@@ -57,6 +62,9 @@ pub static USER_AGENT: &str = "camallo-dkregistry/0.0";
 ///
 /// This is a convenience decoder for docker-client credentials
 /// typically stored under `~/.docker/config.json`.
+///
+/// Only the inline `auths` are decoded, credential helpers are not run. See
+/// `v2::Config::from_docker_config` to use them.
 pub fn get_credentials<T: Read>(
     reader: T,
     index: &str,
@@ -1,12 +1,13 @@
 //! Media-types for API objects.
 
 use crate::errors::Result;
+use std::fmt;
 use strum::EnumProperty;
 
 // For schema1 types, see https://docs.docker.com/registry/spec/manifest-v2-1/
 // For schema2 types, see https://docs.docker.com/registry/spec/manifest-v2-2/
 
-#[derive(EnumProperty, EnumString, Display, Debug, Hash, PartialEq, Eq, Clone)]
+#[derive(EnumProperty, EnumString, Debug, Hash, PartialEq, Eq, Clone)]
 pub enum MediaTypes {
     /// Manifest, version 2 schema 1.
     #[strum(serialize = "application/vnd.docker.distribution.manifest.v1+json")]
@@ -24,10 +25,43 @@ pub enum MediaTypes {
     #[strum(serialize = "application/vnd.docker.distribution.manifest.list.v2+json")]
     #[strum(props(Sub = "vnd.docker.distribution.manifest.list.v2+json"))]
     ManifestList,
+    /// OCI image manifest.
+    #[strum(serialize = "application/vnd.oci.image.manifest.v1+json")]
+    #[strum(props(Sub = "vnd.oci.image.manifest.v1+json"))]
+    OciImageManifest,
+    /// OCI image index, the OCI counterpart of a manifest list.
+    #[strum(serialize = "application/vnd.oci.image.index.v1+json")]
+    #[strum(props(Sub = "vnd.oci.image.index.v1+json"))]
+    OciImageIndex,
+    /// OCI artifact manifest (deprecated in favor of OCI image manifests).
+    #[strum(serialize = "application/vnd.oci.artifact.manifest.v1+json")]
+    #[strum(props(Sub = "vnd.oci.artifact.manifest.v1+json"))]
+    OciArtifactManifest,
+    /// OCI image configuration.
+    #[strum(serialize = "application/vnd.oci.image.config.v1+json")]
+    #[strum(props(Sub = "vnd.oci.image.config.v1+json"))]
+    OciImageConfig,
+    /// Empty OCI descriptor content, e.g. as config of artifacts.
+    #[strum(serialize = "application/vnd.oci.empty.v1+json")]
+    #[strum(props(Sub = "vnd.oci.empty.v1+json"))]
+    OciEmptyJson,
     /// Image layer, as a gzip-compressed tar.
     #[strum(serialize = "application/vnd.docker.image.rootfs.diff.tar.gzip")]
     #[strum(props(Sub = "vnd.docker.image.rootfs.diff.tar.gzip"))]
     ImageLayerTgz,
+    /// Foreign image layer, as a gzip-compressed tar stored outside of
+    /// registries (e.g. Windows base layers).
+    #[strum(serialize = "application/vnd.docker.image.rootfs.foreign.diff.tar.gzip")]
+    #[strum(props(Sub = "vnd.docker.image.rootfs.foreign.diff.tar.gzip"))]
+    ImageLayerForeignTgz,
+    /// Image layer, as a zstd-compressed tar.
+    #[strum(serialize = "application/vnd.docker.image.rootfs.diff.tar.zstd")]
+    #[strum(props(Sub = "vnd.docker.image.rootfs.diff.tar.zstd"))]
+    ImageLayerZstd,
+    /// OCI image layer, as a zstd-compressed tar.
+    #[strum(serialize = "application/vnd.oci.image.layer.v1.tar+zstd")]
+    #[strum(props(Sub = "vnd.oci.image.layer.v1.tar+zstd"))]
+    OciImageLayerZstd,
     /// Configuration object for a container.
     #[strum(serialize = "application/vnd.docker.container.image.v1+json")]
     #[strum(props(Sub = "vnd.docker.container.image.v1+json"))]
@@ -36,9 +70,33 @@ pub enum MediaTypes {
     #[strum(serialize = "application/json")]
     #[strum(props(Sub = "json"))]
     ApplicationJson,
+    /// Any other media type, e.g. of artifacts, kept as it is.
+    #[strum(default)]
+    Other(String),
+}
+
+impl fmt::Display for MediaTypes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MediaTypes::Other(media_type) => f.pad(media_type),
+            m => f.pad(&format!(
+                "application/{}",
+                m.get_str("Sub").unwrap_or_default()
+            )),
+        }
+    }
 }
 
 impl MediaTypes {
+    /// Parse `media_type`, which is `MediaTypes::Other` if unknown.
+    ///
+    /// Unlike the `FromStr` implementation, this cannot fail.
+    pub(crate) fn parse(media_type: &str) -> Self {
+        media_type
+            .parse()
+            .unwrap_or_else(|_| MediaTypes::Other(media_type.to_string()))
+    }
+
     // TODO(lucab): proper error types
     pub fn from_mime(mtype: &mime::Mime) -> Result<Self> {
         match (mtype.type_(), mtype.subtype(), mtype.suffix()) {
@@ -53,17 +111,34 @@ impl MediaTypes {
                     ("vnd.docker.distribution.manifest.list.v2", "json") => {
                         Ok(MediaTypes::ManifestList)
                     }
-                    ("vnd.docker.image.rootfs.diff.tar.gzip", _) => Ok(MediaTypes::ImageLayerTgz),
+                    ("vnd.oci.image.manifest.v1", "json") => Ok(MediaTypes::OciImageManifest),
+                    ("vnd.oci.image.index.v1", "json") => Ok(MediaTypes::OciImageIndex),
+                    ("vnd.oci.artifact.manifest.v1", "json") => Ok(MediaTypes::OciArtifactManifest),
+                    ("vnd.oci.image.config.v1", "json") => Ok(MediaTypes::OciImageConfig),
+                    ("vnd.oci.empty.v1", "json") => Ok(MediaTypes::OciEmptyJson),
+                    ("vnd.oci.image.layer.v1.tar", "zstd") => Ok(MediaTypes::OciImageLayerZstd),
                     ("vnd.docker.container.image.v1", "json") => Ok(MediaTypes::ContainerConfigV1),
                     _ => Err(crate::Error::UnknownMimeType(mtype.clone())),
                 }
             }
+            // Docker layer types have no `+` suffix.
+            (mime::APPLICATION, subt, None) => match subt.as_str() {
+                "vnd.docker.image.rootfs.diff.tar.gzip" => Ok(MediaTypes::ImageLayerTgz),
+                "vnd.docker.image.rootfs.foreign.diff.tar.gzip" => {
+                    Ok(MediaTypes::ImageLayerForeignTgz)
+                }
+                "vnd.docker.image.rootfs.diff.tar.zstd" => Ok(MediaTypes::ImageLayerZstd),
+                _ => Err(crate::Error::UnknownMimeType(mtype.clone())),
+            },
             _ => Err(crate::Error::UnknownMimeType(mtype.clone())),
         }
     }
     pub fn to_mime(&self) -> mime::Mime {
         match self {
             &MediaTypes::ApplicationJson => Ok(mime::APPLICATION_JSON),
+            MediaTypes::Other(media_type) => {
+                Ok(media_type.parse().unwrap_or(mime::APPLICATION_OCTET_STREAM))
+            }
             m => {
                 if let Some(s) = m.get_str("Sub") {
                     ("application/".to_string() + s).parse()
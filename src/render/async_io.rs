@@ -0,0 +1,137 @@
+//! Asynchronous front-end for layers unpacking.
+//!
+//! Unpacking is inherently blocking, so it runs on a dedicated blocking
+//! task while layers are read on the async side and forwarded through
+//! bounded channels, which provide backpressure.
+
+use super::{
+    ensure_target_dir, unpack_streaming_with_options, unpack_with_options, RenderError,
+    UnpackOptions, UnpackReport,
+};
+use std::io::{self, Read};
+use std::path;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::sync::mpsc;
+
+/// Size of chunks forwarded from async readers to the unpacker.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Number of in-flight chunks per layer.
+const CHANNEL_DEPTH: usize = 4;
+
+/// Unpack an ordered list of in-memory layers to a target directory, without
+/// blocking the async runtime.
+///
+/// This is the asynchronous counterpart of `unpack_with_options`: the whole
+/// unpacking runs on a blocking task of the current tokio runtime.
+pub async fn unpack_async(
+    layers: Vec<Vec<u8>>,
+    target_dir: &path::Path,
+    options: &UnpackOptions,
+) -> Result<UnpackReport, RenderError> {
+    ensure_target_dir(target_dir)?;
+    let target = target_dir.to_path_buf();
+    let options = options.clone();
+    tokio::task::spawn_blocking(move || unpack_with_options(&layers, &target, &options)).await?
+}
+
+/// Unpack an ordered list of layers, read from async streams, to a target directory.
+///
+/// This is the asynchronous counterpart of `unpack_streaming`: filesystem
+/// operations are offloaded to a blocking task of the current tokio runtime.
+pub async fn unpack_streaming_async<I, R>(
+    layers: I,
+    target_dir: &path::Path,
+) -> Result<UnpackReport, RenderError>
+where
+    I: IntoIterator<Item = R>,
+    R: AsyncRead + Unpin,
+{
+    unpack_streaming_async_with_options(layers, target_dir, &UnpackOptions::default()).await
+}
+
+/// Unpack layers read from async streams to a target directory, with custom options.
+pub async fn unpack_streaming_async_with_options<I, R>(
+    layers: I,
+    target_dir: &path::Path,
+    options: &UnpackOptions,
+) -> Result<UnpackReport, RenderError>
+where
+    I: IntoIterator<Item = R>,
+    R: AsyncRead + Unpin,
+{
+    ensure_target_dir(target_dir)?;
+
+    let (senders, readers): (Vec<_>, Vec<_>) = layers
+        .into_iter()
+        .map(|layer| {
+            let (tx, rx) = mpsc::channel(CHANNEL_DEPTH);
+            ((layer, tx), ChannelReader::new(rx))
+        })
+        .unzip();
+
+    let target = target_dir.to_path_buf();
+    let options = options.clone();
+    let task = tokio::task::spawn_blocking(move || {
+        unpack_streaming_with_options(readers, &target, &options)
+    });
+
+    // Layers are consumed in order, so they are fed in the same order.
+    // A closed channel means that the unpacker is done with that layer
+    // (or bailed out early, in which case its result carries the error).
+    'layers: for (mut layer, tx) in senders {
+        loop {
+            let mut chunk = vec![0u8; CHUNK_SIZE];
+            match layer.read(&mut chunk).await {
+                Ok(0) => break,
+                Ok(n) => {
+                    chunk.truncate(n);
+                    if tx.send(Ok(chunk)).await.is_err() {
+                        continue 'layers;
+                    }
+                }
+                Err(e) => {
+                    let _ = tx.send(Err(e)).await;
+                    break 'layers;
+                }
+            }
+        }
+    }
+
+    task.await?
+}
+
+/// A blocking reader over chunks received from a channel.
+struct ChannelReader {
+    rx: mpsc::Receiver<io::Result<Vec<u8>>>,
+    chunk: Vec<u8>,
+    pos: usize,
+}
+
+impl ChannelReader {
+    fn new(rx: mpsc::Receiver<io::Result<Vec<u8>>>) -> Self {
+        Self {
+            rx,
+            chunk: Vec::new(),
+            pos: 0,
+        }
+    }
+}
+
+impl Read for ChannelReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos >= self.chunk.len() {
+            match self.rx.blocking_recv() {
+                Some(msg) => {
+                    self.chunk = msg?;
+                    self.pos = 0;
+                }
+                None => return Ok(0),
+            }
+        }
+        let n = std::cmp::min(buf.len(), self.chunk.len() - self.pos);
+        buf[..n].copy_from_slice(&self.chunk[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
@@ -0,0 +1,225 @@
+//! Generation of OCI runtime bundles.
+
+use super::{contained_path, ensure_target_dir, unpack_with_options, RenderError, UnpackOptions};
+use crate::v2::manifest::ConfigBlob;
+use std::{fs, path};
+
+/// Name of the root filesystem directory within a bundle.
+const ROOTFS_DIR: &str = "rootfs";
+
+/// Environment `PATH` used when the image does not define one.
+const DEFAULT_PATH: &str = "PATH=/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin:/sbin:/bin";
+
+/// Render an image as an OCI runtime bundle in `bundle_dir`.
+///
+/// Layers are unpacked to `bundle_dir/rootfs`, and a runc-compatible
+/// `config.json` is derived from `image_config` (the raw JSON image
+/// configuration blob, see `ConfigBlob`): its `Entrypoint`, `Cmd`, `Env`,
+/// `WorkingDir` and `User` become the container process. User and group
+/// names are resolved against the `/etc/passwd` and `/etc/group` files of
+/// the image.
+pub fn to_runtime_bundle(
+    layers: &[Vec<u8>],
+    image_config: &[u8],
+    bundle_dir: &path::Path,
+) -> Result<(), RenderError> {
+    to_runtime_bundle_with_options(layers, image_config, bundle_dir, &UnpackOptions::default())
+}
+
+/// Render an image as an OCI runtime bundle, with custom unpacking options.
+pub fn to_runtime_bundle_with_options(
+    layers: &[Vec<u8>],
+    image_config: &[u8],
+    bundle_dir: &path::Path,
+    options: &UnpackOptions,
+) -> Result<(), RenderError> {
+    ensure_target_dir(bundle_dir)?;
+    let config = *serde_json::from_slice::<ConfigBlob>(image_config)
+        .map_err(RenderError::ImageConfig)?
+        .config
+        .unwrap_or_default();
+
+    let args: Vec<String> = config
+        .entrypoint
+        .unwrap_or_default()
+        .into_iter()
+        .chain(config.cmd.unwrap_or_default())
+        .collect();
+    if args.is_empty() {
+        return Err(RenderError::MissingCommand);
+    }
+    let mut env = config.env.unwrap_or_default();
+    if !env.iter().any(|e| e.starts_with("PATH=")) {
+        env.push(DEFAULT_PATH.to_string());
+    }
+    let cwd = match config.working_dir {
+        Some(dir) if !dir.is_empty() => dir,
+        _ => "/".to_string(),
+    };
+
+    let rootfs = bundle_dir.join(ROOTFS_DIR);
+    fs::create_dir_all(&rootfs)?;
+    unpack_with_options(layers, &rootfs, options)?;
+    let (uid, gid) = resolve_user(&rootfs.canonicalize()?, config.user.as_deref())?;
+
+    let spec = runtime_spec(args, env, cwd, uid, gid);
+    fs::write(
+        bundle_dir.join("config.json"),
+        serde_json::to_vec_pretty(&spec).map_err(std::io::Error::from)?,
+    )?;
+    Ok(())
+}
+
+/// Resolve an image `User` value (`user[:group]`, by name or ID) to IDs.
+fn resolve_user(rootfs: &path::Path, user: Option<&str>) -> Result<(u32, u32), RenderError> {
+    let user = match user {
+        Some(u) if !u.is_empty() => u,
+        _ => return Ok((0, 0)),
+    };
+    let unknown = || RenderError::UnknownUser(user.to_string());
+    let (user_part, group_part) = match user.split_once(':') {
+        Some((u, g)) => (u, Some(g)),
+        None => (user, None),
+    };
+
+    let passwd = read_image_file(rootfs, "etc/passwd")?;
+    // passwd lines are `name:password:uid:gid:...`.
+    let account = db_entries(&passwd)
+        .find(|fields| fields.first() == Some(&user_part) || fields.get(2) == Some(&user_part));
+    let uid = match (user_part.parse::<u32>(), &account) {
+        (Ok(uid), _) => uid,
+        (Err(_), Some(fields)) => fields
+            .get(2)
+            .and_then(|f| f.parse().ok())
+            .ok_or_else(unknown)?,
+        (Err(_), None) => return Err(unknown()),
+    };
+
+    let gid = match group_part {
+        None => account
+            .and_then(|fields| fields.get(3).and_then(|f| f.parse().ok()))
+            .unwrap_or(0),
+        Some(group) => match group.parse::<u32>() {
+            Ok(gid) => gid,
+            Err(_) => {
+                // group lines are `name:password:gid:members`.
+                let groups = read_image_file(rootfs, "etc/group")?;
+                let gid = db_entries(&groups)
+                    .find(|fields| fields.first() == Some(&group))
+                    .and_then(|fields| fields.get(2).and_then(|f| f.parse().ok()))
+                    .ok_or_else(unknown)?;
+                gid
+            }
+        },
+    };
+    Ok((uid, gid))
+}
+
+/// Split the lines of a colon-separated database such as `/etc/passwd`.
+fn db_entries(content: &str) -> impl Iterator<Item = Vec<&str>> {
+    content
+        .lines()
+        .filter(|l| !l.trim().is_empty() && !l.starts_with('#'))
+        .map(|l| l.split(':').collect())
+}
+
+/// Read a regular file of the unpacked image, returning an empty string if
+/// it is missing. Symlinks are not followed, as they could point to the host.
+fn read_image_file(rootfs: &path::Path, rel_path: &str) -> Result<String, RenderError> {
+    let abs_path = contained_path(rootfs, path::Path::new(rel_path))?;
+    match fs::symlink_metadata(&abs_path) {
+        Ok(meta) if meta.is_file() => Ok(fs::read_to_string(abs_path)?),
+        _ => Ok(String::new()),
+    }
+}
+
+/// Build a runtime spec, with the same defaults as `runc spec`.
+fn runtime_spec(
+    args: Vec<String>,
+    env: Vec<String>,
+    cwd: String,
+    uid: u32,
+    gid: u32,
+) -> serde_json::Value {
+    let capabilities = ["CAP_AUDIT_WRITE", "CAP_KILL", "CAP_NET_BIND_SERVICE"];
+    serde_json::json!({
+        "ociVersion": "1.0.2",
+        "process": {
+            "terminal": false,
+            "user": { "uid": uid, "gid": gid },
+            "args": args,
+            "env": env,
+            "cwd": cwd,
+            "capabilities": {
+                "bounding": capabilities,
+                "effective": capabilities,
+                "permitted": capabilities,
+            },
+            "rlimits": [{ "type": "RLIMIT_NOFILE", "hard": 1024, "soft": 1024 }],
+            "noNewPrivileges": true,
+        },
+        "root": { "path": ROOTFS_DIR, "readonly": false },
+        "hostname": "container",
+        "mounts": [
+            { "destination": "/proc", "type": "proc", "source": "proc" },
+            {
+                "destination": "/dev",
+                "type": "tmpfs",
+                "source": "tmpfs",
+                "options": ["nosuid", "strictatime", "mode=755", "size=65536k"],
+            },
+            {
+                "destination": "/dev/pts",
+                "type": "devpts",
+                "source": "devpts",
+                "options": ["nosuid", "noexec", "newinstance", "ptmxmode=0666", "mode=0620", "gid=5"],
+            },
+            {
+                "destination": "/dev/shm",
+                "type": "tmpfs",
+                "source": "shm",
+                "options": ["nosuid", "noexec", "nodev", "mode=1777", "size=65536k"],
+            },
+            {
+                "destination": "/dev/mqueue",
+                "type": "mqueue",
+                "source": "mqueue",
+                "options": ["nosuid", "noexec", "nodev"],
+            },
+            {
+                "destination": "/sys",
+                "type": "sysfs",
+                "source": "sysfs",
+                "options": ["nosuid", "noexec", "nodev", "ro"],
+            },
+        ],
+        "linux": {
+            "namespaces": [
+                { "type": "pid" },
+                { "type": "network" },
+                { "type": "ipc" },
+                { "type": "uts" },
+                { "type": "mount" },
+            ],
+            "maskedPaths": [
+                "/proc/acpi",
+                "/proc/asound",
+                "/proc/kcore",
+                "/proc/keys",
+                "/proc/latency_stats",
+                "/proc/timer_list",
+                "/proc/timer_stats",
+                "/proc/sched_debug",
+                "/sys/firmware",
+                "/proc/scsi",
+            ],
+            "readonlyPaths": [
+                "/proc/bus",
+                "/proc/fs",
+                "/proc/irq",
+                "/proc/sys",
+                "/proc/sysrq-trigger",
+            ],
+        },
+    })
+}
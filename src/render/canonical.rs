@@ -0,0 +1,110 @@
+//! Reproducible generation of tar archives.
+
+use super::{normalize_path, RenderError};
+use libflate::gzip;
+use std::io::{self, Read, Write};
+use std::path;
+
+/// PAX records which are not copied to canonical archives: the first ones
+/// are regenerated, the others vary between otherwise identical builds.
+const PAX_DROPPED: &[&str] = &[
+    "path", "linkpath", "size", "mtime", "atime", "ctime", "uname", "gname",
+];
+
+/// Settings making generated archives byte-for-byte reproducible.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct Canonical {
+    /// Modification time recorded for all entries (e.g. `SOURCE_DATE_EPOCH`).
+    pub(crate) mtime: u64,
+}
+
+/// A buffered archive entry.
+struct Buffered {
+    path: path::PathBuf,
+    header: tar::Header,
+    link_target: Option<path::PathBuf>,
+    pax_records: Vec<(String, Vec<u8>)>,
+    data: Vec<u8>,
+}
+
+impl Canonical {
+    /// Rewrite the uncompressed tar archive `archive` to `builder`.
+    ///
+    /// Entries are sorted by path, all timestamps are set to `mtime`, user
+    /// and group names are dropped (keeping numeric IDs) and PAX records are
+    /// sorted by key.
+    pub(crate) fn rewrite<W: Write>(
+        &self,
+        archive: &[u8],
+        builder: &mut tar::Builder<W>,
+    ) -> Result<(), RenderError> {
+        let mut entries = Vec::new();
+        for entry in tar::Archive::new(archive).entries()? {
+            let mut file = entry?;
+            let mut pax_records = Vec::new();
+            if let Some(extensions) = file.pax_extensions()? {
+                for ext in extensions {
+                    let ext = ext?;
+                    let key = ext
+                        .key()
+                        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+                    if !PAX_DROPPED.contains(&key) {
+                        pax_records.push((key.to_string(), ext.value_bytes().to_vec()));
+                    }
+                }
+            }
+            pax_records.sort();
+            let mut data = Vec::new();
+            file.read_to_end(&mut data)?;
+            entries.push(Buffered {
+                path: normalize_path(&file.path()?),
+                header: self.header(file.header())?,
+                link_target: file.link_name()?.map(|l| l.into_owned()),
+                pax_records,
+                data,
+            });
+        }
+        // Stable, so that duplicate paths keep their relative order.
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+
+        for mut entry in entries {
+            if !entry.pax_records.is_empty() {
+                builder.append_pax_extensions(
+                    entry
+                        .pax_records
+                        .iter()
+                        .map(|(k, v)| (k.as_str(), v.as_slice())),
+                )?;
+            }
+            match entry.link_target {
+                Some(target) => builder.append_link(&mut entry.header, &entry.path, target)?,
+                None => {
+                    builder.append_data(&mut entry.header, &entry.path, entry.data.as_slice())?
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Normalize the metadata of `header`.
+    fn header(&self, header: &tar::Header) -> Result<tar::Header, RenderError> {
+        let mut header = header.clone();
+        header.set_mtime(self.mtime);
+        // Old-style headers do not record names.
+        if header.username_bytes().is_some() {
+            header.set_username("")?;
+            header.set_groupname("")?;
+        }
+        if let Some(gnu) = header.as_gnu_mut() {
+            gnu.set_atime(0);
+            gnu.set_ctime(0);
+        }
+        Ok(header)
+    }
+}
+
+/// Create a gzip encoder whose output does not depend on the current time.
+pub(crate) fn gzip_encoder<W: Write>(writer: W) -> io::Result<gzip::Encoder<W>> {
+    let header = gzip::HeaderBuilder::new().modification_time(0).finish();
+    gzip::Encoder::with_options(writer, gzip::EncodeOptions::new().header(header))
+}
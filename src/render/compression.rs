@@ -0,0 +1,95 @@
+//! Decompression of layer archives.
+
+use super::RenderError;
+use libflate::gzip;
+use std::io::{self, Read};
+
+/// Magic bytes at the start of a gzip stream.
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+
+/// Magic bytes at the start of a zstd frame.
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+/// Compression algorithm of a layer archive.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LayerCompression {
+    /// Detect compression from the leading magic bytes of the layer.
+    #[default]
+    Auto,
+    /// Gzip-compressed tar archive.
+    Gzip,
+    /// Zstandard-compressed tar archive (requires the `zstd` feature).
+    Zstd,
+    /// Plain uncompressed tar archive.
+    Uncompressed,
+}
+
+impl LayerCompression {
+    /// Determine compression from a layer media type, if it is a known one.
+    ///
+    /// This covers both Docker (`...tar.gzip`) and OCI (`...tar+gzip`) naming.
+    pub fn from_media_type(media_type: &str) -> Option<Self> {
+        let media_type = media_type.split(';').next().unwrap_or_default().trim();
+        if media_type.ends_with("tar.gzip") || media_type.ends_with("tar+gzip") {
+            Some(LayerCompression::Gzip)
+        } else if media_type.ends_with("tar.zstd") || media_type.ends_with("tar+zstd") {
+            Some(LayerCompression::Zstd)
+        } else if media_type.ends_with(".tar") {
+            Some(LayerCompression::Uncompressed)
+        } else {
+            None
+        }
+    }
+
+    /// Detect compression from the leading bytes of a layer.
+    pub fn detect(magic: &[u8]) -> Self {
+        if magic.starts_with(GZIP_MAGIC) {
+            LayerCompression::Gzip
+        } else if magic.starts_with(ZSTD_MAGIC) {
+            LayerCompression::Zstd
+        } else {
+            LayerCompression::Uncompressed
+        }
+    }
+
+    /// Wrap `reader` with the matching decompressor, yielding the raw tar stream.
+    pub(crate) fn decoder<'a, R: Read + 'a>(
+        self,
+        mut reader: R,
+    ) -> Result<Box<dyn Read + 'a>, RenderError> {
+        if self != LayerCompression::Auto {
+            return self.open(reader);
+        }
+        let mut magic = [0u8; 4];
+        let len = read_prefix(&mut reader, &mut magic)?;
+        let prefixed = io::Cursor::new(magic).take(len as u64).chain(reader);
+        LayerCompression::detect(&magic[..len]).open(prefixed)
+    }
+
+    /// Wrap `reader` with the decompressor for an already determined algorithm.
+    fn open<'a, R: Read + 'a>(self, reader: R) -> Result<Box<dyn Read + 'a>, RenderError> {
+        match self {
+            LayerCompression::Gzip => Ok(Box::new(gzip::Decoder::new(reader)?)),
+            #[cfg(feature = "zstd")]
+            LayerCompression::Zstd => Ok(Box::new(zstd::stream::read::Decoder::new(reader)?)),
+            #[cfg(not(feature = "zstd"))]
+            LayerCompression::Zstd => Err(RenderError::UnsupportedCompression(self)),
+            // `Auto` is resolved by `decoder` before getting here.
+            LayerCompression::Auto | LayerCompression::Uncompressed => Ok(Box::new(reader)),
+        }
+    }
+}
+
+/// Fill `buf` as much as possible, stopping early only at end of stream.
+fn read_prefix<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut len = 0;
+    while len < buf.len() {
+        match reader.read(&mut buf[len..]) {
+            Ok(0) => break,
+            Ok(n) => len += n,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(len)
+}
@@ -0,0 +1,52 @@
+//! Confinement of filesystem operations within the target directory.
+
+use super::RenderError;
+use std::path;
+
+/// Resolve an archive path below `root`, ensuring that it cannot escape it.
+///
+/// `root` must be canonical and `rel_path` normalized (see `normalize_path`).
+/// The path is rejected if it contains non-plain components (e.g. `..`), or
+/// if its parent resolves outside of `root` through symlinks. The final
+/// component is not resolved, so callers must not follow it.
+pub(crate) fn contained_path(
+    root: &path::Path,
+    rel_path: &path::Path,
+) -> Result<path::PathBuf, RenderError> {
+    let escape = || RenderError::PathEscape(rel_path.to_path_buf());
+    if !rel_path
+        .components()
+        .all(|c| matches!(c, path::Component::Normal(_)))
+    {
+        return Err(escape());
+    }
+
+    let abs_path = root.join(rel_path);
+    let parent = abs_path.parent().unwrap_or(root);
+    let existing = parent
+        .ancestors()
+        .find(|p| p.symlink_metadata().is_ok())
+        .unwrap_or(root);
+    match existing.canonicalize() {
+        Ok(canonical) if canonical.starts_with(root) => Ok(abs_path),
+        _ => Err(escape()),
+    }
+}
+
+/// Resolve a directory below `root`, ensuring that it is a real directory
+/// (not a symlink) contained within `root`.
+///
+/// Returns `None` if the directory does not exist or is not a directory.
+pub(crate) fn contained_dir(
+    root: &path::Path,
+    rel_dir: &path::Path,
+) -> Result<Option<path::PathBuf>, RenderError> {
+    if rel_dir.as_os_str().is_empty() {
+        return Ok(Some(root.to_path_buf()));
+    }
+    let abs_dir = contained_path(root, rel_dir)?;
+    match abs_dir.symlink_metadata() {
+        Ok(meta) if meta.is_dir() => Ok(Some(abs_dir)),
+        _ => Ok(None),
+    }
+}
@@ -0,0 +1,223 @@
+//! Accelerated extraction of uncompressed layers stored on disk.
+
+use super::xattrs::PAX_XATTR_PREFIX;
+use super::{
+    ensure_target_dir, for_each_layer, unpack_layer, LayerCompression, RenderError, UnpackOptions,
+    UnpackReport, Whiteouts, ROOTLESS_MODE_MASK,
+};
+use std::io::{Read, Seek};
+use std::{fs, io, path};
+
+/// Regular files smaller than this are extracted through tar as usual.
+const COPY_THRESHOLD: u64 = 64 * 1024;
+
+/// Unpack an ordered list of layers stored as local files to a target directory.
+///
+/// This behaves like `unpack_with_options`, but regular files of
+/// uncompressed layers are copied with `copy_file_range` (on Linux) rather
+/// than streamed, so that filesystems supporting reflinks (e.g. btrfs or
+/// XFS) can share data with the layer files instead of duplicating it.
+/// Layer files are still read in full, to apply digest checks and limits.
+pub fn unpack_layer_files<P: AsRef<path::Path>>(
+    layer_files: &[P],
+    target_dir: &path::Path,
+    options: &UnpackOptions,
+) -> Result<UnpackReport, RenderError> {
+    ensure_target_dir(target_dir)?;
+    let (readers, sources): (Vec<_>, Vec<_>) = layer_files
+        .iter()
+        .enumerate()
+        .map(|(index, p)| open_layer(index, p.as_ref(), options))
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .unzip();
+
+    let mut report = UnpackReport::default();
+    let readers = readers.into_iter().map(io::BufReader::new);
+    let diff_ids = for_each_layer(readers, options, |index, reader| {
+        let stats = unpack_layer(
+            index,
+            reader,
+            target_dir,
+            options,
+            Whiteouts::Apply,
+            Some(report.skipped_mut()),
+            sources[index].as_ref(),
+        )?;
+        report.push_layer(stats);
+        Ok(())
+    })?;
+    report.set_diff_ids(diff_ids);
+    Ok(report)
+}
+
+/// Open a layer file, along with a second handle to copy data from if the
+/// layer is an uncompressed tar and acceleration is supported.
+fn open_layer(
+    index: usize,
+    layer_path: &path::Path,
+    options: &UnpackOptions,
+) -> Result<(fs::File, Option<fs::File>), RenderError> {
+    let mut file = fs::File::open(layer_path)?;
+    if !cfg!(target_os = "linux") {
+        return Ok((file, None));
+    }
+    let compression = match options.compression_for(index) {
+        LayerCompression::Auto => {
+            let mut magic = [0u8; 4];
+            let len = file.read(&mut magic)?;
+            file.rewind()?;
+            LayerCompression::detect(&magic[..len])
+        }
+        c => c,
+    };
+    let source = match compression {
+        LayerCompression::Uncompressed => Some(fs::File::open(layer_path)?),
+        _ => None,
+    };
+    Ok((file, source))
+}
+
+/// Extract a regular file entry by copying its data range out of `source`,
+/// the uncompressed layer file, instead of streaming it.
+///
+/// On filesystems supporting it (e.g. btrfs or XFS), `copy_file_range`
+/// shares extents instead of duplicating data. Returns `false` if the entry
+/// is not eligible, in which case the caller must extract it as usual.
+pub(crate) fn copy_entry<R: Read>(
+    source: &fs::File,
+    file: &mut tar::Entry<R>,
+    abs_path: &path::Path,
+    options: &UnpackOptions,
+) -> Result<bool, RenderError> {
+    if file.header().entry_type() != tar::EntryType::Regular || file.size() < COPY_THRESHOLD {
+        return Ok(false);
+    }
+    // Extended attributes are only handled by tar itself.
+    if let Some(extensions) = file.pax_extensions()? {
+        for ext in extensions {
+            if ext?.key_bytes().starts_with(PAX_XATTR_PREFIX.as_bytes()) {
+                return Ok(false);
+            }
+        }
+    }
+
+    if let Some(parent) = abs_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    // Like tar, replace any existing non-directory entry.
+    match fs::symlink_metadata(abs_path) {
+        Ok(meta) if !meta.is_dir() => fs::remove_file(abs_path)?,
+        _ => {}
+    }
+    let dest = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(abs_path)?;
+    sys::copy_range(source, file.raw_file_position(), &dest, file.size())?;
+
+    let mut mode = file.header().mode()? & 0o7777;
+    if options.rootless_active() {
+        mode &= !ROOTLESS_MODE_MASK;
+    }
+    sys::set_mode(&dest, mode)?;
+    if !options.discard_mtimes {
+        let mtime = filetime::FileTime::from_unix_time(file.header().mtime()? as i64, 0);
+        filetime::set_file_handle_times(&dest, Some(mtime), Some(mtime))?;
+    }
+    Ok(true)
+}
+
+#[cfg(target_os = "linux")]
+mod sys {
+    use std::os::unix::fs::{FileExt, PermissionsExt};
+    use std::os::unix::io::AsRawFd;
+    use std::{fs, io};
+
+    /// Copy `len` bytes at `offset` in `source` to the start of `dest`.
+    pub(super) fn copy_range(
+        source: &fs::File,
+        offset: u64,
+        dest: &fs::File,
+        len: u64,
+    ) -> io::Result<()> {
+        let mut off_in = offset as libc::loff_t;
+        let mut remaining = len;
+        while remaining > 0 {
+            let chunk = remaining.min(1 << 30) as usize;
+            // SAFETY: both descriptors are valid for the duration of the call,
+            // and `off_in` is a valid pointer.
+            let ret = unsafe {
+                libc::copy_file_range(
+                    source.as_raw_fd(),
+                    &mut off_in,
+                    dest.as_raw_fd(),
+                    std::ptr::null_mut(),
+                    chunk,
+                    0,
+                )
+            };
+            if ret < 0 {
+                let err = io::Error::last_os_error();
+                return match err.raw_os_error() {
+                    // Unsupported across these files, fall back to plain copies.
+                    Some(libc::ENOSYS)
+                    | Some(libc::EXDEV)
+                    | Some(libc::EINVAL)
+                    | Some(libc::EOPNOTSUPP) => {
+                        copy_range_fallback(source, off_in as u64, dest, remaining)
+                    }
+                    _ => Err(err),
+                };
+            }
+            if ret == 0 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            remaining -= ret as u64;
+        }
+        Ok(())
+    }
+
+    /// Copy a range through userspace, appending to `dest`.
+    fn copy_range_fallback(
+        source: &fs::File,
+        mut offset: u64,
+        mut dest: &fs::File,
+        mut remaining: u64,
+    ) -> io::Result<()> {
+        let mut buf = vec![0u8; 64 * 1024];
+        while remaining > 0 {
+            let want = remaining.min(buf.len() as u64) as usize;
+            let n = source.read_at(&mut buf[..want], offset)?;
+            if n == 0 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            io::Write::write_all(&mut dest, &buf[..n])?;
+            offset += n as u64;
+            remaining -= n as u64;
+        }
+        Ok(())
+    }
+
+    pub(super) fn set_mode(dest: &fs::File, mode: u32) -> io::Result<()> {
+        dest.set_permissions(fs::Permissions::from_mode(mode))
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod sys {
+    use std::{fs, io};
+
+    pub(super) fn copy_range(
+        _source: &fs::File,
+        _offset: u64,
+        _dest: &fs::File,
+        _len: u64,
+    ) -> io::Result<()> {
+        Err(io::Error::from(io::ErrorKind::Unsupported))
+    }
+
+    pub(super) fn set_mode(_dest: &fs::File, _mode: u32) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,135 @@
+//! Changesets between rendered images.
+
+use super::listing::{EntryKind, ListedEntry, Listing};
+use super::{list, RenderError, UnpackOptions};
+use std::collections::{BTreeMap, HashSet};
+use std::{fmt, path};
+
+/// Kind of a change to a path, as reported by `docker diff`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
+pub enum ChangeKind {
+    #[strum(serialize = "A")]
+    Added,
+    #[strum(serialize = "C")]
+    Modified,
+    #[strum(serialize = "D")]
+    Deleted,
+}
+
+/// A change to a path of the rendered filesystem.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    /// Path relative to the root of the image.
+    pub path: path::PathBuf,
+    pub kind: ChangeKind,
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} /{}", self.kind, self.path.display())
+    }
+}
+
+/// Compute the changes turning the filesystem of image `a` into that of image `b`.
+///
+/// Both images are rendered in memory (see `list`) with the same `options`.
+/// See `Listing::changes` for the semantics of the returned changeset.
+pub fn diff(
+    a_layers: &[Vec<u8>],
+    b_layers: &[Vec<u8>],
+    options: &UnpackOptions,
+) -> Result<Vec<Change>, RenderError> {
+    let a = list(a_layers, options)?;
+    let b = list(b_layers, options)?;
+    Ok(b.changes(&a))
+}
+
+/// Compute the changes turning directory `a` into directory `b`.
+///
+/// Both directories must hold unpacked filesystems; they are compared on
+/// metadata only, like `Listing::changes` does.
+pub fn diff_dirs(a: &path::Path, b: &path::Path) -> Result<Vec<Change>, RenderError> {
+    let a = Listing::from_dir(a)?;
+    let b = Listing::from_dir(b)?;
+    Ok(b.changes(&a))
+}
+
+impl Listing {
+    /// Compute the changes turning `base` into this listing, sorted by path.
+    ///
+    /// This follows Docker changeset semantics: every added path is reported,
+    /// only the topmost deleted path is, and directories containing changes are
+    /// reported as modified. Entries are compared on their metadata (kind,
+    /// mode, owner, link target and, except for directories, size and mtime)
+    /// rather than on their content.
+    pub fn changes(&self, base: &Listing) -> Vec<Change> {
+        let base_dirs = implied_dirs(base);
+        let dirs = implied_dirs(self);
+        let mut changes = BTreeMap::new();
+
+        for entry in self.entries() {
+            let kind = match base.get(&entry.path) {
+                None if base_dirs.contains(entry.path.as_path()) => {
+                    if entry.kind == EntryKind::Directory {
+                        continue;
+                    }
+                    ChangeKind::Modified
+                }
+                None => ChangeKind::Added,
+                Some(old) if differs(old, entry) => ChangeKind::Modified,
+                Some(_) => continue,
+            };
+            changes.insert(entry.path.clone(), kind);
+        }
+
+        let mut deleted: Option<&path::Path> = None;
+        for entry in base.entries() {
+            if deleted.is_some_and(|d| entry.path.starts_with(d)) {
+                continue;
+            }
+            if self.get(&entry.path).is_none() && !dirs.contains(entry.path.as_path()) {
+                changes.insert(entry.path.clone(), ChangeKind::Deleted);
+                deleted = Some(&entry.path);
+            }
+        }
+
+        let changed: Vec<_> = changes.keys().cloned().collect();
+        for p in changed {
+            for ancestor in p.ancestors().skip(1) {
+                if ancestor.as_os_str().is_empty() {
+                    break;
+                }
+                if self.get(ancestor).is_none() && !dirs.contains(ancestor) {
+                    continue;
+                }
+                changes
+                    .entry(ancestor.to_path_buf())
+                    .or_insert(ChangeKind::Modified);
+            }
+        }
+
+        changes
+            .into_iter()
+            .map(|(path, kind)| Change { path, kind })
+            .collect()
+    }
+}
+
+/// Whether an entry has been modified between two listings.
+fn differs(old: &ListedEntry, new: &ListedEntry) -> bool {
+    old.kind != new.kind
+        || old.mode != new.mode
+        || old.uid != new.uid
+        || old.gid != new.gid
+        || old.link_target != new.link_target
+        || (new.kind != EntryKind::Directory && (old.size != new.size || old.mtime != new.mtime))
+}
+
+/// Directories implied by the entries of `listing`, even if not listed themselves.
+fn implied_dirs(listing: &Listing) -> HashSet<&path::Path> {
+    listing
+        .entries()
+        .flat_map(|e| e.path.ancestors().skip(1))
+        .filter(|p| !p.as_os_str().is_empty())
+        .collect()
+}
@@ -0,0 +1,66 @@
+//! Extraction of single paths out of a layered image.
+
+use super::{
+    list, normalize_path, unpack_with_options, EntryKind, RenderError, UnpackOptions, UnpackReport,
+};
+use std::io::Read;
+use std::path;
+
+/// Read the content of the file at `path`, as seen on top of `layers`.
+///
+/// The path is resolved through the layer stack, honoring whiteouts and
+/// overrides from upper layers, and following symlinks and hardlinks
+/// within the image. Only the layer providing the file is read again.
+pub fn read_file<P: AsRef<path::Path>>(
+    layers: &[Vec<u8>],
+    path: P,
+    options: &UnpackOptions,
+) -> Result<Vec<u8>, RenderError> {
+    let path = path.as_ref();
+    let listing = list(layers, options)?;
+    let not_found = || RenderError::NotFound(path.to_path_buf());
+    let mut entry = listing.resolve(path).ok_or_else(not_found)?;
+    if entry.kind == EntryKind::Hardlink {
+        let target = entry.link_target.as_deref().ok_or_else(not_found)?;
+        entry = listing.get(target).ok_or_else(not_found)?;
+    }
+    if entry.kind != EntryKind::File {
+        return Err(RenderError::NotAFile(path.to_path_buf()));
+    }
+
+    let layer = layers.get(entry.layer).ok_or_else(not_found)?;
+    let decoder = options
+        .compression_for(entry.layer)
+        .decoder(layer.as_slice())?;
+    let mut archive = tar::Archive::new(decoder);
+    for file in archive.entries()? {
+        let mut file = file?;
+        if normalize_path(&file.path()?) == entry.path {
+            let mut content = Vec::with_capacity(entry.size as usize);
+            file.read_to_end(&mut content)?;
+            return Ok(content);
+        }
+    }
+    Err(not_found())
+}
+
+/// Unpack only the subtree at `path`, as seen on top of `layers`, to `target_dir`.
+///
+/// Symlinks in `path` are resolved within the image, then the resolved
+/// subtree is extracted at its own location below `target_dir` (along with
+/// its parent directories). This overrides paths set via
+/// `UnpackOptions::include_paths`.
+pub fn extract_path<P: AsRef<path::Path>>(
+    layers: &[Vec<u8>],
+    path: P,
+    target_dir: &path::Path,
+    options: &UnpackOptions,
+) -> Result<UnpackReport, RenderError> {
+    let path = path.as_ref();
+    let listing = list(layers, options)?;
+    let entry = listing
+        .resolve(path)
+        .ok_or_else(|| RenderError::NotFound(path.to_path_buf()))?;
+    let options = options.clone().include_paths(vec![entry.path.clone()]);
+    unpack_with_options(layers, target_dir, &options)
+}
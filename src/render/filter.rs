@@ -0,0 +1,46 @@
+//! Selection of archive entries to extract.
+
+use std::path;
+use std::sync::Arc;
+
+/// Shared entry predicate, as stored by `UnpackOptions`.
+#[derive(Clone)]
+pub(crate) struct PathPredicate(Arc<dyn Fn(&path::Path) -> bool + Send + Sync>);
+
+impl PathPredicate {
+    pub(crate) fn new<F>(predicate: F) -> Self
+    where
+        F: Fn(&path::Path) -> bool + Send + Sync + 'static,
+    {
+        Self(Arc::new(predicate))
+    }
+}
+
+impl std::fmt::Debug for PathPredicate {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("PathPredicate")
+    }
+}
+
+/// Filter deciding which entries get extracted.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct PathFilter {
+    /// Normalized relative prefixes; empty means no prefix restriction.
+    pub(crate) prefixes: Vec<path::PathBuf>,
+    pub(crate) predicate: Option<PathPredicate>,
+}
+
+impl PathFilter {
+    /// Whether the entry at the normalized relative `rel_path` must be extracted.
+    ///
+    /// Ancestors of a prefix are retained too, so that parent directories
+    /// get created with their archived metadata.
+    pub(crate) fn matches(&self, rel_path: &path::Path, is_dir: bool) -> bool {
+        let prefix_match = self.prefixes.is_empty()
+            || self
+                .prefixes
+                .iter()
+                .any(|p| rel_path.starts_with(p) || (is_dir && p.starts_with(rel_path)));
+        prefix_match && self.predicate.as_ref().map_or(true, |f| (f.0)(rel_path))
+    }
+}
@@ -0,0 +1,100 @@
+//! Merging of layers into a single archive.
+
+use super::{list, normalize_path, EntryKind, RenderError, UnpackOptions};
+use std::io::{Read, Write};
+use std::path;
+
+/// PAX records which are regenerated when re-emitting an entry.
+const PAX_REGENERATED: &[&str] = &["path", "linkpath", "size"];
+
+/// Flatten an ordered list of layers into a single uncompressed tar archive.
+///
+/// The archive is written to `writer`, which is handed back on success.
+/// Whiteouts are applied across layers, so that the resulting archive
+/// contains exactly the filesystem which `unpack` would produce.
+pub fn flatten<W: Write>(layers: &[Vec<u8>], writer: W) -> Result<W, RenderError> {
+    flatten_with_options(layers, writer, &UnpackOptions::default())
+}
+
+/// Flatten an ordered list of layers into a single tar archive, with custom options.
+///
+/// Compression and path-filtering options are honored; options which only
+/// make sense when writing to a directory are ignored.
+pub fn flatten_with_options<W: Write>(
+    layers: &[Vec<u8>],
+    writer: W,
+    options: &UnpackOptions,
+) -> Result<W, RenderError> {
+    if let Some(canonical) = options.canonical {
+        let archive = flatten_entries(layers, Vec::new(), options)?;
+        let mut builder = tar::Builder::new(writer);
+        canonical.rewrite(&archive, &mut builder)?;
+        return builder.into_inner().map_err(Into::into);
+    }
+    flatten_entries(layers, writer, options)
+}
+
+/// Copy the entries surviving across `layers` to an archive in `writer`.
+fn flatten_entries<W: Write>(
+    layers: &[Vec<u8>],
+    writer: W,
+    options: &UnpackOptions,
+) -> Result<W, RenderError> {
+    // First pass: find out which layer provides each surviving entry.
+    let listing = list(layers, options)?;
+
+    // Second pass: copy each surviving entry from its providing layer.
+    let mut builder = tar::Builder::new(writer);
+    for (index, l) in layers.iter().enumerate() {
+        if options.skips_layer(index)? {
+            continue;
+        }
+        let decoder = options.compression_for(index).decoder(l.as_slice())?;
+        let mut archive = tar::Archive::new(decoder);
+        for entry in archive.entries()? {
+            let mut file = entry?;
+            let rel_path = normalize_path(&file.path()?);
+            match listing.get(&rel_path) {
+                Some(listed) if listed.layer == index => {}
+                _ => continue,
+            };
+            append_entry(&mut builder, &rel_path, &mut file)?;
+        }
+    }
+    builder.into_inner().map_err(Into::into)
+}
+
+/// Copy an archive entry, including its PAX extensions, to `builder`.
+pub(crate) fn append_entry<W: Write, R: Read>(
+    builder: &mut tar::Builder<W>,
+    rel_path: &path::Path,
+    file: &mut tar::Entry<R>,
+) -> Result<(), RenderError> {
+    let mut header = file.header().clone();
+    let link_target = file.link_name()?.map(|l| l.into_owned());
+
+    let mut pax_records = Vec::new();
+    if let Some(extensions) = file.pax_extensions()? {
+        for ext in extensions {
+            let ext = ext?;
+            let key = ext
+                .key()
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))?;
+            if !PAX_REGENERATED.contains(&key) {
+                pax_records.push((key.to_string(), ext.value_bytes().to_vec()));
+            }
+        }
+    }
+    if !pax_records.is_empty() {
+        builder
+            .append_pax_extensions(pax_records.iter().map(|(k, v)| (k.as_str(), v.as_slice())))?;
+    }
+
+    match (EntryKind::from(header.entry_type()), link_target) {
+        (EntryKind::Symlink, Some(target)) | (EntryKind::Hardlink, Some(target)) => {
+            builder.append_link(&mut header, rel_path, target)?
+        }
+        _ => builder.append_data(&mut header, rel_path, file)?,
+    };
+    Ok(())
+}
@@ -0,0 +1,116 @@
+//! Resource limits protecting against decompression bombs.
+
+use super::RenderError;
+use std::cell::Cell;
+use std::io::{self, Read};
+use std::path;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// A resource limit which can be exceeded while unpacking.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
+pub enum LimitKind {
+    /// Uncompressed size of a single layer.
+    #[strum(serialize = "layer size")]
+    LayerSize,
+    /// Uncompressed size of all layers.
+    #[strum(serialize = "total size")]
+    TotalSize,
+    /// Number of entries in a single layer.
+    #[strum(serialize = "entry count")]
+    Entries,
+    /// Number of components in an entry path.
+    #[strum(serialize = "path depth")]
+    PathDepth,
+}
+
+/// Configured limits, as stored by `UnpackOptions`.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Limits {
+    pub(crate) layer_size: Option<u64>,
+    pub(crate) total_size: Option<u64>,
+    pub(crate) entries: Option<u64>,
+    pub(crate) path_depth: Option<usize>,
+}
+
+impl Limits {
+    /// Check the `count`-th entry of layer `layer`, at `rel_path`.
+    pub(crate) fn check_entry(
+        &self,
+        layer: usize,
+        count: u64,
+        rel_path: &path::Path,
+    ) -> Result<(), RenderError> {
+        let exceeded = |limit| RenderError::LimitExceeded { layer, limit };
+        if self.entries.is_some_and(|max| count > max) {
+            return Err(exceeded(LimitKind::Entries));
+        }
+        if self
+            .path_depth
+            .is_some_and(|max| rel_path.components().count() > max)
+        {
+            return Err(exceeded(LimitKind::PathDepth));
+        }
+        Ok(())
+    }
+}
+
+/// A reader enforcing size limits on an uncompressed layer.
+///
+/// Once a limit is exceeded, reads fail and the exceeded limit is recorded
+/// in a shared handle, so that it can be reported with a typed error.
+pub(crate) struct LimitedReader<'a, R> {
+    inner: R,
+    limits: &'a Limits,
+    layer_bytes: u64,
+    total_bytes: &'a AtomicU64,
+    exceeded: Rc<Cell<Option<LimitKind>>>,
+}
+
+impl<'a, R: Read> LimitedReader<'a, R> {
+    /// Wrap `inner`, accounting its size into the shared `total_bytes`.
+    ///
+    /// Returns the reader and a handle to the exceeded limit, if any.
+    pub(crate) fn new(
+        inner: R,
+        limits: &'a Limits,
+        total_bytes: &'a AtomicU64,
+    ) -> (Self, Rc<Cell<Option<LimitKind>>>) {
+        let exceeded = Rc::new(Cell::new(None));
+        let reader = Self {
+            inner,
+            limits,
+            layer_bytes: 0,
+            total_bytes,
+            exceeded: exceeded.clone(),
+        };
+        (reader, exceeded)
+    }
+}
+
+impl<R: Read> Read for LimitedReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.layer_bytes += n as u64;
+        let total = self.total_bytes.fetch_add(n as u64, Ordering::Relaxed) + n as u64;
+        let exceeded = if self
+            .limits
+            .layer_size
+            .is_some_and(|max| self.layer_bytes > max)
+        {
+            Some(LimitKind::LayerSize)
+        } else if self.limits.total_size.is_some_and(|max| total > max) {
+            Some(LimitKind::TotalSize)
+        } else {
+            None
+        };
+        if let Some(limit) = exceeded {
+            self.exceeded.set(Some(limit));
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!("{} limit exceeded", limit),
+            ));
+        }
+        Ok(n)
+    }
+}
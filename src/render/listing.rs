@@ -0,0 +1,368 @@
+//! Dry-run rendering of layers, without touching the filesystem.
+
+use super::{
+    is_device_whiteout, normalize_path, RenderError, UnpackOptions, WHITEOUT_OPAQUE,
+    WHITEOUT_PREFIX,
+};
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::ops::Bound;
+use std::{fs, path};
+
+/// Maximum number of symlinks followed while resolving a path.
+const MAX_SYMLINK_HOPS: usize = 40;
+
+/// Type of a filesystem entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryKind {
+    File,
+    Directory,
+    Symlink,
+    Hardlink,
+    CharDevice,
+    BlockDevice,
+    Fifo,
+    Other,
+}
+
+impl From<tar::EntryType> for EntryKind {
+    fn from(entry_type: tar::EntryType) -> Self {
+        match entry_type {
+            tar::EntryType::Regular | tar::EntryType::Continuous => EntryKind::File,
+            tar::EntryType::Directory => EntryKind::Directory,
+            tar::EntryType::Symlink => EntryKind::Symlink,
+            tar::EntryType::Link => EntryKind::Hardlink,
+            tar::EntryType::Char => EntryKind::CharDevice,
+            tar::EntryType::Block => EntryKind::BlockDevice,
+            tar::EntryType::Fifo => EntryKind::Fifo,
+            _ => EntryKind::Other,
+        }
+    }
+}
+
+impl From<fs::FileType> for EntryKind {
+    fn from(file_type: fs::FileType) -> Self {
+        if file_type.is_file() {
+            EntryKind::File
+        } else if file_type.is_dir() {
+            EntryKind::Directory
+        } else if file_type.is_symlink() {
+            EntryKind::Symlink
+        } else {
+            sys::special_kind(file_type)
+        }
+    }
+}
+
+/// An entry of the rendered filesystem.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListedEntry {
+    /// Path relative to the root of the image.
+    pub path: path::PathBuf,
+    pub kind: EntryKind,
+    /// Content size in bytes, for regular files.
+    pub size: u64,
+    pub mode: u32,
+    pub uid: u64,
+    pub gid: u64,
+    /// Modification time, in seconds since the Unix epoch.
+    pub mtime: u64,
+    /// Target of symlinks and hardlinks.
+    pub link_target: Option<path::PathBuf>,
+    /// Index of the layer providing this entry.
+    pub layer: usize,
+}
+
+/// A whiteout applied while rendering.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppliedWhiteout {
+    /// Index of the layer containing the whiteout.
+    pub layer: usize,
+    /// Path being deleted or, for opaque whiteouts, directory being cleared.
+    pub path: path::PathBuf,
+    pub opaque: bool,
+    /// Number of lower-layers entries removed by this whiteout.
+    pub removed: usize,
+}
+
+/// Listing of the filesystem which would result from unpacking some layers.
+#[derive(Debug, Clone, Default)]
+pub struct Listing {
+    entries: BTreeMap<path::PathBuf, ListedEntry>,
+    whiteouts: Vec<AppliedWhiteout>,
+}
+
+impl Listing {
+    /// Entries of the rendered filesystem, sorted by path.
+    pub fn entries(&self) -> impl Iterator<Item = &ListedEntry> {
+        self.entries.values()
+    }
+
+    /// Look up the entry at `path`, relative to the root of the image.
+    pub fn get<P: AsRef<path::Path>>(&self, path: P) -> Option<&ListedEntry> {
+        self.entries.get(&normalize_path(path.as_ref()))
+    }
+
+    /// Look up the entry at `path`, following symlinks like the kernel would.
+    ///
+    /// Symlinks are resolved relative to the root of the image, both in
+    /// intermediate components and in the final one. Returns `None` if the
+    /// path does not exist or if resolution loops.
+    pub fn resolve<P: AsRef<path::Path>>(&self, path: P) -> Option<&ListedEntry> {
+        let mut pending = Vec::new();
+        push_components(&mut pending, path.as_ref());
+        let mut current = path::PathBuf::new();
+        let mut hops = 0;
+        while let Some(component) = pending.pop() {
+            if component == ".." {
+                current.pop();
+                continue;
+            }
+            let candidate = current.join(&component);
+            match self.entries.get(&candidate) {
+                Some(entry) if entry.kind == EntryKind::Symlink => {
+                    hops += 1;
+                    if hops > MAX_SYMLINK_HOPS {
+                        return None;
+                    }
+                    let target = entry.link_target.as_deref()?;
+                    if target.has_root() {
+                        current = path::PathBuf::new();
+                    }
+                    push_components(&mut pending, target);
+                }
+                Some(entry) if entry.kind != EntryKind::Directory && !pending.is_empty() => {
+                    return None
+                }
+                Some(_) => current = candidate,
+                // Parent directories may be implied by their content.
+                None if !pending.is_empty() => current = candidate,
+                None => return None,
+            }
+        }
+        self.entries.get(&current)
+    }
+
+    /// Whiteouts applied across all layers, in application order.
+    pub fn whiteouts(&self) -> &[AppliedWhiteout] {
+        &self.whiteouts
+    }
+
+    /// Total size of regular files content, in bytes.
+    pub fn total_size(&self) -> u64 {
+        self.entries().map(|e| e.size).sum()
+    }
+
+    /// List the content of an unpacked filesystem rooted at `root`.
+    ///
+    /// Symlinks are not followed, and hardlinks are listed as regular files.
+    pub(crate) fn from_dir(root: &path::Path) -> Result<Self, RenderError> {
+        let mut listing = Listing::default();
+        let mut pending = vec![path::PathBuf::new()];
+        while let Some(rel_dir) = pending.pop() {
+            for dirent in fs::read_dir(root.join(&rel_dir))? {
+                let dirent = dirent?;
+                let rel_path = rel_dir.join(dirent.file_name());
+                let meta = dirent.metadata()?;
+                let kind = EntryKind::from(meta.file_type());
+                if kind == EntryKind::Directory {
+                    pending.push(rel_path.clone());
+                }
+                let link_target = match kind {
+                    EntryKind::Symlink => Some(fs::read_link(dirent.path())?),
+                    _ => None,
+                };
+                let (mode, uid, gid) = sys::owner_and_mode(&meta);
+                let mtime = meta
+                    .modified()?
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map_or(0, |d| d.as_secs());
+                let listed = ListedEntry {
+                    path: rel_path.clone(),
+                    kind,
+                    size: if kind == EntryKind::File {
+                        meta.len()
+                    } else {
+                        0
+                    },
+                    mode,
+                    uid,
+                    gid,
+                    mtime,
+                    link_target,
+                    layer: 0,
+                };
+                listing.entries.insert(rel_path, listed);
+            }
+        }
+        Ok(listing)
+    }
+
+    /// Apply a single uncompressed layer on top of this listing.
+    pub(crate) fn apply_layer<R: Read>(
+        &mut self,
+        index: usize,
+        reader: R,
+        options: &UnpackOptions,
+    ) -> Result<(), RenderError> {
+        let mut archive = tar::Archive::new(reader);
+        for (count, entry) in (1..).zip(archive.entries()?) {
+            let file = entry?;
+            let rel_path = normalize_path(&file.path()?);
+            options.limits.check_entry(index, count, &rel_path)?;
+            if !rel_path
+                .components()
+                .all(|c| matches!(c, path::Component::Normal(_)))
+            {
+                return Err(RenderError::PathEscape(rel_path));
+            }
+            let fname = match rel_path.file_name() {
+                Some(f) => f.to_string_lossy().into_owned(),
+                None => continue,
+            };
+            let rel_parent = rel_path
+                .parent()
+                .unwrap_or_else(|| path::Path::new(""))
+                .to_path_buf();
+
+            if fname == WHITEOUT_OPAQUE {
+                let removed = self.remove_descendants(&rel_parent, |e| e.layer != index);
+                self.whiteouts.push(AppliedWhiteout {
+                    layer: index,
+                    path: rel_parent,
+                    opaque: true,
+                    removed,
+                });
+            } else if let Some(real_name) = fname.strip_prefix(WHITEOUT_PREFIX) {
+                self.apply_whiteout(index, rel_parent.join(real_name));
+            } else if is_device_whiteout(file.header())? {
+                self.apply_whiteout(index, rel_path);
+            } else {
+                let header = file.header();
+                let kind = EntryKind::from(header.entry_type());
+                if !options
+                    .filter
+                    .matches(&rel_path, kind == EntryKind::Directory)
+                {
+                    continue;
+                }
+                if kind != EntryKind::Directory {
+                    self.remove_descendants(&rel_path, |_| true);
+                }
+                let listed = ListedEntry {
+                    path: rel_path.clone(),
+                    kind,
+                    size: if kind == EntryKind::File {
+                        file.size()
+                    } else {
+                        0
+                    },
+                    mode: header.mode()?,
+                    uid: header.uid()?,
+                    gid: header.gid()?,
+                    mtime: header.mtime()?,
+                    link_target: file.link_name()?.map(|l| l.into_owned()),
+                    layer: index,
+                };
+                self.entries.insert(rel_path, listed);
+            }
+        }
+        Ok(())
+    }
+
+    /// Apply a plain whiteout of `rel_path` found in layer `index`.
+    fn apply_whiteout(&mut self, index: usize, rel_path: path::PathBuf) {
+        let removed = self.remove_tree(&rel_path);
+        self.whiteouts.push(AppliedWhiteout {
+            layer: index,
+            path: rel_path,
+            opaque: false,
+            removed,
+        });
+    }
+
+    /// Remove the entry at `rel_path` and everything below it.
+    fn remove_tree(&mut self, rel_path: &path::Path) -> usize {
+        let removed = self.remove_descendants(rel_path, |_| true);
+        removed + self.entries.remove(rel_path).map_or(0, |_| 1)
+    }
+
+    /// Remove entries strictly below `rel_dir` which satisfy `select`.
+    fn remove_descendants<F>(&mut self, rel_dir: &path::Path, select: F) -> usize
+    where
+        F: Fn(&ListedEntry) -> bool,
+    {
+        let lower = if rel_dir.as_os_str().is_empty() {
+            Bound::Unbounded
+        } else {
+            Bound::Excluded(rel_dir.to_path_buf())
+        };
+        let doomed: Vec<_> = self
+            .entries
+            .range((lower, Bound::Unbounded))
+            .take_while(|(p, _)| p.starts_with(rel_dir))
+            .filter(|(_, e)| select(e))
+            .map(|(p, _)| p.clone())
+            .collect();
+        for p in &doomed {
+            self.entries.remove(p);
+        }
+        doomed.len()
+    }
+}
+
+/// Push the components of `p` to `stack`, in reverse order, skipping
+/// root and current-directory components.
+fn push_components(stack: &mut Vec<std::ffi::OsString>, p: &path::Path) {
+    let start = stack.len();
+    for component in p.components() {
+        match component {
+            path::Component::Normal(name) => stack.push(name.to_os_string()),
+            path::Component::ParentDir => stack.push("..".into()),
+            _ => {}
+        }
+    }
+    stack[start..].reverse();
+}
+
+#[cfg(unix)]
+mod sys {
+    use super::EntryKind;
+    use std::fs;
+    use std::os::unix::fs::{FileTypeExt, MetadataExt};
+
+    pub(super) fn special_kind(file_type: fs::FileType) -> EntryKind {
+        if file_type.is_char_device() {
+            EntryKind::CharDevice
+        } else if file_type.is_block_device() {
+            EntryKind::BlockDevice
+        } else if file_type.is_fifo() {
+            EntryKind::Fifo
+        } else {
+            EntryKind::Other
+        }
+    }
+
+    pub(super) fn owner_and_mode(meta: &fs::Metadata) -> (u32, u64, u64) {
+        (meta.mode() & 0o7777, meta.uid().into(), meta.gid().into())
+    }
+}
+
+#[cfg(not(unix))]
+mod sys {
+    use super::EntryKind;
+    use std::fs;
+
+    pub(super) fn special_kind(_file_type: fs::FileType) -> EntryKind {
+        EntryKind::Other
+    }
+
+    pub(super) fn owner_and_mode(meta: &fs::Metadata) -> (u32, u64, u64) {
+        let mode = if meta.permissions().readonly() {
+            0o444
+        } else {
+            0o644
+        };
+        (mode, 0, 0)
+    }
+}
@@ -0,0 +1,1770 @@
+//! Render a docker image.
+
+// Docker image format is specified at
+// https://github.com/moby/moby/blob/v17.05.0-ce/image/spec/v1.md
+
+use std::collections::HashSet;
+use std::io::Read;
+use std::sync::atomic::AtomicU64;
+use std::{fs, io, path};
+
+mod async_io;
+pub use self::async_io::{
+    unpack_async, unpack_streaming_async, unpack_streaming_async_with_options,
+};
+
+mod bundle;
+pub use self::bundle::{to_runtime_bundle, to_runtime_bundle_with_options};
+
+mod canonical;
+
+mod compression;
+pub use self::compression::LayerCompression;
+
+mod copy;
+pub use self::copy::unpack_layer_files;
+
+mod containment;
+use self::containment::{contained_dir, contained_path};
+
+mod diff;
+pub use self::diff::{diff, diff_dirs, Change, ChangeKind};
+
+mod extract;
+pub use self::extract::{extract_path, read_file};
+
+mod filter;
+
+mod flatten;
+pub use self::flatten::{flatten, flatten_with_options};
+
+mod limits;
+pub use self::limits::LimitKind;
+use self::limits::LimitedReader;
+
+mod listing;
+pub use self::listing::{AppliedWhiteout, EntryKind, ListedEntry, Listing};
+
+mod options;
+pub use self::options::UnpackOptions;
+
+mod overlay;
+pub use self::overlay::{unpack_overlay, unpack_overlay_streaming};
+
+mod ownership;
+pub use self::ownership::IdMapping;
+
+mod pack;
+pub use self::pack::{
+    pack, pack_changes, pack_changes_with_options, pack_with_options, PackOptions,
+};
+
+mod parallel;
+
+mod progress;
+pub use self::progress::UnpackProgress;
+
+mod report;
+pub use self::report::{LayerStats, SkippedEntry, UnpackReport};
+
+mod verify;
+use self::verify::{DigestKind, DigestReader};
+
+mod xattrs;
+pub use self::xattrs::XattrClass;
+
+#[derive(Debug, thiserror::Error)]
+pub enum RenderError {
+    #[error("wrong target path {}: must be absolute path to existing directory", _0.display())]
+    WrongTargetPath(path::PathBuf),
+    #[error("io error")]
+    Io(#[from] std::io::Error),
+    #[error("unpacking task failed")]
+    Task(#[from] tokio::task::JoinError),
+    #[error("unsupported layer compression {0:?}")]
+    UnsupportedCompression(LayerCompression),
+    #[error("owner ID {0} of {} is not mapped", _1.display())]
+    UnmappedId(u64, path::PathBuf),
+    #[error("archive path {} escapes the target directory", _0.display())]
+    PathEscape(path::PathBuf),
+    #[error("path {} not found in image", _0.display())]
+    NotFound(path::PathBuf),
+    #[error("path {} is not a regular file", _0.display())]
+    NotAFile(path::PathBuf),
+    #[error("invalid image config")]
+    ImageConfig(#[source] serde_json::Error),
+    #[error("image config has neither Entrypoint nor Cmd")]
+    MissingCommand,
+    #[error("user {0} not found in image")]
+    UnknownUser(String),
+    #[error("layer {layer} exceeds the {limit} limit")]
+    LimitExceeded { layer: usize, limit: LimitKind },
+    #[error("invalid expected digest")]
+    Digest(#[from] crate::v2::ContentDigestError),
+    #[error("layer {layer} digest mismatch: expected {expected}, got {got}")]
+    DigestMismatch {
+        layer: usize,
+        expected: String,
+        got: String,
+    },
+    #[error("layer {layer} is a foreign layer ({media_type}), its content is not distributed by registries")]
+    ForeignLayer { layer: usize, media_type: String },
+    #[error("layer {layer} DiffID mismatch: expected {expected}, got {got}")]
+    DiffIdMismatch {
+        layer: usize,
+        expected: String,
+        got: String,
+    },
+}
+
+/// Prefix marking a whiteout entry, which deletes a path from lower layers.
+const WHITEOUT_PREFIX: &str = ".wh.";
+
+/// Permission bits cleared in rootless mode (setuid and setgid).
+const ROOTLESS_MODE_MASK: u32 = 0o6000;
+
+/// Marker entry making a directory opaque, hiding all its lower-layers content.
+const WHITEOUT_OPAQUE: &str = ".wh..wh..opq";
+
+/// Whether `header` describes an overlayfs-style whiteout, i.e. a `0:0`
+/// character device standing for the deletion of its own path.
+fn is_device_whiteout(header: &tar::Header) -> io::Result<bool> {
+    Ok(header.entry_type() == tar::EntryType::Char
+        && header.device_major()? == Some(0)
+        && header.device_minor()? == Some(0))
+}
+
+/// How whiteout entries are handled when unpacking a layer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Whiteouts {
+    /// Delete whiteouted paths from the target directory.
+    Apply,
+    /// Translate whiteouts to their overlayfs encoding.
+    Overlay,
+}
+
+/// Unpack an ordered list of layers to a target directory.
+///
+/// Layers must be provided as tar archives (either plain or compressed, as
+/// detected from their content), with lower layers coming first.
+/// Target directory must be an existing absolute path.
+pub fn unpack(layers: &[Vec<u8>], target_dir: &path::Path) -> Result<UnpackReport, RenderError> {
+    unpack_with_options(layers, target_dir, &UnpackOptions::default())
+}
+
+/// Unpack an ordered list of layers to a target directory, with custom options.
+pub fn unpack_with_options(
+    layers: &[Vec<u8>],
+    target_dir: &path::Path,
+    options: &UnpackOptions,
+) -> Result<UnpackReport, RenderError> {
+    if options.decompression_workers > 1 {
+        return parallel::unpack_parallel(layers, target_dir, options);
+    }
+    unpack_streaming_with_options(layers.iter().map(Vec::as_slice), target_dir, options)
+}
+
+/// Unpack an ordered list of layers, read from streams, to a target directory.
+///
+/// This behaves like `unpack`, but each layer is consumed incrementally from
+/// its reader, so that layers do not have to be fully buffered in memory.
+pub fn unpack_streaming<I, R>(
+    layers: I,
+    target_dir: &path::Path,
+) -> Result<UnpackReport, RenderError>
+where
+    I: IntoIterator<Item = R>,
+    R: Read,
+{
+    unpack_streaming_with_options(layers, target_dir, &UnpackOptions::default())
+}
+
+/// Unpack an ordered list of layers, read from streams, with custom options.
+pub fn unpack_streaming_with_options<I, R>(
+    layers: I,
+    target_dir: &path::Path,
+    options: &UnpackOptions,
+) -> Result<UnpackReport, RenderError>
+where
+    I: IntoIterator<Item = R>,
+    R: Read,
+{
+    ensure_target_dir(target_dir)?;
+    let mut report = UnpackReport::default();
+    let diff_ids = for_each_layer(layers, options, |index, reader| {
+        let skipped = Some(report.skipped_mut());
+        let stats = unpack_layer(
+            index,
+            reader,
+            target_dir,
+            options,
+            Whiteouts::Apply,
+            skipped,
+            None,
+        )?;
+        report.push_layer(stats);
+        Ok(())
+    })?;
+    report.set_diff_ids(diff_ids);
+    Ok(report)
+}
+
+/// List the filesystem which would result from unpacking `layers`.
+///
+/// This is a dry-run of `unpack_with_options`: whiteouts and filters are
+/// applied, but nothing is written to the filesystem.
+pub fn list(layers: &[Vec<u8>], options: &UnpackOptions) -> Result<Listing, RenderError> {
+    list_streaming(layers.iter().map(Vec::as_slice), options)
+}
+
+/// List the filesystem which would result from unpacking layers read from streams.
+pub fn list_streaming<I, R>(layers: I, options: &UnpackOptions) -> Result<Listing, RenderError>
+where
+    I: IntoIterator<Item = R>,
+    R: Read,
+{
+    let mut listing = Listing::default();
+    for_each_layer(layers, options, |index, reader| {
+        listing.apply_layer(index, reader, options)
+    })?;
+    Ok(listing)
+}
+
+/// Decompress each layer and hand it to `f`, verifying digests afterwards.
+///
+/// Layers are hashed while being consumed; whatever `f` leaves unread is
+/// drained so that the whole layer is accounted for. The computed DiffIDs
+/// are returned in layer order.
+fn for_each_layer<I, R, F>(
+    layers: I,
+    options: &UnpackOptions,
+    mut f: F,
+) -> Result<Vec<String>, RenderError>
+where
+    I: IntoIterator<Item = R>,
+    R: Read,
+    F: FnMut(usize, &mut dyn Read) -> Result<(), RenderError>,
+{
+    let total_bytes = AtomicU64::new(0);
+    layers
+        .into_iter()
+        .enumerate()
+        .map(|(index, l)| read_layer(index, l, options, &total_bytes, &mut f))
+        .collect()
+}
+
+/// Decompress the layer at `index` and hand it to `f`, see `for_each_layer`.
+///
+/// Size limits are enforced on the uncompressed stream, with `total_bytes`
+/// accounting for all layers processed so far. Returns the DiffID of the layer,
+/// which is empty for skipped foreign layers.
+fn read_layer<R, F>(
+    index: usize,
+    layer: R,
+    options: &UnpackOptions,
+    total_bytes: &AtomicU64,
+    f: F,
+) -> Result<String, RenderError>
+where
+    R: Read,
+    F: FnOnce(usize, &mut dyn Read) -> Result<(), RenderError>,
+{
+    if options.skips_layer(index)? {
+        f(index, &mut io::empty())?;
+        return Ok(String::new());
+    }
+    let mut blob = DigestReader::verifying(layer, options.layer_digest_for(index))?;
+    let diff_id = {
+        let decoder = options.compression_for(index).decoder(&mut blob)?;
+        let (limited, exceeded) = LimitedReader::new(decoder, &options.limits, total_bytes);
+        let mut tar = DigestReader::hashing(limited, options.diff_id_for(index))?;
+        let res = f(index, &mut tar).and_then(|_| tar.finish(index, DigestKind::DiffId));
+        // The underlying error from the reader is opaque, report the limit instead.
+        if let Some(limit) = exceeded.get() {
+            return Err(RenderError::LimitExceeded {
+                layer: index,
+                limit,
+            });
+        }
+        res?
+    };
+    blob.finish(index, DigestKind::Blob)?;
+    Ok(diff_id.unwrap_or_default())
+}
+
+/// Check that `target_dir` is an absolute path to an existing directory.
+fn ensure_target_dir(target_dir: &path::Path) -> Result<(), RenderError> {
+    if !target_dir.is_absolute() || !target_dir.exists() || !target_dir.is_dir() {
+        return Err(RenderError::WrongTargetPath(target_dir.to_path_buf()));
+    }
+    Ok(())
+}
+
+/// Unpack a single uncompressed layer on top of the content of `target_dir`.
+///
+/// Whiteouts are applied while walking the archive: plain whiteouts (either
+/// `.wh.`-prefixed entries or `0:0` character devices) remove the matching
+/// path, while opaque whiteouts clear every entry in their directory which
+/// has not been written by this same layer. With `Whiteouts::Overlay`, they
+/// are instead recorded in overlayfs format.
+///
+/// When continuing on errors, entries which fail to extract are pushed
+/// to `skipped` (if provided) instead of aborting. If `source` is provided,
+/// it must be the uncompressed layer file, which large files get copied from.
+///
+/// All filesystem operations are confined to `target_dir`: entries trying to
+/// reach outside of it (via `..` components or symlinked parents) are rejected
+/// with `RenderError::PathEscape`.
+fn unpack_layer<R: Read>(
+    index: usize,
+    reader: R,
+    target_dir: &path::Path,
+    options: &UnpackOptions,
+    whiteouts: Whiteouts,
+    mut skipped: Option<&mut Vec<SkippedEntry>>,
+    source: Option<&fs::File>,
+) -> Result<LayerStats, RenderError> {
+    let report = |event: UnpackProgress| {
+        if let Some(progress) = &options.progress {
+            progress.report(&event);
+        }
+    };
+    report(UnpackProgress::LayerStarted { layer: index });
+
+    let root = target_dir.canonicalize()?;
+    let (reader, bytes_decompressed) = progress::CountingReader::new(reader);
+    let mut archive = tar::Archive::new(reader);
+    archive.set_preserve_permissions(true);
+    archive.set_preserve_mtime(!options.discard_mtimes);
+    // Filtered extended attributes are set after extraction instead.
+    archive.set_unpack_xattrs(options.xattrs.is_unfiltered());
+    if options.rootless_active() {
+        archive.set_mask(ROOTLESS_MODE_MASK);
+    }
+
+    // Relative paths (and their ancestors) provided by this layer.
+    let mut layer_paths = HashSet::new();
+    // Directories are applied last, so that their permissions do not
+    // interfere with the extraction of their content.
+    let mut directories = Vec::new();
+    let mut stats = LayerStats::default();
+    let mut entries_written = 0;
+    let mut entry_written = |path: path::PathBuf, kind: EntryKind, size: u64| {
+        stats.record(kind, size);
+        entries_written += 1;
+        report(UnpackProgress::EntryWritten {
+            layer: index,
+            path,
+            bytes_decompressed: bytes_decompressed.get(),
+            entries_written,
+        });
+    };
+
+    // Entries which cannot be extracted are either fatal or, when
+    // continuing on errors, recorded as skipped.
+    let mut skip = |rel_path: path::PathBuf, error: RenderError| match skipped.as_deref_mut() {
+        Some(skipped) if options.continue_on_error => {
+            skipped.push(SkippedEntry {
+                layer: index,
+                path: rel_path,
+                error,
+            });
+            Ok(())
+        }
+        _ => Err(error),
+    };
+
+    let mut entries_seen = 0;
+    let mut whiteouts_applied = 0;
+    for entry in archive.entries()? {
+        let mut file = entry?;
+        let rel_path = normalize_path(&file.path()?);
+        entries_seen += 1;
+        options.limits.check_entry(index, entries_seen, &rel_path)?;
+        match apply_entry(
+            &root,
+            &mut file,
+            &rel_path,
+            &mut layer_paths,
+            options,
+            whiteouts,
+            source,
+        ) {
+            Ok(EntryOutcome::Done) => {}
+            Ok(EntryOutcome::Whiteout) => whiteouts_applied += 1,
+            Ok(EntryOutcome::Written) => {
+                let kind = EntryKind::from(file.header().entry_type());
+                entry_written(rel_path, kind, file.size())
+            }
+            Ok(EntryOutcome::Deferred) => directories.push(file),
+            Err(e) => skip(rel_path, e)?,
+        }
+    }
+
+    // Apply directories in reverse order, so that parents come after children.
+    directories.sort_by(|a, b| b.path_bytes().cmp(&a.path_bytes()));
+    for mut dir in directories {
+        let rel_path = normalize_path(&dir.path()?);
+        let written = (|| {
+            // Parents may have been replaced in the meantime, check again.
+            let abs_path = contained_path(&root, &rel_path)?;
+            let written = dir.unpack_in(&root)?;
+            if written {
+                if !options.xattrs.is_unfiltered() {
+                    options.xattrs.apply(&mut dir, &abs_path)?;
+                }
+                apply_ownership(&abs_path, dir.header(), options)?;
+                // Directories mtimes are not handled by tar.
+                if !options.discard_mtimes {
+                    let mtime = filetime::FileTime::from_unix_time(dir.header().mtime()? as i64, 0);
+                    filetime::set_symlink_file_times(&abs_path, mtime, mtime)?;
+                }
+            }
+            Ok(written)
+        })();
+        match written {
+            Ok(true) => entry_written(rel_path, EntryKind::Directory, 0),
+            Ok(false) => {}
+            Err(e) => skip(rel_path, e)?,
+        }
+    }
+
+    report(UnpackProgress::LayerFinished {
+        layer: index,
+        bytes_decompressed: bytes_decompressed.get(),
+        entries_written,
+    });
+    stats.whiteouts = whiteouts_applied;
+    Ok(stats)
+}
+
+/// Result of applying a single archive entry.
+enum EntryOutcome {
+    /// Nothing was written (e.g. filtered out).
+    Done,
+    /// A whiteout has been applied.
+    Whiteout,
+    /// The entry has been written to the filesystem.
+    Written,
+    /// The entry is a directory, to be applied at the end of the layer.
+    Deferred,
+}
+
+/// Apply a single non-directory entry, or a whiteout, below `root`.
+fn apply_entry<R: Read>(
+    root: &path::Path,
+    file: &mut tar::Entry<R>,
+    rel_path: &path::Path,
+    layer_paths: &mut HashSet<path::PathBuf>,
+    options: &UnpackOptions,
+    whiteouts: Whiteouts,
+    source: Option<&fs::File>,
+) -> Result<EntryOutcome, RenderError> {
+    let fname = match rel_path.file_name() {
+        Some(f) => f.to_string_lossy().into_owned(),
+        None => return Ok(EntryOutcome::Done),
+    };
+    let rel_parent = rel_path.parent().unwrap_or_else(|| path::Path::new(""));
+
+    if fname == WHITEOUT_OPAQUE {
+        match whiteouts {
+            Whiteouts::Apply => clear_opaque_dir(root, rel_parent, layer_paths)?,
+            Whiteouts::Overlay => overlay::mark_opaque(root, rel_parent)?,
+        }
+        return Ok(EntryOutcome::Whiteout);
+    }
+    if let Some(real_name) = fname.strip_prefix(WHITEOUT_PREFIX) {
+        let real_path = rel_parent.join(real_name);
+        match whiteouts {
+            Whiteouts::Apply => remove_path(&contained_path(root, &real_path)?)?,
+            Whiteouts::Overlay => overlay::write_whiteout(root, &real_path)?,
+        }
+        return Ok(EntryOutcome::Whiteout);
+    }
+    if whiteouts == Whiteouts::Apply && is_device_whiteout(file.header())? {
+        remove_path(&contained_path(root, rel_path)?)?;
+        return Ok(EntryOutcome::Whiteout);
+    }
+
+    let entry_type = file.header().entry_type();
+    let is_dir = entry_type == tar::EntryType::Directory;
+    if !options.filter.matches(rel_path, is_dir) {
+        return Ok(EntryOutcome::Done);
+    }
+    if (entry_type.is_character_special() || entry_type.is_block_special())
+        && options.rootless_active()
+    {
+        warn!(
+            "skipping device node {} in rootless mode",
+            rel_path.display()
+        );
+        return Ok(EntryOutcome::Done);
+    }
+    let abs_path = contained_path(root, rel_path)?;
+    if entry_type == tar::EntryType::Link {
+        if let Some(target) = file.link_name()? {
+            contained_path(root, &normalize_path(&target))?;
+        }
+    }
+    layer_paths.extend(rel_path.ancestors().map(path::Path::to_path_buf));
+    if is_dir {
+        return Ok(EntryOutcome::Deferred);
+    }
+    let copied = match source {
+        Some(source) => copy::copy_entry(source, file, &abs_path, options)?,
+        None => false,
+    };
+    if !copied {
+        if !file.unpack_in(root)? {
+            return Ok(EntryOutcome::Done);
+        }
+        if !options.xattrs.is_unfiltered() {
+            options.xattrs.apply(file, &abs_path)?;
+        }
+    }
+    apply_ownership(&abs_path, file.header(), options)?;
+    Ok(EntryOutcome::Written)
+}
+
+/// Set the owner of an unpacked entry, tolerating failures in rootless mode.
+fn apply_ownership(
+    abs_path: &path::Path,
+    header: &tar::Header,
+    options: &UnpackOptions,
+) -> Result<(), RenderError> {
+    match options.ownership.apply(abs_path, header) {
+        Err(RenderError::Io(e))
+            if e.kind() == io::ErrorKind::PermissionDenied && options.rootless_active() =>
+        {
+            warn!("cannot change owner of {}: {}", abs_path.display(), e);
+            Ok(())
+        }
+        Err(e) => Err(e),
+        Ok(()) => restore_special_bits(abs_path, header, options),
+    }
+}
+
+/// Restore setuid/setgid bits, which changing ownership clears on Linux.
+#[cfg(unix)]
+fn restore_special_bits(
+    abs_path: &path::Path,
+    header: &tar::Header,
+    options: &UnpackOptions,
+) -> Result<(), RenderError> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mut mode = header.mode()? & 0o7777;
+    if options.rootless_active() {
+        mode &= !ROOTLESS_MODE_MASK;
+    }
+    let entry_type = header.entry_type();
+    if mode & 0o6000 == 0 || entry_type.is_symlink() || entry_type.is_hard_link() {
+        return Ok(());
+    }
+    fs::set_permissions(abs_path, fs::Permissions::from_mode(mode))?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn restore_special_bits(
+    _abs_path: &path::Path,
+    _header: &tar::Header,
+    _options: &UnpackOptions,
+) -> Result<(), RenderError> {
+    Ok(())
+}
+
+/// Strip root and current-directory components from an archive path.
+fn normalize_path(p: &path::Path) -> path::PathBuf {
+    p.components()
+        .filter(|c| !matches!(c, path::Component::RootDir | path::Component::CurDir))
+        .collect()
+}
+
+/// Remove all lower-layers content from the directory at `rel_dir`.
+///
+/// Entries listed in `layer_paths` belong to the current layer and are kept;
+/// directories among them are walked so that stale lower content nested
+/// inside them gets removed too.
+fn clear_opaque_dir(
+    root: &path::Path,
+    rel_dir: &path::Path,
+    layer_paths: &HashSet<path::PathBuf>,
+) -> Result<(), RenderError> {
+    // Symlinks are never followed here, as they could point outside of `root`.
+    let abs_dir = match contained_dir(root, rel_dir)? {
+        Some(dir) => dir,
+        None => return Ok(()),
+    };
+    for dir_entry in fs::read_dir(&abs_dir)? {
+        let dir_entry = dir_entry?;
+        let rel_child = rel_dir.join(dir_entry.file_name());
+        if !layer_paths.contains(&rel_child) {
+            remove_path(&dir_entry.path())?;
+        } else if dir_entry.file_type()?.is_dir() {
+            clear_opaque_dir(root, &rel_child, layer_paths)?;
+        }
+    }
+    Ok(())
+}
+
+/// Remove a path of any type, without following symlinks.
+///
+/// Missing paths are not an error, as whiteouts may refer to content that
+/// was never materialized.
+fn remove_path(abs_path: &path::Path) -> Result<(), RenderError> {
+    let res = match fs::symlink_metadata(abs_path) {
+        Ok(meta) if meta.is_dir() => fs::remove_dir_all(abs_path),
+        Ok(_) => fs::remove_file(abs_path),
+        Err(e) => Err(e),
+    };
+    match res {
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        r => r.map_err(Into::into),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libflate::gzip;
+    use std::io::Write;
+
+    /// Build an uncompressed layer with the given `(path, content)` entries;
+    /// a `None` content denotes a directory.
+    fn make_tar(entries: &[(&str, Option<&str>)]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (path, content) in entries {
+            let mut header = tar::Header::new_gnu();
+            header.set_uid(0);
+            header.set_gid(0);
+            match content {
+                Some(data) => {
+                    header.set_entry_type(tar::EntryType::Regular);
+                    header.set_size(data.len() as u64);
+                    header.set_mode(0o644);
+                    header.set_cksum();
+                    builder
+                        .append_data(&mut header, path, data.as_bytes())
+                        .unwrap();
+                }
+                None => {
+                    header.set_entry_type(tar::EntryType::Directory);
+                    header.set_size(0);
+                    header.set_mode(0o755);
+                    header.set_cksum();
+                    builder.append_data(&mut header, path, io::empty()).unwrap();
+                }
+            }
+        }
+        builder.into_inner().unwrap()
+    }
+
+    /// Build a gzip-compressed layer, see `make_tar`.
+    fn make_layer(entries: &[(&str, Option<&str>)]) -> Vec<u8> {
+        let mut encoder = gzip::Encoder::new(Vec::new()).unwrap();
+        encoder.write_all(&make_tar(entries)).unwrap();
+        encoder.finish().into_result().unwrap()
+    }
+
+    #[test]
+    fn unpack_applies_whiteouts() {
+        let lower = make_layer(&[("etc/", None), ("etc/a", Some("a")), ("etc/b", Some("b"))]);
+        let upper = make_layer(&[("etc/.wh.a", Some(""))]);
+        let tmp = tempfile::tempdir().unwrap();
+
+        unpack(&[lower, upper], tmp.path()).unwrap();
+
+        assert!(!tmp.path().join("etc/a").exists());
+        assert!(!tmp.path().join("etc/.wh.a").exists());
+        assert!(tmp.path().join("etc/b").exists());
+    }
+
+    #[test]
+    fn unpack_applies_opaque_whiteouts() {
+        let lower = make_layer(&[
+            ("etc/", None),
+            ("etc/a", Some("lower")),
+            ("etc/sub/", None),
+            ("etc/sub/lower", Some("lower")),
+            ("etc/keep/", None),
+            ("keep", Some("lower")),
+        ]);
+        let upper = make_layer(&[
+            ("etc/", None),
+            ("etc/sub/", None),
+            ("etc/sub/upper", Some("upper")),
+            ("etc/.wh..wh..opq", Some("")),
+            ("etc/a", Some("upper")),
+        ]);
+        let tmp = tempfile::tempdir().unwrap();
+
+        unpack(&[lower, upper], tmp.path()).unwrap();
+
+        let root = tmp.path();
+        assert_eq!(fs::read_to_string(root.join("etc/a")).unwrap(), "upper");
+        assert!(root.join("etc/sub/upper").exists());
+        assert!(!root.join("etc/sub/lower").exists());
+        assert!(!root.join("etc/keep").exists());
+        assert!(!root.join("etc/.wh..wh..opq").exists());
+        assert!(root.join("keep").exists());
+    }
+
+    #[tokio::test]
+    async fn unpack_streaming_async_applies_layers() {
+        let lower = make_layer(&[("etc/", None), ("etc/a", Some("a")), ("etc/b", Some("b"))]);
+        let upper = make_layer(&[("etc/.wh.a", Some("")), ("etc/b", Some("upper"))]);
+        let tmp = tempfile::tempdir().unwrap();
+
+        unpack_streaming_async(vec![lower.as_slice(), upper.as_slice()], tmp.path())
+            .await
+            .unwrap();
+
+        assert!(!tmp.path().join("etc/a").exists());
+        assert_eq!(
+            fs::read_to_string(tmp.path().join("etc/b")).unwrap(),
+            "upper"
+        );
+    }
+
+    #[tokio::test]
+    async fn unpack_async_honors_options() {
+        let layer = make_layer(&[("etc/", None), ("etc/a", Some("a")), ("usr/", None)]);
+        let tmp = tempfile::tempdir().unwrap();
+
+        let options = UnpackOptions::new().include_paths(["etc"]);
+        let report = unpack_async(vec![layer], tmp.path(), &options)
+            .await
+            .unwrap();
+
+        assert_eq!(report.totals().files, 1);
+        assert!(tmp.path().join("etc/a").exists());
+        assert!(!tmp.path().join("usr").exists());
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn unpack_zstd_layers() {
+        let layer = zstd::encode_all(
+            make_tar(&[("etc/", None), ("etc/a", Some("a"))]).as_slice(),
+            0,
+        )
+        .unwrap();
+        let tmp = tempfile::tempdir().unwrap();
+        let options = UnpackOptions::new().compression(LayerCompression::Zstd);
+
+        unpack_with_options(std::slice::from_ref(&layer), tmp.path(), &options).unwrap();
+
+        assert_eq!(fs::read_to_string(tmp.path().join("etc/a")).unwrap(), "a");
+
+        let tmp = tempfile::tempdir().unwrap();
+        let options = UnpackOptions::new()
+            .layer_media_types(vec!["application/vnd.oci.image.layer.v1.tar+zstd".into()]);
+        unpack_with_options(&[layer], tmp.path(), &options).unwrap();
+        assert_eq!(fs::read_to_string(tmp.path().join("etc/a")).unwrap(), "a");
+    }
+
+    #[cfg(not(feature = "zstd"))]
+    #[test]
+    fn unpack_zstd_layers_unsupported() {
+        let tmp = tempfile::tempdir().unwrap();
+        let options = UnpackOptions::new().compression(LayerCompression::Zstd);
+
+        let res = unpack_with_options(&[vec![]], tmp.path(), &options);
+
+        assert!(matches!(
+            res,
+            Err(RenderError::UnsupportedCompression(LayerCompression::Zstd))
+        ));
+    }
+
+    #[test]
+    fn unpack_detects_mixed_compressions() {
+        let lower = make_layer(&[("etc/", None), ("etc/a", Some("a"))]);
+        let upper = make_tar(&[("etc/b", Some("b"))]);
+        let tmp = tempfile::tempdir().unwrap();
+
+        unpack(&[lower, upper], tmp.path()).unwrap();
+
+        assert!(tmp.path().join("etc/a").exists());
+        assert!(tmp.path().join("etc/b").exists());
+    }
+
+    #[test]
+    fn layer_compression_from_media_type() {
+        for (media_type, expected) in &[
+            (
+                "application/vnd.docker.image.rootfs.diff.tar.gzip",
+                Some(LayerCompression::Gzip),
+            ),
+            (
+                "application/vnd.oci.image.layer.v1.tar+gzip",
+                Some(LayerCompression::Gzip),
+            ),
+            (
+                "application/vnd.oci.image.layer.v1.tar+zstd",
+                Some(LayerCompression::Zstd),
+            ),
+            (
+                "application/vnd.docker.image.rootfs.diff.tar.zstd",
+                Some(LayerCompression::Zstd),
+            ),
+            (
+                "application/vnd.oci.image.layer.v1.tar",
+                Some(LayerCompression::Uncompressed),
+            ),
+            ("application/json", None),
+        ] {
+            assert_eq!(&LayerCompression::from_media_type(media_type), expected);
+        }
+    }
+
+    #[test]
+    fn unpack_reports_progress() {
+        use std::sync::{Arc, Mutex};
+
+        let layer = make_layer(&[("etc/", None), ("etc/a", Some("a"))]);
+        let tmp = tempfile::tempdir().unwrap();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = events.clone();
+        let options =
+            UnpackOptions::new().progress(move |ev| sink.lock().unwrap().push(ev.clone()));
+
+        unpack_with_options(&[layer], tmp.path(), &options).unwrap();
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 4);
+        assert_eq!(events[0], UnpackProgress::LayerStarted { layer: 0 });
+        match &events[1] {
+            UnpackProgress::EntryWritten {
+                path,
+                entries_written,
+                ..
+            } => {
+                assert_eq!(path, path::Path::new("etc/a"));
+                assert_eq!(*entries_written, 1);
+            }
+            ev => panic!("unexpected event {:?}", ev),
+        }
+        match &events[3] {
+            UnpackProgress::LayerFinished {
+                bytes_decompressed,
+                entries_written,
+                ..
+            } => {
+                assert!(*bytes_decompressed > 0);
+                assert_eq!(*entries_written, 2);
+            }
+            ev => panic!("unexpected event {:?}", ev),
+        }
+    }
+
+    #[test]
+    fn unpack_filters_paths() {
+        let layer = make_layer(&[
+            ("etc/", None),
+            ("etc/os-release", Some("os")),
+            ("etc/passwd", Some("root")),
+            ("usr/", None),
+            ("usr/lib/", None),
+            ("usr/lib/os-release", Some("os")),
+            ("usr/lib/libc.so", Some("elf")),
+            ("var/", None),
+        ]);
+        let tmp = tempfile::tempdir().unwrap();
+        let options = UnpackOptions::new()
+            .include_paths(["/etc", "/usr/lib/os-release"])
+            .filter(|p| p != path::Path::new("etc/passwd"));
+
+        unpack_with_options(&[layer], tmp.path(), &options).unwrap();
+
+        let root = tmp.path();
+        assert!(root.join("etc/os-release").exists());
+        assert!(!root.join("etc/passwd").exists());
+        assert!(root.join("usr/lib/os-release").exists());
+        assert!(!root.join("usr/lib/libc.so").exists());
+        assert!(!root.join("var").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unpack_remaps_ownership() {
+        use std::os::unix::fs::MetadataExt;
+
+        let layer = make_layer(&[("etc/", None), ("etc/a", Some("a"))]);
+        let tmp = tempfile::tempdir().unwrap();
+        let owner = fs::metadata(tmp.path()).unwrap();
+        let options = UnpackOptions::new()
+            .uid_map(vec![IdMapping::new(0, owner.uid(), 1)])
+            .gid_map(vec![IdMapping::new(0, owner.gid(), 1)]);
+
+        unpack_with_options(std::slice::from_ref(&layer), tmp.path(), &options).unwrap();
+        let meta = fs::symlink_metadata(tmp.path().join("etc/a")).unwrap();
+        assert_eq!((meta.uid(), meta.gid()), (owner.uid(), owner.gid()));
+
+        let options = UnpackOptions::new().uid_map(vec![IdMapping::new(1, owner.uid(), 1)]);
+        let res = unpack_with_options(&[layer], tmp.path(), &options);
+        assert!(matches!(res, Err(RenderError::UnmappedId(0, _))));
+    }
+
+    #[test]
+    fn diff_reports_docker_changes() {
+        let lower = make_layer(&[
+            ("etc/", None),
+            ("etc/a", Some("lower")),
+            ("etc/b", Some("b")),
+            ("usr/", None),
+            ("usr/lib/", None),
+            ("usr/lib/x", Some("x")),
+        ]);
+        let upper = make_layer(&[
+            ("etc/a", Some("changed")),
+            ("etc/.wh.b", Some("")),
+            ("usr/.wh.lib", Some("")),
+            ("opt/", None),
+            ("opt/y", Some("y")),
+        ]);
+
+        let base = vec![lower.clone()];
+        let changes = diff(&base, &[lower, upper], &UnpackOptions::new()).unwrap();
+
+        let changes: Vec<_> = changes.iter().map(Change::to_string).collect();
+        assert_eq!(
+            changes,
+            vec![
+                "C /etc",
+                "C /etc/a",
+                "D /etc/b",
+                "A /opt",
+                "A /opt/y",
+                "C /usr",
+                "D /usr/lib",
+            ]
+        );
+    }
+
+    #[test]
+    fn pack_roundtrips_changes() {
+        let lower = make_layer(&[
+            ("etc/", None),
+            ("etc/a", Some("lower")),
+            ("etc/b", Some("b")),
+            ("usr/", None),
+            ("usr/lib/", None),
+            ("usr/lib/x", Some("x")),
+        ]);
+        let upper = make_layer(&[
+            ("etc/a", Some("changed")),
+            ("etc/.wh.b", Some("")),
+            ("usr/.wh.lib", Some("")),
+            ("opt/", None),
+            ("opt/y", Some("y")),
+        ]);
+        let base = tempfile::tempdir().unwrap();
+        let modified = tempfile::tempdir().unwrap();
+        unpack(std::slice::from_ref(&lower), base.path()).unwrap();
+        unpack(&[lower.clone(), upper], modified.path()).unwrap();
+
+        let layer = pack(base.path(), modified.path(), Vec::new()).unwrap();
+
+        let listing = list(std::slice::from_ref(&layer), &UnpackOptions::new()).unwrap();
+        let whiteouts: Vec<_> = listing.whiteouts().iter().map(|w| w.path.clone()).collect();
+        assert_eq!(
+            whiteouts,
+            vec![path::PathBuf::from("etc/b"), path::PathBuf::from("usr/lib")]
+        );
+        let rebuilt = tempfile::tempdir().unwrap();
+        unpack(&[lower, layer], rebuilt.path()).unwrap();
+        assert_eq!(diff_dirs(modified.path(), rebuilt.path()).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn pack_deterministic() {
+        let base = tempfile::tempdir().unwrap();
+        let layers: Vec<_> = [10, 20]
+            .iter()
+            .map(|&mtime| {
+                let dir = tempfile::tempdir().unwrap();
+                fs::write(dir.path().join("b"), "b").unwrap();
+                fs::write(dir.path().join("a"), "a").unwrap();
+                let time = filetime::FileTime::from_unix_time(mtime, 0);
+                filetime::set_file_mtime(dir.path().join("a"), time).unwrap();
+                let options = PackOptions::new().source_date_epoch(1);
+                pack_with_options(base.path(), dir.path(), Vec::new(), &options).unwrap()
+            })
+            .collect();
+
+        assert_eq!(layers[0], layers[1]);
+        let listing = list(&layers[..1], &UnpackOptions::new()).unwrap();
+        assert!(listing.entries().all(|e| e.mtime == 1));
+    }
+
+    #[test]
+    fn flatten_deterministic() {
+        let layer = make_layer(&[("b", Some("b")), ("a", Some("a"))]);
+
+        let options = UnpackOptions::new().source_date_epoch(42);
+        let flat = flatten_with_options(&[layer], Vec::new(), &options).unwrap();
+
+        let mut archive = tar::Archive::new(flat.as_slice());
+        let entries: Vec<_> = archive
+            .entries()
+            .unwrap()
+            .map(|e| {
+                let e = e.unwrap();
+                (e.path().unwrap().into_owned(), e.header().mtime().unwrap())
+            })
+            .collect();
+        assert_eq!(
+            entries,
+            vec![
+                (path::PathBuf::from("a"), 42),
+                (path::PathBuf::from("b"), 42)
+            ]
+        );
+    }
+
+    #[test]
+    fn list_applies_whiteouts() {
+        let lower = make_layer(&[
+            ("etc/", None),
+            ("etc/a", Some("lower")),
+            ("etc/sub/", None),
+            ("etc/sub/b", Some("lower")),
+            ("usr/", None),
+            ("usr/c", Some("c")),
+        ]);
+        let upper = make_layer(&[
+            ("etc/.wh..wh..opq", Some("")),
+            ("etc/a", Some("upper")),
+            ("usr/.wh.c", Some("")),
+        ]);
+
+        let listing = list(&[lower, upper], &UnpackOptions::new()).unwrap();
+
+        let paths: Vec<_> = listing.entries().map(|e| e.path.clone()).collect();
+        assert_eq!(
+            paths,
+            vec![
+                path::PathBuf::from("etc"),
+                path::PathBuf::from("etc/a"),
+                path::PathBuf::from("usr"),
+            ]
+        );
+        let a = listing.get("/etc/a").unwrap();
+        assert_eq!((a.kind, a.size, a.layer), (EntryKind::File, 5, 1));
+        assert_eq!(listing.total_size(), 5);
+        assert_eq!(
+            listing.whiteouts(),
+            &[
+                AppliedWhiteout {
+                    layer: 1,
+                    path: "etc".into(),
+                    opaque: true,
+                    removed: 3,
+                },
+                AppliedWhiteout {
+                    layer: 1,
+                    path: "usr/c".into(),
+                    opaque: false,
+                    removed: 1,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn flatten_merges_layers() {
+        let lower = make_layer(&[
+            ("etc/", None),
+            ("etc/a", Some("lower")),
+            ("etc/b", Some("b")),
+        ]);
+        let upper = make_layer(&[("etc/.wh.b", Some("")), ("etc/a", Some("upper"))]);
+
+        let flat = flatten(&[lower, upper], Vec::new()).unwrap();
+
+        let mut archive = tar::Archive::new(flat.as_slice());
+        let mut found = Vec::new();
+        for entry in archive.entries().unwrap() {
+            let mut entry = entry.unwrap();
+            let mut content = String::new();
+            entry.read_to_string(&mut content).unwrap();
+            found.push((
+                entry.path().unwrap().to_string_lossy().into_owned(),
+                content,
+            ));
+        }
+        assert_eq!(
+            found,
+            vec![
+                ("etc".to_string(), "".to_string()),
+                ("etc/a".to_string(), "upper".to_string()),
+            ]
+        );
+    }
+
+    /// Build a gzip-compressed layer holding a single symlink.
+    fn make_symlink_layer(path: &str, target: &path::Path) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Symlink);
+        header.set_uid(0);
+        header.set_gid(0);
+        header.set_size(0);
+        header.set_mode(0o777);
+        builder.append_link(&mut header, path, target).unwrap();
+        let mut encoder = gzip::Encoder::new(Vec::new()).unwrap();
+        encoder.write_all(&builder.into_inner().unwrap()).unwrap();
+        encoder.finish().into_result().unwrap()
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn unpack_filters_xattrs() {
+        let mut builder = tar::Builder::new(Vec::new());
+        builder
+            .append_pax_extensions([
+                ("SCHILY.xattr.user.keep", &b"yes"[..]),
+                ("SCHILY.xattr.bogus.attr", &b"no"[..]),
+            ])
+            .unwrap();
+        let mut header = tar::Header::new_gnu();
+        header.set_uid(0);
+        header.set_gid(0);
+        header.set_size(1);
+        header.set_mode(0o644);
+        builder.append_data(&mut header, "a", &b"a"[..]).unwrap();
+        let layer = builder.into_inner().unwrap();
+
+        // No filesystem supports the `bogus` namespace.
+        let tmp = tempfile::tempdir().unwrap();
+        assert!(unpack(std::slice::from_ref(&layer), tmp.path()).is_err());
+
+        let tmp = tempfile::tempdir().unwrap();
+        let options = UnpackOptions::new().unpack_xattrs(XattrClass::Other, false);
+        unpack_with_options(&[layer], tmp.path(), &options).unwrap();
+        let value = xattr::get(tmp.path().join("a"), "user.keep").unwrap();
+        assert_eq!(value.as_deref(), Some(&b"yes"[..]));
+    }
+
+    #[test]
+    fn unpack_foreign_layers() {
+        let foreign = b"not available".to_vec();
+        let layer = make_layer(&[("etc/", None), ("etc/a", Some("a"))]);
+        let layers = vec![foreign, layer];
+        let media_types = vec![
+            "application/vnd.docker.image.rootfs.foreign.diff.tar.gzip".to_string(),
+            "application/vnd.docker.image.rootfs.diff.tar.gzip".to_string(),
+        ];
+
+        let tmp = tempfile::tempdir().unwrap();
+        let options = UnpackOptions::new().layer_media_types(media_types.clone());
+        let res = unpack_with_options(&layers, tmp.path(), &options);
+        assert!(matches!(
+            res,
+            Err(RenderError::ForeignLayer { layer: 0, .. })
+        ));
+
+        let options = options.foreign_layers(crate::v2::ForeignLayerPolicy::Skip);
+        let report = unpack_with_options(&layers, tmp.path(), &options).unwrap();
+        assert_eq!(report.layers().len(), 2);
+        assert_eq!(report.diff_ids()[0], "");
+        assert!(tmp.path().join("etc/a").exists());
+    }
+
+    #[test]
+    fn xattr_classes() {
+        assert_eq!(
+            XattrClass::of(b"security.capability"),
+            XattrClass::Capability
+        );
+        assert_eq!(XattrClass::of(b"security.selinux"), XattrClass::Selinux);
+        assert_eq!(XattrClass::of(b"user.mime_type"), XattrClass::User);
+        assert_eq!(XattrClass::of(b"security.ima"), XattrClass::Other);
+    }
+
+    #[test]
+    fn unpack_rejects_escaping_whiteouts() {
+        let outside = tempfile::tempdir().unwrap();
+        fs::write(outside.path().join("victim"), "precious").unwrap();
+        let link = make_symlink_layer("evil", outside.path());
+        let tmp = tempfile::tempdir().unwrap();
+
+        let whiteout = make_layer(&[("evil/.wh.victim", Some(""))]);
+        let res = unpack(&[link.clone(), whiteout], tmp.path());
+        assert!(matches!(res, Err(RenderError::PathEscape(_))));
+        assert!(outside.path().join("victim").exists());
+
+        // Opaque whiteouts never follow symlinks, and leave them alone.
+        let opaque = make_layer(&[("evil/.wh..wh..opq", Some(""))]);
+        unpack(&[link, opaque], tmp.path()).unwrap();
+        assert!(outside.path().join("victim").exists());
+    }
+
+    #[test]
+    fn unpack_rejects_escaping_entries() {
+        let outside = tempfile::tempdir().unwrap();
+        let link = make_symlink_layer("evil", outside.path());
+        let through_link = make_layer(&[("evil/planted", Some("x"))]);
+        let tmp = tempfile::tempdir().unwrap();
+
+        let res = unpack(&[link, through_link], tmp.path());
+        assert!(matches!(res, Err(RenderError::PathEscape(_))));
+        assert!(!outside.path().join("planted").exists());
+
+        let dotdot = contained_path(tmp.path(), path::Path::new("../planted"));
+        assert!(matches!(dotdot, Err(RenderError::PathEscape(_))));
+    }
+
+    #[test]
+    fn runtime_bundle_from_image_config() {
+        let layer = make_layer(&[
+            ("etc/", None),
+            (
+                "etc/passwd",
+                Some("root:x:0:0::/root:/bin/sh\napp:x:1000:1001::/home/app:/bin/sh\n"),
+            ),
+            ("etc/group", Some("root:x:0:\nstaff:x:50:app\n")),
+        ]);
+        let image_config = br#"{
+            "architecture": "amd64",
+            "config": {
+                "User": "app:staff",
+                "Env": ["FOO=bar"],
+                "Entrypoint": ["/bin/sh"],
+                "Cmd": ["-c", "true"],
+                "WorkingDir": "/srv"
+            }
+        }"#;
+        let tmp = tempfile::tempdir().unwrap();
+        to_runtime_bundle(std::slice::from_ref(&layer), image_config, tmp.path()).unwrap();
+
+        assert!(tmp.path().join("rootfs/etc/passwd").exists());
+        let spec: serde_json::Value =
+            serde_json::from_slice(&fs::read(tmp.path().join("config.json")).unwrap()).unwrap();
+        let process = &spec["process"];
+        assert_eq!(
+            process["args"],
+            serde_json::json!(["/bin/sh", "-c", "true"])
+        );
+        assert_eq!(process["env"][0], "FOO=bar");
+        assert!(process["env"][1].as_str().unwrap().starts_with("PATH="));
+        assert_eq!(process["cwd"], "/srv");
+        assert_eq!(
+            process["user"],
+            serde_json::json!({ "uid": 1000, "gid": 50 })
+        );
+        assert_eq!(spec["root"]["path"], "rootfs");
+
+        let tmp = tempfile::tempdir().unwrap();
+        let res = to_runtime_bundle(
+            std::slice::from_ref(&layer),
+            br#"{"architecture": "amd64", "config": {"User": "nobody", "Cmd": ["true"]}}"#,
+            tmp.path(),
+        );
+        assert!(matches!(res, Err(RenderError::UnknownUser(_))));
+        let res = to_runtime_bundle(
+            &[layer],
+            br#"{"architecture": "amd64", "config": {}}"#,
+            tmp.path(),
+        );
+        assert!(matches!(res, Err(RenderError::MissingCommand)));
+    }
+
+    #[test]
+    fn read_single_files() {
+        let bottom = make_layer(&[
+            ("usr/", None),
+            ("usr/lib/", None),
+            ("usr/lib/os-release", Some("ID=old")),
+            ("etc/hostname", Some("box")),
+            ("etc/gone", Some("x")),
+        ]);
+        let top = make_layer(&[
+            ("usr/lib/os-release", Some("ID=new")),
+            ("etc/.wh.gone", Some("")),
+        ]);
+        let link = make_symlink_layer("etc/os-release", path::Path::new("../usr/lib/os-release"));
+        let layers = [bottom, top, link];
+        let options = UnpackOptions::new();
+
+        assert_eq!(
+            read_file(&layers, "/etc/hostname", &options).unwrap(),
+            b"box"
+        );
+        assert_eq!(
+            read_file(&layers, "/etc/os-release", &options).unwrap(),
+            b"ID=new"
+        );
+        assert!(matches!(
+            read_file(&layers, "/etc/gone", &options),
+            Err(RenderError::NotFound(_))
+        ));
+        assert!(matches!(
+            read_file(&layers, "/usr/lib", &options),
+            Err(RenderError::NotAFile(_))
+        ));
+
+        let tmp = tempfile::tempdir().unwrap();
+        extract_path(&layers, "/etc/os-release", tmp.path(), &options).unwrap();
+        assert_eq!(
+            fs::read_to_string(tmp.path().join("usr/lib/os-release")).unwrap(),
+            "ID=new"
+        );
+        assert!(!tmp.path().join("etc").exists());
+    }
+
+    #[test]
+    fn unpack_enforces_limits() {
+        let big = "x".repeat(64 * 1024);
+        let layers = vec![
+            make_layer(&[("a/", None), ("a/b", Some(&big))]),
+            make_layer(&[("c", Some(&big))]),
+        ];
+        let exceeded = |options: UnpackOptions| {
+            let tmp = tempfile::tempdir().unwrap();
+            match unpack_with_options(&layers, tmp.path(), &options.continue_on_error(true)) {
+                Err(RenderError::LimitExceeded { layer, limit }) => Some((layer, limit)),
+                Err(e) => panic!("unexpected error {:?}", e),
+                Ok(_) => None,
+            }
+        };
+
+        assert_eq!(
+            exceeded(UnpackOptions::new().max_layer_size(96 * 1024)),
+            None
+        );
+        assert_eq!(
+            exceeded(UnpackOptions::new().max_layer_size(32 * 1024)),
+            Some((0, LimitKind::LayerSize))
+        );
+        assert_eq!(
+            exceeded(UnpackOptions::new().max_total_size(100 * 1024)),
+            Some((1, LimitKind::TotalSize))
+        );
+        assert_eq!(
+            exceeded(
+                UnpackOptions::new()
+                    .decompression_workers(2)
+                    .max_total_size(100 * 1024)
+            )
+            .map(|(_, limit)| limit),
+            Some(LimitKind::TotalSize)
+        );
+        assert_eq!(exceeded(UnpackOptions::new().max_entries(2)), None);
+        assert_eq!(
+            exceeded(UnpackOptions::new().max_entries(1)),
+            Some((0, LimitKind::Entries))
+        );
+        assert_eq!(
+            exceeded(UnpackOptions::new().max_path_depth(1)),
+            Some((0, LimitKind::PathDepth))
+        );
+        assert!(matches!(
+            list(&layers, &UnpackOptions::new().max_entries(1)),
+            Err(RenderError::LimitExceeded { .. })
+        ));
+    }
+
+    #[test]
+    fn unpack_reports_stats() {
+        let bottom = make_layer(&[("d/", None), ("d/a", Some("abc")), ("old", Some("x"))]);
+        let mut builder = tar::Builder::new(Vec::new());
+        for (path, kind, target) in &[
+            ("d/link", tar::EntryType::Symlink, "a"),
+            ("d/hard", tar::EntryType::Link, "d/a"),
+        ] {
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(*kind);
+            header.set_uid(0);
+            header.set_gid(0);
+            header.set_size(0);
+            header.set_mode(0o777);
+            builder.append_link(&mut header, path, target).unwrap();
+        }
+        for (path, data) in &[(".wh.old", ""), ("new", "12345")] {
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(tar::EntryType::Regular);
+            header.set_uid(0);
+            header.set_gid(0);
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, path, data.as_bytes())
+                .unwrap();
+        }
+        let top = builder.into_inner().unwrap();
+
+        let tmp = tempfile::tempdir().unwrap();
+        let report = unpack(&[bottom, top], tmp.path()).unwrap();
+        let expected = [
+            LayerStats {
+                files: 2,
+                directories: 1,
+                bytes_written: 4,
+                ..LayerStats::default()
+            },
+            LayerStats {
+                files: 1,
+                symlinks: 1,
+                hardlinks: 1,
+                bytes_written: 5,
+                whiteouts: 1,
+                ..LayerStats::default()
+            },
+        ];
+        assert_eq!(report.layers(), &expected);
+        assert_eq!(report.totals().files, 3);
+        assert_eq!(report.totals().bytes_written, 9);
+    }
+
+    #[test]
+    fn unpack_from_layer_files() {
+        let big = "0123456789abcdef".repeat(8 * 1024);
+        let tar = make_tar(&[("d/", None), ("d/big", Some(&big)), ("d/small", Some("s"))]);
+        let gz = make_layer(&[("d/big", Some("replaced"))]);
+        let files = tempfile::tempdir().unwrap();
+        let tar_path = files.path().join("layer.tar");
+        let gz_path = files.path().join("layer.tar.gz");
+        fs::write(&tar_path, &tar).unwrap();
+        fs::write(&gz_path, &gz).unwrap();
+
+        let tmp = tempfile::tempdir().unwrap();
+        let report = unpack_layer_files(&[&tar_path], tmp.path(), &UnpackOptions::new()).unwrap();
+        assert_eq!(fs::read_to_string(tmp.path().join("d/big")).unwrap(), big);
+        assert_eq!(fs::read_to_string(tmp.path().join("d/small")).unwrap(), "s");
+        assert_eq!(report.diff_ids(), &[sha256(&tar)]);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let meta = fs::metadata(tmp.path().join("d/big")).unwrap();
+            assert_eq!(meta.permissions().mode() & 0o7777, 0o644);
+        }
+
+        let report =
+            unpack_layer_files(&[&tar_path, &gz_path], tmp.path(), &UnpackOptions::new()).unwrap();
+        assert_eq!(report.totals().files, 3);
+        assert_eq!(
+            fs::read_to_string(tmp.path().join("d/big")).unwrap(),
+            "replaced"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unpack_rootless() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Regular);
+        header.set_uid(0);
+        header.set_gid(0);
+        header.set_size(0);
+        header.set_mode(0o4755);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "suid", io::empty())
+            .unwrap();
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Char);
+        header.set_device_major(1).unwrap();
+        header.set_device_minor(3).unwrap();
+        header.set_size(0);
+        header.set_mode(0o666);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "null", io::empty())
+            .unwrap();
+        let layer = builder.into_inner().unwrap();
+
+        let tmp = tempfile::tempdir().unwrap();
+        let options = UnpackOptions::new().rootless(true).chown(0, 0);
+        unpack_with_options(&[layer], tmp.path(), &options).unwrap();
+
+        // Privileged runs are not affected by rootless mode.
+        let privileged = ownership::running_as_root();
+        let mode = fs::metadata(tmp.path().join("suid"))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o7777, if privileged { 0o4755 } else { 0o755 });
+        assert_eq!(
+            fs::symlink_metadata(tmp.path().join("null")).is_ok(),
+            privileged
+        );
+    }
+
+    #[test]
+    fn unpack_preserves_mtimes() {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (path, kind) in &[
+            ("d", tar::EntryType::Directory),
+            ("d/f", tar::EntryType::Regular),
+        ] {
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(*kind);
+            header.set_size(0);
+            header.set_mode(0o755);
+            header.set_mtime(1_000_000);
+            header.set_cksum();
+            builder.append_data(&mut header, path, io::empty()).unwrap();
+        }
+        let layer = builder.into_inner().unwrap();
+
+        let mtime = |p: &path::Path| {
+            let meta = fs::symlink_metadata(p).unwrap();
+            filetime::FileTime::from_last_modification_time(&meta).unix_seconds()
+        };
+        let tmp = tempfile::tempdir().unwrap();
+        unpack(std::slice::from_ref(&layer), tmp.path()).unwrap();
+        assert_eq!(mtime(&tmp.path().join("d")), 1_000_000);
+        assert_eq!(mtime(&tmp.path().join("d/f")), 1_000_000);
+
+        let tmp = tempfile::tempdir().unwrap();
+        let options = UnpackOptions::new().preserve_mtimes(false);
+        unpack_with_options(&[layer], tmp.path(), &options).unwrap();
+        assert_ne!(mtime(&tmp.path().join("d")), 1_000_000);
+        assert_ne!(mtime(&tmp.path().join("d/f")), 1_000_000);
+    }
+
+    #[test]
+    fn unpack_continues_on_error() {
+        let outside = tempfile::tempdir().unwrap();
+        let link = make_symlink_layer("evil", outside.path());
+        let mixed = make_layer(&[("evil/planted", Some("x")), ("ok", Some("y"))]);
+        let tmp = tempfile::tempdir().unwrap();
+
+        let options = UnpackOptions::new().continue_on_error(true);
+        let report = unpack_with_options(&[link, mixed], tmp.path(), &options).unwrap();
+        assert_eq!(report.skipped().len(), 1);
+        let skipped = &report.skipped()[0];
+        assert_eq!(skipped.layer, 1);
+        assert_eq!(skipped.path, path::Path::new("evil/planted"));
+        assert!(matches!(skipped.error, RenderError::PathEscape(_)));
+        assert!(!outside.path().join("planted").exists());
+        assert_eq!(fs::read_to_string(tmp.path().join("ok")).unwrap(), "y");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unpack_forces_ownership() {
+        use std::os::unix::fs::MetadataExt;
+
+        let layer = make_layer(&[("etc/", None), ("etc/a", Some("a"))]);
+        let tmp = tempfile::tempdir().unwrap();
+        let owner = fs::metadata(tmp.path()).unwrap();
+        // Mappings are ignored when a fixed owner is set.
+        let options = UnpackOptions::new()
+            .uid_map(vec![])
+            .chown(owner.uid(), owner.gid());
+
+        unpack_with_options(std::slice::from_ref(&layer), tmp.path(), &options).unwrap();
+        let meta = fs::symlink_metadata(tmp.path().join("etc/a")).unwrap();
+        assert_eq!((meta.uid(), meta.gid()), (owner.uid(), owner.gid()));
+
+        let options = UnpackOptions::new().chown_current_user();
+        unpack_with_options(&[layer], tmp.path(), &options).unwrap();
+        let meta = fs::symlink_metadata(tmp.path().join("etc")).unwrap();
+        assert_eq!((meta.uid(), meta.gid()), (owner.uid(), owner.gid()));
+    }
+
+    /// Build a gzip-compressed layer of overlayfs-style whiteouts at `paths`.
+    fn make_device_whiteouts_layer(paths: &[&str]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        for path in paths {
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(tar::EntryType::Char);
+            header.set_device_major(0).unwrap();
+            header.set_device_minor(0).unwrap();
+            header.set_size(0);
+            header.set_mode(0);
+            header.set_cksum();
+            builder.append_data(&mut header, path, io::empty()).unwrap();
+        }
+        let mut encoder = gzip::Encoder::new(Vec::new()).unwrap();
+        encoder.write_all(&builder.into_inner().unwrap()).unwrap();
+        encoder.finish().into_result().unwrap()
+    }
+
+    #[test]
+    fn unpack_device_whiteouts() {
+        let bottom = make_layer(&[
+            ("a", Some("a")),
+            ("d/", None),
+            ("d/x", Some("x")),
+            ("k", Some("k")),
+        ]);
+        let top = make_device_whiteouts_layer(&["a", "d"]);
+        let layers = [bottom, top];
+
+        let tmp = tempfile::tempdir().unwrap();
+        unpack(&layers, tmp.path()).unwrap();
+        assert!(fs::symlink_metadata(tmp.path().join("a")).is_err());
+        assert!(fs::symlink_metadata(tmp.path().join("d")).is_err());
+        assert!(tmp.path().join("k").exists());
+
+        let listing = list(&layers, &UnpackOptions::new()).unwrap();
+        let paths: Vec<_> = listing.entries().map(|e| e.path.clone()).collect();
+        assert_eq!(paths, vec![path::PathBuf::from("k")]);
+        let removed: Vec<_> = listing.whiteouts().iter().map(|w| w.removed).collect();
+        assert_eq!(removed, vec![1, 2]);
+    }
+
+    #[test]
+    fn unpack_parallel_decompression() {
+        let layers = vec![
+            make_layer(&[("etc/", None), ("etc/a", Some("a")), ("etc/b", Some("b"))]),
+            make_tar(&[("etc/.wh.a", Some("")), ("etc/c", Some("c"))]),
+            make_layer(&[("etc/b", Some("bb"))]),
+        ];
+        let sequential = tempfile::tempdir().unwrap();
+        let sequential_report =
+            unpack_with_options(&layers, sequential.path(), &UnpackOptions::new()).unwrap();
+        let parallel = tempfile::tempdir().unwrap();
+        let options = UnpackOptions::new().decompression_workers(2);
+        let parallel_report = unpack_with_options(&layers, parallel.path(), &options).unwrap();
+        assert_eq!(sequential_report.diff_ids(), parallel_report.diff_ids());
+
+        for path in &["etc/b", "etc/c"] {
+            assert_eq!(
+                fs::read(sequential.path().join(path)).unwrap(),
+                fs::read(parallel.path().join(path)).unwrap()
+            );
+        }
+        assert!(!parallel.path().join("etc/a").exists());
+
+        let options = options.diff_ids(vec![String::new(), sha256(b"other")]);
+        assert!(matches!(
+            unpack_with_options(&layers, parallel.path(), &options),
+            Err(RenderError::Digest(_))
+        ));
+    }
+
+    #[test]
+    fn unpack_parallel_aborts_on_digest_mismatch() {
+        let layers = vec![
+            make_layer(&[("etc/", None), ("etc/a", Some("a"))]),
+            make_layer(&[("etc/b", Some("b"))]),
+            make_layer(&[("etc/c", Some("c"))]),
+        ];
+        let scratch = tempfile::tempdir().unwrap();
+        let report = unpack_with_options(&layers, scratch.path(), &UnpackOptions::new()).unwrap();
+        let options = UnpackOptions::new().decompression_workers(3);
+
+        // A worker decompressing a later layer fails, ordered application
+        // stops before it.
+        let mut diff_ids = report.diff_ids().to_vec();
+        diff_ids[1] = sha256(b"other");
+        let tmp = tempfile::tempdir().unwrap();
+        let res = unpack_with_options(&layers, tmp.path(), &options.clone().diff_ids(diff_ids));
+        assert!(matches!(
+            res,
+            Err(RenderError::DiffIdMismatch { layer: 1, .. })
+        ));
+        assert!(tmp.path().join("etc/a").exists());
+        assert!(!tmp.path().join("etc/b").exists());
+        assert!(!tmp.path().join("etc/c").exists());
+
+        let mut digests: Vec<_> = layers.iter().map(|l| sha256(l)).collect();
+        digests[2] = sha256(b"other");
+        let tmp = tempfile::tempdir().unwrap();
+        let res = unpack_with_options(&layers, tmp.path(), &options.layer_digests(digests));
+        assert!(matches!(
+            res,
+            Err(RenderError::DigestMismatch { layer: 2, .. })
+        ));
+        assert!(tmp.path().join("etc/b").exists());
+        assert!(!tmp.path().join("etc/c").exists());
+    }
+
+    fn sha256(data: &[u8]) -> String {
+        use sha2::Digest;
+        format!("sha256:{:x}", sha2::Sha256::digest(data))
+    }
+
+    #[test]
+    fn unpack_verifies_digests() {
+        let tar = make_tar(&[("a", Some("a"))]);
+        let layer = make_layer(&[("a", Some("a"))]);
+        let tmp = tempfile::tempdir().unwrap();
+
+        let report = unpack_with_options(
+            std::slice::from_ref(&layer),
+            tmp.path(),
+            &UnpackOptions::new(),
+        )
+        .unwrap();
+        assert_eq!(report.diff_ids(), &[sha256(&tar)]);
+
+        let options = UnpackOptions::new()
+            .layer_digests(vec![sha256(&layer)])
+            .diff_ids(vec![sha256(&tar)]);
+        unpack_with_options(std::slice::from_ref(&layer), tmp.path(), &options).unwrap();
+
+        let options = UnpackOptions::new().layer_digests(vec![sha256(b"other")]);
+        let err = unpack_with_options(std::slice::from_ref(&layer), tmp.path(), &options);
+        match err {
+            Err(RenderError::DigestMismatch { layer: 0, got, .. }) => {
+                assert_eq!(got, sha256(&layer))
+            }
+            r => panic!("unexpected result {:?}", r),
+        }
+
+        let options = UnpackOptions::new().diff_ids(vec![sha256(&layer)]);
+        let err = list(&[layer], &options);
+        match err {
+            Err(RenderError::DiffIdMismatch { layer: 0, got, .. }) => assert_eq!(got, sha256(&tar)),
+            r => panic!("unexpected result {:?}", r),
+        }
+
+        let options = UnpackOptions::new().layer_digests(vec!["bogus".to_string()]);
+        assert!(matches!(
+            unpack_with_options(&[tar], tmp.path(), &options),
+            Err(RenderError::Digest(_))
+        ));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn unpack_overlay_layers() {
+        use std::os::unix::fs::{FileTypeExt, MetadataExt};
+
+        let bottom = make_layer(&[("etc/", None), ("etc/a", Some("a")), ("b", Some("b"))]);
+        let top = make_layer(&[
+            ("etc/", None),
+            ("etc/.wh..wh..opq", Some("")),
+            ("etc/c", Some("c")),
+            (".wh.b", Some("")),
+        ]);
+        let tmp = tempfile::tempdir().unwrap();
+        let dirs = match unpack_overlay(&[bottom, top], tmp.path(), &UnpackOptions::new()) {
+            Ok(dirs) => dirs,
+            // Creating whiteouts requires privileges.
+            Err(RenderError::Io(ref e)) if e.kind() == io::ErrorKind::PermissionDenied => return,
+            Err(e) => panic!("{:?}", e),
+        };
+        assert_eq!(dirs, vec![tmp.path().join("0"), tmp.path().join("1")]);
+
+        // Lower layers are left untouched.
+        assert!(dirs[0].join("etc/a").exists());
+        assert!(dirs[0].join("b").exists());
+
+        let whiteout = fs::symlink_metadata(dirs[1].join("b")).unwrap();
+        assert!(whiteout.file_type().is_char_device());
+        assert_eq!(whiteout.rdev(), 0);
+        assert!(!dirs[1].join(".wh.b").exists());
+        assert!(!dirs[1].join("etc/.wh..wh..opq").exists());
+        assert!(dirs[1].join("etc/c").exists());
+
+        let opaque = xattr_value(&dirs[1].join("etc"), "trusted.overlay.opaque");
+        assert_eq!(opaque.as_deref(), Some(&b"y"[..]));
+    }
+
+    #[cfg(target_os = "linux")]
+    fn xattr_value(p: &path::Path, name: &str) -> Option<Vec<u8>> {
+        xattr::get(p, name).ok().flatten()
+    }
+}
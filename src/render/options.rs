@@ -0,0 +1,322 @@
+//! Tunables for layers unpacking.
+
+use super::canonical::Canonical;
+use super::filter::{PathFilter, PathPredicate};
+use super::limits::Limits;
+use super::ownership::{running_as_root, FixedOwner, Ownership};
+use super::progress::ProgressCallback;
+use super::xattrs::{XattrClass, XattrFilter};
+use super::{normalize_path, IdMapping, LayerCompression, RenderError, UnpackProgress};
+use crate::v2::{is_foreign, ForeignLayerPolicy};
+use std::path;
+
+/// Options controlling how layers are unpacked.
+#[derive(Debug, Default, Clone)]
+pub struct UnpackOptions {
+    compression: LayerCompression,
+    layer_compressions: Vec<LayerCompression>,
+    pub(crate) progress: Option<ProgressCallback>,
+    pub(crate) filter: PathFilter,
+    pub(crate) ownership: Ownership,
+    layer_digests: Vec<String>,
+    diff_ids: Vec<String>,
+    pub(crate) decompression_workers: usize,
+    pub(crate) continue_on_error: bool,
+    pub(crate) discard_mtimes: bool,
+    pub(crate) limits: Limits,
+    rootless: bool,
+    pub(crate) canonical: Option<Canonical>,
+    pub(crate) xattrs: XattrFilter,
+    layer_media_types: Vec<String>,
+    foreign_layers: ForeignLayerPolicy,
+}
+
+impl UnpackOptions {
+    /// Initialize `UnpackOptions` with default values.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the compression algorithm used by all layers (default: auto-detect).
+    pub fn compression(mut self, compression: LayerCompression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Set the compression algorithm of each layer, by position.
+    ///
+    /// Layers beyond the end of this list use the value set via `compression`,
+    /// or the one of their media type if it is left to auto-detection.
+    pub fn layer_compressions(mut self, compressions: Vec<LayerCompression>) -> Self {
+        self.layer_compressions = compressions;
+        self
+    }
+
+    /// Set a callback to be notified of unpacking progress.
+    ///
+    /// The callback is invoked synchronously from the unpacking thread, so it
+    /// should return quickly.
+    pub fn progress<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&UnpackProgress) + Send + Sync + 'static,
+    {
+        self.progress = Some(ProgressCallback::new(callback));
+        self
+    }
+
+    /// Only extract entries located under one of the given paths.
+    ///
+    /// Paths are interpreted relative to the root of the image, e.g. `/etc`
+    /// selects the whole `etc` directory. Hardlinks pointing outside of the
+    /// selected paths cannot be extracted.
+    pub fn include_paths<I, P>(mut self, paths: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<path::Path>,
+    {
+        self.filter.prefixes = paths
+            .into_iter()
+            .map(|p| normalize_path(p.as_ref()))
+            .collect();
+        self
+    }
+
+    /// Only extract entries for which `predicate` returns `true`.
+    ///
+    /// The predicate receives entry paths relative to the root of the image.
+    pub fn filter<F>(mut self, predicate: F) -> Self
+    where
+        F: Fn(&path::Path) -> bool + Send + Sync + 'static,
+    {
+        self.filter.predicate = Some(PathPredicate::new(predicate));
+        self
+    }
+
+    /// Remap owner user IDs of unpacked entries through the given ranges.
+    ///
+    /// When either this or `gid_map` is set, ownership of entries is changed
+    /// according to the archive headers, with an unset map meaning identity.
+    /// Entries with an unmapped owner cause an error.
+    pub fn uid_map(mut self, mappings: Vec<IdMapping>) -> Self {
+        self.ownership.uid_map = Some(mappings);
+        self
+    }
+
+    /// Remap owner group IDs of unpacked entries through the given ranges.
+    ///
+    /// See `uid_map` for details.
+    pub fn gid_map(mut self, mappings: Vec<IdMapping>) -> Self {
+        self.ownership.gid_map = Some(mappings);
+        self
+    }
+
+    /// Force ownership of all unpacked entries to the given user and group IDs.
+    ///
+    /// This takes precedence over `uid_map` and `gid_map`.
+    pub fn chown(mut self, uid: u32, gid: u32) -> Self {
+        self.ownership.fixed = Some(FixedOwner::Ids { uid, gid });
+        self
+    }
+
+    /// Force ownership of all unpacked entries to the current effective user.
+    ///
+    /// This takes precedence over `uid_map` and `gid_map`.
+    pub fn chown_current_user(mut self) -> Self {
+        self.ownership.fixed = Some(FixedOwner::CurrentUser);
+        self
+    }
+
+    /// Set the expected digest of each layer blob, by position.
+    ///
+    /// Layers are hashed while being unpacked, and a mismatch fails with
+    /// `RenderError::DigestMismatch`. As the check can only complete once a
+    /// layer has been fully consumed, its content may already have been
+    /// written by then. Layers beyond the end of this list are not verified.
+    pub fn layer_digests(mut self, digests: Vec<String>) -> Self {
+        self.layer_digests = digests;
+        self
+    }
+
+    /// Set the expected DiffID (digest of the uncompressed tar) of each layer,
+    /// by position.
+    ///
+    /// This behaves like `layer_digests`, failing with
+    /// `RenderError::DiffIdMismatch` on mismatch.
+    pub fn diff_ids(mut self, diff_ids: Vec<String>) -> Self {
+        self.diff_ids = diff_ids;
+        self
+    }
+
+    /// Decompress up to `workers` layers concurrently (default: 1).
+    ///
+    /// Layers are decompressed into temporary files, which are then applied
+    /// in order, trading disk space for speed on many-layer images. This only
+    /// affects `unpack_with_options`, as streamed layers are consumed in order.
+    pub fn decompression_workers(mut self, workers: usize) -> Self {
+        self.decompression_workers = workers;
+        self
+    }
+
+    /// Skip entries which fail to extract, instead of aborting (default: false).
+    ///
+    /// Skipped entries and the corresponding errors are listed in the
+    /// returned `UnpackReport`. Errors affecting a whole layer (e.g. a
+    /// corrupted stream or a digest mismatch) are still fatal. This is not
+    /// supported by `unpack_overlay`.
+    pub fn continue_on_error(mut self, continue_on_error: bool) -> Self {
+        self.continue_on_error = continue_on_error;
+        self
+    }
+
+    /// Set whether modification times from the archive are applied (default: true).
+    ///
+    /// When enabled, all entries (including directories, which are set after
+    /// their content) get the mtime recorded in their layer. Otherwise, they
+    /// keep the time at which they were written.
+    pub fn preserve_mtimes(mut self, preserve: bool) -> Self {
+        self.discard_mtimes = !preserve;
+        self
+    }
+
+    /// Set whether extended attributes of `class` are unpacked (default: true).
+    ///
+    /// This allows unpacking to targets which reject some attribute
+    /// namespaces, e.g. `security.selinux` on filesystems without SELinux
+    /// support, instead of failing.
+    pub fn unpack_xattrs(mut self, class: XattrClass, unpack: bool) -> Self {
+        self.xattrs.set(class, unpack);
+        self
+    }
+
+    /// Make archives produced by `flatten_with_options` reproducible
+    /// (default: false).
+    ///
+    /// Entries are sorted by path, with zeroed timestamps, without user and
+    /// group names and with sorted PAX records.
+    ///
+    /// To be sorted, the uncompressed archive is buffered in memory, as
+    /// well as a copy of its entries: this needs about twice the size of the
+    /// flattened filesystem.
+    pub fn deterministic(mut self, deterministic: bool) -> Self {
+        self.canonical = if deterministic {
+            Some(Canonical::default())
+        } else {
+            None
+        };
+        self
+    }
+
+    /// Make flattened archives reproducible, recording `epoch` (in seconds
+    /// since the Unix epoch) as modification time of all entries.
+    ///
+    /// This is meant for `SOURCE_DATE_EPOCH`, see `deterministic`, which
+    /// also describes the memory this needs.
+    pub fn source_date_epoch(mut self, epoch: u64) -> Self {
+        self.canonical = Some(Canonical { mtime: epoch });
+        self
+    }
+
+    /// Abort when a single layer decompresses to more than `bytes` bytes.
+    ///
+    /// Exceeding any limit fails with `RenderError::LimitExceeded`, even when
+    /// continuing on errors.
+    pub fn max_layer_size(mut self, bytes: u64) -> Self {
+        self.limits.layer_size = Some(bytes);
+        self
+    }
+
+    /// Abort when all layers together decompress to more than `bytes` bytes.
+    pub fn max_total_size(mut self, bytes: u64) -> Self {
+        self.limits.total_size = Some(bytes);
+        self
+    }
+
+    /// Abort when a single layer contains more than `count` entries.
+    pub fn max_entries(mut self, count: u64) -> Self {
+        self.limits.entries = Some(count);
+        self
+    }
+
+    /// Abort when an entry path has more than `depth` components.
+    pub fn max_path_depth(mut self, depth: usize) -> Self {
+        self.limits.path_depth = Some(depth);
+        self
+    }
+
+    /// Set the media type of each layer, by position, as listed in the manifest.
+    ///
+    /// This is used to recognize foreign layers, see `foreign_layers`, and
+    /// the compression of layers not set via `layer_compressions`.
+    pub fn layer_media_types(mut self, media_types: Vec<String>) -> Self {
+        self.layer_media_types = media_types;
+        self
+    }
+
+    /// Set how foreign layers are handled (default:
+    /// `ForeignLayerPolicy::Error`).
+    ///
+    /// `Fetch` and `Registry` unpack them as other layers, as in both cases
+    /// their content was downloaded.
+    pub fn foreign_layers(mut self, policy: ForeignLayerPolicy) -> Self {
+        self.foreign_layers = policy;
+        self
+    }
+
+    /// Degrade privileged operations gracefully when not running as root
+    /// (default: false).
+    ///
+    /// When enabled and running unprivileged, device nodes are skipped,
+    /// failures to change ownership are logged instead of aborting, and
+    /// setuid/setgid bits are cleared from permissions.
+    pub fn rootless(mut self, rootless: bool) -> Self {
+        self.rootless = rootless;
+        self
+    }
+
+    /// Whether rootless mode is requested and actually needed.
+    pub(crate) fn rootless_active(&self) -> bool {
+        self.rootless && !running_as_root()
+    }
+
+    /// Whether the layer at `index` must be skipped, as a foreign layer.
+    pub(crate) fn skips_layer(&self, index: usize) -> Result<bool, RenderError> {
+        let media_type = match self.layer_media_types.get(index) {
+            Some(m) if is_foreign(m) => m,
+            _ => return Ok(false),
+        };
+        match self.foreign_layers {
+            ForeignLayerPolicy::Error => Err(RenderError::ForeignLayer {
+                layer: index,
+                media_type: media_type.clone(),
+            }),
+            ForeignLayerPolicy::Skip => {
+                warn!("skipping foreign layer {} ({})", index, media_type);
+                Ok(true)
+            }
+            ForeignLayerPolicy::Fetch | ForeignLayerPolicy::Registry => Ok(false),
+        }
+    }
+
+    /// Expected blob digest for the layer at `index`, if any.
+    pub(crate) fn layer_digest_for(&self, index: usize) -> Option<&str> {
+        self.layer_digests.get(index).map(String::as_str)
+    }
+
+    /// Expected DiffID for the layer at `index`, if any.
+    pub(crate) fn diff_id_for(&self, index: usize) -> Option<&str> {
+        self.diff_ids.get(index).map(String::as_str)
+    }
+
+    /// Compression algorithm for the layer at `index`.
+    pub(crate) fn compression_for(&self, index: usize) -> LayerCompression {
+        if let Some(compression) = self.layer_compressions.get(index) {
+            return *compression;
+        }
+        match self.layer_media_types.get(index) {
+            Some(media_type) if self.compression == LayerCompression::Auto => {
+                LayerCompression::from_media_type(media_type).unwrap_or(self.compression)
+            }
+            _ => self.compression,
+        }
+    }
+}
@@ -0,0 +1,146 @@
+//! Rendering of layers as overlayfs lower directories.
+
+use super::{
+    contained_path, ensure_target_dir, for_each_layer, unpack_layer, RenderError, UnpackOptions,
+    Whiteouts,
+};
+use std::io::Read;
+use std::{fs, path};
+
+/// Extended attribute marking a directory as opaque for overlayfs.
+const OVERLAY_OPAQUE_XATTR: &str = "trusted.overlay.opaque";
+
+/// Unpack each layer into its own directory, laid out for overlayfs.
+///
+/// Layer `N` is extracted to `target_dir/N`, which is created if missing.
+/// Instead of being applied, whiteouts are translated to their overlayfs
+/// encoding: deleted paths become `0:0` character devices and opaque
+/// directories get the `trusted.overlay.opaque` extended attribute. This
+/// usually requires root privileges.
+///
+/// The layer directories are returned lowest first; note that overlayfs
+/// expects `lowerdir` entries in the opposite order.
+pub fn unpack_overlay(
+    layers: &[Vec<u8>],
+    target_dir: &path::Path,
+    options: &UnpackOptions,
+) -> Result<Vec<path::PathBuf>, RenderError> {
+    unpack_overlay_streaming(layers.iter().map(Vec::as_slice), target_dir, options)
+}
+
+/// Unpack each layer, read from streams, into its own overlayfs directory.
+///
+/// See `unpack_overlay` for details.
+pub fn unpack_overlay_streaming<I, R>(
+    layers: I,
+    target_dir: &path::Path,
+    options: &UnpackOptions,
+) -> Result<Vec<path::PathBuf>, RenderError>
+where
+    I: IntoIterator<Item = R>,
+    R: Read,
+{
+    ensure_target_dir(target_dir)?;
+    let mut layer_dirs = Vec::new();
+    for_each_layer(layers, options, |index, reader| {
+        let layer_dir = target_dir.join(index.to_string());
+        fs::create_dir_all(&layer_dir)?;
+        unpack_layer(
+            index,
+            reader,
+            &layer_dir,
+            options,
+            Whiteouts::Overlay,
+            None,
+            None,
+        )?;
+        layer_dirs.push(layer_dir);
+        Ok(())
+    })?;
+    Ok(layer_dirs)
+}
+
+/// Record a whiteout of `rel_path` as an overlayfs `0:0` character device.
+pub(crate) fn write_whiteout(root: &path::Path, rel_path: &path::Path) -> Result<(), RenderError> {
+    let abs_path = contained_path(root, rel_path)?;
+    if let Some(parent) = abs_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    sys::mknod_whiteout(&abs_path)?;
+    Ok(())
+}
+
+/// Mark the directory at `rel_dir` as opaque for overlayfs.
+pub(crate) fn mark_opaque(root: &path::Path, rel_dir: &path::Path) -> Result<(), RenderError> {
+    let abs_dir = if rel_dir.as_os_str().is_empty() {
+        root.to_path_buf()
+    } else {
+        contained_path(root, rel_dir)?
+    };
+    fs::create_dir_all(&abs_dir)?;
+    sys::set_xattr(&abs_dir, OVERLAY_OPAQUE_XATTR, b"y")?;
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+mod sys {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+    use std::{io, path};
+
+    fn c_path(p: &path::Path) -> io::Result<CString> {
+        CString::new(p.as_os_str().as_bytes())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+    }
+
+    pub(super) fn mknod_whiteout(abs_path: &path::Path) -> io::Result<()> {
+        let c_path = c_path(abs_path)?;
+        // SAFETY: `c_path` is a valid NUL-terminated string.
+        let ret = unsafe { libc::mknod(c_path.as_ptr(), libc::S_IFCHR, libc::makedev(0, 0)) };
+        if ret != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    pub(super) fn set_xattr(abs_path: &path::Path, name: &str, value: &[u8]) -> io::Result<()> {
+        let c_path = c_path(abs_path)?;
+        let c_name =
+            CString::new(name).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        // SAFETY: `c_path` and `c_name` are valid NUL-terminated strings, and
+        // `value` is valid for `value.len()` bytes.
+        let ret = unsafe {
+            libc::lsetxattr(
+                c_path.as_ptr(),
+                c_name.as_ptr(),
+                value.as_ptr().cast(),
+                value.len(),
+                0,
+            )
+        };
+        if ret != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod sys {
+    use std::{io, path};
+
+    fn unsupported() -> io::Error {
+        io::Error::new(
+            io::ErrorKind::Other,
+            "overlayfs layout is only supported on Linux",
+        )
+    }
+
+    pub(super) fn mknod_whiteout(_abs_path: &path::Path) -> io::Result<()> {
+        Err(unsupported())
+    }
+
+    pub(super) fn set_xattr(_abs_path: &path::Path, _name: &str, _value: &[u8]) -> io::Result<()> {
+        Err(unsupported())
+    }
+}
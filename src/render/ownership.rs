@@ -0,0 +1,152 @@
+//! Ownership of unpacked entries.
+
+use super::RenderError;
+use std::convert::TryFrom;
+use std::path;
+
+/// A contiguous range of IDs mapped from the image to the host,
+/// with the same semantics as user namespaces `uid_map`/`gid_map` lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IdMapping {
+    /// First ID of the range, as found in the image.
+    pub container_id: u32,
+    /// First ID of the range, as written on the host.
+    pub host_id: u32,
+    /// Number of IDs in the range.
+    pub size: u32,
+}
+
+impl IdMapping {
+    pub fn new(container_id: u32, host_id: u32, size: u32) -> Self {
+        Self {
+            container_id,
+            host_id,
+            size,
+        }
+    }
+
+    /// Map an image ID through this range, if it belongs to it.
+    pub fn map(&self, id: u32) -> Option<u32> {
+        let offset = id.checked_sub(self.container_id)?;
+        if offset < self.size {
+            self.host_id.checked_add(offset)
+        } else {
+            None
+        }
+    }
+}
+
+/// Fixed owner forced onto all unpacked entries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FixedOwner {
+    Ids { uid: u32, gid: u32 },
+    CurrentUser,
+}
+
+/// Ownership policy for unpacked entries.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Ownership {
+    pub(crate) uid_map: Option<Vec<IdMapping>>,
+    pub(crate) gid_map: Option<Vec<IdMapping>>,
+    pub(crate) fixed: Option<FixedOwner>,
+}
+
+impl Ownership {
+    /// Whether ownership has to be changed at all.
+    fn is_enabled(&self) -> bool {
+        self.fixed.is_some() || self.uid_map.is_some() || self.gid_map.is_some()
+    }
+
+    /// Set the owner of an unpacked entry at `abs_path`, given its tar `header`.
+    pub(crate) fn apply(
+        &self,
+        abs_path: &path::Path,
+        header: &tar::Header,
+    ) -> Result<(), RenderError> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+        match self.fixed {
+            Some(FixedOwner::Ids { uid, gid }) => return chown(abs_path, uid, gid),
+            Some(FixedOwner::CurrentUser) => {
+                let (uid, gid) = current_user();
+                return chown(abs_path, uid, gid);
+            }
+            None => {}
+        }
+        let uid = map_id(self.uid_map.as_deref(), header.uid()?, abs_path)?;
+        let gid = map_id(self.gid_map.as_deref(), header.gid()?, abs_path)?;
+        chown(abs_path, uid, gid)
+    }
+}
+
+/// Map `id` through `mappings`, with a missing map meaning identity.
+fn map_id(
+    mappings: Option<&[IdMapping]>,
+    id: u64,
+    abs_path: &path::Path,
+) -> Result<u32, RenderError> {
+    let unmapped = || RenderError::UnmappedId(id, abs_path.to_path_buf());
+    let id = u32::try_from(id).map_err(|_| unmapped())?;
+    match mappings {
+        None => Ok(id),
+        Some(ranges) => ranges.iter().find_map(|m| m.map(id)).ok_or_else(unmapped),
+    }
+}
+
+/// Whether the current process runs with root privileges.
+pub(crate) fn running_as_root() -> bool {
+    current_user().0 == 0
+}
+
+/// Effective user and group IDs of the current process.
+#[cfg(unix)]
+fn current_user() -> (u32, u32) {
+    // SAFETY: these calls have no preconditions and cannot fail.
+    unsafe { (libc::geteuid(), libc::getegid()) }
+}
+
+#[cfg(not(unix))]
+fn current_user() -> (u32, u32) {
+    (0, 0)
+}
+
+#[cfg(unix)]
+fn chown(abs_path: &path::Path, uid: u32, gid: u32) -> Result<(), RenderError> {
+    use std::ffi::CString;
+    use std::io;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = CString::new(abs_path.as_os_str().as_bytes())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    // SAFETY: `c_path` is a valid NUL-terminated string.
+    let ret = unsafe { libc::lchown(c_path.as_ptr(), uid, gid) };
+    if ret != 0 {
+        return Err(io::Error::last_os_error().into());
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn chown(_abs_path: &path::Path, _uid: u32, _gid: u32) -> Result<(), RenderError> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn id_mapping_ranges() {
+        let mapping = IdMapping::new(0, 100000, 65536);
+        assert_eq!(mapping.map(0), Some(100000));
+        assert_eq!(mapping.map(1000), Some(101000));
+        assert_eq!(mapping.map(65535), Some(165535));
+        assert_eq!(mapping.map(65536), None);
+
+        let shifted = IdMapping::new(1000, 0, 1);
+        assert_eq!(shifted.map(999), None);
+        assert_eq!(shifted.map(1000), Some(0));
+        assert_eq!(shifted.map(1001), None);
+    }
+}
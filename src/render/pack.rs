@@ -0,0 +1,146 @@
+//! Creation of layers from filesystem changes.
+
+use super::canonical::{gzip_encoder, Canonical};
+use super::{diff_dirs, Change, ChangeKind, RenderError, WHITEOUT_PREFIX};
+use std::io::Write;
+use std::path;
+
+/// Options controlling how layers are created.
+#[derive(Debug, Default, Clone)]
+pub struct PackOptions {
+    canonical: Option<Canonical>,
+}
+
+impl PackOptions {
+    /// Initialize `PackOptions` with default values.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Make the layer reproducible (default: false), so that the same input
+    /// always yields the same layer digest.
+    ///
+    /// Entries are written, and buffered in memory, as with
+    /// `UnpackOptions::deterministic`.
+    pub fn deterministic(mut self, deterministic: bool) -> Self {
+        self.canonical = if deterministic {
+            Some(Canonical::default())
+        } else {
+            None
+        };
+        self
+    }
+
+    /// Make the layer reproducible, as with `UnpackOptions::source_date_epoch`.
+    pub fn source_date_epoch(mut self, epoch: u64) -> Self {
+        self.canonical = Some(Canonical { mtime: epoch });
+        self
+    }
+}
+
+/// Create a gzip-compressed layer turning `base_dir` into `modified_dir`.
+///
+/// This is the inverse of `unpack`: the layer holds every path added or
+/// modified in `modified_dir` (see `diff_dirs`), along with whiteouts for
+/// deleted paths. The layer is written to `writer`, which is handed back on
+/// success.
+pub fn pack<W: Write>(
+    base_dir: &path::Path,
+    modified_dir: &path::Path,
+    writer: W,
+) -> Result<W, RenderError> {
+    pack_with_options(base_dir, modified_dir, writer, &PackOptions::default())
+}
+
+/// Create a gzip-compressed layer turning `base_dir` into `modified_dir`,
+/// with custom options.
+pub fn pack_with_options<W: Write>(
+    base_dir: &path::Path,
+    modified_dir: &path::Path,
+    writer: W,
+    options: &PackOptions,
+) -> Result<W, RenderError> {
+    let changes = diff_dirs(base_dir, modified_dir)?;
+    pack_changes_with_options(&changes, modified_dir, writer, options)
+}
+
+/// Create a gzip-compressed layer from a list of `changes`.
+///
+/// Added and modified paths are read from `modified_dir`; directories are
+/// archived without their content, which must be listed separately.
+pub fn pack_changes<W: Write>(
+    changes: &[Change],
+    modified_dir: &path::Path,
+    writer: W,
+) -> Result<W, RenderError> {
+    pack_changes_with_options(changes, modified_dir, writer, &PackOptions::default())
+}
+
+/// Create a gzip-compressed layer from a list of `changes`, with custom options.
+pub fn pack_changes_with_options<W: Write>(
+    changes: &[Change],
+    modified_dir: &path::Path,
+    writer: W,
+    options: &PackOptions,
+) -> Result<W, RenderError> {
+    let mut encoder = gzip_encoder(writer)?;
+    match options.canonical {
+        Some(canonical) => {
+            let archive = write_changes(changes, modified_dir, Vec::new())?;
+            let mut builder = tar::Builder::new(&mut encoder);
+            canonical.rewrite(&archive, &mut builder)?;
+            builder.finish()?;
+        }
+        None => {
+            write_changes(changes, modified_dir, &mut encoder)?;
+        }
+    }
+    encoder.finish().into_result().map_err(Into::into)
+}
+
+/// Write `changes` as an uncompressed tar archive.
+fn write_changes<W: Write>(
+    changes: &[Change],
+    modified_dir: &path::Path,
+    writer: W,
+) -> Result<W, RenderError> {
+    let mut builder = tar::Builder::new(writer);
+    builder.follow_symlinks(false);
+    for change in changes {
+        if !change
+            .path
+            .components()
+            .all(|c| matches!(c, path::Component::Normal(_)))
+        {
+            return Err(RenderError::PathEscape(change.path.clone()));
+        }
+        match change.kind {
+            ChangeKind::Added | ChangeKind::Modified => {
+                builder.append_path_with_name(modified_dir.join(&change.path), &change.path)?
+            }
+            ChangeKind::Deleted => append_whiteout(&mut builder, &change.path)?,
+        }
+    }
+    builder.into_inner().map_err(Into::into)
+}
+
+/// Append a whiteout entry deleting `rel_path`.
+fn append_whiteout<W: Write>(
+    builder: &mut tar::Builder<W>,
+    rel_path: &path::Path,
+) -> Result<(), RenderError> {
+    let fname = match rel_path.file_name() {
+        Some(f) => f.to_string_lossy(),
+        None => return Err(RenderError::PathEscape(rel_path.to_path_buf())),
+    };
+    let whiteout = rel_path.with_file_name(format!("{}{}", WHITEOUT_PREFIX, fname));
+    let mut header = tar::Header::new_gnu();
+    header.set_entry_type(tar::EntryType::Regular);
+    header.set_size(0);
+    header.set_mode(0);
+    header.set_uid(0);
+    header.set_gid(0);
+    header.set_mtime(0);
+    builder.append_data(&mut header, whiteout, std::io::empty())?;
+    Ok(())
+}
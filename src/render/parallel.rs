@@ -0,0 +1,111 @@
+//! Concurrent decompression of layers, with sequential application.
+
+use super::{
+    ensure_target_dir, read_layer, unpack_layer, RenderError, UnpackOptions, UnpackReport,
+    Whiteouts,
+};
+use std::fs;
+use std::io::{self, Seek};
+use std::path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc;
+use std::thread;
+
+/// Unpack layers, decompressing them concurrently into temporary spools.
+///
+/// Layers are distributed round-robin across `decompression_workers`
+/// threads, while the calling thread applies spools in layer order as soon
+/// as they become available.
+pub(crate) fn unpack_parallel(
+    layers: &[Vec<u8>],
+    target_dir: &path::Path,
+    options: &UnpackOptions,
+) -> Result<UnpackReport, RenderError> {
+    ensure_target_dir(target_dir)?;
+    let workers = options.decompression_workers.min(layers.len()).max(1);
+    let cancelled = AtomicBool::new(false);
+    let total_bytes = AtomicU64::new(0);
+
+    let mut jobs: Vec<Vec<_>> = (0..workers).map(|_| Vec::new()).collect();
+    let mut spools = Vec::with_capacity(layers.len());
+    for (index, layer) in layers.iter().enumerate() {
+        let (tx, rx) = mpsc::sync_channel(1);
+        jobs[index % workers].push((index, layer, tx));
+        spools.push(rx);
+    }
+
+    thread::scope(|scope| {
+        for worker_jobs in jobs {
+            let cancelled = &cancelled;
+            let total_bytes = &total_bytes;
+            scope.spawn(move || {
+                for (index, layer, tx) in worker_jobs {
+                    if cancelled.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    // A closed channel means that applying already failed.
+                    let _ = tx.send(spool_layer(index, layer, options, total_bytes));
+                }
+            });
+        }
+
+        let result = apply_spools(spools, target_dir, options);
+        if result.is_err() {
+            cancelled.store(true, Ordering::Relaxed);
+        }
+        result
+    })
+}
+
+/// A decompressed layer, along with its DiffID.
+type Spool = (fs::File, String);
+
+/// Decompress (and verify) a layer into an anonymous temporary file.
+fn spool_layer(
+    index: usize,
+    layer: &[u8],
+    options: &UnpackOptions,
+    total_bytes: &AtomicU64,
+) -> Result<Spool, RenderError> {
+    let mut spool = tempfile::tempfile()?;
+    let diff_id = read_layer(index, layer, options, total_bytes, |_, reader| {
+        io::copy(reader, &mut spool)?;
+        Ok(())
+    })?;
+    spool.rewind()?;
+    Ok((spool, diff_id))
+}
+
+/// Apply spooled layers in order, as they get ready.
+fn apply_spools(
+    spools: Vec<mpsc::Receiver<Result<Spool, RenderError>>>,
+    target_dir: &path::Path,
+    options: &UnpackOptions,
+) -> Result<UnpackReport, RenderError> {
+    let mut report = UnpackReport::default();
+    let mut diff_ids = Vec::with_capacity(spools.len());
+    for (index, rx) in spools.into_iter().enumerate() {
+        let (spool, diff_id) = rx.recv().map_err(|_| {
+            io::Error::new(io::ErrorKind::Other, "decompression worker exited early")
+        })??;
+        // Spools are uncompressed, so large files can be copied out of them.
+        let source = if cfg!(target_os = "linux") {
+            Some(spool.try_clone()?)
+        } else {
+            None
+        };
+        let stats = unpack_layer(
+            index,
+            io::BufReader::new(spool),
+            target_dir,
+            options,
+            Whiteouts::Apply,
+            Some(report.skipped_mut()),
+            source.as_ref(),
+        )?;
+        report.push_layer(stats);
+        diff_ids.push(diff_id);
+    }
+    report.set_diff_ids(diff_ids);
+    Ok(report)
+}
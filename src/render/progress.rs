@@ -0,0 +1,78 @@
+//! Progress reporting for layers unpacking.
+
+use std::cell::Cell;
+use std::io::{self, Read};
+use std::path;
+use std::rc::Rc;
+use std::sync::Arc;
+
+/// A progress event emitted while unpacking layers.
+///
+/// Byte counters refer to the decompressed tar stream of the current layer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UnpackProgress {
+    /// Unpacking of a layer has started.
+    LayerStarted { layer: usize },
+    /// An entry has been written to the target directory.
+    EntryWritten {
+        layer: usize,
+        path: path::PathBuf,
+        bytes_decompressed: u64,
+        entries_written: u64,
+    },
+    /// Unpacking of a layer has completed.
+    LayerFinished {
+        layer: usize,
+        bytes_decompressed: u64,
+        entries_written: u64,
+    },
+}
+
+/// Shared progress callback, as stored by `UnpackOptions`.
+#[derive(Clone)]
+pub(crate) struct ProgressCallback(Arc<dyn Fn(&UnpackProgress) + Send + Sync>);
+
+impl ProgressCallback {
+    pub(crate) fn new<F>(callback: F) -> Self
+    where
+        F: Fn(&UnpackProgress) + Send + Sync + 'static,
+    {
+        Self(Arc::new(callback))
+    }
+
+    pub(crate) fn report(&self, event: &UnpackProgress) {
+        (self.0)(event)
+    }
+}
+
+impl std::fmt::Debug for ProgressCallback {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("ProgressCallback")
+    }
+}
+
+/// A reader keeping track of how many bytes went through it.
+pub(crate) struct CountingReader<R> {
+    inner: R,
+    count: Rc<Cell<u64>>,
+}
+
+impl<R: Read> CountingReader<R> {
+    /// Wrap `inner`, returning the reader and a handle to its counter.
+    pub(crate) fn new(inner: R) -> (Self, Rc<Cell<u64>>) {
+        let count = Rc::new(Cell::new(0));
+        let reader = Self {
+            inner,
+            count: count.clone(),
+        };
+        (reader, count)
+    }
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count.set(self.count.get() + n as u64);
+        Ok(n)
+    }
+}
@@ -0,0 +1,113 @@
+//! Outcome of layers unpacking.
+
+use super::{EntryKind, RenderError};
+use std::path;
+
+/// Statistics about the unpacking of a single layer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LayerStats {
+    /// Regular files written.
+    pub files: u64,
+    pub directories: u64,
+    pub symlinks: u64,
+    pub hardlinks: u64,
+    /// Other entries written, such as devices and fifos.
+    pub other: u64,
+    /// Content bytes of regular files written.
+    pub bytes_written: u64,
+    /// Whiteouts (plain or opaque) applied.
+    pub whiteouts: u64,
+}
+
+impl LayerStats {
+    /// Account for a written entry of the given `kind` and content `size`.
+    pub(crate) fn record(&mut self, kind: EntryKind, size: u64) {
+        match kind {
+            EntryKind::File => {
+                self.files += 1;
+                self.bytes_written += size;
+            }
+            EntryKind::Directory => self.directories += 1,
+            EntryKind::Symlink => self.symlinks += 1,
+            EntryKind::Hardlink => self.hardlinks += 1,
+            _ => self.other += 1,
+        }
+    }
+}
+
+impl std::ops::Add for LayerStats {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            files: self.files + other.files,
+            directories: self.directories + other.directories,
+            symlinks: self.symlinks + other.symlinks,
+            hardlinks: self.hardlinks + other.hardlinks,
+            other: self.other + other.other,
+            bytes_written: self.bytes_written + other.bytes_written,
+            whiteouts: self.whiteouts + other.whiteouts,
+        }
+    }
+}
+
+/// An archive entry which could not be extracted.
+#[derive(Debug)]
+pub struct SkippedEntry {
+    /// Index of the layer containing the entry.
+    pub layer: usize,
+    /// Path of the entry, relative to the root of the image.
+    pub path: path::PathBuf,
+    /// Why extracting the entry failed.
+    pub error: RenderError,
+}
+
+/// Report of a completed unpacking.
+#[derive(Debug, Default)]
+pub struct UnpackReport {
+    layers: Vec<LayerStats>,
+    skipped: Vec<SkippedEntry>,
+    diff_ids: Vec<String>,
+}
+
+impl UnpackReport {
+    /// Statistics of each unpacked layer, in layer order.
+    pub fn layers(&self) -> &[LayerStats] {
+        &self.layers
+    }
+
+    /// Statistics summed over all layers.
+    pub fn totals(&self) -> LayerStats {
+        self.layers
+            .iter()
+            .fold(LayerStats::default(), |acc, stats| acc + *stats)
+    }
+
+    /// Entries skipped because of errors, in extraction order.
+    ///
+    /// This is always empty unless `UnpackOptions::continue_on_error` is set.
+    pub fn skipped(&self) -> &[SkippedEntry] {
+        &self.skipped
+    }
+
+    /// DiffIDs (sha256 digests of the uncompressed tar) of the unpacked
+    /// layers, in layer order.
+    ///
+    /// These can be matched against the `rootfs.diff_ids` of the image
+    /// configuration.
+    pub fn diff_ids(&self) -> &[String] {
+        &self.diff_ids
+    }
+
+    pub(crate) fn push_layer(&mut self, stats: LayerStats) {
+        self.layers.push(stats);
+    }
+
+    pub(crate) fn set_diff_ids(&mut self, diff_ids: Vec<String>) {
+        self.diff_ids = diff_ids;
+    }
+
+    pub(crate) fn skipped_mut(&mut self) -> &mut Vec<SkippedEntry> {
+        &mut self.skipped
+    }
+}
@@ -0,0 +1,88 @@
+//! Verification of layer digests while unpacking.
+
+use super::RenderError;
+use crate::v2::ContentDigest;
+use sha2::{Digest, Sha256};
+use std::io::{self, Read};
+
+/// Which digest of a layer is being verified.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DigestKind {
+    /// Digest of the layer blob, as stored in the registry.
+    Blob,
+    /// Digest of the uncompressed layer tar (DiffID).
+    DiffId,
+}
+
+/// A reader computing the sha256 digest of all data going through it.
+pub(crate) struct DigestReader<R> {
+    inner: R,
+    hasher: Option<Sha256>,
+    expected: Option<String>,
+}
+
+impl<R: Read> DigestReader<R> {
+    /// Wrap `inner`, only hashing it if an `expected` digest is set.
+    pub(crate) fn verifying(inner: R, expected: Option<&str>) -> Result<Self, RenderError> {
+        Self::new(inner, expected, expected.is_some())
+    }
+
+    /// Wrap `inner`, always hashing it and verifying it against `expected` (if any).
+    pub(crate) fn hashing(inner: R, expected: Option<&str>) -> Result<Self, RenderError> {
+        Self::new(inner, expected, true)
+    }
+
+    fn new(inner: R, expected: Option<&str>, hash: bool) -> Result<Self, RenderError> {
+        // Reject malformed digests and unsupported algorithms upfront.
+        if let Some(expected) = expected {
+            ContentDigest::try_new(expected)?;
+        }
+        Ok(Self {
+            inner,
+            hasher: if hash { Some(Sha256::new()) } else { None },
+            expected: expected.map(str::to_string),
+        })
+    }
+
+    /// Consume the rest of the stream and verify its digest.
+    ///
+    /// Returns the computed digest, if the stream has been hashed.
+    pub(crate) fn finish(
+        mut self,
+        layer: usize,
+        kind: DigestKind,
+    ) -> Result<Option<String>, RenderError> {
+        if self.hasher.is_some() {
+            io::copy(&mut self, &mut io::sink())?;
+        }
+        let got = match self.hasher {
+            Some(hasher) => format!("sha256:{:x}", hasher.finalize()),
+            None => return Ok(None),
+        };
+        match self.expected {
+            Some(expected) if expected != got => Err(match kind {
+                DigestKind::Blob => RenderError::DigestMismatch {
+                    layer,
+                    expected,
+                    got,
+                },
+                DigestKind::DiffId => RenderError::DiffIdMismatch {
+                    layer,
+                    expected,
+                    got,
+                },
+            }),
+            _ => Ok(Some(got)),
+        }
+    }
+}
+
+impl<R: Read> Read for DigestReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        if let Some(hasher) = &mut self.hasher {
+            hasher.update(&buf[..n]);
+        }
+        Ok(n)
+    }
+}
@@ -0,0 +1,97 @@
+//! Selective restoration of extended attributes.
+
+use super::RenderError;
+use std::io::Read;
+use std::path;
+
+/// Prefix of PAX records carrying extended attributes.
+pub(crate) const PAX_XATTR_PREFIX: &str = "SCHILY.xattr.";
+
+/// A class of extended attributes, which can be unpacked or not.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XattrClass {
+    /// File capabilities (`security.capability`).
+    Capability,
+    /// SELinux labels (`security.selinux`).
+    Selinux,
+    /// User attributes (`user.*`).
+    User,
+    /// Any other attribute (e.g. `trusted.*` or other `security.*` ones).
+    Other,
+}
+
+impl XattrClass {
+    /// Classify an extended attribute by name.
+    pub fn of(name: &[u8]) -> Self {
+        match name {
+            b"security.capability" => XattrClass::Capability,
+            b"security.selinux" => XattrClass::Selinux,
+            n if n.starts_with(b"user.") => XattrClass::User,
+            _ => XattrClass::Other,
+        }
+    }
+}
+
+/// Classes of extended attributes excluded from unpacking.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct XattrFilter {
+    excluded: Vec<XattrClass>,
+}
+
+impl XattrFilter {
+    /// Set whether attributes of `class` are unpacked.
+    pub(crate) fn set(&mut self, class: XattrClass, unpack: bool) {
+        self.excluded.retain(|c| *c != class);
+        if !unpack {
+            self.excluded.push(class);
+        }
+    }
+
+    /// Whether all attributes are unpacked, so that tar can handle them.
+    pub(crate) fn is_unfiltered(&self) -> bool {
+        self.excluded.is_empty()
+    }
+
+    /// Set the allowed extended attributes of `file` on `abs_path`.
+    pub(crate) fn apply<R: Read>(
+        &self,
+        file: &mut tar::Entry<R>,
+        abs_path: &path::Path,
+    ) -> Result<(), RenderError> {
+        let extensions = match file.pax_extensions()? {
+            Some(extensions) => extensions,
+            None => return Ok(()),
+        };
+        for ext in extensions {
+            let ext = ext?;
+            let name = match ext.key_bytes().strip_prefix(PAX_XATTR_PREFIX.as_bytes()) {
+                Some(name) => name,
+                None => continue,
+            };
+            if !self.excluded.contains(&XattrClass::of(name)) {
+                sys::set_xattr(abs_path, name, ext.value_bytes())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+mod sys {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+    use std::{io, path};
+
+    pub(super) fn set_xattr(abs_path: &path::Path, name: &[u8], value: &[u8]) -> io::Result<()> {
+        xattr::set(abs_path, OsStr::from_bytes(name), value)
+    }
+}
+
+#[cfg(not(unix))]
+mod sys {
+    use std::{io, path};
+
+    pub(super) fn set_xattr(_abs_path: &path::Path, _name: &[u8], _value: &[u8]) -> io::Result<()> {
+        Ok(())
+    }
+}
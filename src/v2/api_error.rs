@@ -0,0 +1,152 @@
+use crate::errors::Error;
+use reqwest::Response;
+use std::fmt;
+
+/// Error code defined by the distribution spec.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(from = "String")]
+pub enum ErrorCode {
+    BlobUnknown,
+    BlobUploadInvalid,
+    BlobUploadUnknown,
+    DigestInvalid,
+    ManifestBlobUnknown,
+    ManifestInvalid,
+    ManifestUnknown,
+    NameInvalid,
+    NameUnknown,
+    SizeInvalid,
+    Unauthorized,
+    Denied,
+    Unsupported,
+    TooManyRequests,
+    /// A code not defined by the spec, as sent by the registry.
+    Other(String),
+}
+
+impl ErrorCode {
+    /// Code as sent by the registry, e.g. `MANIFEST_UNKNOWN`.
+    pub fn as_str(&self) -> &str {
+        match self {
+            ErrorCode::BlobUnknown => "BLOB_UNKNOWN",
+            ErrorCode::BlobUploadInvalid => "BLOB_UPLOAD_INVALID",
+            ErrorCode::BlobUploadUnknown => "BLOB_UPLOAD_UNKNOWN",
+            ErrorCode::DigestInvalid => "DIGEST_INVALID",
+            ErrorCode::ManifestBlobUnknown => "MANIFEST_BLOB_UNKNOWN",
+            ErrorCode::ManifestInvalid => "MANIFEST_INVALID",
+            ErrorCode::ManifestUnknown => "MANIFEST_UNKNOWN",
+            ErrorCode::NameInvalid => "NAME_INVALID",
+            ErrorCode::NameUnknown => "NAME_UNKNOWN",
+            ErrorCode::SizeInvalid => "SIZE_INVALID",
+            ErrorCode::Unauthorized => "UNAUTHORIZED",
+            ErrorCode::Denied => "DENIED",
+            ErrorCode::Unsupported => "UNSUPPORTED",
+            ErrorCode::TooManyRequests => "TOOMANYREQUESTS",
+            ErrorCode::Other(code) => code,
+        }
+    }
+}
+
+impl From<String> for ErrorCode {
+    fn from(code: String) -> Self {
+        match code.as_str() {
+            "BLOB_UNKNOWN" => ErrorCode::BlobUnknown,
+            "BLOB_UPLOAD_INVALID" => ErrorCode::BlobUploadInvalid,
+            "BLOB_UPLOAD_UNKNOWN" => ErrorCode::BlobUploadUnknown,
+            "DIGEST_INVALID" => ErrorCode::DigestInvalid,
+            "MANIFEST_BLOB_UNKNOWN" => ErrorCode::ManifestBlobUnknown,
+            "MANIFEST_INVALID" => ErrorCode::ManifestInvalid,
+            "MANIFEST_UNKNOWN" => ErrorCode::ManifestUnknown,
+            "NAME_INVALID" => ErrorCode::NameInvalid,
+            "NAME_UNKNOWN" => ErrorCode::NameUnknown,
+            "SIZE_INVALID" => ErrorCode::SizeInvalid,
+            "UNAUTHORIZED" => ErrorCode::Unauthorized,
+            "DENIED" => ErrorCode::Denied,
+            "UNSUPPORTED" => ErrorCode::Unsupported,
+            "TOOMANYREQUESTS" => ErrorCode::TooManyRequests,
+            _ => ErrorCode::Other(code),
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Error reported by a registry in the body of a failed response.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ApiError {
+    pub code: ErrorCode,
+    #[serde(default)]
+    pub message: String,
+    /// Unstructured details, whose content depends on the error.
+    #[serde(default)]
+    pub detail: Option<serde_json::Value>,
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.message.is_empty() {
+            write!(f, "{}", self.code)
+        } else {
+            write!(f, "{}: {}", self.code, self.message)
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiErrors {
+    errors: Vec<ApiError>,
+}
+
+/// Build the error for a failed response.
+///
+/// This is `Error::Registry` if the body lists errors in the format of the
+/// distribution spec, or `fallback` otherwise.
+pub(crate) async fn response_error(res: Response, fallback: Error) -> Error {
+    let status = res.status();
+    let errors = match res.bytes().await {
+        Ok(body) => serde_json::from_slice::<ApiErrors>(&body)
+            .map(|e| e.errors)
+            .unwrap_or_default(),
+        Err(_) => Vec::new(),
+    };
+    if errors.is_empty() {
+        fallback
+    } else {
+        Error::Registry { status, errors }
+    }
+}
+
+/// Like `response_error`, falling back to an error for the status class.
+pub(crate) async fn status_error(res: Response) -> Error {
+    let status = res.status();
+    let fallback = match status {
+        s if s.is_client_error() => Error::Client { status },
+        s if s.is_server_error() => Error::Server { status },
+        _ => Error::UnexpectedHttpStatus(status),
+    };
+    response_error(res, fallback).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn api_errors_parse() {
+        let body = r#"{"errors": [
+            {"code": "MANIFEST_UNKNOWN", "message": "manifest unknown", "detail": {"Tag": "v1"}},
+            {"code": "CUSTOM_CODE"}
+        ]}"#;
+        let errors = serde_json::from_str::<ApiErrors>(body).unwrap().errors;
+        assert_eq!(errors[0].code, ErrorCode::ManifestUnknown);
+        assert_eq!(errors[0].to_string(), "MANIFEST_UNKNOWN: manifest unknown");
+        assert_eq!(errors[0].detail, Some(serde_json::json!({"Tag": "v1"})));
+        assert_eq!(errors[1].code, ErrorCode::Other("CUSTOM_CODE".to_string()));
+        assert_eq!(errors[1].to_string(), "CUSTOM_CODE");
+    }
+}
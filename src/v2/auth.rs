@@ -1,6 +1,65 @@
 use crate::errors::{Error, Result};
 use crate::v2::*;
 use reqwest::{header::HeaderValue, RequestBuilder, StatusCode, Url};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use web_time::{Instant, SystemTime};
+
+/// Lifetime of tokens issued without `expires_in`, as per the token spec.
+const DEFAULT_TOKEN_LIFETIME: Duration = Duration::from_secs(60);
+
+/// How long before their expiry tokens get refreshed, at most.
+const TOKEN_REFRESH_MARGIN: Duration = Duration::from_secs(10);
+
+/// Client identifier sent in OAuth2 token requests.
+const OAUTH2_CLIENT_ID: &str = "dkregistry";
+
+/// Maximum number of resources (e.g. repositories) whose scopes are
+/// requested together, beyond which those requested first are dropped.
+const MAX_SCOPES: usize = 16;
+
+/// Bearer tokens issued to a client and its clones.
+pub(crate) type TokenCache = Arc<Mutex<Tokens>>;
+
+#[derive(Debug, Default)]
+pub(crate) struct Tokens {
+    /// Tokens by registry and scopes.
+    tokens: HashMap<(String, String), BearerAuth>,
+    /// Scopes requested so far, one per repository, at most `MAX_SCOPES`.
+    scopes: Vec<String>,
+    /// Credentials obtained from the credentials provider.
+    provided: Option<provider::ProvidedCredentials>,
+}
+
+/// How bearer tokens are requested from the authorization server.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TokenFlow {
+    /// Pick the flow from the challenge, if credentials are set (default):
+    /// the OAuth2 flow for realms which are OAuth2 token endpoints (e.g.
+    /// `/oauth2/token`), or else `GET` requests. The other flow is used if
+    /// the server does not support the first one, or rejects the OAuth2
+    /// request with a client error.
+    #[default]
+    Auto,
+    /// Only use `GET` requests, authenticated with basic authentication.
+    Get,
+    /// Use the OAuth2 `POST` flow with a password grant, if credentials are
+    /// set.
+    OAuth2,
+}
+
+/// How a client is authenticated to the registry, see `Client::auth_mode`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthMode {
+    /// Requests are sent without credentials, or with a token obtained
+    /// without credentials.
+    Anonymous,
+    /// Requests are sent with basic authentication.
+    Basic,
+    /// Requests are sent with a token obtained with credentials.
+    Bearer,
+}
 
 /// Represents all supported authentication schemes and is stored by `Client`.
 #[derive(Debug, Clone)]
@@ -24,45 +83,181 @@ impl Auth {
 /// Used for Bearer HTTP Authentication.
 #[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct BearerAuth {
+    #[serde(default)]
     token: String,
+    #[serde(default, skip_serializing)]
+    access_token: Option<String>,
     expires_in: Option<u32>,
     issued_at: Option<String>,
     refresh_token: Option<String>,
+    /// When the token was received, from which its expiry is computed.
+    ///
+    /// Registry clocks may be off, so `issued_at` is only informative.
+    #[serde(skip)]
+    received_at: Option<Instant>,
+    /// Whether the token was obtained without credentials.
+    #[serde(skip)]
+    anonymous: bool,
 }
 
 impl BearerAuth {
+    /// Whether the token is valid long enough to be sent.
+    fn is_fresh(&self) -> bool {
+        let lifetime = self
+            .expires_in
+            .map(|secs| Duration::from_secs(secs.into()))
+            .unwrap_or(DEFAULT_TOKEN_LIFETIME);
+        let margin = TOKEN_REFRESH_MARGIN.min(lifetime / 2);
+        match self.received_at {
+            Some(received_at) => received_at.elapsed() + margin < lifetime,
+            None => false,
+        }
+    }
+
     async fn try_from_header_content(
         client: Client,
         scopes: &[&str],
         credentials: Option<(String, String)>,
+        refresh_token: Option<String>,
         bearer_header_content: WwwAuthenticateHeaderContentBearer,
+    ) -> Result<Self> {
+        if let Some(refresh_token) = refresh_token {
+            let form = vec![
+                ("grant_type", "refresh_token".to_string()),
+                ("refresh_token", refresh_token.clone()),
+            ];
+            match Self::post_token(&client, &bearer_header_content, scopes, form).await {
+                Ok(mut bearer_auth) => {
+                    bearer_auth.refresh_token.get_or_insert(refresh_token);
+                    return Ok(bearer_auth);
+                }
+                // Identity tokens replace credentials, so there is nothing to fall back to.
+                Err(e) if credentials.is_none() => return Err(e),
+                Err(e) => debug!("authenticate: refreshing token failed: {}", e),
+            }
+        }
+
+        let password_form = credentials.as_ref().map(|(user, password)| {
+            vec![
+                ("grant_type", "password".to_string()),
+                ("username", user.clone()),
+                ("password", password.clone()),
+            ]
+        });
+        match (client.token_flow, password_form) {
+            (TokenFlow::OAuth2, Some(form)) => {
+                Self::post_token(&client, &bearer_header_content, scopes, form).await
+            }
+            (TokenFlow::Auto, Some(form)) if bearer_header_content.is_oauth2() => {
+                // Servers answer the password grant in many ways when they
+                // only support the other flow, e.g. with `400 Bad Request`.
+                match Self::post_token(&client, &bearer_header_content, scopes, form).await {
+                    Err(Error::UnexpectedHttpStatus(status))
+                        if status.is_client_error() || status == StatusCode::NOT_IMPLEMENTED =>
+                    {
+                        debug!(
+                            "authenticate: OAuth2 token flow failed with {}, using GET",
+                            status
+                        );
+                        Self::get_token(client, &bearer_header_content, scopes, credentials).await
+                    }
+                    res => res,
+                }
+            }
+            (TokenFlow::Auto, Some(form)) => {
+                match Self::get_token(client.clone(), &bearer_header_content, scopes, credentials)
+                    .await
+                {
+                    Err(Error::UnexpectedHttpStatus(
+                        StatusCode::NOT_FOUND | StatusCode::METHOD_NOT_ALLOWED,
+                    )) => {
+                        debug!("authenticate: GET token flow unsupported, using OAuth2");
+                        Self::post_token(&client, &bearer_header_content, scopes, form).await
+                    }
+                    res => res,
+                }
+            }
+            _ => Self::get_token(client, &bearer_header_content, scopes, credentials).await,
+        }
+    }
+
+    /// Request a token with the `GET` flow, authenticating with `credentials`.
+    async fn get_token(
+        client: Client,
+        bearer_header_content: &WwwAuthenticateHeaderContentBearer,
+        scopes: &[&str],
+        credentials: Option<(String, String)>,
     ) -> Result<Self> {
         let auth_ep = bearer_header_content.auth_ep(scopes);
         trace!("authenticate: token endpoint: {}", auth_ep);
 
         let url = reqwest::Url::parse(&auth_ep)?;
 
-        let auth_req = {
+        let anonymous = credentials.is_none();
+        let auth_client = {
             Client {
                 auth: credentials.map(|(user, password)| {
                     Auth::Basic(BasicAuth {
                         user,
                         password: Some(password),
+                        expires_at: None,
                     })
                 }),
                 ..client
             }
+        };
+
+        let r = auth_client
+            .execute(auth_client.build_reqwest(Method::GET, url))
+            .await?;
+        let mut bearer_auth = Self::try_from_response(r).await?;
+        bearer_auth.anonymous = anonymous;
+        Ok(bearer_auth)
+    }
+
+    /// Request a token with the OAuth2 `POST` flow, for the `form` grant.
+    async fn post_token(
+        client: &Client,
+        bearer_header_content: &WwwAuthenticateHeaderContentBearer,
+        scopes: &[&str],
+        mut form: Vec<(&str, String)>,
+    ) -> Result<Self> {
+        trace!(
+            "authenticate: OAuth2 token endpoint: {}",
+            bearer_header_content.realm
+        );
+        let url = reqwest::Url::parse(&bearer_header_content.realm)?;
+
+        form.push(("client_id", OAUTH2_CLIENT_ID.to_string()));
+        form.push(("access_type", "offline".to_string()));
+        if let Some(service) = &bearer_header_content.service {
+            form.push(("service", service.clone()));
+        }
+        if !scopes.is_empty() {
+            form.push(("scope", scopes.join(" ")));
         }
-        .build_reqwest(Method::GET, url);
 
-        let r = auth_req.send().await?;
+        let r = client
+            .execute(client.build_reqwest(Method::POST, url).form(&form))
+            .await?;
+        Self::try_from_response(r).await
+    }
+
+    async fn try_from_response(r: reqwest::Response) -> Result<Self> {
         let status = r.status();
         trace!("authenticate: got status {}", status);
         if status != StatusCode::OK {
             return Err(Error::UnexpectedHttpStatus(status));
         }
 
-        let bearer_auth = r.json::<BearerAuth>().await?;
+        let mut bearer_auth = r.json::<BearerAuth>().await?;
+        bearer_auth.received_at = Some(Instant::now());
+        // OAuth2 responses only have `access_token`, which is a synonym.
+        if bearer_auth.token.is_empty() {
+            if let Some(access_token) = bearer_auth.access_token.take() {
+                bearer_auth.token = access_token;
+            }
+        }
 
         match bearer_auth.token.as_str() {
             "unauthenticated" | "" => return Err(Error::InvalidAuthToken(bearer_auth.token)),
@@ -87,6 +282,8 @@ impl BearerAuth {
 pub struct BasicAuth {
     user: String,
     password: Option<String>,
+    /// Expiry of credentials obtained from a credentials provider.
+    expires_at: Option<SystemTime>,
 }
 
 /// Structured representation for the content of the authentication response header.
@@ -119,6 +316,29 @@ pub enum WwwHeaderParseError {
 }
 
 impl WwwAuthenticateHeaderContent {
+    /// Authentication scheme of the challenge, in lowercase.
+    pub(crate) fn scheme(&self) -> &'static str {
+        match self {
+            Self::Bearer(_) => "bearer",
+            Self::Basic(_) => "basic",
+        }
+    }
+
+    pub(crate) fn realm(&self) -> &str {
+        match self {
+            Self::Bearer(bearer) => &bearer.realm,
+            Self::Basic(basic) => &basic.realm,
+        }
+    }
+
+    /// Service of a `Bearer` challenge, if any.
+    pub(crate) fn service(&self) -> Option<&str> {
+        match self {
+            Self::Bearer(bearer) => bearer.service.as_deref(),
+            Self::Basic(_) => None,
+        }
+    }
+
     /// Create a `WwwAuthenticateHeaderContent` by parsing a `HeaderValue` instance.
     pub(crate) fn from_www_authentication_header(header_value: HeaderValue) -> Result<Self> {
         let header = String::from_utf8(header_value.as_bytes().to_vec())?;
@@ -129,7 +349,7 @@ impl WwwAuthenticateHeaderContent {
         let captures = re.captures_iter(&header).collect::<Vec<_>>();
 
         let method = captures
-            .get(0)
+            .first()
             .ok_or(WwwHeaderParseError::InvalidValue)?
             .name("method")
             .ok_or(WwwHeaderParseError::FieldMethodMissing)?
@@ -183,14 +403,29 @@ impl WwwAuthenticateHeaderContent {
 }
 
 /// Structured content for the Bearer authentication response header.
-#[derive(Debug, Default, PartialEq, Eq, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
 pub(crate) struct WwwAuthenticateHeaderContentBearer {
     realm: String,
     service: Option<String>,
     scope: Option<String>,
+    /// Reason of the challenge, e.g. `insufficient_scope`.
+    #[serde(default)]
+    error: Option<String>,
 }
 
 impl WwwAuthenticateHeaderContentBearer {
+    /// Whether the realm is an OAuth2 token endpoint, as those of Azure or
+    /// GitLab-style `/oauth/token` ones, which expect the `POST` flow.
+    fn is_oauth2(&self) -> bool {
+        match reqwest::Url::parse(&self.realm) {
+            Ok(url) => {
+                let path = url.path().trim_end_matches('/');
+                path.ends_with("/oauth2/token") || path.ends_with("/oauth/token")
+            }
+            Err(_) => false,
+        }
+    }
+
     fn auth_ep(&self, scopes: &[&str]) -> String {
         let service = self
             .service
@@ -224,6 +459,90 @@ pub(crate) struct WwwAuthenticateHeaderContentBasic {
     realm: String,
 }
 
+/// Get the scopes required by the `Bearer` challenge of a `401` response.
+fn challenge_scopes(res: &reqwest::Response) -> Vec<String> {
+    let content = res
+        .headers()
+        .get(reqwest::header::WWW_AUTHENTICATE)
+        .and_then(|h| WwwAuthenticateHeaderContent::from_www_authentication_header(h.clone()).ok());
+    match content {
+        Some(WwwAuthenticateHeaderContent::Bearer(bearer)) => bearer
+            .scope
+            .map(|s| s.split_whitespace().map(String::from).collect())
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    }
+}
+
+/// Add `requested` to `scopes`, merging the actions of scopes of the same
+/// resource, then drop the scopes first added beyond `MAX_SCOPES`, except
+/// the requested ones.
+fn add_scopes(scopes: &mut Vec<String>, requested: &[&str]) {
+    for scope in requested {
+        let resource = scope_resource(scope);
+        let existing = match scopes.iter_mut().find(|s| scope_resource(s) == resource) {
+            Some(existing) => existing,
+            None => {
+                scopes.push(scope.to_string());
+                continue;
+            }
+        };
+        let mut actions: Vec<&str> = scope_actions(existing).collect();
+        for action in scope_actions(scope) {
+            if !actions.contains(&action) {
+                actions.push(action);
+            }
+        }
+        let merged = format!("{}:{}", resource, actions.join(","));
+        *existing = merged;
+    }
+    while scopes.len() > MAX_SCOPES {
+        let stale = scopes.iter().position(|s| {
+            let resource = scope_resource(s);
+            !requested.iter().any(|r| scope_resource(r) == resource)
+        });
+        match stale {
+            Some(i) => {
+                scopes.remove(i);
+            }
+            None => break,
+        }
+    }
+}
+
+/// Whether `scopes` grant all the actions of `scope`.
+fn scopes_cover(scopes: &[String], scope: &str) -> bool {
+    let resource = scope_resource(scope);
+    scopes.iter().any(|s| {
+        scope_resource(s) == resource
+            && scope_actions(scope).all(|a| scope_actions(s).any(|b| a == b))
+    })
+}
+
+/// Resource of a scope, e.g. `repository:foo/bar` for
+/// `repository:foo/bar:pull`.
+fn scope_resource(scope: &str) -> &str {
+    scope
+        .rsplit_once(':')
+        .map_or(scope, |(resource, _)| resource)
+}
+
+/// Actions of a scope, e.g. `pull` and `push` for `repository:foo:pull,push`.
+fn scope_actions(scope: &str) -> impl Iterator<Item = &str> {
+    let actions = scope.rsplit_once(':').map_or("", |(_, actions)| actions);
+    actions.split(',').filter(|a| !a.is_empty())
+}
+
+/// Token scope granting pull and push access to repository `name`.
+pub(crate) fn push_scope(name: &str) -> String {
+    format!("repository:{}:pull,push", name)
+}
+
+/// Token scope granting deletion access to repository `name`.
+pub(crate) fn delete_scope(name: &str) -> String {
+    format!("repository:{}:delete", name)
+}
+
 impl Client {
     /// Make a request and return the response's www authentication header.
     async fn get_www_authentication_header(&self) -> Result<HeaderValue> {
@@ -232,7 +551,9 @@ impl Client {
             reqwest::Url::parse(&ep)?
         };
 
-        let r = self.build_reqwest(Method::GET, url.clone()).send().await?;
+        let r = self
+            .execute(self.build_reqwest(Method::GET, url.clone()))
+            .await?;
 
         trace!("GET '{}' status: {:?}", r.url(), r.status());
         r.headers()
@@ -244,68 +565,283 @@ impl Client {
     /// Perform registry authentication and return the authenticated client.
     ///
     /// If Bearer authentication is used the returned client will be authorized for the requested scopes.
+    /// Tokens are cached by the client and its clones until they are about to expire.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(registry = %self.base_url, scopes = ?scopes))
+    )]
     pub async fn authenticate(mut self, scopes: &[&str]) -> Result<Self> {
-        let credentials = self.credentials.clone();
+        let deadline = self.deadline();
+        deadline
+            .run(async move {
+                let key = self.token_key(scopes);
+                let cached = self.tokens.lock().unwrap().tokens.get(&key).cloned();
+                let refresh_token = match cached {
+                    Some(bearer_auth) if bearer_auth.is_fresh() => {
+                        trace!("authenticate: using cached token");
+                        self.auth = Some(Auth::Bearer(bearer_auth));
+                        self.scopes = scopes.iter().map(|s| s.to_string()).collect();
+                        return Ok(self);
+                    }
+                    Some(bearer_auth) => bearer_auth.refresh_token,
+                    None => None,
+                }
+                .or_else(|| self.identity_token.clone());
+
+                let (credentials, expires_at) = self.current_credentials().await?;
+
+                let client = Client {
+                    auth: None,
+                    ..self.clone()
+                };
+
+                let authentication_header = client.get_www_authentication_header().await?;
+                let auth = match WwwAuthenticateHeaderContent::from_www_authentication_header(
+                    authentication_header,
+                )? {
+                    WwwAuthenticateHeaderContent::Basic(_) => {
+                        let basic_auth = credentials
+                            .map(|(user, password)| BasicAuth {
+                                user,
+                                password: Some(password),
+                                expires_at,
+                            })
+                            .ok_or(Error::NoCredentials)?;
+
+                        Auth::Basic(basic_auth)
+                    }
+                    WwwAuthenticateHeaderContent::Bearer(bearer_header_content) => {
+                        let had_credentials = credentials.is_some() || refresh_token.is_some();
+                        let res = BearerAuth::try_from_header_content(
+                            client.clone(),
+                            scopes,
+                            credentials,
+                            refresh_token,
+                            bearer_header_content.clone(),
+                        )
+                        .await;
+                        if res.is_err() {
+                            self.record_auth_failure();
+                        }
+                        let bearer_auth = match res {
+                            Err(Error::UnexpectedHttpStatus(StatusCode::UNAUTHORIZED))
+                                if self.anonymous_fallback && had_credentials =>
+                            {
+                                warn!("authenticate: credentials rejected, trying anonymously");
+                                BearerAuth::get_token(client, &bearer_header_content, scopes, None)
+                                    .await?
+                            }
+                            res => res?,
+                        };
 
-        let client = Client {
-            auth: None,
-            ..self.clone()
+                        self.tokens
+                            .lock()
+                            .unwrap()
+                            .tokens
+                            .insert(key, bearer_auth.clone());
+                        Auth::Bearer(bearer_auth)
+                    }
+                };
+
+                trace!("authenticate: login succeeded");
+                self.auth = Some(auth);
+                self.scopes = scopes.iter().map(|s| s.to_string()).collect();
+
+                Ok(self)
+            })
+            .await
+    }
+
+    /// Report an authentication failure to the metrics sink, if any.
+    pub(crate) fn record_auth_failure(&self) {
+        if let Some(sink) = &self.metrics {
+            let host = self.base_url.splitn(2, "://").last().unwrap_or_default();
+            sink.record_auth_failure(host);
+        }
+    }
+
+    /// Get how this client is authenticated, e.g. to find out whether
+    /// it fell back to anonymous access (see `Config::anonymous_fallback`).
+    pub fn auth_mode(&self) -> AuthMode {
+        match &self.auth {
+            None => AuthMode::Anonymous,
+            Some(Auth::Basic(_)) => AuthMode::Basic,
+            Some(Auth::Bearer(bearer_auth)) if bearer_auth.anonymous => AuthMode::Anonymous,
+            Some(Auth::Bearer(_)) => AuthMode::Bearer,
+        }
+    }
+
+    /// Get the credentials to authenticate with, with their expiry.
+    ///
+    /// Credentials are obtained from the credentials provider if there is
+    /// one, and shared by clones until they are about to expire.
+    async fn current_credentials(&self) -> Result<(Option<(String, String)>, Option<SystemTime>)> {
+        let provider = match &self.provider {
+            Some(provider) => provider,
+            None => return Ok((self.credentials.clone(), None)),
+        };
+        let cached = self.tokens.lock().unwrap().provided.clone();
+        let provided = match cached {
+            Some(provided) if !provided.is_expiring() => provided,
+            _ => {
+                let registry = self.base_url.split_once("://").map_or("", |(_, r)| r);
+                trace!("authenticate: obtaining credentials from {:?}", provider);
+                let provided = provider.credentials(registry).await?;
+                self.tokens.lock().unwrap().provided = Some(provided.clone());
+                provided
+            }
         };
+        let expires_at = provided.expires_at;
+        Ok((Some((provided.username, provided.password)), expires_at))
+    }
 
-        let authentication_header = client.get_www_authentication_header().await?;
-        let auth = match WwwAuthenticateHeaderContent::from_www_authentication_header(
-            authentication_header,
-        )? {
-            WwwAuthenticateHeaderContent::Basic(_) => {
-                let basic_auth = credentials
-                    .map(|(user, password)| BasicAuth {
-                        user,
-                        password: Some(password),
-                    })
-                    .ok_or(Error::NoCredentials)?;
+    fn token_key(&self, scopes: &[&str]) -> (String, String) {
+        (self.base_url.clone(), scopes.join(" "))
+    }
 
-                Auth::Basic(basic_auth)
+    /// Return a client with a better token than this one, if any: a token
+    /// granted more scopes by a clone, or a new token if this one is about
+    /// to expire.
+    pub(crate) async fn refreshed(&self) -> Result<Option<Client>> {
+        let bearer_auth = match &self.auth {
+            Some(Auth::Bearer(bearer_auth)) => bearer_auth,
+            Some(Auth::Basic(BasicAuth {
+                expires_at: Some(expires_at),
+                ..
+            })) if SystemTime::now() + TOKEN_REFRESH_MARGIN >= *expires_at => {
+                trace!("credentials about to expire, renewing them");
+                let scopes: Vec<&str> = self.scopes.iter().map(String::as_str).collect();
+                return self.clone().authenticate(&scopes).await.map(Some);
             }
-            WwwAuthenticateHeaderContent::Bearer(bearer_header_content) => {
-                let bearer_auth = BearerAuth::try_from_header_content(
-                    client,
-                    scopes,
-                    credentials,
-                    bearer_header_content,
-                )
-                .await?;
-
-                Auth::Bearer(bearer_auth)
+            _ => return Ok(None),
+        };
+        let merged = {
+            let tokens = self.tokens.lock().unwrap();
+            let covered = self.scopes.iter().all(|s| scopes_cover(&tokens.scopes, s));
+            let key = (self.base_url.clone(), tokens.scopes.join(" "));
+            match tokens.tokens.get(&key) {
+                Some(merged) if covered && tokens.scopes != self.scopes => {
+                    Some((tokens.scopes.clone(), merged.clone()))
+                }
+                _ => None,
             }
         };
+        match merged {
+            Some((scopes, merged)) if merged.is_fresh() => {
+                trace!("using token granted scopes {:?}", scopes);
+                let mut client = self.clone();
+                client.auth = Some(Auth::Bearer(merged));
+                client.scopes = scopes;
+                Ok(Some(client))
+            }
+            _ if !bearer_auth.is_fresh() => {
+                trace!("token about to expire, refreshing it");
+                let scopes: Vec<&str> = self.scopes.iter().map(String::as_str).collect();
+                self.clone().authenticate(&scopes).await.map(Some)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Add the scopes of this client, `scopes` and those required by the
+    /// challenge of a `401` response to the scopes requested so far, and
+    /// return them all.
+    ///
+    /// A single token is thus used for the repositories accessed by a client
+    /// and its clones, up to `MAX_SCOPES` of them.
+    pub(crate) fn merge_scopes(&self, scopes: &[&str], res: &reqwest::Response) -> Vec<String> {
+        let challenged = challenge_scopes(res);
+        let requested: Vec<&str> = self
+            .scopes
+            .iter()
+            .map(String::as_str)
+            .chain(scopes.iter().copied())
+            .chain(challenged.iter().map(String::as_str))
+            .collect();
+        let mut tokens = self.tokens.lock().unwrap();
+        add_scopes(&mut tokens.scopes, &requested);
+        tokens.scopes.clone()
+    }
 
-        trace!("authenticate: login succeeded");
-        self.auth = Some(auth);
+    /// Authenticate again for `scopes`, dropping the cached token which the
+    /// registry refused.
+    pub(crate) async fn reauthenticate(&self, scopes: &[&str]) -> Result<Client> {
+        let key = self.token_key(scopes);
+        self.tokens.lock().unwrap().tokens.remove(&key);
+        self.clone().authenticate(scopes).await
+    }
 
-        Ok(self)
+    /// Send a request built by `build`, retrying it once with a client
+    /// authenticated for `scopes` if the registry answers `401 Unauthorized`.
+    ///
+    /// This allows write operations with a client which has only been
+    /// authorized to pull, as the token must be granted the matching action.
+    pub(crate) async fn send_with_scope<F>(
+        &self,
+        scopes: &[&str],
+        build: F,
+    ) -> Result<reqwest::Response>
+    where
+        F: Fn(&Client) -> RequestBuilder,
+    {
+        self.send_reauthenticating(scopes, build)
+            .await
+            .map(|(res, _)| res)
+    }
+
+    /// Like `send_with_scope`, also returning the client which ended up
+    /// being used, so that follow-up requests can reuse its authorization.
+    pub(crate) async fn send_reauthenticating<F>(
+        &self,
+        scopes: &[&str],
+        build: F,
+    ) -> Result<(reqwest::Response, Client)>
+    where
+        F: Fn(&Client) -> RequestBuilder,
+    {
+        let refreshed = self.refreshed().await?;
+        let this = refreshed.as_ref().unwrap_or(self);
+        let res = this.execute(build(this)).await?;
+        if res.status() != StatusCode::UNAUTHORIZED {
+            return Ok((res, this.clone()));
+        }
+        // Keep the scopes already granted, for later requests made with the returned client.
+        let all_scopes = this.merge_scopes(scopes, &res);
+        let all_scopes: Vec<&str> = all_scopes.iter().map(String::as_str).collect();
+        trace!("got 401, authenticating for scopes {:?}", all_scopes);
+        let client = this.reauthenticate(&all_scopes).await?;
+        let res = client.execute(build(&client)).await?;
+        if res.status() == StatusCode::UNAUTHORIZED {
+            client.record_auth_failure();
+        }
+        Ok((res, client))
     }
 
     /// Check whether the client can successfully make requests to the registry.
     ///
     /// This could be due to granted anonymous access or valid credentials.
     pub async fn is_auth(&self) -> Result<bool> {
-        let url = {
-            let ep = format!("{}/v2/", self.base_url.clone(),);
-            Url::parse(&ep)?
-        };
+        self.deadline()
+            .run(async move {
+                let url = {
+                    let ep = format!("{}/v2/", self.base_url.clone(),);
+                    Url::parse(&ep)?
+                };
 
-        let req = self.build_reqwest(Method::GET, url.clone());
+                let req = self.build_reqwest(Method::GET, url.clone());
 
-        trace!("Sending request to '{}'", url);
-        let resp = req.send().await?;
-        trace!("GET '{:?}'", resp);
+                trace!("Sending request to '{}'", url);
+                let resp = self.execute(req).await?;
+                trace!("GET '{:?}'", resp);
 
-        let status = resp.status();
-        match status {
-            reqwest::StatusCode::OK => Ok(true),
-            reqwest::StatusCode::UNAUTHORIZED => Ok(false),
-            _ => Err(Error::UnexpectedHttpStatus(status)),
-        }
+                let status = resp.status();
+                match status {
+                    reqwest::StatusCode::OK => Ok(true),
+                    reqwest::StatusCode::UNAUTHORIZED => Ok(false),
+                    _ => Err(Error::UnexpectedHttpStatus(status)),
+                }
+            })
+            .await
     }
 }
 
@@ -314,6 +850,32 @@ mod tests {
     use super::*;
     use test_case::test_case;
 
+    #[test]
+    fn scopes_merge_per_repository() {
+        let mut scopes = Vec::new();
+        add_scopes(&mut scopes, &["repository:a:pull", "repository:b:pull"]);
+        add_scopes(
+            &mut scopes,
+            &["repository:a:pull,push", "repository:a:delete"],
+        );
+        assert_eq!(
+            scopes,
+            vec!["repository:a:pull,push,delete", "repository:b:pull"]
+        );
+        assert!(scopes_cover(&scopes, "repository:a:push"));
+        assert!(!scopes_cover(&scopes, "repository:b:push"));
+
+        let names: Vec<String> = (0..2 * MAX_SCOPES)
+            .map(|i| format!("repository:r{}:pull", i))
+            .collect();
+        for name in &names {
+            add_scopes(&mut scopes, &["repository:a:pull", name]);
+        }
+        assert_eq!(scopes.len(), MAX_SCOPES);
+        assert!(scopes.contains(&"repository:a:pull,push,delete".to_string()));
+        assert_eq!(scopes.last(), names.last());
+    }
+
     #[test]
     fn bearer_realm_parses_correctly() -> Result<()> {
         let realm = "https://sat-r220-02.lab.eng.rdu2.redhat.com/v2/token";
@@ -358,6 +920,7 @@ mod tests {
                     realm: realm.to_string(),
                     service: Some(service.to_string()),
                     scope: Some(scope.to_string()),
+                    error: None,
                 }),
                 content
             );
@@ -423,6 +986,7 @@ mod tests {
                 None
             },
             scope: None,
+            error: None,
         };
 
         // build list of expected headers
@@ -443,4 +1007,18 @@ mod tests {
             expected_headers
         );
     }
+
+    #[test_case(Some(300), 0 => true; "new token")]
+    #[test_case(Some(300), 295 => false; "token about to expire")]
+    #[test_case(None, 30 => true; "default lifetime")]
+    #[test_case(None, 55 => false; "default lifetime about to expire")]
+    fn bearer_token_freshness(expires_in: Option<u32>, age_secs: u64) -> bool {
+        let received_at = Instant::now().checked_sub(Duration::from_secs(age_secs));
+        BearerAuth {
+            expires_in,
+            received_at,
+            ..Default::default()
+        }
+        .is_fresh()
+    }
 }
@@ -1,57 +1,126 @@
 use crate::errors::{Error, Result};
+use crate::v2::progress::ProgressHook;
 use crate::v2::*;
 
 use std::pin::Pin;
 
+use async_stream::try_stream;
 use bytes::Bytes;
-use futures::stream::Stream;
+use futures::stream::{Stream, StreamExt, TryStreamExt};
 use futures::task::{Context, Poll};
 use pin_project::pin_project;
-use reqwest::{self, Method, StatusCode};
+use reqwest::{self, header, Method, StatusCode};
+
+/// Number of times a blob download is resumed after a transient error.
+const MAX_RESUMES: u32 = 3;
 
 impl Client {
     /// Check if a blob exists.
     pub async fn has_blob(&self, name: &str, digest: &str) -> Result<bool> {
-        let url = {
-            let ep = format!("{}/v2/{}/blobs/{}", self.base_url, name, digest);
-            reqwest::Url::parse(&ep)?
-        };
+        self.deadline()
+            .run(async move {
+                let url = {
+                    let ep = format!("{}/v2/{}/blobs/{}", self.base_url, name, digest);
+                    reqwest::Url::parse(&ep)?
+                };
 
-        let res = self.build_reqwest(Method::HEAD, url.clone()).send().await?;
+                let res = self
+                    .send(|client| client.build_reqwest(Method::HEAD, url.clone()))
+                    .await?;
 
-        trace!("Blob HEAD status: {:?}", res.status());
+                trace!("Blob HEAD status: {:?}", res.status());
 
-        match res.status() {
-            StatusCode::OK => Ok(true),
-            _ => Ok(false),
-        }
+                match res.status() {
+                    StatusCode::OK => Ok(true),
+                    _ => Ok(false),
+                }
+            })
+            .await
+    }
+
+    /// Check if a blob exists, returning its size.
+    ///
+    /// Returns `None` if the blob does not exist. Unlike `has_blob`, other
+    /// failures are reported as errors.
+    pub async fn get_blob_size(&self, name: &str, digest: &str) -> Result<Option<u64>> {
+        self.deadline()
+            .run(async move {
+                let url = {
+                    let ep = format!("{}/v2/{}/blobs/{}", self.base_url, name, digest);
+                    reqwest::Url::parse(&ep)?
+                };
+
+                let res = self
+                    .send(|client| client.build_reqwest(Method::HEAD, url.clone()))
+                    .await?;
+
+                let status = res.status();
+                trace!("Blob HEAD status: {:?}", status);
+
+                match status {
+                    StatusCode::OK => {}
+                    StatusCode::NOT_FOUND => return Ok(None),
+                    _ if status.is_client_error() => return Err(Error::Client { status }),
+                    _ if status.is_server_error() => return Err(Error::Server { status }),
+                    _ => return Err(Error::UnexpectedHttpStatus(status)),
+                }
+
+                // `Response::content_length` reports the (empty) body size for HEAD requests.
+                let size = res
+                    .headers()
+                    .get(reqwest::header::CONTENT_LENGTH)
+                    .ok_or(Error::MissingHeader("Content-Length"))?
+                    .to_str()?;
+                size.parse()
+                    .map(Some)
+                    .map_err(|_| Error::InvalidHeader("Content-Length", size.to_string()))
+            })
+            .await
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(registry = %self.base_url, repository = name, digest = %digest))
+    )]
     pub async fn get_blob_response(&self, name: &str, digest: &str) -> Result<BlobResponse> {
-        let ep = format!("{}/v2/{}/blobs/{}", self.base_url, name, digest);
-        let url = reqwest::Url::parse(&ep)?;
+        let deadline = self.deadline();
+        deadline
+            .run(async move {
+                let ep = format!("{}/v2/{}/blobs/{}", self.base_url, name, digest);
+                let url = reqwest::Url::parse(&ep)?;
 
-        let resp = self.build_reqwest(Method::GET, url.clone()).send().await?;
+                let resp = self
+                    .send(|client| client.build_reqwest(Method::GET, url.clone()))
+                    .await?;
 
-        let status = resp.status();
-        trace!("GET {} status: {}", resp.url(), status);
-
-        match resp.error_for_status_ref() {
-            Ok(_) => {
-                if let Some(len) = resp.content_length() {
-                    trace!("Receiving a blob with {} bytes", len);
-                } else {
-                    trace!("Receiving a blob");
+                let status = resp.status();
+                trace!("GET {} status: {}", resp.url(), status);
+
+                match resp.error_for_status_ref() {
+                    Ok(_) => {
+                        if let Some(len) = resp.content_length() {
+                            trace!("Receiving a blob with {} bytes", len);
+                        } else {
+                            trace!("Receiving a blob");
+                        }
+                        Ok(BlobResponse::new(
+                            resp,
+                            ContentDigest::try_new(digest)?,
+                            digest.to_string(),
+                            self.progress.clone(),
+                        )
+                        .with_deadline(deadline))
+                    }
+                    Err(_) if status.is_client_error() || status.is_server_error() => {
+                        Err(status_error(resp).await)
+                    }
+                    Err(_) => {
+                        error!("Received unexpected HTTP status '{}'", status);
+                        Err(Error::UnexpectedHttpStatus(status))
+                    }
                 }
-                Ok(BlobResponse::new(resp, ContentDigest::try_new(digest)?))
-            }
-            Err(_) if status.is_client_error() => Err(Error::Client { status }),
-            Err(_) if status.is_server_error() => Err(Error::Server { status }),
-            Err(_) => {
-                error!("Received unexpected HTTP status '{}'", status);
-                Err(Error::UnexpectedHttpStatus(status))
-            }
-        }
+            })
+            .await
     }
 
     /// Retrieve blob.
@@ -59,25 +128,280 @@ impl Client {
         self.get_blob_response(name, digest).await?.bytes().await
     }
 
-    /// Retrieve blob stream.
+    /// Retrieve several blobs, downloading up to `concurrency` of them at once.
+    ///
+    /// Blobs are returned in the order of `digests`. The first failure
+    /// aborts all downloads.
+    pub async fn get_blobs(
+        &self,
+        name: &str,
+        digests: &[&str],
+        concurrency: usize,
+    ) -> Result<Vec<Vec<u8>>> {
+        self.deadline()
+            .run(async move {
+                futures::stream::iter(digests)
+                    .map(|digest| self.get_blob(name, digest))
+                    .buffered(concurrency.max(1))
+                    .try_collect()
+                    .await
+            })
+            .await
+    }
+
+    /// Retrieve blob stream, along with the blob size if known.
+    ///
+    /// The content is not buffered, so that large blobs can be written to
+    /// disk or decompressed as they are received. The digest is verified
+    /// once the stream is exhausted, which then yields a final error if it
+    /// does not match.
     pub async fn get_blob_stream(
         &self,
         name: &str,
         digest: &str,
-    ) -> Result<impl Stream<Item = Result<Vec<u8>>>> {
-        Ok(self.get_blob_response(name, digest).await?.stream())
+    ) -> Result<(impl Stream<Item = Result<Bytes>>, Option<u64>)> {
+        let resp = self.get_blob_response(name, digest).await?;
+        let size = resp.size();
+        Ok((resp.bytes_stream(), size))
+    }
+
+    /// Retrieve blob stream, resuming the download on transient errors.
+    ///
+    /// Interrupted transfers are resumed where they stopped with `Range`
+    /// requests, up to a few times, and the digest is verified over the
+    /// whole blob.
+    pub fn get_blob_stream_resumable<'a>(
+        &'a self,
+        name: &'a str,
+        digest: &'a str,
+    ) -> impl Stream<Item = Result<Vec<u8>>> + 'a {
+        self.resume_blob_stream(name, digest, &[])
+    }
+
+    /// Resume the download of a blob, of which `downloaded` has already been
+    /// retrieved.
+    ///
+    /// Only the remaining content is streamed; the digest is verified over
+    /// `downloaded` followed by that content. See `get_blob_stream_resumable`.
+    pub fn resume_blob_stream<'a>(
+        &'a self,
+        name: &'a str,
+        digest: &'a str,
+        downloaded: &'a [u8],
+    ) -> impl Stream<Item = Result<Vec<u8>>> + 'a {
+        self.deadline().bound(try_stream! {
+            let mut content_digest = ContentDigest::try_new(digest)?;
+            content_digest.update(downloaded);
+            let mut offset = downloaded.len() as u64;
+            let mut resumes = 0;
+
+            'request: loop {
+                let (resp, mut skip) = match self.get_blob_range(name, digest, offset).await? {
+                    Some(range) => range,
+                    None => break,
+                };
+                // Responses of servers ignoring `Range` include the skipped content.
+                let total = resp.content_length().map(|len| len + offset - skip);
+                let mut stream = resp.bytes_stream();
+                while let Some(chunk) = stream.next().await {
+                    let chunk = match chunk {
+                        Ok(chunk) => chunk,
+                        Err(e) if is_transient(&e) && resumes < MAX_RESUMES => {
+                            resumes += 1;
+                            debug!("blob download interrupted at {} bytes, resuming: {}", offset, e);
+                            continue 'request;
+                        }
+                        Err(e) => Err(e)?,
+                    };
+                    // Servers ignoring `Range` resend the content from the start.
+                    let already = skip.min(chunk.len() as u64);
+                    skip -= already;
+                    let chunk = &chunk[already as usize..];
+                    if chunk.is_empty() {
+                        continue;
+                    }
+                    content_digest.update(chunk);
+                    offset += chunk.len() as u64;
+                    if let Some(hook) = &self.progress {
+                        hook.report(digest, offset, total);
+                    }
+                    yield chunk.to_vec();
+                }
+                break;
+            }
+
+            content_digest.verify()?;
+        })
+    }
+
+    /// Request the content of a blob from `offset` on.
+    ///
+    /// Returns the response along with the number of bytes to skip from it,
+    /// or `None` if there is nothing left to download.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(registry = %self.base_url, repository = name, digest = %digest, offset = offset))
+    )]
+    async fn get_blob_range(
+        &self,
+        name: &str,
+        digest: &str,
+        offset: u64,
+    ) -> Result<Option<(reqwest::Response, u64)>> {
+        let ep = format!("{}/v2/{}/blobs/{}", self.base_url, name, digest);
+        let url = reqwest::Url::parse(&ep)?;
+
+        let resp = self
+            .send(|client| {
+                let req = client.build_reqwest(Method::GET, url.clone());
+                match offset {
+                    0 => req,
+                    _ => req.header(header::RANGE, format!("bytes={}-", offset)),
+                }
+            })
+            .await?;
+
+        let status = resp.status();
+        trace!("GET {} (from {}) status: {}", resp.url(), offset, status);
+
+        match status {
+            StatusCode::OK => Ok(Some((resp, offset))),
+            StatusCode::PARTIAL_CONTENT => {
+                let content_range = resp
+                    .headers()
+                    .get(header::CONTENT_RANGE)
+                    .ok_or(Error::MissingHeader("Content-Range"))?
+                    .to_str()?;
+                if !content_range.starts_with(&format!("bytes {}-", offset)) {
+                    return Err(Error::InvalidHeader(
+                        "Content-Range",
+                        content_range.to_string(),
+                    ));
+                }
+                Ok(Some((resp, 0)))
+            }
+            StatusCode::RANGE_NOT_SATISFIABLE if offset > 0 => Ok(None),
+            _ => Err(status_error(resp).await),
+        }
+    }
+
+    /// Retrieve the bytes of a blob within `range`, without verifying them.
+    ///
+    /// Responses of servers ignoring `Range` are cut down to `range`.
+    #[cfg(feature = "render")]
+    pub(crate) async fn get_blob_slice(
+        &self,
+        name: &str,
+        digest: &str,
+        range: std::ops::Range<u64>,
+    ) -> Result<Vec<u8>> {
+        if range.is_empty() {
+            return Ok(Vec::new());
+        }
+        let ep = format!("{}/v2/{}/blobs/{}", self.base_url, name, digest);
+        let url = reqwest::Url::parse(&ep)?;
+
+        let resp = self
+            .send(|client| {
+                client.build_reqwest(Method::GET, url.clone()).header(
+                    header::RANGE,
+                    format!("bytes={}-{}", range.start, range.end - 1),
+                )
+            })
+            .await?;
+
+        let status = resp.status();
+        trace!("GET {} ({:?}) status: {}", resp.url(), range, status);
+
+        match status {
+            StatusCode::PARTIAL_CONTENT => Ok(resp.bytes().await?.to_vec()),
+            StatusCode::OK => {
+                let blob = resp.bytes().await?;
+                let end = (range.end as usize).min(blob.len());
+                Ok(blob
+                    .get(range.start as usize..end)
+                    .unwrap_or_default()
+                    .to_vec())
+            }
+            _ => Err(status_error(resp).await),
+        }
     }
 }
 
+/// Whether a download failed in a way that is worth resuming: the
+/// connection failed, which is reported as a body error mid-stream, or
+/// timed out.
+///
+/// Malformed content (decode errors) would fail the same way again.
+fn is_transient(e: &reqwest::Error) -> bool {
+    e.is_body() || e.is_timeout() || super::retry::is_connect(e)
+}
+
 #[derive(Debug)]
 pub struct BlobResponse {
     resp: reqwest::Response,
     digest: ContentDigest,
+    /// Digest of the blob, as requested.
+    reference: String,
+    progress: Option<ProgressHook>,
+    /// Media type of the blob, as described by its manifest.
+    media_type: Option<String>,
+    /// Deadline of the operation the blob is downloaded for.
+    deadline: Deadline,
 }
 
 impl BlobResponse {
-    fn new(resp: reqwest::Response, digest: ContentDigest) -> Self {
-        Self { resp, digest }
+    pub(crate) fn new(
+        resp: reqwest::Response,
+        digest: ContentDigest,
+        reference: String,
+        progress: Option<ProgressHook>,
+    ) -> Self {
+        Self {
+            resp,
+            digest,
+            reference,
+            progress,
+            media_type: None,
+            deadline: Deadline::default(),
+        }
+    }
+
+    /// Bound the download of the blob by `deadline`.
+    pub(crate) fn with_deadline(mut self, deadline: Deadline) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
+    /// Set the media type of the blob, from the descriptor it was fetched for.
+    pub(crate) fn with_media_type(mut self, media_type: &str) -> Self {
+        self.media_type = Some(media_type.to_string());
+        self
+    }
+
+    /// Get the media type of the blob.
+    ///
+    /// This is the media type of the descriptor of layers retrieved with
+    /// `Client::get_layer_response`, otherwise the `Content-Type` of the
+    /// response, if any.
+    pub fn media_type(&self) -> Option<&str> {
+        self.media_type.as_deref().or_else(|| {
+            self.resp
+                .headers()
+                .get(header::CONTENT_TYPE)
+                .and_then(|t| t.to_str().ok())
+        })
+    }
+
+    /// Get the compression of the layer, from its media type.
+    ///
+    /// Unknown media types (e.g. `application/octet-stream`) leave the
+    /// compression to be detected when unpacking.
+    #[cfg(feature = "render")]
+    pub fn compression(&self) -> crate::render::LayerCompression {
+        self.media_type()
+            .and_then(crate::render::LayerCompression::from_media_type)
+            .unwrap_or_default()
     }
 
     /// Get size of the blob.
@@ -88,18 +412,29 @@ impl BlobResponse {
 
     /// Retrieve content of the blob.
     pub async fn bytes(self) -> Result<Vec<u8>> {
-        let blob = self.resp.bytes().await?.to_vec();
-
-        let mut digest = self.digest;
-        digest.update(&blob);
-        digest.verify()?;
-
-        Ok(blob)
+        self.bytes_stream()
+            .try_fold(Vec::new(), |mut blob, chunk| async move {
+                blob.extend_from_slice(&chunk);
+                Ok(blob)
+            })
+            .await
     }
 
     /// Get bytes stream of the blob.
     pub fn stream(self) -> impl Stream<Item = Result<Vec<u8>>> {
-        BlobStream::new(self.resp.bytes_stream(), self.digest)
+        self.bytes_stream().map_ok(|chunk| chunk.to_vec())
+    }
+
+    /// Get bytes stream of the blob, without copying chunks.
+    pub fn bytes_stream(self) -> impl Stream<Item = Result<Bytes>> {
+        let total = self.resp.content_length();
+        self.deadline.bound(BlobStream::new(
+            self.resp.bytes_stream(),
+            self.digest,
+            self.reference,
+            total,
+            self.progress,
+        ))
     }
 }
 
@@ -112,16 +447,38 @@ where
     stream: S,
     #[pin]
     digest: Option<ContentDigest>,
+    reference: String,
+    total: Option<u64>,
+    progress: Option<ProgressHook>,
+    transferred: u64,
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
 }
 
 impl<S> BlobStream<S>
 where
     S: Stream<Item = reqwest::Result<Bytes>> + Unpin,
 {
-    fn new(stream: S, digest: ContentDigest) -> Self {
+    fn new(
+        stream: S,
+        digest: ContentDigest,
+        reference: String,
+        total: Option<u64>,
+        progress: Option<ProgressHook>,
+    ) -> Self {
         Self {
             stream,
             digest: Some(digest),
+            #[cfg(feature = "tracing")]
+            span: tracing::debug_span!(
+                "blob_stream",
+                digest = %reference,
+                bytes = tracing::field::Empty
+            ),
+            reference,
+            total,
+            progress,
+            transferred: 0,
         }
     }
 }
@@ -130,10 +487,12 @@ impl<S> Stream for BlobStream<S>
 where
     S: Stream<Item = reqwest::Result<Bytes>> + Unpin,
 {
-    type Item = Result<Vec<u8>>;
+    type Item = Result<Bytes>;
 
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
         let mut this = self.project();
+        #[cfg(feature = "tracing")]
+        let _entered = this.span.enter();
         match this.stream.poll_next(cx) {
             Poll::Ready(Some(chunk_res)) => {
                 let mut digest = match this.digest.as_pin_mut() {
@@ -142,7 +501,13 @@ where
                 };
                 let chunk = chunk_res?;
                 digest.update(&chunk);
-                Poll::Ready(Some(Ok(chunk.to_vec())))
+                *this.transferred += chunk.len() as u64;
+                #[cfg(feature = "tracing")]
+                this.span.record("bytes", *this.transferred);
+                if let Some(hook) = this.progress {
+                    hook.report(this.reference, *this.transferred, *this.total);
+                }
+                Poll::Ready(Some(Ok(chunk)))
             }
             Poll::Ready(None) => match this.digest.take() {
                 Some(digest) => match digest.verify() {
@@ -0,0 +1,126 @@
+use crate::errors::{Error, Result};
+use crate::v2::auth::WwwAuthenticateHeaderContent;
+use crate::v2::*;
+use reqwest::header;
+
+/// Digest of empty content, which no manifest can have.
+const EMPTY_DIGEST: &str =
+    "sha256:e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+/// Version, authentication and capabilities of a registry, see
+/// `Client::check_api`.
+#[non_exhaustive]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ApiCheck {
+    /// Value of the `Docker-Distribution-API-Version` header, if any.
+    pub api_version: Option<String>,
+    /// Whether anonymous requests to the `/v2/` endpoint are rejected.
+    pub auth_required: bool,
+    /// Scheme of the authentication challenge (`bearer` or `basic`).
+    pub auth_scheme: Option<String>,
+    /// Realm of the authentication challenge.
+    pub realm: Option<String>,
+    /// Service of the `Bearer` challenge.
+    pub service: Option<String>,
+    /// Whether the registry implements the OCI Referrers API.
+    pub referrers: Option<bool>,
+    /// Whether the client may delete manifests.
+    pub delete: Option<bool>,
+    /// Whether the client may push blobs.
+    pub push: Option<bool>,
+}
+
+impl Client {
+    /// Probe the API version and the authentication of the registry, and
+    /// the capabilities of `repository` if set.
+    ///
+    /// Capabilities are `None` when not probed, or when the registry answer
+    /// is inconclusive. They are probed with the credentials of the client,
+    /// without side effects: the delete probe targets a manifest which
+    /// cannot exist, and the push probe cancels the upload it starts.
+    pub async fn check_api(&self, repository: Option<&str>) -> Result<ApiCheck> {
+        self.deadline()
+            .run(async move {
+                let url = Url::parse(&format!("{}/v2/", self.base_url))?;
+                let anonymous = Client {
+                    auth: None,
+                    ..self.clone()
+                };
+                let res = anonymous
+                    .execute(anonymous.build_reqwest(Method::GET, url))
+                    .await?;
+
+                let status = res.status();
+                trace!("GET '{}' status: {:?}", res.url(), status);
+                let auth_required = match status {
+                    StatusCode::OK => false,
+                    StatusCode::UNAUTHORIZED => true,
+                    _ => return Err(Error::UnexpectedHttpStatus(status)),
+                };
+                let challenge = res.headers().get(header::WWW_AUTHENTICATE).and_then(|h| {
+                    WwwAuthenticateHeaderContent::from_www_authentication_header(h.clone()).ok()
+                });
+                let mut check = ApiCheck {
+                    api_version: res
+                        .headers()
+                        .get("Docker-Distribution-API-Version")
+                        .and_then(|v| v.to_str().ok())
+                        .map(str::to_string),
+                    auth_required,
+                    auth_scheme: challenge.as_ref().map(|c| c.scheme().to_string()),
+                    realm: challenge.as_ref().map(|c| c.realm().to_string()),
+                    service: challenge
+                        .as_ref()
+                        .and_then(|c| c.service())
+                        .map(str::to_string),
+                    ..Default::default()
+                };
+
+                if let Some(name) = repository {
+                    check.referrers = self.probe_referrers(name).await;
+                    check.delete = match self.delete_manifest(name, EMPTY_DIGEST).await {
+                        Ok(()) | Err(Error::ManifestNotFound(_)) => Some(true),
+                        Err(Error::DeletionUnsupported) => Some(false),
+                        Err(e) => denied(&e),
+                    };
+                    check.push = match self.start_blob_upload(name).await {
+                        Ok(session) => {
+                            if let Err(e) = session.cancel().await {
+                                debug!("failed to cancel probe upload: {}", e);
+                            }
+                            Some(true)
+                        }
+                        Err(e) => denied(&e),
+                    };
+                }
+                Ok(check)
+            })
+            .await
+    }
+
+    async fn probe_referrers(&self, name: &str) -> Option<bool> {
+        let url = format!("{}/v2/{}/referrers/{}", self.base_url, name, EMPTY_DIGEST);
+        let url = Url::parse(&url).ok()?;
+        let res = self
+            .send(|client| client.build_reqwest(Method::GET, url.clone()))
+            .await
+            .ok()?;
+
+        trace!("GET '{}' status: {:?}", res.url(), res.status());
+        match res.status() {
+            StatusCode::OK => Some(true),
+            StatusCode::NOT_FOUND => Some(false),
+            _ => None,
+        }
+    }
+}
+
+/// Whether an error reports that an operation is not allowed.
+fn denied(e: &Error) -> Option<bool> {
+    match e.status() {
+        Some(StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN | StatusCode::METHOD_NOT_ALLOWED) => {
+            Some(false)
+        }
+        _ => None,
+    }
+}
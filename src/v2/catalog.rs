@@ -1,9 +1,4 @@
-use crate::errors::Result;
-use crate::v2;
-use async_stream::try_stream;
-use futures::stream::Stream;
-use futures::{self};
-use reqwest::{Method, RequestBuilder, StatusCode};
+use crate::v2::{self, Paginate};
 
 #[derive(Debug, Default, Deserialize, Serialize)]
 struct Catalog {
@@ -11,39 +6,22 @@ struct Catalog {
 }
 
 impl v2::Client {
-    pub fn get_catalog<'a, 'b: 'a>(
-        &'b self,
-        paginate: Option<u32>,
-    ) -> impl Stream<Item = Result<String>> + 'a {
-        let url = {
-            let suffix = if let Some(n) = paginate {
-                format!("?n={}", n)
-            } else {
-                "".to_string()
-            };
-            let ep = format!("{}/v2/_catalog{}", self.base_url.clone(), suffix);
-
-            reqwest::Url::parse(&ep).map_err(crate::Error::from)
+    /// List repositories of the registry.
+    ///
+    /// Repositories are fetched in pages of `paginate` entries (or the
+    /// registry default, if unset), following `Link` headers to the next page.
+    pub fn get_catalog<'a, 'b: 'a>(&'b self, paginate: Option<u32>) -> Paginate<'a, String> {
+        let url = match paginate {
+            Some(n) => format!("{}/v2/_catalog?n={}", self.base_url, n),
+            None => format!("{}/v2/_catalog", self.base_url),
+        };
+        let url = match reqwest::Url::parse(&url) {
+            Ok(url) => url,
+            Err(e) => return Paginate::failed(e.into()),
         };
 
-        try_stream! {
-            let req = self.build_reqwest(Method::GET, url?);
-
-            let catalog = fetch_catalog(req).await?;
-
-            for repo in catalog.repositories {
-                yield repo;
-            }
-        }
-    }
-}
-
-async fn fetch_catalog(req: RequestBuilder) -> Result<Catalog> {
-    let r = req.send().await?;
-    let status = r.status();
-    trace!("Got status: {:?}", status);
-    match status {
-        StatusCode::OK => r.json::<Catalog>().await.map_err(Into::into),
-        _ => Err(crate::Error::UnexpectedHttpStatus(status)),
+        self.paginate(url, "application/json", |_, catalog: Catalog| {
+            catalog.repositories
+        })
     }
 }
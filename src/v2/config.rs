@@ -1,4 +1,14 @@
+use crate::docker_config::DockerConfig;
+use crate::v2::manifest::MediaTypeHandlers;
+use crate::v2::metrics::MetricsSink;
+use crate::v2::progress::ProgressHook;
+use crate::v2::provider::CredentialsProvider;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::v2::redirect::redirect_policy;
 use crate::{mediatypes::MediaTypes, v2::*};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
 
 /// Configuration for a `Client`.
 #[derive(Debug)]
@@ -6,26 +16,121 @@ pub struct Config {
     index: String,
     insecure_registry: bool,
     user_agent: Option<String>,
+    default_headers: reqwest::header::HeaderMap,
     username: Option<String>,
     password: Option<String>,
+    identity_token: Option<String>,
+    provider: Option<Arc<dyn CredentialsProvider>>,
+    token_flow: TokenFlow,
+    anonymous_fallback: bool,
+    docker_config: Option<DockerConfig>,
     accept_invalid_certs: bool,
     accepted_types: Option<Vec<(MediaTypes, Option<f64>)>>,
+    accept_media_types: Option<Vec<MediaTypes>>,
+    media_type_handlers: MediaTypeHandlers,
+    #[cfg(feature = "schema1-signatures")]
+    require_schema1_signatures: bool,
+    retry_policy: RetryPolicy,
+    connect_timeout: Option<Duration>,
+    timeout: Option<Duration>,
+    deadline: Option<Duration>,
+    progress: Option<ProgressHook>,
+    metrics: Option<Arc<dyn MetricsSink>>,
+    transport: Option<Arc<dyn Transport>>,
+    proxy: Option<String>,
+    proxy_credentials: Option<(String, String)>,
+    no_proxy: Option<String>,
+    system_proxy: bool,
+    redirect_auth_hosts: Vec<String>,
+    foreign_layers: ForeignLayerPolicy,
+    mirrors: Vec<String>,
+    #[cfg(any(feature = "reqwest-default-tls", feature = "reqwest-rustls"))]
+    client_identity: Option<(Vec<u8>, Vec<u8>)>,
+    #[cfg(any(feature = "reqwest-default-tls", feature = "reqwest-rustls"))]
+    root_certificates: Vec<(Option<String>, RootCertificate)>,
 }
 
-impl Config {
+/// An additional trusted root certificate.
+#[cfg(any(feature = "reqwest-default-tls", feature = "reqwest-rustls"))]
+#[derive(Debug)]
+enum RootCertificate {
+    PemBundle(Vec<u8>),
+    Der(Vec<u8>),
+}
+
+impl Default for Config {
     /// Initialize `Config` with default values.
-    pub fn default() -> Self {
+    fn default() -> Self {
         Self {
             index: "registry-1.docker.io".into(),
             insecure_registry: false,
             accept_invalid_certs: false,
             accepted_types: None,
+            accept_media_types: None,
+            media_type_handlers: MediaTypeHandlers::default(),
+            #[cfg(feature = "schema1-signatures")]
+            require_schema1_signatures: false,
+            retry_policy: RetryPolicy::default(),
+            connect_timeout: None,
+            timeout: None,
+            deadline: None,
+            progress: None,
+            metrics: None,
+            transport: None,
+            proxy: None,
+            proxy_credentials: None,
+            no_proxy: None,
+            system_proxy: true,
+            redirect_auth_hosts: Vec::new(),
+            foreign_layers: ForeignLayerPolicy::Fetch,
+            mirrors: Vec::new(),
+            #[cfg(any(feature = "reqwest-default-tls", feature = "reqwest-rustls"))]
+            client_identity: None,
+            #[cfg(any(feature = "reqwest-default-tls", feature = "reqwest-rustls"))]
+            root_certificates: Vec::new(),
             user_agent: Some(crate::USER_AGENT.to_owned()),
+            default_headers: reqwest::header::HeaderMap::new(),
             username: None,
             password: None,
+            identity_token: None,
+            provider: None,
+            token_flow: TokenFlow::default(),
+            anonymous_fallback: false,
+            docker_config: None,
+        }
+    }
+}
+
+impl Config {
+    /// Initialize `Config` with credentials from the Docker configuration,
+    /// at `$DOCKER_CONFIG/config.json` or `~/.docker/config.json`.
+    ///
+    /// A missing configuration file is not an error. See
+    /// `from_docker_config_path`.
+    pub fn from_docker_config() -> Result<Self> {
+        match DockerConfig::default_path() {
+            Some(path) if path.exists() => Self::from_docker_config_path(path),
+            _ => Ok(Self::default()),
         }
     }
 
+    /// Initialize `Config` with credentials from the Docker configuration
+    /// at `path`.
+    ///
+    /// Credentials of the configured registry are selected when building
+    /// the client, by registry host, unless a username, password or identity
+    /// token is set.
+    /// They are obtained from credential helpers or `auths` entries, whose
+    /// identity tokens are used to request OAuth2 tokens.
+    pub fn from_docker_config_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = std::fs::File::open(path)?;
+        let docker_config = serde_json::from_reader(std::io::BufReader::new(file))?;
+        Ok(Self {
+            docker_config: Some(docker_config),
+            ..Self::default()
+        })
+    }
+
     /// Set registry service to use (vhost or IP).
     pub fn registry(mut self, reg: &str) -> Self {
         self.index = reg.to_owned();
@@ -53,12 +158,235 @@ impl Config {
         self
     }
 
-    /// Set the user-agent to be used for registry authentication.
+    /// Set the manifest media types to accept, in order of preference.
+    ///
+    /// Registries are asked for the first of `media_types` they can serve,
+    /// with decreasing `q` values, and types which are not listed are not
+    /// accepted (e.g. leave out `MediaTypes::ManifestV2S1Signed` to never
+    /// get schema 1 manifests). This is ignored if `accepted_types` is set.
+    pub fn accept_media_types(mut self, media_types: Vec<MediaTypes>) -> Self {
+        self.accept_media_types = Some(media_types);
+        self
+    }
+
+    /// Register a handler parsing manifests of the custom media type
+    /// `media_type`, which then gets accepted when fetching manifests.
+    ///
+    /// Such manifests are returned as `Manifest::Custom`, along with the value
+    /// returned by `handler`, see `CustomManifest::parsed`. Media types
+    /// supported by this crate are parsed as usual.
+    pub fn media_type_handler<T, F>(mut self, media_type: &str, handler: F) -> Self
+    where
+        T: std::any::Any + Send + Sync,
+        F: Fn(&[u8]) -> Result<T> + Send + Sync + 'static,
+    {
+        self.media_type_handlers.register(media_type, handler);
+        self
+    }
+
+    /// Set whether manifests of unknown media types, without a handler set
+    /// via `media_type_handler`, are returned as `Manifest::Custom` with
+    /// their bytes only, instead of failing with `Error::UnsupportedMediaType`
+    /// (default: false).
+    pub fn passthrough_unknown_media_types(mut self, passthrough: bool) -> Self {
+        self.media_type_handlers.passthrough = passthrough;
+        self
+    }
+
+    /// Set whether schema 1 manifests must carry valid signatures.
+    ///
+    /// Signatures of schema 1 manifests are always verified, exposing the
+    /// signing keys with `ManifestSchema1Signed::signing_keys`. By default, a
+    /// manifest failing verification is still returned, without keys; when
+    /// required, fetching it fails with `Error::Schema1Signature` instead.
+    #[cfg(feature = "schema1-signatures")]
+    pub fn require_schema1_signatures(mut self, require: bool) -> Self {
+        self.require_schema1_signatures = require;
+        self
+    }
+
+    /// Set the policy for retrying requests on transient errors.
+    ///
+    /// By default, requests are not retried.
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Set the timeout for establishing connections (default: none).
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Set the timeout of each request, from sending it until its response
+    /// body has been read (default: none).
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Set the deadline of each operation, such as fetching a manifest,
+    /// bounding the time spent on all its requests, including retries and
+    /// authentication, and on reading their responses (default: none).
+    ///
+    /// Operations which do not complete in time fail with
+    /// `Error::DeadlineExceeded`. Streams, such as blob downloads or
+    /// paginated listings, are bounded from when they are created: once the
+    /// deadline passes, they yield this error and end.
+    pub fn deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Set a callback receiving the progress of blob and manifest downloads,
+    /// e.g. to render progress bars.
+    ///
+    /// The callback is invoked as chunks of content are received, with the
+    /// number of bytes transferred so far. It must not block.
+    pub fn progress<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(Progress<'_>) + Send + Sync + 'static,
+    {
+        self.progress = Some(ProgressHook::new(hook));
+        self
+    }
+
+    /// Set the sink receiving metrics about requests, such as their count,
+    /// latency, retries and sizes, and authentication failures.
+    pub fn metrics_sink(mut self, sink: Arc<dyn MetricsSink>) -> Self {
+        self.metrics = Some(sink);
+        self
+    }
+
+    /// Set the transport sending requests, in place of the HTTP client built
+    /// from this configuration (e.g. a preconfigured `reqwest::Client`).
+    ///
+    /// Requests are still built with the configured user-agent and default
+    /// headers, but settings of the HTTP client itself, such as timeouts,
+    /// proxies and certificates, are left to the transport.
+    pub fn transport(mut self, transport: Arc<dyn Transport>) -> Self {
+        self.transport = Some(transport);
+        self
+    }
+
+    /// Send all requests through the proxy at `url` (e.g. `http://proxy:3128`).
+    ///
+    /// This takes precedence over the system proxy configuration.
+    pub fn proxy(mut self, url: &str) -> Self {
+        self.proxy = Some(url.to_owned());
+        self
+    }
+
+    /// Set the credentials to authenticate to the proxy set with `proxy`.
+    pub fn proxy_credentials(mut self, username: &str, password: &str) -> Self {
+        self.proxy_credentials = Some((username.to_owned(), password.to_owned()));
+        self
+    }
+
+    /// Set hosts for which the proxy set with `proxy` is bypassed, as a
+    /// comma-separated list in the `NO_PROXY` format.
+    ///
+    /// By default, the `NO_PROXY` environment variable is used.
+    pub fn no_proxy(mut self, no_proxy: &str) -> Self {
+        self.no_proxy = Some(no_proxy.to_owned());
+        self
+    }
+
+    /// Set whether the proxy configured in the environment is used, as given
+    /// by `HTTP_PROXY`, `HTTPS_PROXY` and `NO_PROXY` (default: true).
+    pub fn system_proxy(mut self, system_proxy: bool) -> Self {
+        self.system_proxy = system_proxy;
+        self
+    }
+
+    /// Keep the authorization of requests redirected to one of `hosts`.
+    ///
+    /// Registries commonly redirect blob downloads to a CDN or an object
+    /// store, with URLs carrying their own (presigned) authorization. By
+    /// default, the `Authorization` header is stripped from requests
+    /// redirected to another host, as sending it breaks some CDNs. Some
+    /// private setups need it on their storage hosts though.
+    pub fn redirect_auth_hosts(mut self, hosts: Vec<String>) -> Self {
+        self.redirect_auth_hosts = hosts;
+        self
+    }
+
+    /// Set how `Client::get_layer_response` downloads foreign layers
+    /// (default: from their URLs).
+    pub fn foreign_layers(mut self, policy: ForeignLayerPolicy) -> Self {
+        self.foreign_layers = policy;
+        self
+    }
+
+    /// Set the TLS client certificate, for registries requiring mutual TLS.
+    ///
+    /// `cert` is the PEM-encoded certificate chain, and `key` the
+    /// PEM-encoded PKCS#8 private key.
+    #[cfg(any(feature = "reqwest-default-tls", feature = "reqwest-rustls"))]
+    pub fn client_identity(mut self, cert: &[u8], key: &[u8]) -> Self {
+        self.client_identity = Some((cert.to_vec(), key.to_vec()));
+        self
+    }
+
+    /// Trust the root certificates of a PEM bundle, in addition to the
+    /// system ones.
+    #[cfg(any(feature = "reqwest-default-tls", feature = "reqwest-rustls"))]
+    pub fn add_root_certificates(mut self, pem_bundle: &[u8]) -> Self {
+        self.root_certificates
+            .push((None, RootCertificate::PemBundle(pem_bundle.to_vec())));
+        self
+    }
+
+    /// Trust a DER-encoded root certificate, in addition to the system ones.
+    #[cfg(any(feature = "reqwest-default-tls", feature = "reqwest-rustls"))]
+    pub fn add_root_certificate_der(mut self, der: &[u8]) -> Self {
+        self.root_certificates
+            .push((None, RootCertificate::Der(der.to_vec())));
+        self
+    }
+
+    /// Trust the root certificates of a PEM bundle for registry `host` only.
+    ///
+    /// The certificates are ignored unless `host` (with its port, if any) is
+    /// the registry set with `registry`. This allows configuring the CAs of
+    /// several private registries at once, as in `/etc/docker/certs.d`.
+    #[cfg(any(feature = "reqwest-default-tls", feature = "reqwest-rustls"))]
+    pub fn add_registry_root_certificates(mut self, host: &str, pem_bundle: &[u8]) -> Self {
+        self.root_certificates.push((
+            Some(host.to_owned()),
+            RootCertificate::PemBundle(pem_bundle.to_vec()),
+        ));
+        self
+    }
+
+    /// Set mirrors of the registry (e.g. `https://mirror.gcr.io`), tried in
+    /// order when pulling, before the registry itself.
+    ///
+    /// Requests fall back to the next mirror, and eventually to the
+    /// registry, if a mirror fails or answers with `404` or a server error.
+    pub fn mirrors(mut self, mirrors: Vec<String>) -> Self {
+        self.mirrors = mirrors;
+        self
+    }
+
+    /// Set the user-agent sent with every request, including those to the
+    /// authorization server.
     pub fn user_agent(mut self, user_agent: Option<String>) -> Self {
         self.user_agent = user_agent;
         self
     }
 
+    /// Add headers sent with every request, including those to the
+    /// authorization server (e.g. tracking headers required by proxies).
+    ///
+    /// Headers set by requests themselves, such as `Authorization` or
+    /// `User-Agent`, take precedence.
+    pub fn default_headers(mut self, headers: reqwest::header::HeaderMap) -> Self {
+        self.default_headers.extend(headers);
+        self
+    }
+
     /// Set the username to be used for registry authentication.
     pub fn username(mut self, user: Option<String>) -> Self {
         self.username = user;
@@ -71,6 +399,46 @@ impl Config {
         self
     }
 
+    /// Set the identity token to be used for registry authentication, in
+    /// place of a username and password.
+    ///
+    /// Identity tokens are OAuth2 refresh tokens, as stored by `docker login`
+    /// for some registries (e.g. ACR), exchanged for bearer tokens with the
+    /// `refresh_token` grant.
+    pub fn identity_token(mut self, token: Option<String>) -> Self {
+        self.identity_token = token;
+        self
+    }
+
+    /// Set the provider of credentials for registry authentication, in
+    /// place of a username and password.
+    ///
+    /// With the `acr`, `ecr` and `gcp` features, a provider is selected by
+    /// default for ACR, ECR, and Artifact Registry or Container Registry
+    /// registries.
+    pub fn credentials_provider(mut self, provider: Arc<dyn CredentialsProvider>) -> Self {
+        self.provider = Some(provider);
+        self
+    }
+
+    /// Set how bearer tokens are requested (default: `TokenFlow::Auto`).
+    ///
+    /// Some registries only support the OAuth2 `POST` flow with credentials.
+    pub fn token_flow(mut self, token_flow: TokenFlow) -> Self {
+        self.token_flow = token_flow;
+        self
+    }
+
+    /// Whether to request tokens anonymously if the authorization server
+    /// rejects the credentials (default: false).
+    ///
+    /// This allows pulling public images despite wrong credentials. See
+    /// `Client::auth_mode` to find out whether this happened.
+    pub fn anonymous_fallback(mut self, fallback: bool) -> Self {
+        self.anonymous_fallback = fallback;
+        self
+    }
+
     /// Read credentials from a JSON config file
     pub fn read_credentials<T: ::std::io::Read>(mut self, reader: T) -> Self {
         if let Ok(creds) = crate::get_credentials(reader, &self.index) {
@@ -81,7 +449,35 @@ impl Config {
     }
 
     /// Return a `Client` to interact with a v2 registry.
-    pub fn build(self) -> Result<Client> {
+    pub fn build(mut self) -> Result<Client> {
+        let mut identity_token = self.identity_token.take();
+        if let (Some(docker_config), None, None, None) = (
+            &self.docker_config,
+            &self.username,
+            &self.password,
+            &identity_token,
+        ) {
+            match docker_config.credentials(&self.index) {
+                Err(Error::AuthInfoMissing(_)) => {}
+                res => {
+                    let creds = res?;
+                    self.username = creds.username;
+                    self.password = creds.password;
+                    identity_token = creds.identity_token;
+                }
+            }
+        }
+        let client = self.http_client()?;
+        let provider = match self.provider.take() {
+            Some(provider) => Some(provider),
+            None if self.username.is_none()
+                && self.password.is_none()
+                && identity_token.is_none() =>
+            {
+                provider::default_provider(&self.index, &client)
+            }
+            None => None,
+        };
         let base = if self.insecure_registry {
             "http://".to_string() + &self.index
         } else {
@@ -100,13 +496,12 @@ impl Config {
                 p.unwrap_or_else(|| "".into()),
             )),
         };
-        let client = reqwest::ClientBuilder::new()
-            .danger_accept_invalid_certs(self.accept_invalid_certs)
-            .build()?;
 
-        let accepted_types = match self.accepted_types {
-            Some(a) => a,
-            None => match self.index == "gcr.io" || self.index.ends_with(".gcr.io") {
+        let is_gcr = self.index == "gcr.io" || self.index.ends_with(".gcr.io");
+        let mut accepted_types = match (self.accepted_types, self.accept_media_types) {
+            (Some(a), _) => a,
+            (None, Some(media_types)) => preference_q_values(media_types, is_gcr),
+            (None, None) => match is_gcr {
                 false => vec![
                     // accept header types and their q value, as documented in
                     // https://tools.ietf.org/html/rfc7231#section-5.3.2
@@ -124,14 +519,190 @@ impl Config {
                 ],
             },
         };
-        let c = Client {
+        // Custom media types are accepted as the least preferred ones.
+        let q = accepted_types
+            .iter()
+            .filter_map(|(_, q)| *q)
+            .reduce(f64::min);
+        for media_type in self.media_type_handlers.media_types() {
+            accepted_types.push((MediaTypes::Other(media_type.to_string()), q));
+        }
+        let mut c = Client {
             base_url: base,
             credentials: creds,
+            identity_token,
+            provider,
             user_agent: self.user_agent,
+            default_headers: self.default_headers,
+            token_flow: self.token_flow,
+            anonymous_fallback: self.anonymous_fallback,
             auth: None,
             client,
             accepted_types,
+            media_type_handlers: self.media_type_handlers,
+            #[cfg(feature = "schema1-signatures")]
+            require_schema1_signatures: self.require_schema1_signatures,
+            retry_policy: self.retry_policy,
+            deadline: self.deadline,
+            progress: self.progress,
+            metrics: self.metrics,
+            transport: self.transport,
+            redirect_auth_hosts: self.redirect_auth_hosts,
+            foreign_layers: self.foreign_layers,
+            scopes: Vec::new(),
+            tokens: Default::default(),
+            rate_limit: Default::default(),
+            mirrors: Vec::new(),
+            upstream: None,
         };
+        c.mirrors = self.mirrors.iter().map(|m| c.mirror(m)).collect();
         Ok(c)
     }
+
+    /// Build the HTTP client, with the TLS, proxy and timeout settings.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn http_client(&self) -> Result<reqwest::Client> {
+        let mut client_builder =
+            reqwest::ClientBuilder::new().danger_accept_invalid_certs(self.accept_invalid_certs);
+        if let Some(timeout) = self.connect_timeout {
+            client_builder = client_builder.connect_timeout(timeout);
+        }
+        if let Some(timeout) = self.timeout {
+            client_builder = client_builder.timeout(timeout);
+        }
+        #[cfg(any(feature = "reqwest-default-tls", feature = "reqwest-rustls"))]
+        if let Some((cert, key)) = &self.client_identity {
+            client_builder = client_builder.identity(client_identity(cert, key)?);
+        }
+        #[cfg(any(feature = "reqwest-default-tls", feature = "reqwest-rustls"))]
+        for (host, certificate) in &self.root_certificates {
+            if matches!(host, Some(h) if *h != self.index) {
+                continue;
+            }
+            let certificates = match certificate {
+                RootCertificate::PemBundle(pem) => reqwest::Certificate::from_pem_bundle(pem)?,
+                RootCertificate::Der(der) => vec![reqwest::Certificate::from_der(der)?],
+            };
+            for certificate in certificates {
+                client_builder = client_builder.add_root_certificate(certificate);
+            }
+        }
+        if !self.system_proxy {
+            client_builder = client_builder.no_proxy();
+        }
+        if let Some(url) = &self.proxy {
+            let mut proxy = reqwest::Proxy::all(url.as_str())?;
+            if let Some((username, password)) = &self.proxy_credentials {
+                proxy = proxy.basic_auth(username, password);
+            }
+            proxy = proxy.no_proxy(match &self.no_proxy {
+                Some(no_proxy) => reqwest::NoProxy::from_string(no_proxy),
+                None => reqwest::NoProxy::from_env(),
+            });
+            client_builder = client_builder.proxy(proxy);
+        }
+        if !self.redirect_auth_hosts.is_empty() {
+            let hosts = self.redirect_auth_hosts.clone();
+            client_builder = client_builder.redirect(redirect_policy(hosts));
+        }
+        client_builder.build().map_err(Into::into)
+    }
+
+    /// Build the HTTP client, backed by the `fetch` API of the browser,
+    /// which handles TLS, proxies and timeouts itself.
+    #[cfg(target_arch = "wasm32")]
+    fn http_client(&self) -> Result<reqwest::Client> {
+        reqwest::Client::builder().build().map_err(Into::into)
+    }
+}
+
+#[cfg(all(feature = "reqwest-default-tls", not(target_arch = "wasm32")))]
+fn client_identity(cert: &[u8], key: &[u8]) -> Result<reqwest::Identity> {
+    reqwest::Identity::from_pkcs8_pem(cert, key).map_err(Into::into)
+}
+
+#[cfg(all(
+    feature = "reqwest-rustls",
+    not(feature = "reqwest-default-tls"),
+    not(target_arch = "wasm32")
+))]
+fn client_identity(cert: &[u8], key: &[u8]) -> Result<reqwest::Identity> {
+    let pem = [key, b"\n", cert].concat();
+    reqwest::Identity::from_pem(&pem).map_err(Into::into)
+}
+
+/// Weigh `media_types` with `q` values decreasing in their order.
+///
+/// On GCR, which mishandles `q` values, the order alone conveys preference.
+fn preference_q_values(
+    media_types: Vec<MediaTypes>,
+    is_gcr: bool,
+) -> Vec<(MediaTypes, Option<f64>)> {
+    media_types
+        .into_iter()
+        .enumerate()
+        .map(|(i, ty)| {
+            let q = (!is_gcr).then(|| f64::from(10u8.saturating_sub(i as u8).max(1)) / 10.0);
+            (ty, q)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn preference_q_values_decrease() {
+        let types = vec![
+            MediaTypes::OciImageIndex,
+            MediaTypes::ManifestList,
+            MediaTypes::OciImageManifest,
+        ];
+        let weighed = preference_q_values(types.clone(), false);
+        let q: Vec<_> = weighed.iter().map(|(_, q)| *q).collect();
+        assert_eq!(q, vec![Some(1.0), Some(0.9), Some(0.8)]);
+        assert!(preference_q_values(types, true)
+            .iter()
+            .all(|(_, q)| q.is_none()));
+    }
+
+    #[test]
+    #[cfg(any(feature = "reqwest-default-tls", feature = "reqwest-rustls"))]
+    fn client_identity_is_validated() {
+        let cert = include_bytes!("../../tests/fixtures/tls/client.crt");
+        let key = include_bytes!("../../tests/fixtures/tls/client.key");
+        Config::default()
+            .client_identity(cert, key)
+            .build()
+            .unwrap();
+
+        let res = Config::default().client_identity(b"junk", key).build();
+        assert!(res.is_err());
+    }
+
+    #[test]
+    #[cfg(any(feature = "reqwest-default-tls", feature = "reqwest-rustls"))]
+    fn root_certificates_are_scoped() {
+        let pem = include_bytes!("../../tests/fixtures/tls/client.crt");
+        let der = include_bytes!("../../tests/fixtures/tls/client.der");
+        Config::default()
+            .add_root_certificates(pem)
+            .add_root_certificate_der(der)
+            .build()
+            .unwrap();
+
+        // Invalid certificates only fail the registry they are scoped to.
+        let junk = b"-----BEGIN CERTIFICATE-----\nanVuaw==\n-----END CERTIFICATE-----\n";
+        let config = || {
+            Config::default()
+                .registry("registry.example.com:5000")
+                .add_registry_root_certificates("other.example.com", junk)
+        };
+        config().build().unwrap();
+        let res = config()
+            .add_registry_root_certificates("registry.example.com:5000", junk)
+            .build();
+        assert!(res.is_err());
+    }
 }
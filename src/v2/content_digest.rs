@@ -72,6 +72,16 @@ impl ContentDigest {
     }
 }
 
+/// Compute the digest of `content`, as registries do for blobs and manifests.
+///
+/// Digests are computed over the raw bytes of documents, which must thus be
+/// pushed as serialized here, and not re-serialized, for digests to match.
+pub fn sha256_digest(content: &[u8]) -> String {
+    let mut algorithm = DigestAlgorithm::Sha256(sha2::Sha256::new());
+    algorithm.update(content);
+    algorithm.digest()
+}
+
 impl std::fmt::Display for ContentDigest {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         write!(f, "{}:{}", self.algorithm, self.digest)
@@ -96,12 +106,21 @@ impl DigestAlgorithm {
 }
 
 #[cfg(test)]
+#[allow(clippy::single_element_loop)]
 mod tests {
     use super::*;
     use sha2;
 
     type Fallible<T> = Result<T, crate::Error>;
 
+    #[test]
+    fn sha256_digest_of_content() {
+        assert_eq!(
+            sha256_digest(b"{}"),
+            "sha256:44136fa355b3678a1146ad16f7e8649e94fb4fc21fe77e8310c060f61caaff8a"
+        );
+    }
+
     #[test]
     fn try_new_succeeds_with_correct_digest() -> Fallible<()> {
         for correct_digest in
@@ -0,0 +1,111 @@
+//! Deadlines of client operations, see `Config::deadline`.
+
+use crate::errors::{Error, Result};
+use futures::future;
+use futures::stream::Stream;
+use pin_project::pin_project;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use web_time::Instant;
+
+#[cfg(target_arch = "wasm32")]
+use gloo_timers::future::{sleep, TimeoutFuture as Sleep};
+#[cfg(not(target_arch = "wasm32"))]
+use tokio::time::{sleep, Sleep};
+
+/// Point in time by which an operation must complete, if any.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Deadline(Option<(Instant, Duration)>);
+
+impl Deadline {
+    /// Deadline of an operation starting now, which may take `duration`.
+    pub(crate) fn start(duration: Option<Duration>) -> Self {
+        Deadline(duration.map(|duration| (Instant::now() + duration, duration)))
+    }
+
+    /// Time left until the deadline, with the error to fail with once it
+    /// has passed.
+    fn remaining(&self) -> Option<(Duration, Error)> {
+        self.0.map(|(at, duration)| {
+            (
+                at.saturating_duration_since(Instant::now()),
+                Error::DeadlineExceeded(duration),
+            )
+        })
+    }
+
+    /// Run `op`, failing with `Error::DeadlineExceeded` if it does not
+    /// complete by the deadline.
+    ///
+    /// `op` is moved to the heap, so that nested operations do not add up
+    /// to large futures.
+    pub(crate) fn run<T, F>(self, op: F) -> impl Future<Output = Result<T>>
+    where
+        F: Future<Output = Result<T>>,
+    {
+        let op = Box::pin(op);
+        async move {
+            let (remaining, exceeded) = match self.remaining() {
+                Some(remaining) => remaining,
+                None => return op.await,
+            };
+            match future::select(op, Box::pin(sleep(remaining))).await {
+                future::Either::Left((output, _)) => output,
+                future::Either::Right(_) => Err(exceeded),
+            }
+        }
+    }
+
+    /// Bound `stream` by the deadline: once it passes, the stream yields
+    /// `Error::DeadlineExceeded` and ends.
+    pub(crate) fn bound<S>(self, stream: S) -> Bounded<S> {
+        Bounded {
+            stream,
+            deadline: self,
+            timer: None,
+            expired: false,
+        }
+    }
+}
+
+/// Stream bounded by a deadline, see `Deadline::bound`.
+#[pin_project]
+pub(crate) struct Bounded<S> {
+    #[pin]
+    stream: S,
+    deadline: Deadline,
+    /// Timer of the deadline, started when the stream is first polled.
+    timer: Option<Pin<Box<Sleep>>>,
+    expired: bool,
+}
+
+impl<S, T> Stream for Bounded<S>
+where
+    S: Stream<Item = Result<T>>,
+{
+    type Item = Result<T>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        if *this.expired {
+            return Poll::Ready(None);
+        }
+        if let Some((remaining, exceeded)) = this.deadline.remaining() {
+            let timer = this.timer.get_or_insert_with(|| Box::pin(sleep(remaining)));
+            if timer.as_mut().poll(cx).is_ready() {
+                *this.expired = true;
+                return Poll::Ready(Some(Err(exceeded)));
+            }
+        }
+        this.stream.poll_next(cx)
+    }
+}
+
+impl super::Client {
+    /// Deadline of an operation starting now, see `Config::deadline`.
+    pub(crate) fn deadline(&self) -> Deadline {
+        Deadline::start(self.deadline)
+    }
+}
@@ -0,0 +1,233 @@
+//! Lazy access to the files of eStargz layers.
+//!
+//! eStargz layers are gzip-compressed tar archives, compressed per file and
+//! ending with a table of contents (TOC) of their files, so that single
+//! files can be read with range requests. See
+//! https://github.com/containerd/stargz-snapshotter/blob/main/docs/estargz.md.
+
+use crate::errors::{Error, Result};
+use crate::v2::manifest::Descriptor;
+use crate::v2::*;
+use libflate::gzip;
+use std::io::Read;
+
+/// Annotation of eStargz layer descriptors with the digest of their TOC.
+pub const ANNOTATION_TOC_DIGEST: &str = "containerd.io/snapshot/stargz/toc.digest";
+/// Annotation of eStargz layer descriptors with their uncompressed size.
+pub const ANNOTATION_UNCOMPRESSED_SIZE: &str = "io.containers.estargz.uncompressed-size";
+
+/// Name of the TOC entry in the archive.
+const TOC_NAME: &str = "stargz.index.json";
+/// Size of the footer of eStargz layers.
+const FOOTER_SIZE: u64 = 51;
+/// Size of the footer of legacy stargz layers.
+const LEGACY_FOOTER_SIZE: u64 = 47;
+
+/// Table of contents of an eStargz layer, see `Client::get_estargz_toc`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct EstargzToc {
+    pub version: u32,
+    pub entries: Vec<TocEntry>,
+    /// Offset of the TOC in the layer blob, where the layer content ends.
+    #[serde(skip)]
+    toc_offset: u64,
+}
+
+/// Entry of an eStargz TOC, describing a file or a chunk of one.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TocEntry {
+    /// Path of the file, relative to the root of the layer.
+    pub name: String,
+    /// Type of the entry: `dir`, `reg`, `symlink`, `hardlink`, `char`,
+    /// `block`, `fifo`, or `chunk` for the chunks of a `reg` file after its
+    /// first one.
+    #[serde(rename = "type")]
+    pub entry_type: String,
+    #[serde(default)]
+    pub size: u64,
+    #[serde(rename = "modtime", default)]
+    pub mod_time: Option<String>,
+    #[serde(default)]
+    pub link_name: Option<String>,
+    #[serde(default)]
+    pub mode: u32,
+    #[serde(default)]
+    pub uid: u32,
+    #[serde(default)]
+    pub gid: u32,
+    /// Offset in the layer blob of the gzip member holding the content.
+    #[serde(default)]
+    pub offset: u64,
+    /// Offset of the chunk in the file.
+    #[serde(default)]
+    pub chunk_offset: u64,
+    /// Size of the chunk, 0 for the rest of the file.
+    #[serde(default)]
+    pub chunk_size: u64,
+    /// Digest of the file content, for `reg` entries.
+    #[serde(default)]
+    pub digest: Option<String>,
+    #[serde(default)]
+    pub chunk_digest: Option<String>,
+}
+
+impl Descriptor {
+    /// Whether this describes an eStargz layer, whose files can be read
+    /// without downloading the whole blob.
+    ///
+    /// See `Client::get_estargz_toc`.
+    pub fn is_estargz(&self) -> bool {
+        self.annotations.contains_key(ANNOTATION_TOC_DIGEST)
+    }
+}
+
+impl EstargzToc {
+    /// Get the entry of the file at `path`.
+    pub fn entry(&self, path: &str) -> Option<&TocEntry> {
+        let path = normalize(path);
+        self.entries
+            .iter()
+            .find(|e| e.entry_type != "chunk" && normalize(&e.name) == path)
+    }
+
+    /// Range of the layer blob holding the content of a `reg` entry.
+    fn content_range(&self, entry: &TocEntry) -> std::ops::Range<u64> {
+        let last = self
+            .entries
+            .iter()
+            .filter(|e| e.entry_type == "chunk" && e.name == entry.name)
+            .map(|e| e.offset)
+            .fold(entry.offset, u64::max);
+        // Members end where the next one starts.
+        let end = self
+            .entries
+            .iter()
+            .map(|e| e.offset)
+            .filter(|offset| *offset > last)
+            .min()
+            .unwrap_or(self.toc_offset);
+        entry.offset..end
+    }
+}
+
+impl Client {
+    /// Retrieve the TOC of the eStargz layer `layer`.
+    ///
+    /// Only the end of the blob is downloaded. The TOC is verified against
+    /// the digest annotation of the descriptor, see `Descriptor::is_estargz`.
+    pub async fn get_estargz_toc(&self, name: &str, layer: &Descriptor) -> Result<EstargzToc> {
+        self.deadline()
+            .run(async move {
+                let start = layer.size.saturating_sub(FOOTER_SIZE);
+                let footer = self
+                    .get_blob_slice(name, &layer.digest, start..layer.size)
+                    .await?;
+                let (toc_offset, footer_size) = parse_footer(&footer)
+                    .ok_or_else(|| Error::Estargz("no eStargz footer".into()))?;
+                let end = layer.size - footer_size;
+                if toc_offset >= end {
+                    return Err(Error::Estargz(format!(
+                        "TOC offset {} out of range",
+                        toc_offset
+                    )));
+                }
+
+                let blob = self
+                    .get_blob_slice(name, &layer.digest, toc_offset..end)
+                    .await?;
+                let mut archive = tar::Archive::new(gzip::MultiDecoder::new(blob.as_slice())?);
+                let mut toc = Vec::new();
+                for entry in archive.entries()? {
+                    let mut entry = entry?;
+                    if entry.path_bytes().as_ref() == TOC_NAME.as_bytes() {
+                        entry.read_to_end(&mut toc)?;
+                        break;
+                    }
+                }
+                if toc.is_empty() {
+                    return Err(Error::Estargz(format!("no {} in TOC", TOC_NAME)));
+                }
+                if let Some(expected) = layer.annotations.get(ANNOTATION_TOC_DIGEST) {
+                    let got = sha256_digest(&toc);
+                    if *expected != got {
+                        return Err(Error::DigestMismatch {
+                            expected: expected.clone(),
+                            got,
+                        });
+                    }
+                }
+
+                let mut toc = serde_json::from_slice::<EstargzToc>(&toc)?;
+                toc.toc_offset = toc_offset;
+                Ok(toc)
+            })
+            .await
+    }
+
+    /// Read the file at `path` from the eStargz layer `layer`, with its TOC.
+    ///
+    /// Only the part of the blob holding the file is downloaded, and the file
+    /// is verified against its digest in the TOC.
+    pub async fn read_estargz_file(
+        &self,
+        name: &str,
+        layer: &Descriptor,
+        toc: &EstargzToc,
+        path: &str,
+    ) -> Result<Vec<u8>> {
+        self.deadline()
+            .run(async move {
+                let entry = toc
+                    .entry(path)
+                    .filter(|e| e.entry_type == "reg")
+                    .ok_or_else(|| Error::EstargzFileNotFound(path.to_string()))?;
+                if entry.size == 0 {
+                    return Ok(Vec::new());
+                }
+
+                let blob = self
+                    .get_blob_slice(name, &layer.digest, toc.content_range(entry))
+                    .await?;
+                // Chunks of a file are consecutive gzip members.
+                let mut content = Vec::new();
+                gzip::MultiDecoder::new(blob.as_slice())?
+                    .take(entry.size)
+                    .read_to_end(&mut content)?;
+                if content.len() as u64 != entry.size {
+                    return Err(Error::Estargz(format!("truncated content of {}", path)));
+                }
+                if let Some(digest) = &entry.digest {
+                    let mut digest = ContentDigest::try_new(digest)?;
+                    digest.update(&content);
+                    digest.verify()?;
+                }
+                Ok(content)
+            })
+            .await
+    }
+}
+
+/// Parse the TOC offset out of the footer of a layer, along with the size
+/// of the footer.
+fn parse_footer(footer: &[u8]) -> Option<(u64, u64)> {
+    // The footer is an empty gzip member, with the offset in its extra field.
+    let parse = |footer: &[u8], extra: &[u8]| {
+        let offset = footer.get(10..)?;
+        let offset = offset.strip_prefix(extra)?.get(..22)?;
+        let offset = std::str::from_utf8(offset).ok()?.strip_suffix("STARGZ")?;
+        u64::from_str_radix(offset, 16).ok()
+    };
+    if footer.len() as u64 == FOOTER_SIZE {
+        if let Some(offset) = parse(footer, b"\x1a\x00SG\x16\x00") {
+            return Some((offset, FOOTER_SIZE));
+        }
+    }
+    let legacy = footer.get(footer.len().checked_sub(LEGACY_FOOTER_SIZE as usize)?..)?;
+    parse(legacy, b"\x16\x00").map(|offset| (offset, LEGACY_FOOTER_SIZE))
+}
+
+fn normalize(path: &str) -> &str {
+    let path = path.trim_start_matches("./").trim_start_matches('/');
+    path.trim_end_matches('/')
+}
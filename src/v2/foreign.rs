@@ -0,0 +1,110 @@
+use crate::errors::Result;
+use crate::v2::manifest::Descriptor;
+use crate::v2::*;
+
+/// How to handle foreign layers, see `Descriptor::is_foreign`.
+///
+/// Registries do not store foreign (or non-distributable) layers, such as
+/// those of Windows base images, which are downloaded from the URLs of their
+/// descriptor instead.
+///
+/// This applies both to downloading layers, see `Config::foreign_layers`,
+/// and to unpacking them, see `render::UnpackOptions::foreign_layers`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ForeignLayerPolicy {
+    /// Download foreign layers from their URLs, falling back to the registry,
+    /// and unpack them as other layers.
+    Fetch,
+    /// Download foreign layers from the registry, as other layers, e.g. from
+    /// registries mirroring them, and unpack them as other layers.
+    Registry,
+    /// Skip foreign layers, with a warning when unpacking.
+    Skip,
+    /// Fail with `Error::ForeignLayer` when downloading foreign layers, or
+    /// with `RenderError::ForeignLayer` when unpacking them (default).
+    #[default]
+    Error,
+}
+
+/// Whether `media_type` denotes a foreign layer.
+///
+/// This covers both Docker (`...rootfs.foreign.diff...`) and OCI
+/// (`...layer.nondistributable...`) naming.
+pub(crate) fn is_foreign(media_type: &str) -> bool {
+    let media_type = media_type.split(';').next().unwrap_or_default();
+    media_type.contains(".foreign.") || media_type.contains(".nondistributable.")
+}
+
+impl Client {
+    /// Retrieve the blob of a layer, as described by its descriptor.
+    ///
+    /// Foreign layers are handled according to `Config::foreign_layers`,
+    /// returning `None` if skipped. They are downloaded from their URLs
+    /// without the registry credentials, following redirects.
+    ///
+    /// The response carries the media type of the layer, from which its
+    /// compression is known, see `BlobResponse::compression`.
+    pub async fn get_layer_response(
+        &self,
+        name: &str,
+        layer: &Descriptor,
+    ) -> Result<Option<BlobResponse>> {
+        let deadline = self.deadline();
+        deadline
+            .run(async move {
+                let res = match self.foreign_layers {
+                    _ if !layer.is_foreign() => {
+                        Some(self.get_blob_response(name, &layer.digest).await?)
+                    }
+                    ForeignLayerPolicy::Skip => {
+                        trace!("skipping foreign layer {}", layer.digest);
+                        None
+                    }
+                    ForeignLayerPolicy::Registry => {
+                        Some(self.get_blob_response(name, &layer.digest).await?)
+                    }
+                    ForeignLayerPolicy::Fetch => Some(self.get_foreign_layer(name, layer).await?),
+                    ForeignLayerPolicy::Error => {
+                        return Err(Error::ForeignLayer(layer.digest.clone()))
+                    }
+                };
+                Ok(res.map(|res| {
+                    res.with_media_type(&layer.media_type)
+                        .with_deadline(deadline)
+                }))
+            })
+            .await
+    }
+
+    async fn get_foreign_layer(&self, name: &str, layer: &Descriptor) -> Result<BlobResponse> {
+        for url in layer.urls.iter().flatten() {
+            match self.get_foreign_blob(url, &layer.digest).await {
+                Ok(blob) => return Ok(blob),
+                Err(e) => debug!("failed to download foreign layer from {}: {}", url, e),
+            }
+        }
+        self.get_blob_response(name, &layer.digest).await
+    }
+
+    async fn get_foreign_blob(&self, url: &str, digest: &str) -> Result<BlobResponse> {
+        let url = Url::parse(url)?;
+        // Sent without the default headers and authorization of the registry.
+        let req = self.client.get(url).build()?;
+        let res = match &self.transport {
+            Some(transport) => transport.execute(req).await?,
+            None => self.client.execute(req).await?,
+        };
+
+        let status = res.status();
+        trace!("GET {} status: {}", res.url(), status);
+        match status {
+            s if s.is_success() => Ok(BlobResponse::new(
+                res,
+                ContentDigest::try_new(digest)?,
+                digest.to_string(),
+                self.progress.clone(),
+            )),
+            _ => Err(status_error(res).await),
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use super::{Descriptor, ImageIndex, IndexEntry, ManifestSchema2Spec, OciManifest};
+use std::collections::HashMap;
+
+/// Date and time the image was built, as an RFC 3339 timestamp.
+pub const ANNOTATION_CREATED: &str = "org.opencontainers.image.created";
+/// URL of the source code the image was built from.
+pub const ANNOTATION_SOURCE: &str = "org.opencontainers.image.source";
+/// Revision of the source code the image was built from.
+pub const ANNOTATION_REVISION: &str = "org.opencontainers.image.revision";
+/// Licenses of the image contents, as an SPDX expression.
+pub const ANNOTATION_LICENSES: &str = "org.opencontainers.image.licenses";
+
+/// OCI annotations of a manifest, index or descriptor.
+///
+/// Accessors are provided for the annotations predefined by the OCI image
+/// spec which carry supply-chain metadata, see
+/// https://github.com/opencontainers/image-spec/blob/main/annotations.md.
+pub trait Annotated {
+    fn annotations(&self) -> &HashMap<String, String>;
+
+    fn annotations_mut(&mut self) -> &mut HashMap<String, String>;
+
+    /// Get the value of the annotation `key`.
+    fn annotation(&self, key: &str) -> Option<&str> {
+        self.annotations().get(key).map(String::as_str)
+    }
+
+    /// Set the annotation `key` to `value`.
+    fn set_annotation(&mut self, key: &str, value: &str) {
+        self.annotations_mut()
+            .insert(key.to_string(), value.to_string());
+    }
+
+    /// Get the `org.opencontainers.image.created` annotation.
+    fn created(&self) -> Option<&str> {
+        self.annotation(ANNOTATION_CREATED)
+    }
+
+    /// Set the `org.opencontainers.image.created` annotation.
+    fn set_created(&mut self, created: &str) {
+        self.set_annotation(ANNOTATION_CREATED, created)
+    }
+
+    /// Get the `org.opencontainers.image.source` annotation.
+    fn source(&self) -> Option<&str> {
+        self.annotation(ANNOTATION_SOURCE)
+    }
+
+    /// Set the `org.opencontainers.image.source` annotation.
+    fn set_source(&mut self, source: &str) {
+        self.set_annotation(ANNOTATION_SOURCE, source)
+    }
+
+    /// Get the `org.opencontainers.image.revision` annotation.
+    fn revision(&self) -> Option<&str> {
+        self.annotation(ANNOTATION_REVISION)
+    }
+
+    /// Set the `org.opencontainers.image.revision` annotation.
+    fn set_revision(&mut self, revision: &str) {
+        self.set_annotation(ANNOTATION_REVISION, revision)
+    }
+
+    /// Get the `org.opencontainers.image.licenses` annotation.
+    fn licenses(&self) -> Option<&str> {
+        self.annotation(ANNOTATION_LICENSES)
+    }
+
+    /// Set the `org.opencontainers.image.licenses` annotation.
+    fn set_licenses(&mut self, licenses: &str) {
+        self.set_annotation(ANNOTATION_LICENSES, licenses)
+    }
+}
+
+impl Annotated for OciManifest {
+    fn annotations(&self) -> &HashMap<String, String> {
+        &self.annotations
+    }
+
+    fn annotations_mut(&mut self) -> &mut HashMap<String, String> {
+        &mut self.annotations
+    }
+}
+
+impl Annotated for ManifestSchema2Spec {
+    fn annotations(&self) -> &HashMap<String, String> {
+        &self.annotations
+    }
+
+    fn annotations_mut(&mut self) -> &mut HashMap<String, String> {
+        &mut self.annotations
+    }
+}
+
+impl Annotated for ImageIndex {
+    fn annotations(&self) -> &HashMap<String, String> {
+        &self.annotations
+    }
+
+    fn annotations_mut(&mut self) -> &mut HashMap<String, String> {
+        &mut self.annotations
+    }
+}
+
+impl Annotated for IndexEntry {
+    fn annotations(&self) -> &HashMap<String, String> {
+        &self.annotations
+    }
+
+    fn annotations_mut(&mut self) -> &mut HashMap<String, String> {
+        &mut self.annotations
+    }
+}
+
+impl Annotated for Descriptor {
+    fn annotations(&self) -> &HashMap<String, String> {
+        &self.annotations
+    }
+
+    fn annotations_mut(&mut self) -> &mut HashMap<String, String> {
+        &mut self.annotations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn annotations_accessors() {
+        let mut descriptor = Descriptor::default();
+        assert_eq!(descriptor.created(), None);
+
+        descriptor.set_created("2024-01-02T03:04:05Z");
+        descriptor.set_source("https://github.com/camallo/dkregistry-rs");
+        descriptor.set_revision("0123abcd");
+        descriptor.set_licenses("MIT OR Apache-2.0");
+        assert_eq!(descriptor.created(), Some("2024-01-02T03:04:05Z"));
+        assert_eq!(
+            descriptor.source(),
+            Some("https://github.com/camallo/dkregistry-rs")
+        );
+        assert_eq!(descriptor.revision(), Some("0123abcd"));
+        assert_eq!(descriptor.licenses(), Some("MIT OR Apache-2.0"));
+        assert_eq!(
+            descriptor.annotations()[ANNOTATION_LICENSES],
+            "MIT OR Apache-2.0"
+        );
+    }
+}
@@ -0,0 +1,302 @@
+use super::{Descriptor, ManifestError};
+use crate::errors::{Error, Result};
+use crate::mediatypes::MediaTypes;
+use crate::v2::{sha256_digest, ContentDigest};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Digest of the empty configuration, `{}`.
+const EMPTY_CONFIG_DIGEST: &str =
+    "sha256:44136fa355b3678a1146ad16f7e8649e94fb4fc21fe77e8310c060f61caaff8a";
+
+/// Serialized manifest, ready to push with `Client::put_manifest`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestBytes {
+    pub media_type: MediaTypes,
+    pub body: Vec<u8>,
+    /// Digest of `body`, under which registries store the manifest.
+    pub digest: String,
+}
+
+impl ManifestBytes {
+    /// Serialize `manifest` as canonical JSON.
+    pub(crate) fn new<T: Serialize>(media_type: MediaTypes, manifest: &T) -> Result<Self> {
+        Ok(Self::from_raw(media_type, canonical_json(manifest)?))
+    }
+
+    /// Wrap an already serialized manifest, keeping its bytes as they are,
+    /// e.g. to copy a manifest to another repository.
+    pub fn from_raw(media_type: MediaTypes, body: Vec<u8>) -> Self {
+        let digest = sha256_digest(&body);
+        ManifestBytes {
+            media_type,
+            body,
+            digest,
+        }
+    }
+}
+
+/// Builder for image manifests, to push images assembled from their blobs.
+///
+/// By default, a Docker schema 2 manifest is built.
+#[derive(Debug, Clone)]
+pub struct ManifestBuilder {
+    media_type: MediaTypes,
+    config: Option<Descriptor>,
+    layers: Vec<Descriptor>,
+    artifact_type: Option<String>,
+    subject: Option<Descriptor>,
+    annotations: HashMap<String, String>,
+}
+
+impl Default for ManifestBuilder {
+    fn default() -> Self {
+        Self {
+            media_type: MediaTypes::ManifestV2S2,
+            config: None,
+            layers: Vec::new(),
+            artifact_type: None,
+            subject: None,
+            annotations: HashMap::new(),
+        }
+    }
+}
+
+/// Manifest as serialized by `ManifestBuilder`.
+#[derive(Serialize)]
+struct BuiltManifest<'a> {
+    #[serde(rename = "schemaVersion")]
+    schema_version: u16,
+    #[serde(rename = "mediaType")]
+    media_type: String,
+    config: &'a Descriptor,
+    layers: &'a [Descriptor],
+    #[serde(rename = "artifactType", skip_serializing_if = "Option::is_none")]
+    artifact_type: &'a Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    subject: &'a Option<Descriptor>,
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    annotations: &'a HashMap<String, String>,
+}
+
+impl ManifestBuilder {
+    /// Initialize a `ManifestBuilder` with default values.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the media type of the manifest, either `MediaTypes::ManifestV2S2`
+    /// (default) or `MediaTypes::OciImageManifest`.
+    pub fn media_type(mut self, media_type: MediaTypes) -> Self {
+        self.media_type = media_type;
+        self
+    }
+
+    /// Set the descriptor of the image configuration.
+    pub fn config(mut self, config: Descriptor) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    /// Set the image configuration, of type `media_type`.
+    pub fn config_blob(self, media_type: MediaTypes, digest: &str, size: u64) -> Self {
+        self.config(Descriptor {
+            media_type: media_type.to_string(),
+            digest: digest.to_string(),
+            size,
+            ..Default::default()
+        })
+    }
+
+    /// Set the empty configuration, as used by artifacts which are not images.
+    ///
+    /// Its content, `{}`, must be pushed as a blob along with the manifest.
+    pub fn empty_config(self) -> Self {
+        self.config_blob(MediaTypes::OciEmptyJson, EMPTY_CONFIG_DIGEST, 2)
+    }
+
+    /// Add a layer to the manifest, on top of the previous ones.
+    pub fn layer(mut self, layer: Descriptor) -> Self {
+        self.layers.push(layer);
+        self
+    }
+
+    /// Add a layer blob of type `media_type` to the manifest.
+    pub fn layer_blob(self, media_type: MediaTypes, digest: &str, size: u64) -> Self {
+        self.layer(Descriptor {
+            media_type: media_type.to_string(),
+            digest: digest.to_string(),
+            size,
+            ..Default::default()
+        })
+    }
+
+    /// Set the type of the artifact described by the (OCI) manifest.
+    pub fn artifact_type(mut self, artifact_type: &str) -> Self {
+        self.artifact_type = Some(artifact_type.to_string());
+        self
+    }
+
+    /// Set the manifest this (OCI) manifest refers to, e.g. the image of a
+    /// signature, to push it with `Client::put_referrer`.
+    pub fn subject(mut self, subject: Descriptor) -> Self {
+        self.subject = Some(subject);
+        self
+    }
+
+    /// Add an annotation to the manifest.
+    pub fn annotation(mut self, key: &str, value: &str) -> Self {
+        self.annotations.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    /// Serialize the manifest after validating its descriptors.
+    ///
+    /// The configuration is required, and digests must be valid. Artifact
+    /// types and subjects are only supported by OCI manifests.
+    pub fn build(self) -> Result<ManifestBytes> {
+        match self.media_type {
+            MediaTypes::ManifestV2S2 if self.artifact_type.is_none() && self.subject.is_none() => {}
+            MediaTypes::OciImageManifest => {}
+            unsupported => return Err(Error::UnsupportedMediaType(unsupported)),
+        }
+        let config = self.config.as_ref().ok_or(ManifestError::MissingConfig)?;
+        let descriptors = std::iter::once(config)
+            .chain(&self.layers)
+            .chain(&self.subject);
+        for descriptor in descriptors {
+            ContentDigest::try_new(&descriptor.digest)?;
+        }
+        let manifest = BuiltManifest {
+            schema_version: 2,
+            media_type: self.media_type.to_string(),
+            config,
+            layers: &self.layers,
+            artifact_type: &self.artifact_type,
+            subject: &self.subject,
+            annotations: &self.annotations,
+        };
+        ManifestBytes::new(self.media_type, &manifest)
+    }
+}
+
+/// Serialize `value` as JSON without whitespace and with object keys sorted,
+/// so that equal manifests (or configurations) have equal digests.
+///
+/// This is only meant for new documents; existing ones are pushed as they
+/// were received, see `ManifestBytes::from_raw`.
+pub fn canonical_json<T: Serialize>(value: &T) -> Result<Vec<u8>> {
+    fn sorted(value: serde_json::Value) -> serde_json::Value {
+        match value {
+            serde_json::Value::Object(map) => {
+                let mut entries = map.into_iter().collect::<Vec<_>>();
+                entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+                serde_json::Value::Object(
+                    entries.into_iter().map(|(k, v)| (k, sorted(v))).collect(),
+                )
+            }
+            serde_json::Value::Array(values) => {
+                serde_json::Value::Array(values.into_iter().map(sorted).collect())
+            }
+            value => value,
+        }
+    }
+    serde_json::to_vec(&sorted(serde_json::to_value(value)?)).map_err(Into::into)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sha2::{Digest, Sha256};
+
+    const DIGEST: &str = "sha256:e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+    #[test]
+    fn manifest_builder_is_canonical() {
+        let built = ManifestBuilder::new()
+            .media_type(MediaTypes::OciImageManifest)
+            .config_blob(MediaTypes::OciImageConfig, DIGEST, 2)
+            .layer(Descriptor {
+                media_type: "application/vnd.oci.image.layer.v1.tar+gzip".to_string(),
+                digest: DIGEST.to_string(),
+                size: 32,
+                annotations: HashMap::from([
+                    ("z".to_string(), "1".to_string()),
+                    ("a".to_string(), "2".to_string()),
+                ]),
+                ..Default::default()
+            })
+            .annotation("org.opencontainers.image.ref.name", "v1")
+            .build()
+            .unwrap();
+
+        let expected = format!(
+            concat!(
+                r#"{{"annotations":{{"org.opencontainers.image.ref.name":"v1"}},"#,
+                r#""config":{{"digest":"{d}","mediaType":"application/vnd.oci.image.config.v1+json","size":2}},"#,
+                r#""layers":[{{"annotations":{{"a":"2","z":"1"}},"digest":"{d}","#,
+                r#""mediaType":"application/vnd.oci.image.layer.v1.tar+gzip","size":32}}],"#,
+                r#""mediaType":"application/vnd.oci.image.manifest.v1+json","schemaVersion":2}}"#
+            ),
+            d = DIGEST
+        );
+        assert_eq!(String::from_utf8(built.body.clone()).unwrap(), expected);
+        assert_eq!(
+            built.digest,
+            format!("sha256:{:x}", Sha256::digest(&built.body))
+        );
+    }
+
+    #[test]
+    fn manifest_builder_artifact() {
+        let built = ManifestBuilder::new()
+            .media_type(MediaTypes::OciImageManifest)
+            .artifact_type("application/spdx+json")
+            .empty_config()
+            .layer_blob(MediaTypes::ApplicationJson, DIGEST, 0)
+            .subject(Descriptor {
+                media_type: MediaTypes::OciImageManifest.to_string(),
+                digest: DIGEST.to_string(),
+                size: 100,
+                ..Default::default()
+            })
+            .build()
+            .unwrap();
+
+        let manifest = serde_json::from_slice::<super::super::OciManifest>(&built.body).unwrap();
+        assert_eq!(manifest.artifact_type(), Some("application/spdx+json"));
+        assert_eq!(manifest.subject.unwrap().digest, DIGEST);
+        assert_eq!(
+            manifest.config.unwrap().digest,
+            format!("sha256:{:x}", Sha256::digest(b"{}"))
+        );
+    }
+
+    #[test]
+    fn manifest_builder_validates() {
+        let res = ManifestBuilder::new()
+            .layer_blob(MediaTypes::ImageLayerTgz, DIGEST, 32)
+            .build();
+        assert!(matches!(
+            res,
+            Err(Error::Manifest(ManifestError::MissingConfig))
+        ));
+
+        let res = ManifestBuilder::new()
+            .config_blob(MediaTypes::ContainerConfigV1, "junk", 2)
+            .build();
+        assert!(matches!(res, Err(Error::ContentDigestParse(_))));
+
+        let res = ManifestBuilder::new()
+            .artifact_type("application/spdx+json")
+            .empty_config()
+            .build();
+        assert!(matches!(res, Err(Error::UnsupportedMediaType(_))));
+
+        let res = ManifestBuilder::new()
+            .media_type(MediaTypes::ManifestList)
+            .config_blob(MediaTypes::ContainerConfigV1, DIGEST, 2)
+            .build();
+        assert!(matches!(res, Err(Error::UnsupportedMediaType(_))));
+    }
+}
@@ -0,0 +1,207 @@
+//! Conversion of schema 1 manifests to schema 2.
+
+use super::{canonical_json, History, ManifestError, ManifestSchema1Signed};
+use crate::errors::Result;
+use crate::mediatypes::MediaTypes;
+use crate::render::LayerCompression;
+use crate::v2::{sha256_digest, ContentDigest};
+use libflate::gzip;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::io;
+
+/// Fields of `v1Compatibility` entries which have no schema 2 counterpart.
+const V1_ONLY_FIELDS: &[&str] = &["id", "parent", "parent_id", "layer_id", "Size", "throwaway"];
+
+/// Schema 2 manifest and image configuration, converted from a schema 1
+/// manifest by `ManifestSchema1Signed::to_schema2`.
+#[derive(Debug, Clone)]
+pub struct Schema2Conversion {
+    /// Serialized manifest, to push with `Client::put_manifest` as
+    /// `MediaTypes::ManifestV2S2`.
+    pub manifest: Vec<u8>,
+    /// Serialized image configuration, to push as the blob `config_digest`
+    /// before the manifest.
+    pub config: Vec<u8>,
+    pub config_digest: String,
+}
+
+/// Subset of a `v1Compatibility` entry describing its layer.
+#[derive(Deserialize)]
+struct V1Layer {
+    created: Option<String>,
+    author: Option<String>,
+    comment: Option<String>,
+    container_config: Option<V1ContainerConfig>,
+    #[serde(default)]
+    throwaway: bool,
+}
+
+#[derive(Deserialize)]
+struct V1ContainerConfig {
+    #[serde(rename = "Cmd")]
+    cmd: Option<Vec<String>>,
+}
+
+impl ManifestSchema1Signed {
+    /// Synthesize the schema 2 manifest and image configuration of this image.
+    ///
+    /// `blobs` maps the digests of the layers to their content, from which
+    /// the uncompressed `rootfs.diff_ids` of the configuration are computed.
+    /// Empty layers marked as `throwaway` are left out, and need no blob. The
+    /// configuration is the one of the top `v1Compatibility` entry, with the
+    /// history of all entries.
+    pub fn to_schema2(&self, blobs: &HashMap<String, Vec<u8>>) -> Result<Schema2Conversion> {
+        let mut layers = Vec::new();
+        let mut diff_ids = Vec::new();
+        let mut history = Vec::new();
+        // Entries are ordered from the top layer down.
+        for (layer, compat) in self.fs_layers.iter().zip(&self.history).rev() {
+            let v1 = serde_json::from_str::<V1Layer>(&compat.v1_compat)?;
+            history.push(History {
+                created: v1.created,
+                created_by: v1
+                    .container_config
+                    .and_then(|c| c.cmd)
+                    .map(|cmd| cmd.join(" ")),
+                author: v1.author,
+                comment: v1.comment,
+                empty_layer: v1.throwaway,
+            });
+            if v1.throwaway {
+                continue;
+            }
+
+            let blob = blobs
+                .get(&layer.blob_sum)
+                .ok_or_else(|| ManifestError::MissingLayer(layer.blob_sum.clone()))?;
+            let mut digest = ContentDigest::try_new(&layer.blob_sum)?;
+            digest.update(blob);
+            digest.verify()?;
+            diff_ids.push(diff_id(blob)?);
+            layers.push(serde_json::json!({
+                "mediaType": MediaTypes::ImageLayerTgz.to_string(),
+                "size": blob.len(),
+                "digest": layer.blob_sum,
+            }));
+        }
+
+        let top = self.history.first().ok_or(ManifestError::NoHistory)?;
+        let mut config =
+            serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(&top.v1_compat)?;
+        for field in V1_ONLY_FIELDS {
+            config.remove(*field);
+        }
+        config.insert(
+            "rootfs".into(),
+            serde_json::json!({"type": "layers", "diff_ids": diff_ids}),
+        );
+        config.insert("history".into(), serde_json::to_value(history)?);
+        let config = canonical_json(&config)?;
+        let config_digest = sha256_digest(&config);
+
+        let manifest = serde_json::json!({
+            "schemaVersion": 2,
+            "mediaType": MediaTypes::ManifestV2S2.to_string(),
+            "config": {
+                "mediaType": MediaTypes::ContainerConfigV1.to_string(),
+                "size": config.len(),
+                "digest": config_digest,
+            },
+            "layers": layers,
+        });
+        Ok(Schema2Conversion {
+            manifest: canonical_json(&manifest)?,
+            config,
+            config_digest,
+        })
+    }
+}
+
+/// Digest of the uncompressed content of a layer.
+fn diff_id(blob: &[u8]) -> Result<String> {
+    let mut hasher = Sha256::new();
+    match LayerCompression::detect(blob) {
+        LayerCompression::Gzip => {
+            io::copy(&mut gzip::Decoder::new(blob)?, &mut hasher)?;
+        }
+        #[cfg(feature = "zstd")]
+        LayerCompression::Zstd => {
+            io::copy(&mut zstd::stream::read::Decoder::new(blob)?, &mut hasher)?;
+        }
+        _ => hasher.update(blob),
+    }
+    Ok(format!("sha256:{:x}", hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::v2::manifest::{ConfigBlob, ManifestSchema2Spec};
+    use std::io::Write;
+
+    fn digest(data: &[u8]) -> String {
+        format!("sha256:{:x}", Sha256::digest(data))
+    }
+
+    #[test]
+    fn schema1_converts_to_schema2() {
+        let tar = b"not really a tar".to_vec();
+        let mut encoder = gzip::Encoder::new(Vec::new()).unwrap();
+        encoder.write_all(&tar).unwrap();
+        let blob = encoder.finish().into_result().unwrap();
+        let throwaway = "sha256:a3ed95caeb02ffe68cdd9fd84406680ae93d633cb16422d00e8a7c22955b46d4";
+
+        let manifest = serde_json::json!({
+            "schemaVersion": 1,
+            "name": "library/hello-world",
+            "tag": "latest",
+            "architecture": "amd64",
+            "fsLayers": [{"blobSum": throwaway}, {"blobSum": digest(&blob)}],
+            "history": [
+                {"v1Compatibility": serde_json::json!({
+                    "id": "top", "parent": "base", "throwaway": true,
+                    "created": "2015-04-08T18:52:59Z", "architecture": "amd64", "os": "linux",
+                    "config": {"Cmd": ["/hello"]},
+                    "container_config": {"Cmd": ["/bin/sh", "-c", "#(nop) CMD [\"/hello\"]"]},
+                }).to_string()},
+                {"v1Compatibility": serde_json::json!({
+                    "id": "base", "created": "2015-04-08T18:52:58Z",
+                    "container_config": {"Cmd": ["/bin/sh", "-c", "#(nop) COPY file:hello in /"]},
+                }).to_string()},
+            ],
+            "signatures": [],
+        });
+        let manifest = serde_json::from_value::<ManifestSchema1Signed>(manifest).unwrap();
+
+        let blobs = HashMap::from([(digest(&blob), blob.clone())]);
+        let converted = manifest.to_schema2(&blobs).unwrap();
+        assert_eq!(converted.config_digest, digest(&converted.config));
+
+        let config = serde_json::from_slice::<ConfigBlob>(&converted.config).unwrap();
+        assert_eq!(config.architecture, "amd64");
+        assert_eq!(config.rootfs.diff_ids, vec![digest(&tar)]);
+        assert_eq!(config.history.len(), 2);
+        assert_eq!(
+            config.history[0].created_by.as_deref(),
+            Some("/bin/sh -c #(nop) COPY file:hello in /")
+        );
+        assert!(config.history[1].empty_layer);
+        let v1 = serde_json::from_slice::<serde_json::Value>(&converted.config).unwrap();
+        assert!(v1.get("id").is_none() && v1.get("throwaway").is_none());
+
+        let s2 = serde_json::from_slice::<ManifestSchema2Spec>(&converted.manifest).unwrap();
+        assert_eq!(s2.config().digest, converted.config_digest);
+        let layers = serde_json::from_slice::<serde_json::Value>(&converted.manifest).unwrap();
+        assert_eq!(layers["layers"][0]["digest"], digest(&blob));
+        assert_eq!(layers["layers"].as_array().unwrap().len(), 1);
+
+        let res = manifest.to_schema2(&HashMap::new());
+        assert!(matches!(
+            res,
+            Err(crate::errors::Error::Manifest(ManifestError::MissingLayer(
+                _
+            )))
+        ));
+    }
+}
@@ -0,0 +1,92 @@
+//! Manifests of media types unknown to this crate.
+
+use super::Manifest;
+use crate::errors::Result;
+use crate::mediatypes::MediaTypes;
+use std::any::Any;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+
+type Parsed = Arc<dyn Any + Send + Sync>;
+
+type Handler = Arc<dyn Fn(&[u8]) -> Result<Parsed> + Send + Sync>;
+
+/// Manifest of a media type this crate does not support, registered with
+/// `Config::media_type_handler` or passed through with
+/// `Config::passthrough_unknown_media_types`.
+#[derive(Clone)]
+pub struct CustomManifest {
+    /// Media type the manifest was served as.
+    pub media_type: MediaTypes,
+    /// Bytes of the manifest, as served.
+    pub body: Vec<u8>,
+    parsed: Option<Parsed>,
+}
+
+impl CustomManifest {
+    /// Get the manifest as parsed by the handler of its media type, if it
+    /// returned a `T`.
+    pub fn parsed<T: Any>(&self) -> Option<&T> {
+        self.parsed.as_deref()?.downcast_ref()
+    }
+}
+
+impl fmt::Debug for CustomManifest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CustomManifest")
+            .field("media_type", &self.media_type)
+            .field("size", &self.body.len())
+            .field("parsed", &self.parsed.is_some())
+            .finish()
+    }
+}
+
+/// Handlers of custom manifest media types.
+#[derive(Clone, Default)]
+pub(crate) struct MediaTypeHandlers {
+    handlers: BTreeMap<String, Handler>,
+    /// Whether to return unknown manifests instead of failing.
+    pub(crate) passthrough: bool,
+}
+
+impl MediaTypeHandlers {
+    pub(crate) fn register<T, F>(&mut self, media_type: &str, handler: F)
+    where
+        T: Any + Send + Sync,
+        F: Fn(&[u8]) -> Result<T> + Send + Sync + 'static,
+    {
+        let handler = move |body: &[u8]| handler(body).map(|parsed| Arc::new(parsed) as Parsed);
+        self.handlers
+            .insert(media_type.to_string(), Arc::new(handler));
+    }
+
+    /// Media types with a handler, in a stable order.
+    pub(crate) fn media_types(&self) -> impl Iterator<Item = &str> {
+        self.handlers.keys().map(String::as_str)
+    }
+
+    /// Parse a manifest of a media type not supported by this crate, if it
+    /// is handled or passed through.
+    pub(crate) fn parse(&self, media_type: &MediaTypes, body: &[u8]) -> Result<Option<Manifest>> {
+        let parsed = match self.handlers.get(&media_type.to_string()) {
+            Some(handler) => Some(handler(body)?),
+            None if self.passthrough => None,
+            None => return Ok(None),
+        };
+        Ok(Some(Manifest::Custom(Box::new(CustomManifest {
+            media_type: media_type.clone(),
+            body: body.to_vec(),
+            parsed,
+        }))))
+    }
+}
+
+impl fmt::Debug for MediaTypeHandlers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MediaTypeHandlers")
+            .field("media_types", &self.handlers.keys().collect::<Vec<_>>())
+            .field("passthrough", &self.passthrough)
+            .finish()
+    }
+}
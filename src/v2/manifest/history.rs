@@ -0,0 +1,150 @@
+//! Reconstruction of Dockerfiles from image histories.
+
+use super::{ConfigBlob, History};
+
+/// Dockerfile instructions, as found at the start of BuildKit history entries.
+const INSTRUCTIONS: &[&str] = &[
+    "ADD",
+    "ARG",
+    "CMD",
+    "COPY",
+    "ENTRYPOINT",
+    "ENV",
+    "EXPOSE",
+    "HEALTHCHECK",
+    "LABEL",
+    "MAINTAINER",
+    "ONBUILD",
+    "RUN",
+    "SHELL",
+    "STOPSIGNAL",
+    "USER",
+    "VOLUME",
+    "WORKDIR",
+];
+
+/// Shells prepended to the commands of `RUN` instructions.
+const SHELLS: &[&str] = &["/bin/sh -c ", "cmd /S /C "];
+
+impl ConfigBlob {
+    /// Render the history of the image as an approximate Dockerfile, one
+    /// instruction per entry, base layer first.
+    ///
+    /// Instructions are recovered from the `created_by` commands of both
+    /// the legacy builder (`/bin/sh -c #(nop) CMD ...`) and BuildKit
+    /// (`RUN /bin/sh -c ... # buildkit`), and plain commands become `RUN`
+    /// instructions. Build arguments are not shown. Entries with no command
+    /// are left out, unless they created a layer, which is then noted by a
+    /// comment. The base image cannot be told from its own history, so no
+    /// `FROM` instruction is rendered.
+    pub fn dockerfile(&self) -> String {
+        self.history
+            .iter()
+            .filter_map(instruction)
+            .map(|line| line + "\n")
+            .collect()
+    }
+}
+
+/// Dockerfile instruction of a history entry.
+fn instruction(entry: &History) -> Option<String> {
+    let created_by = match entry.created_by.as_deref().map(str::trim) {
+        Some(created_by) if !created_by.is_empty() => created_by,
+        _ if entry.empty_layer => return None,
+        _ => return Some("# layer created without a command".to_string()),
+    };
+    let command = created_by
+        .strip_suffix("# buildkit")
+        .map(str::trim_end)
+        .unwrap_or(created_by);
+    let command = skip_build_args(command);
+    let (run, command) = match command.strip_prefix("RUN ") {
+        Some(command) => (true, skip_build_args(command)),
+        None => (false, command),
+    };
+
+    match SHELLS.iter().find_map(|shell| command.strip_prefix(shell)) {
+        Some(script) => match script.trim_start().strip_prefix("#(nop)") {
+            Some(metadata) => Some(metadata.trim().to_string()),
+            None => Some(format!("RUN {}", script)),
+        },
+        None if run => Some(format!("RUN {}", command)),
+        None => {
+            let keyword = command.split_whitespace().next().unwrap_or_default();
+            if INSTRUCTIONS.contains(&keyword) {
+                Some(command.to_string())
+            } else {
+                Some(format!("RUN {}", command))
+            }
+        }
+    }
+}
+
+/// Skip the build arguments prepended to `RUN` commands, as in
+/// `|2 VERSION=1.0 TARGET=x86_64 /bin/sh -c make`.
+fn skip_build_args(command: &str) -> &str {
+    let count = match command
+        .strip_prefix('|')
+        .and_then(|c| c.split_whitespace().next())
+        .and_then(|n| n.parse::<usize>().ok())
+    {
+        Some(count) => count,
+        None => return command,
+    };
+    let mut rest = command;
+    for _ in 0..=count {
+        rest = rest.trim_start();
+        rest = rest.find(char::is_whitespace).map_or("", |i| &rest[i..]);
+    }
+    rest.trim_start()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(created_by: Option<&str>, empty_layer: bool) -> History {
+        History {
+            created_by: created_by.map(str::to_string),
+            empty_layer,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn history_renders_dockerfile() {
+        let config = ConfigBlob {
+            history: vec![
+                step(Some("/bin/sh -c #(nop) ADD file:0b2d in / "), false),
+                step(Some("/bin/sh -c #(nop)  CMD [\"bash\"]"), true),
+                step(Some("/bin/sh -c apt-get update"), false),
+                step(Some("|1 VERSION=1.2 /bin/sh -c make install"), false),
+                step(Some("ENV PATH=/usr/local/bin"), true),
+                step(
+                    Some("RUN |1 VERSION=1.2 /bin/sh -c ./configure # buildkit"),
+                    false,
+                ),
+                step(Some("COPY app /app # buildkit"), false),
+                step(Some("mkdir /data"), false),
+                step(None, true),
+                step(None, false),
+            ],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            config.dockerfile(),
+            concat!(
+                "ADD file:0b2d in /\n",
+                "CMD [\"bash\"]\n",
+                "RUN apt-get update\n",
+                "RUN make install\n",
+                "ENV PATH=/usr/local/bin\n",
+                "RUN ./configure\n",
+                "COPY app /app\n",
+                "RUN mkdir /data\n",
+                "# layer created without a command\n",
+            )
+        );
+    }
+}
@@ -0,0 +1,368 @@
+use super::{
+    build_accept_headers, evaluate_media_type, verify_manifest, Descriptor, Manifest,
+    ManifestBytes, ManifestError, Platform,
+};
+use crate::errors::{Error, Result};
+use crate::mediatypes::MediaTypes;
+use crate::v2::{response_error, Client, ContentDigest, Method};
+use reqwest::{header, StatusCode};
+use std::collections::HashMap;
+
+/// Image index, either a Docker manifest list or an OCI image index.
+///
+/// Specification is at https://github.com/opencontainers/image-spec/blob/main/image-index.md.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ImageIndex {
+    #[serde(rename = "schemaVersion")]
+    pub schema_version: u16,
+    #[serde(rename = "mediaType", default, skip_serializing_if = "Option::is_none")]
+    pub media_type: Option<String>,
+    #[serde(
+        rename = "artifactType",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub artifact_type: Option<String>,
+    pub manifests: Vec<IndexEntry>,
+    /// Manifest this index refers to, e.g. the image of a set of signatures.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subject: Option<Descriptor>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub annotations: HashMap<String, String>,
+}
+
+/// Manifest referenced by an image index.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct IndexEntry {
+    #[serde(rename = "mediaType")]
+    pub media_type: String,
+    pub digest: String,
+    pub size: u64,
+    /// Type of the artifact, for entries which are not images.
+    #[serde(
+        rename = "artifactType",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub artifact_type: Option<String>,
+    /// Platform of the image, optional in OCI image indexes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub platform: Option<Platform>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub annotations: HashMap<String, String>,
+}
+
+impl ImageIndex {
+    /// Find the manifest for a platform, returning its digest.
+    ///
+    /// If `variant` is `None`, manifests match regardless of their variant.
+    /// See `select`.
+    pub fn select_platform(&self, os: &str, arch: &str, variant: Option<&str>) -> Option<&str> {
+        let mut host = Platform::new(os, arch);
+        host.variant = variant.map(str::to_string);
+        self.select(&host).map(|entry| entry.digest.as_str())
+    }
+
+    /// Find the manifest of the image to run on `host`.
+    ///
+    /// Manifests match as by `Platform::matches`. The one with the highest
+    /// variant is selected, e.g. `arm/v7` rather than `arm/v6` on an `arm/v8`
+    /// host, or else the first one.
+    pub fn select(&self, host: &Platform) -> Option<&IndexEntry> {
+        self.select_by(host, Platform::matches)
+    }
+
+    /// Like `select`, with manifests matching as by `Platform::matches_compat`.
+    ///
+    /// Images of the host architecture are preferred.
+    pub fn select_compat(&self, host: &Platform) -> Option<&IndexEntry> {
+        self.select_by(host, Platform::matches_compat)
+    }
+
+    fn select_by<F>(&self, host: &Platform, matches: F) -> Option<&IndexEntry>
+    where
+        F: Fn(&Platform, &Platform) -> bool,
+    {
+        self.manifests
+            .iter()
+            .filter_map(|entry| Some((entry, entry.platform.as_ref()?)))
+            .filter(|(_, platform)| matches(platform, host))
+            // `max_by_key` returns the last maximum, so search from the end.
+            .rev()
+            .max_by_key(|(_, platform)| platform.match_rank(host))
+            .map(|(entry, _)| entry)
+    }
+
+    /// Serialize the index as canonical JSON, of its own media type or as
+    /// an OCI image index if unset.
+    pub fn to_bytes(&self) -> Result<ManifestBytes> {
+        let media_type = match &self.media_type {
+            Some(media_type) => MediaTypes::parse(media_type),
+            None => MediaTypes::OciImageIndex,
+        };
+        ManifestBytes::new(media_type, self)
+    }
+}
+
+/// Builder for image indexes, to publish multi-arch images.
+///
+/// By default, a Docker manifest list is built. The artifact type, the
+/// subject and annotations are only supported by OCI image indexes.
+#[derive(Debug, Clone)]
+pub struct ImageIndexBuilder {
+    media_type: MediaTypes,
+    artifact_type: Option<String>,
+    manifests: Vec<IndexEntry>,
+    subject: Option<Descriptor>,
+    annotations: HashMap<String, String>,
+}
+
+impl Default for ImageIndexBuilder {
+    fn default() -> Self {
+        Self {
+            media_type: MediaTypes::ManifestList,
+            artifact_type: None,
+            manifests: Vec::new(),
+            subject: None,
+            annotations: HashMap::new(),
+        }
+    }
+}
+
+impl ImageIndexBuilder {
+    /// Initialize an `ImageIndexBuilder` with default values.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the media type of the index, either `MediaTypes::ManifestList`
+    /// (default) or `MediaTypes::OciImageIndex`.
+    pub fn media_type(mut self, media_type: MediaTypes) -> Self {
+        self.media_type = media_type;
+        self
+    }
+
+    /// Set the type of the artifact the index is for.
+    pub fn artifact_type(mut self, artifact_type: &str) -> Self {
+        self.artifact_type = Some(artifact_type.to_string());
+        self
+    }
+
+    /// Set the manifest the index refers to.
+    pub fn subject(mut self, subject: Descriptor) -> Self {
+        self.subject = Some(subject);
+        self
+    }
+
+    /// Add an annotation to the index, e.g.
+    /// `org.opencontainers.image.ref.name`.
+    pub fn annotation(mut self, key: &str, value: &str) -> Self {
+        self.annotations.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    /// Add a manifest to the index.
+    pub fn manifest(mut self, entry: IndexEntry) -> Self {
+        self.manifests.push(entry);
+        self
+    }
+
+    /// Add the manifest of an image for a platform to the index.
+    pub fn platform_manifest(
+        self,
+        media_type: MediaTypes,
+        digest: &str,
+        size: u64,
+        platform: Platform,
+    ) -> Self {
+        self.manifest(IndexEntry {
+            media_type: media_type.to_string(),
+            digest: digest.to_string(),
+            size,
+            artifact_type: None,
+            platform: Some(platform),
+            annotations: HashMap::new(),
+        })
+    }
+
+    /// Add the manifest of an artifact of type `artifact_type` to the index.
+    pub fn artifact_manifest(
+        self,
+        media_type: MediaTypes,
+        digest: &str,
+        size: u64,
+        artifact_type: &str,
+    ) -> Self {
+        self.manifest(IndexEntry {
+            media_type: media_type.to_string(),
+            digest: digest.to_string(),
+            size,
+            artifact_type: Some(artifact_type.to_string()),
+            platform: None,
+            annotations: HashMap::new(),
+        })
+    }
+
+    /// Return an `ImageIndex` after validating its entries.
+    ///
+    /// Digests must be valid, and manifest lists require a platform for
+    /// each entry and support no OCI extensions.
+    pub fn build(self) -> Result<ImageIndex> {
+        match self.media_type {
+            MediaTypes::OciImageIndex => {}
+            MediaTypes::ManifestList
+                if self.artifact_type.is_none()
+                    && self.subject.is_none()
+                    && self.annotations.is_empty() => {}
+            unsupported => return Err(Error::UnsupportedMediaType(unsupported)),
+        }
+        for entry in &self.manifests {
+            ContentDigest::try_new(&entry.digest)?;
+            if self.media_type == MediaTypes::ManifestList && entry.platform.is_none() {
+                return Err(ManifestError::MissingPlatform(entry.digest.clone()).into());
+            }
+        }
+        if let Some(subject) = &self.subject {
+            ContentDigest::try_new(&subject.digest)?;
+        }
+        Ok(ImageIndex {
+            schema_version: 2,
+            media_type: Some(self.media_type.to_string()),
+            artifact_type: self.artifact_type,
+            manifests: self.manifests,
+            subject: self.subject,
+            annotations: self.annotations,
+        })
+    }
+
+    /// Build the index, and serialize it as by `ImageIndex::to_bytes`.
+    pub fn build_bytes(self) -> Result<ManifestBytes> {
+        self.build()?.to_bytes()
+    }
+}
+
+impl Client {
+    /// Fetch an image index (manifest list or OCI image index).
+    ///
+    /// The name and reference parameters identify the image.
+    /// The reference may be either a tag or digest.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(registry = %self.base_url, repository = name, reference = %reference, bytes = tracing::field::Empty))
+    )]
+    pub async fn get_image_index(&self, name: &str, reference: &str) -> Result<ImageIndex> {
+        self.deadline()
+            .run(async move {
+                let url = self.build_url(name, reference)?;
+
+                let accept_headers = build_accept_headers(&[
+                    (MediaTypes::ManifestList, None),
+                    (MediaTypes::OciImageIndex, None),
+                ]);
+
+                let res = self
+                    .send(|client| {
+                        client
+                            .build_reqwest(Method::GET, url.clone())
+                            .headers(accept_headers.clone())
+                    })
+                    .await?;
+
+                let status = res.status();
+                trace!("GET '{}' status: {:?}", res.url(), status);
+
+                match status {
+                    StatusCode::OK => {}
+                    StatusCode::NOT_FOUND => {
+                        return Err(Error::ManifestNotFound(reference.to_string()))
+                    }
+                    _ => return Err(response_error(res, Error::UnexpectedHttpStatus(status)).await),
+                }
+
+                let content_digest = match res.headers().get("docker-content-digest") {
+                    Some(content_digest_value) => Some(content_digest_value.to_str()?.to_string()),
+                    None => {
+                        debug!("cannot find manifestref in headers");
+                        None
+                    }
+                };
+
+                match evaluate_media_type(res.headers().get(header::CONTENT_TYPE), &url)? {
+                    MediaTypes::ManifestList | MediaTypes::OciImageIndex => {
+                        let body = self.read_body(res, reference).await?;
+                        verify_manifest(reference, content_digest.as_deref(), &body)?;
+                        serde_json::from_slice(&body).map_err(Into::into)
+                    }
+                    unsupported => Err(Error::UnsupportedMediaType(unsupported)),
+                }
+            })
+            .await
+    }
+
+    /// Fetch the image manifest for a platform, and return it with its digest.
+    ///
+    /// The reference must resolve to an image index, in which the manifest
+    /// is selected as by `ImageIndex::select_platform`.
+    pub async fn get_platform_manifest(
+        &self,
+        name: &str,
+        reference: &str,
+        os: &str,
+        arch: &str,
+        variant: Option<&str>,
+    ) -> Result<(Manifest, Option<String>)> {
+        self.deadline()
+            .run(async move {
+                let index = self.get_image_index(name, reference).await?;
+                let digest = index.select_platform(os, arch, variant).ok_or_else(|| {
+                    let platform = match variant {
+                        Some(v) => format!("{}/{}/{}", os, arch, v),
+                        None => format!("{}/{}", os, arch),
+                    };
+                    Error::PlatformNotFound(platform)
+                })?;
+
+                let entry = index.manifests.iter().find(|e| e.digest == digest);
+                self.get_index_entry(name, digest, entry.map(|e| e.media_type.as_str()))
+                    .await
+            })
+            .await
+    }
+
+    /// Fetch the manifest `digest` of an image index, and return it with its
+    /// digest.
+    pub(super) async fn get_index_entry(
+        &self,
+        name: &str,
+        digest: &str,
+        media_type: Option<&str>,
+    ) -> Result<(Manifest, Option<String>)> {
+        // Make sure the media type announced by the index gets accepted.
+        let mut client = self.clone();
+        if let Some(media_type) = media_type.map(MediaTypes::parse) {
+            if !client.accepted_types.iter().any(|(t, _)| *t == media_type) {
+                client.accepted_types.push((media_type, None));
+            }
+        }
+
+        let (manifest, manifest_digest) = client.get_manifest_and_ref(name, digest).await?;
+        Ok((
+            manifest,
+            manifest_digest.or_else(|| Some(digest.to_string())),
+        ))
+    }
+
+    /// Upload an image index, returning its digest.
+    ///
+    /// The index is uploaded as serialized by `ImageIndex::to_bytes`. See
+    /// `put_manifest_bytes`.
+    pub async fn put_image_index(
+        &self,
+        name: &str,
+        reference: &str,
+        index: &ImageIndex,
+    ) -> Result<String> {
+        self.put_manifest_bytes(name, reference, &index.to_bytes()?)
+            .await
+    }
+}
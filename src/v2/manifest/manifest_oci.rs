@@ -0,0 +1,158 @@
+use super::{build_accept_headers, evaluate_media_type, verify_manifest};
+use crate::errors::{Error, Result};
+use crate::mediatypes::MediaTypes;
+use crate::v2::{response_error, Client, Method};
+use reqwest::{header, StatusCode};
+use std::collections::HashMap;
+
+/// OCI image manifest, as used for images and other artifacts.
+///
+/// Specification is at https://github.com/opencontainers/image-spec/blob/main/manifest.md.
+/// Manifests of the (deprecated) `application/vnd.oci.artifact.manifest.v1+json`
+/// media type are parsed as well, with their `blobs` as layers.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct OciManifest {
+    #[serde(rename = "schemaVersion", default)]
+    pub schema_version: u16,
+    #[serde(rename = "mediaType", default, skip_serializing_if = "Option::is_none")]
+    pub media_type: Option<String>,
+    #[serde(
+        rename = "artifactType",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub artifact_type: Option<String>,
+    /// Configuration descriptor, missing in artifact manifests.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config: Option<Descriptor>,
+    #[serde(default, alias = "blobs")]
+    pub layers: Vec<Descriptor>,
+    /// Manifest this one refers to, e.g. the image of a signature.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subject: Option<Descriptor>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub annotations: HashMap<String, String>,
+}
+
+/// Content descriptor, referencing a blob or manifest.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Descriptor {
+    #[serde(rename = "mediaType")]
+    pub media_type: String,
+    pub digest: String,
+    pub size: u64,
+    #[serde(
+        rename = "artifactType",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub artifact_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub urls: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub annotations: HashMap<String, String>,
+}
+
+impl Descriptor {
+    /// Whether this describes a foreign (or non-distributable) layer, which
+    /// registries may not store, and is downloaded from `urls` instead.
+    ///
+    /// See `Client::get_layer_response`.
+    pub fn is_foreign(&self) -> bool {
+        crate::v2::is_foreign(&self.media_type)
+    }
+}
+
+impl OciManifest {
+    /// Get the type of the artifact described by this manifest.
+    ///
+    /// This is `artifactType` if set, otherwise the config media type, except
+    /// for the empty config.
+    pub fn artifact_type(&self) -> Option<&str> {
+        let empty = MediaTypes::OciEmptyJson.to_string();
+        self.artifact_type.as_deref().or_else(|| {
+            self.config
+                .as_ref()
+                .map(|c| c.media_type.as_str())
+                .filter(|t| *t != empty)
+        })
+    }
+
+    /// Whether this manifest describes a container image.
+    pub fn is_image(&self) -> bool {
+        let config_type = self.config.as_ref().map(|c| c.media_type.as_str());
+        self.artifact_type.is_none()
+            && [MediaTypes::OciImageConfig, MediaTypes::ContainerConfigV1]
+                .iter()
+                .any(|t| config_type == Some(t.to_string().as_str()))
+    }
+
+    /// List digests of all layers (or blobs) referenced by this manifest.
+    pub fn get_layers(&self) -> Vec<String> {
+        self.layers.iter().map(|l| l.digest.clone()).collect()
+    }
+}
+
+impl Client {
+    /// Fetch an OCI manifest, for images as well as other artifacts, and
+    /// return it with its digest.
+    ///
+    /// The name and reference parameters identify the artifact.
+    /// The reference may be either a tag or digest.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(registry = %self.base_url, repository = name, reference = %reference, bytes = tracing::field::Empty))
+    )]
+    pub async fn get_artifact_manifest(
+        &self,
+        name: &str,
+        reference: &str,
+    ) -> Result<(OciManifest, Option<String>)> {
+        self.deadline()
+            .run(async move {
+                let url = self.build_url(name, reference)?;
+
+                let accept_headers = build_accept_headers(&[
+                    (MediaTypes::OciImageManifest, None),
+                    (MediaTypes::OciArtifactManifest, None),
+                ]);
+
+                let res = self
+                    .send(|client| {
+                        client
+                            .build_reqwest(Method::GET, url.clone())
+                            .headers(accept_headers.clone())
+                    })
+                    .await?;
+
+                let status = res.status();
+                trace!("GET '{}' status: {:?}", res.url(), status);
+
+                match status {
+                    StatusCode::OK => {}
+                    StatusCode::NOT_FOUND => {
+                        return Err(Error::ManifestNotFound(reference.to_string()))
+                    }
+                    _ => return Err(response_error(res, Error::UnexpectedHttpStatus(status)).await),
+                }
+
+                let content_digest = match res.headers().get("docker-content-digest") {
+                    Some(content_digest_value) => Some(content_digest_value.to_str()?.to_string()),
+                    None => {
+                        debug!("cannot find manifestref in headers");
+                        None
+                    }
+                };
+
+                match evaluate_media_type(res.headers().get(header::CONTENT_TYPE), &url)? {
+                    MediaTypes::OciImageManifest | MediaTypes::OciArtifactManifest => {
+                        let body = self.read_body(res, reference).await?;
+                        verify_manifest(reference, content_digest.as_deref(), &body)?;
+                        Ok((serde_json::from_slice(&body)?, content_digest))
+                    }
+                    unsupported => Err(Error::UnsupportedMediaType(unsupported)),
+                }
+            })
+            .await
+    }
+}
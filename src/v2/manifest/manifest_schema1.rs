@@ -1,3 +1,5 @@
+#[cfg(feature = "schema1-signatures")]
+use crate::errors::{Error, Result};
 use std::collections::HashMap;
 
 /// Manifest version 2 schema 1, signed.
@@ -6,39 +8,102 @@ use std::collections::HashMap;
 #[derive(Debug, Default, Deserialize, Serialize)]
 pub struct ManifestSchema1Signed {
     #[serde(rename = "schemaVersion")]
-    schema_version: u16,
+    pub(super) schema_version: u16,
     pub name: String,
     pub tag: String,
     pub architecture: String,
     #[serde(rename = "fsLayers")]
-    fs_layers: Vec<S1Layer>,
-    history: Vec<V1Compat>,
+    pub(super) fs_layers: Vec<S1Layer>,
+    pub(super) history: Vec<V1Compat>,
     signatures: Vec<Signature>,
+    #[serde(skip)]
+    signing_keys: Vec<SigningKey>,
 }
 
 #[derive(Debug, Default, Deserialize, Serialize)]
 struct Signature {
-    // TODO(lucab): switch to jsonwebtokens crate
-    // https://github.com/Keats/rust-jwt/pull/23
     header: serde_json::Value,
     signature: String,
     protected: String,
 }
 
+/// Key which signed a schema 1 manifest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SigningKey {
+    /// Signature algorithm, e.g. `ES256`.
+    pub algorithm: String,
+    /// Key ID, in the libtrust format (e.g. `OD6I:6DRK:...`).
+    pub key_id: Option<String>,
+    /// Public key, as a JSON Web Key.
+    pub jwk: serde_json::Value,
+}
+
 /// Compatibility entry for version 1 manifest interoperability.
 #[derive(Debug, Deserialize, Serialize)]
-struct V1Compat {
+pub(super) struct V1Compat {
     #[serde(rename = "v1Compatibility")]
-    v1_compat: String,
+    pub(super) v1_compat: String,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
-struct S1Layer {
+pub(super) struct S1Layer {
     #[serde(rename = "blobSum")]
-    blob_sum: String,
+    pub(super) blob_sum: String,
 }
 
 impl ManifestSchema1Signed {
+    /// Parse a manifest and verify all its signatures.
+    ///
+    /// `body` must be the manifest exactly as served by the registry, as
+    /// signatures cover its bytes. Fails if the manifest is not signed, or
+    /// if any of the signatures is invalid.
+    ///
+    /// The manifest is parsed from the signed payload only, so that bytes
+    /// of `body` which no signature covers are ignored.
+    #[cfg(feature = "schema1-signatures")]
+    pub fn verify(body: &[u8]) -> Result<Self> {
+        let signed = serde_json::from_slice::<Self>(body)?;
+        if signed.signatures.is_empty() {
+            return Err(Error::Schema1Signature("manifest is not signed".into()));
+        }
+        let mut payload = None;
+        let mut signing_keys = Vec::with_capacity(signed.signatures.len());
+        for signature in &signed.signatures {
+            let (key, signed_payload) = signature.verify(body)?;
+            match &payload {
+                Some(p) if p != &signed_payload => {
+                    return Err(Error::Schema1Signature(
+                        "signatures cover different payloads".into(),
+                    ))
+                }
+                Some(_) => {}
+                None => payload = Some(signed_payload),
+            }
+            signing_keys.push(key);
+        }
+
+        // The payload is the manifest without its signatures.
+        let mut value = serde_json::from_slice::<serde_json::Value>(&payload.unwrap_or_default())?;
+        value
+            .as_object_mut()
+            .ok_or_else(|| Error::Schema1Signature("payload is not a JSON object".into()))?
+            .insert(
+                "signatures".into(),
+                serde_json::to_value(&signed.signatures)?,
+            );
+        let mut manifest = serde_json::from_value::<Self>(value)?;
+        manifest.signing_keys = signing_keys;
+        Ok(manifest)
+    }
+
+    /// Keys whose signatures were verified when the manifest was fetched.
+    ///
+    /// This is empty without the `schema1-signatures` feature, or when the
+    /// verification failed and valid signatures were not required.
+    pub fn signing_keys(&self) -> &[SigningKey] {
+        &self.signing_keys
+    }
+
     /// List digests of all layers referenced by this manifest.
     ///
     /// The returned layers list is ordered starting with the base image first.
@@ -66,3 +131,178 @@ impl ManifestSchema1Signed {
         )
     }
 }
+
+#[cfg(feature = "schema1-signatures")]
+#[derive(Deserialize)]
+struct SignatureHeader {
+    alg: String,
+    jwk: Option<serde_json::Value>,
+}
+
+/// Part of the manifest which is covered by a signature, see
+/// https://github.com/docker/libtrust/blob/master/jsonsign.go.
+#[cfg(feature = "schema1-signatures")]
+#[derive(Deserialize)]
+struct ProtectedHeader {
+    #[serde(rename = "formatLength")]
+    format_length: usize,
+    #[serde(rename = "formatTail")]
+    format_tail: String,
+}
+
+#[cfg(feature = "schema1-signatures")]
+impl Signature {
+    /// Verify this signature of the manifest `body`, returning its key and
+    /// the signed payload.
+    fn verify(&self, body: &[u8]) -> Result<(SigningKey, Vec<u8>)> {
+        use ring::signature::{self as sig, RsaPublicKeyComponents, UnparsedPublicKey};
+
+        let invalid = |e: &dyn std::fmt::Display| Error::Schema1Signature(e.to_string());
+        let header = serde_json::from_value::<SignatureHeader>(self.header.clone())
+            .map_err(|e| invalid(&e))?;
+        let jwk = header
+            .jwk
+            .ok_or_else(|| Error::Schema1Signature("no JSON Web Key in header".into()))?;
+        let protected =
+            serde_json::from_slice::<ProtectedHeader>(&base64url_decode(&self.protected)?)
+                .map_err(|e| invalid(&e))?;
+        let formatted = body
+            .get(..protected.format_length)
+            .ok_or_else(|| Error::Schema1Signature("format length exceeds the manifest".into()))?;
+        let payload = [formatted, &base64url_decode(&protected.format_tail)?].concat();
+        let message = format!(
+            "{}.{}",
+            self.protected,
+            base64::encode_config(&payload, base64::URL_SAFE_NO_PAD)
+        );
+        let signature = base64url_decode(&self.signature)?;
+
+        let param = |name: &str| -> Result<Vec<u8>> {
+            let value = jwk.get(name).and_then(|v| v.as_str()).ok_or_else(|| {
+                Error::Schema1Signature(format!("no parameter {:?} in JSON Web Key", name))
+            })?;
+            base64url_decode(value)
+        };
+        let ec_point =
+            || -> Result<Vec<u8>> { Ok([&[4][..], &param("x")?, &param("y")?].concat()) };
+        let verified = match header.alg.as_str() {
+            "ES256" => UnparsedPublicKey::new(&sig::ECDSA_P256_SHA256_FIXED, ec_point()?)
+                .verify(message.as_bytes(), &signature),
+            "ES384" => UnparsedPublicKey::new(&sig::ECDSA_P384_SHA384_FIXED, ec_point()?)
+                .verify(message.as_bytes(), &signature),
+            alg @ ("RS256" | "RS384" | "RS512") => {
+                let params = match alg {
+                    "RS256" => &sig::RSA_PKCS1_2048_8192_SHA256,
+                    "RS384" => &sig::RSA_PKCS1_2048_8192_SHA384,
+                    _ => &sig::RSA_PKCS1_2048_8192_SHA512,
+                };
+                RsaPublicKeyComponents {
+                    n: param("n")?,
+                    e: param("e")?,
+                }
+                .verify(params, message.as_bytes(), &signature)
+            }
+            alg => {
+                return Err(Error::Schema1Signature(format!(
+                    "unsupported algorithm {}",
+                    alg
+                )))
+            }
+        };
+        verified.map_err(|_| Error::Schema1Signature("signature does not match".into()))?;
+
+        let key = SigningKey {
+            key_id: jwk.get("kid").and_then(|v| v.as_str()).map(str::to_string),
+            algorithm: header.alg,
+            jwk,
+        };
+        Ok((key, payload))
+    }
+}
+
+#[cfg(feature = "schema1-signatures")]
+fn base64url_decode(input: &str) -> Result<Vec<u8>> {
+    base64::decode_config(input.trim_end_matches('='), base64::URL_SAFE_NO_PAD)
+        .map_err(|e| Error::Schema1Signature(e.to_string()))
+}
+
+#[cfg(all(test, feature = "schema1-signatures"))]
+mod tests {
+    use super::*;
+    use ring::rand::SystemRandom;
+    use ring::signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_FIXED_SIGNING};
+
+    fn encode(input: &[u8]) -> String {
+        base64::encode_config(input, base64::URL_SAFE_NO_PAD)
+    }
+
+    /// Sign `payload` the way libtrust does.
+    fn sign(payload: &str) -> Vec<u8> {
+        let rng = SystemRandom::new();
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng).unwrap();
+        let key = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, pkcs8.as_ref(), &rng)
+            .unwrap();
+        let point = key.public_key().as_ref();
+
+        let format_length = payload.len() - 2;
+        let protected = encode(
+            format!(
+                r#"{{"formatLength":{},"formatTail":"{}"}}"#,
+                format_length,
+                encode(&payload.as_bytes()[format_length..])
+            )
+            .as_bytes(),
+        );
+        let message = format!("{}.{}", protected, encode(payload.as_bytes()));
+        let signature = key.sign(&rng, message.as_bytes()).unwrap();
+        let signatures = serde_json::json!([{
+            "header": {
+                "jwk": {
+                    "crv": "P-256",
+                    "kid": "TEST:KEY",
+                    "kty": "EC",
+                    "x": encode(&point[1..33]),
+                    "y": encode(&point[33..]),
+                },
+                "alg": "ES256",
+            },
+            "signature": encode(signature.as_ref()),
+            "protected": protected,
+        }]);
+        format!(
+            "{},\n   \"signatures\": {}{}",
+            &payload[..format_length],
+            signatures,
+            &payload[format_length..]
+        )
+        .into_bytes()
+    }
+
+    #[test]
+    fn signatures_verify() {
+        let payload = r#"{
+   "schemaVersion": 1,
+   "name": "library/hello-world",
+   "tag": "latest",
+   "architecture": "amd64",
+   "fsLayers": [],
+   "history": []
+}"#;
+        let body = sign(payload);
+        let manifest = ManifestSchema1Signed::verify(&body).unwrap();
+        assert_eq!(manifest.signing_keys().len(), 1);
+        assert_eq!(manifest.signing_keys()[0].algorithm, "ES256");
+        assert_eq!(
+            manifest.signing_keys()[0].key_id.as_deref(),
+            Some("TEST:KEY")
+        );
+
+        let tampered = String::from_utf8(body).unwrap().replace("amd64", "arm64");
+        let res = ManifestSchema1Signed::verify(tampered.as_bytes());
+        assert!(matches!(res, Err(Error::Schema1Signature(_))));
+
+        let unsigned = payload.replace(r#""history": []"#, r#""history": [], "signatures": []"#);
+        let res = ManifestSchema1Signed::verify(unsigned.as_bytes());
+        assert!(matches!(res, Err(Error::Schema1Signature(_))));
+    }
+}
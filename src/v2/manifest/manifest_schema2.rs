@@ -1,5 +1,8 @@
+use super::Descriptor;
 use crate::errors::{Error, Result};
+use crate::v2::{response_error, Client, ContentDigest};
 use reqwest::Method;
+use std::collections::{BTreeMap, HashMap};
 
 /// Manifest version 2 schema 2.
 ///
@@ -7,11 +10,23 @@ use reqwest::Method;
 #[derive(Debug, Default, Deserialize, Serialize)]
 pub struct ManifestSchema2Spec {
     #[serde(rename = "schemaVersion")]
-    schema_version: u16,
-    #[serde(rename = "mediaType")]
-    media_type: String,
+    pub(super) schema_version: u16,
+    // Optional in OCI image manifests.
+    #[serde(rename = "mediaType", default)]
+    pub(super) media_type: String,
     config: Config,
     layers: Vec<S2Layer>,
+    // Only found in OCI image manifests.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub(super) annotations: HashMap<String, String>,
+    #[serde(
+        rename = "artifactType",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    artifact_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    subject: Option<Descriptor>,
 }
 
 /// Super-type for combining a ManifestSchema2 with a ConfigBlob.
@@ -29,14 +44,99 @@ pub struct Config {
     pub digest: String,
 }
 
-/// Partial representation of a container image (application/vnd.docker.container.image.v1+json).
+/// Configuration of a container image (`application/vnd.docker.container.image.v1+json`
+/// or `application/vnd.oci.image.config.v1+json`).
 ///
-/// The remaining fields according to [the image spec v1][image-spec-v1] are not covered.
+/// Fields are those shared by [the Docker image spec v1][image-spec-v1] and
+/// [the OCI image spec][oci-config]; timestamps are kept as RFC 3339 strings.
 ///
 /// [image-spec-v1]: https://github.com/moby/moby/blob/a30990b3c8d0d42280fa501287859e1d2393a951/image/spec/v1.md#image-json-description
-#[derive(Debug, Default, Deserialize, Serialize)]
+/// [oci-config]: https://github.com/opencontainers/image-spec/blob/main/config.md
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
 pub struct ConfigBlob {
-    architecture: String,
+    pub architecture: String,
+    #[serde(default)]
+    pub os: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub variant: Option<String>,
+    /// Creation time of the image.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    /// Execution parameters of containers run from the image.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub config: Option<Box<ContainerConfig>>,
+    #[serde(default)]
+    pub rootfs: RootFs,
+    /// History of each layer, base layer first.
+    #[serde(default)]
+    pub history: Vec<History>,
+}
+
+/// Execution parameters of a container image.
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ContainerConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    /// Ports to expose, as `port/protocol` keys mapped to empty objects.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exposed_ports: Option<BTreeMap<String, serde_json::Value>>,
+    /// Environment variables, in `NAME=value` form.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub env: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entrypoint: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cmd: Option<Vec<String>>,
+    /// Volume paths, as keys mapped to empty objects.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub volumes: Option<BTreeMap<String, serde_json::Value>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub working_dir: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub labels: Option<BTreeMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_signal: Option<String>,
+}
+
+impl ContainerConfig {
+    /// Environment variables, split into names and values.
+    pub fn env_vars(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.env
+            .iter()
+            .flatten()
+            .map(|var| var.split_once('=').unwrap_or((var, "")))
+    }
+}
+
+/// Layers of the root filesystem of an image.
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct RootFs {
+    /// Always `layers`.
+    #[serde(rename = "type")]
+    pub fs_type: String,
+    /// Digests of the uncompressed layers, base layer first.
+    #[serde(default)]
+    pub diff_ids: Vec<String>,
+}
+
+/// History entry of an image layer.
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct History {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created: Option<String>,
+    /// Command which created the layer.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_by: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    /// Whether the entry has no layer in `rootfs.diff_ids`.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub empty_layer: bool,
 }
 
 #[derive(Debug, Default, Deserialize, Serialize)]
@@ -46,15 +146,19 @@ struct S2Layer {
     size: u64,
     digest: String,
     urls: Option<Vec<String>>,
+    // Only found in OCI image manifests.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    annotations: HashMap<String, String>,
 }
 
 /// Manifest List.
 #[derive(Debug, Default, Deserialize, Serialize)]
 pub struct ManifestList {
     #[serde(rename = "schemaVersion")]
-    schema_version: u16,
-    #[serde(rename = "mediaType")]
-    media_type: String,
+    pub(super) schema_version: u16,
+    // Optional in OCI image indexes.
+    #[serde(rename = "mediaType", default)]
+    pub(super) media_type: String,
     pub manifests: Vec<ManifestObj>,
 }
 
@@ -62,22 +166,26 @@ pub struct ManifestList {
 #[derive(Debug, Default, Deserialize, Serialize)]
 pub struct ManifestObj {
     #[serde(rename = "mediaType")]
-    media_type: String,
-    size: u64,
+    pub(super) media_type: String,
+    pub(super) size: u64,
     pub digest: String,
+    // Missing on non-image entries of OCI image indexes, e.g. attestations.
+    #[serde(default)]
     pub platform: Platform,
 }
 
 /// Platform-related manifest entries.
-#[derive(Debug, Default, Deserialize, Serialize)]
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq, Eq)]
 pub struct Platform {
     pub architecture: String,
     pub os: String,
-    #[serde(rename = "os.version")]
+    #[serde(rename = "os.version", skip_serializing_if = "Option::is_none")]
     pub os_version: Option<String>,
-    #[serde(rename = "os.features")]
+    #[serde(rename = "os.features", skip_serializing_if = "Option::is_none")]
     pub os_features: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub variant: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub features: Option<Vec<String>>,
 }
 
@@ -87,40 +195,84 @@ impl ManifestSchema2Spec {
         &self.config
     }
 
+    /// Get the type of the artifact described by this (OCI) manifest, if set.
+    pub fn artifact_type(&self) -> Option<&str> {
+        self.artifact_type.as_deref()
+    }
+
+    /// Get the manifest this (OCI) manifest refers to, if any.
+    pub fn subject(&self) -> Option<&Descriptor> {
+        self.subject.as_ref()
+    }
+
+    /// List descriptors of all layers referenced by this manifest, base
+    /// layer first.
+    pub(super) fn layer_descriptors(&self) -> Vec<Descriptor> {
+        self.layers
+            .iter()
+            .map(|l| Descriptor {
+                media_type: l.media_type.clone(),
+                digest: l.digest.clone(),
+                size: l.size,
+                urls: l.urls.clone(),
+                annotations: l.annotations.clone(),
+                ..Descriptor::default()
+            })
+            .collect()
+    }
+
     /// Fetch the config blob for this manifest
     pub(crate) async fn fetch_config_blob(
         self,
         client: crate::v2::Client,
         repo: String,
     ) -> Result<ManifestSchema2> {
+        let config_blob = client
+            .fetch_config(&repo, &self.config.digest, self.config.size)
+            .await?;
+
+        Ok(ManifestSchema2 {
+            manifest_spec: self,
+            config_blob,
+        })
+    }
+}
+
+impl Client {
+    /// Fetch the image configuration `digest`, checking its digest and size.
+    pub(super) async fn fetch_config(
+        &self,
+        name: &str,
+        digest: &str,
+        size: u64,
+    ) -> Result<ConfigBlob> {
         let url = {
-            let ep = format!(
-                "{}/v2/{}/blobs/{}",
-                client.base_url.clone(),
-                repo,
-                self.config.digest
-            );
+            let ep = format!("{}/v2/{}/blobs/{}", self.base_url.clone(), name, digest);
             reqwest::Url::parse(&ep)?
         };
 
-        let r = client
-            .build_reqwest(Method::GET, url.clone())
-            .send()
+        let r = self
+            .send(|client| client.build_reqwest(Method::GET, url.clone()))
             .await?;
 
         let status = r.status();
         trace!("GET {:?}: {}", url, &status);
 
         if !status.is_success() {
-            return Err(Error::UnexpectedHttpStatus(status));
+            return Err(response_error(r, Error::UnexpectedHttpStatus(status)).await);
         }
 
-        let config_blob = r.json::<ConfigBlob>().await?;
-
-        Ok(ManifestSchema2 {
-            manifest_spec: self,
-            config_blob,
-        })
+        let mut content_digest = ContentDigest::try_new(digest)?;
+        let body = self.read_body(r, digest).await?;
+        if body.len() as u64 != size {
+            return Err(Error::SizeMismatch {
+                expected: size,
+                got: body.len() as u64,
+            });
+        }
+        content_digest.update(&body);
+        content_digest.verify()?;
+        Ok(serde_json::from_slice::<ConfigBlob>(&body)?)
     }
 }
 
@@ -136,10 +288,21 @@ impl ManifestSchema2 {
             .collect()
     }
 
+    /// List descriptors of all layers referenced by this manifest, base
+    /// layer first.
+    pub fn layer_descriptors(&self) -> Vec<Descriptor> {
+        self.manifest_spec.layer_descriptors()
+    }
+
     /// Get the architecture from the config
     pub fn architecture(&self) -> String {
         self.config_blob.architecture.to_owned()
     }
+
+    /// Get the image configuration.
+    pub fn image_config(&self) -> &ConfigBlob {
+        &self.config_blob
+    }
 }
 
 impl ManifestObj {
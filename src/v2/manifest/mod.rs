@@ -1,9 +1,12 @@
 use crate::errors::{Error, Result};
 use crate::mediatypes;
-use crate::v2::*;
+use crate::v2::auth::{delete_scope, push_scope};
+use crate::v2::{
+    response_error, sha256_digest, status_error, Client, ContentDigest, MediaTypes, Method,
+};
 use reqwest::{self, header, StatusCode, Url};
+use std::collections::BTreeMap;
 use std::iter::FromIterator;
-use std::str::FromStr;
 
 mod manifest_schema1;
 pub use self::manifest_schema1::*;
@@ -11,11 +14,46 @@ pub use self::manifest_schema1::*;
 mod manifest_schema2;
 pub use self::manifest_schema2::*;
 
+mod manifest_oci;
+pub use self::manifest_oci::*;
+
+mod image_index;
+pub use self::image_index::*;
+
+mod platform;
+
+mod history;
+
+mod size;
+pub use self::size::{ImageSize, ImageSizes, LayerSize};
+
+mod validate;
+pub use self::validate::{Violation, MAX_INDEX_ENTRIES, MAX_LAYERS};
+
+mod annotations;
+pub use self::annotations::*;
+
+mod custom;
+pub use self::custom::CustomManifest;
+pub(crate) use self::custom::MediaTypeHandlers;
+
+mod builder;
+pub use self::builder::{canonical_json, ManifestBuilder, ManifestBytes};
+
+#[cfg(feature = "render")]
+mod convert;
+#[cfg(feature = "render")]
+pub use self::convert::Schema2Conversion;
+
 impl Client {
     /// Fetch an image manifest.
     ///
     /// The name and reference parameters identify the image.
     /// The reference may be either a tag or digest.
+    ///
+    /// The configuration of schema 2 manifests is fetched along, and checked
+    /// against both the digest and size of its descriptor, failing with
+    /// `Error::SizeMismatch` for the latter (it was not checked before).
     pub async fn get_manifest(&self, name: &str, reference: &str) -> Result<Manifest> {
         self.get_manifest_and_ref(name, reference)
             .await
@@ -31,66 +69,361 @@ impl Client {
         name: &str,
         reference: &str,
     ) -> Result<(Manifest, Option<String>)> {
-        let url = self.build_url(name, reference)?;
+        match self.fetch_manifest(name, reference, None).await? {
+            ManifestUpdate::Modified {
+                manifest, digest, ..
+            } => Ok((manifest, digest)),
+            ManifestUpdate::NotModified => {
+                Err(Error::UnexpectedHttpStatus(StatusCode::NOT_MODIFIED))
+            }
+        }
+    }
+
+    /// Fetch an image manifest and return it with the media type it was
+    /// served as.
+    ///
+    /// The media type tells apart manifests parsed into the same variant,
+    /// e.g. a Docker manifest list from an OCI image index.
+    pub async fn get_manifest_and_media_type(
+        &self,
+        name: &str,
+        reference: &str,
+    ) -> Result<(Manifest, MediaTypes)> {
+        match self.fetch_manifest(name, reference, None).await? {
+            ManifestUpdate::Modified {
+                manifest,
+                media_type,
+                ..
+            } => Ok((manifest, media_type)),
+            ManifestUpdate::NotModified => {
+                Err(Error::UnexpectedHttpStatus(StatusCode::NOT_MODIFIED))
+            }
+        }
+    }
 
-        let accept_headers = build_accept_headers(&self.accepted_types);
+    /// Fetch an image manifest along with the bytes it was served as.
+    ///
+    /// The name and reference parameters identify the image.
+    /// The reference may be either a tag or digest.
+    pub async fn get_raw_manifest(&self, name: &str, reference: &str) -> Result<RawManifest> {
+        match self.fetch_manifest(name, reference, None).await? {
+            ManifestUpdate::Modified {
+                manifest,
+                media_type,
+                body,
+                ..
+            } => Ok(RawManifest {
+                manifest,
+                raw: ManifestBytes::from_raw(media_type, body),
+            }),
+            ManifestUpdate::NotModified => {
+                Err(Error::UnexpectedHttpStatus(StatusCode::NOT_MODIFIED))
+            }
+        }
+    }
 
-        let client_spare0 = self.clone();
+    /// Fetch an image manifest, unless it is the one previously seen.
+    ///
+    /// `etag` is the entity tag, or digest, of the manifest previously
+    /// fetched for this reference, as found in `ManifestUpdate::Modified`.
+    /// If the manifest has not changed since, the registry answers with
+    /// `304 Not Modified` and `ManifestUpdate::NotModified` is returned,
+    /// without downloading the manifest again. This makes polling tags cheap.
+    pub async fn get_manifest_if_changed(
+        &self,
+        name: &str,
+        reference: &str,
+        etag: &str,
+    ) -> Result<ManifestUpdate> {
+        self.fetch_manifest(name, reference, Some(etag)).await
+    }
 
-        let res = self
-            .build_reqwest(Method::GET, url.clone())
-            .headers(accept_headers)
-            .send()
-            .await?;
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(registry = %self.base_url, repository = name, reference = %reference, bytes = tracing::field::Empty))
+    )]
+    async fn fetch_manifest(
+        &self,
+        name: &str,
+        reference: &str,
+        etag: Option<&str>,
+    ) -> Result<ManifestUpdate> {
+        self.deadline()
+            .run(async move {
+                let url = self.build_url(name, reference)?;
+
+                let accept_headers = build_accept_headers(&self.accepted_types);
+                // Digests are sent as entity tags, which registries derive from them.
+                let if_none_match = etag.map(|etag| {
+                    if etag.starts_with('"') || etag.starts_with("W/") {
+                        etag.to_string()
+                    } else {
+                        format!("\"{}\"", etag)
+                    }
+                });
+
+                let client_spare0 = self.clone();
+
+                let res = self
+                    .send(|client| {
+                        let req = client
+                            .build_reqwest(Method::GET, url.clone())
+                            .headers(accept_headers.clone());
+                        match &if_none_match {
+                            Some(etag) => req.header(header::IF_NONE_MATCH, etag.as_str()),
+                            None => req,
+                        }
+                    })
+                    .await?;
+
+                let status = res.status();
+                trace!("GET '{}' status: {:?}", res.url(), status);
+
+                match status {
+                    StatusCode::OK => {}
+                    StatusCode::NOT_MODIFIED if etag.is_some() => {
+                        return Ok(ManifestUpdate::NotModified)
+                    }
+                    _ => return Err(response_error(res, Error::UnexpectedHttpStatus(status)).await),
+                }
 
-        let status = res.status();
-        trace!("GET '{}' status: {:?}", res.url(), status);
+                let headers = res.headers();
+                let content_digest = match headers.get("docker-content-digest") {
+                    Some(content_digest_value) => Some(content_digest_value.to_str()?.to_string()),
+                    None => {
+                        debug!("cannot find manifestref in headers");
+                        None
+                    }
+                };
+
+                let etag = match headers.get(header::ETAG) {
+                    Some(etag) => Some(etag.to_str()?.to_string()),
+                    None => content_digest.clone(),
+                };
+
+                let header_content_type = headers.get(header::CONTENT_TYPE);
+                let media_type = evaluate_media_type(header_content_type, &url)?;
+
+                trace!(
+                    "content-type: {:?}, media-type: {:?}",
+                    header_content_type,
+                    media_type
+                );
+
+                let body = self.read_body(res, reference).await?;
+                // Digests of schema 1 manifests are computed without their signatures.
+                if media_type != mediatypes::MediaTypes::ManifestV2S1Signed {
+                    verify_manifest(reference, content_digest.as_deref(), &body)?;
+                }
+                let manifest = match &media_type {
+                    mediatypes::MediaTypes::ManifestV2S1Signed => {
+                        self.parse_schema1(&body).map(Manifest::S1Signed)?
+                    }
+                    mediatypes::MediaTypes::OciImageManifest => {
+                        let m = serde_json::from_slice::<OciManifest>(&body)?;
+                        if m.is_image() {
+                            serde_json::from_slice::<ManifestSchema2Spec>(&body)?
+                                .fetch_config_blob(client_spare0, name.to_string())
+                                .await
+                                .map(Manifest::S2)?
+                        } else {
+                            Manifest::Artifact(Box::new(m))
+                        }
+                    }
+                    mediatypes::MediaTypes::OciArtifactManifest => {
+                        serde_json::from_slice::<OciManifest>(&body)
+                            .map(|m| Manifest::Artifact(Box::new(m)))?
+                    }
+                    mediatypes::MediaTypes::ManifestV2S2 => {
+                        serde_json::from_slice::<ManifestSchema2Spec>(&body)?
+                            .fetch_config_blob(client_spare0, name.to_string())
+                            .await
+                            .map(Manifest::S2)?
+                    }
+                    mediatypes::MediaTypes::ManifestList
+                    | mediatypes::MediaTypes::OciImageIndex => {
+                        serde_json::from_slice::<ManifestList>(&body).map(Manifest::ML)?
+                    }
+                    unsupported => match self.media_type_handlers.parse(unsupported, &body)? {
+                        Some(manifest) => manifest,
+                        None => return Err(Error::UnsupportedMediaType(unsupported.clone())),
+                    },
+                };
+                Ok(ManifestUpdate::Modified {
+                    manifest,
+                    digest: content_digest,
+                    etag,
+                    media_type,
+                    body,
+                })
+            })
+            .await
+    }
 
-        match status {
-            StatusCode::OK => {}
-            _ => return Err(Error::UnexpectedHttpStatus(status)),
+    /// Parse a schema 1 manifest, verifying its signatures.
+    #[cfg(feature = "schema1-signatures")]
+    fn parse_schema1(&self, body: &[u8]) -> Result<ManifestSchema1Signed> {
+        match ManifestSchema1Signed::verify(body) {
+            Err(Error::Schema1Signature(e)) if !self.require_schema1_signatures => {
+                debug!("ignoring invalid schema 1 manifest signature: {}", e);
+                serde_json::from_slice(body).map_err(Into::into)
+            }
+            res => res,
         }
+    }
 
-        let headers = res.headers();
-        let content_digest = match headers.get("docker-content-digest") {
-            Some(content_digest_value) => Some(content_digest_value.to_str()?.to_string()),
-            None => {
-                debug!("cannot find manifestref in headers");
-                None
-            }
-        };
+    #[cfg(not(feature = "schema1-signatures"))]
+    fn parse_schema1(&self, body: &[u8]) -> Result<ManifestSchema1Signed> {
+        serde_json::from_slice(body).map_err(Into::into)
+    }
 
-        let header_content_type = headers.get(header::CONTENT_TYPE);
-        let media_type = evaluate_media_type(header_content_type, &url)?;
+    /// Upload an image manifest.
+    ///
+    /// The name and reference parameters identify the image; the reference
+    /// may be either a tag or digest. `body` must be the serialized manifest,
+    /// of type `media_type`. If needed, the client is re-authenticated for
+    /// the `push` action on `name`.
+    ///
+    /// Returns the digest of the manifest, as computed by the registry.
+    pub async fn put_manifest(
+        &self,
+        name: &str,
+        reference: &str,
+        media_type: &str,
+        body: Vec<u8>,
+    ) -> Result<String> {
+        let (digest, _) = self
+            .push_manifest(name, reference, media_type, body)
+            .await?;
+        Ok(digest)
+    }
 
-        trace!(
-            "content-type: {:?}, media-type: {:?}",
-            header_content_type,
-            media_type
-        );
+    /// Upload a serialized manifest, e.g. built by `ManifestBuilder`.
+    ///
+    /// Unlike `put_manifest`, this fails with `Error::DigestMismatch` if the
+    /// registry reports another digest than the one of `manifest`.
+    pub async fn put_manifest_bytes(
+        &self,
+        name: &str,
+        reference: &str,
+        manifest: &ManifestBytes,
+    ) -> Result<String> {
+        let (digest, _) = self
+            .push_manifest(
+                name,
+                reference,
+                &manifest.media_type.to_string(),
+                manifest.body.clone(),
+            )
+            .await?;
+        check_pushed_digest(manifest, digest)
+    }
 
-        match media_type {
-            mediatypes::MediaTypes::ManifestV2S1Signed => Ok((
-                res.json::<ManifestSchema1Signed>()
-                    .await
-                    .map(Manifest::S1Signed)?,
-                content_digest,
-            )),
-            mediatypes::MediaTypes::ManifestV2S2 => {
-                let m = res.json::<ManifestSchema2Spec>().await?;
-                Ok((
-                    m.fetch_config_blob(client_spare0, name.to_string())
-                        .await
-                        .map(Manifest::S2)?,
-                    content_digest,
-                ))
-            }
-            mediatypes::MediaTypes::ManifestList => Ok((
-                res.json::<ManifestList>().await.map(Manifest::ML)?,
-                content_digest,
-            )),
-            unsupported => Err(Error::UnsupportedMediaType(unsupported)),
-        }
+    /// Upload a manifest as `put_manifest` does, also returning the headers
+    /// of the response.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(registry = %self.base_url, repository = name, reference = %reference, bytes = body.len()))
+    )]
+    pub(crate) async fn push_manifest(
+        &self,
+        name: &str,
+        reference: &str,
+        media_type: &str,
+        body: Vec<u8>,
+    ) -> Result<(String, header::HeaderMap)> {
+        self.deadline()
+            .run(async move {
+                let url = self.build_url(name, reference)?;
+                let body = bytes::Bytes::from(body);
+
+                let res = self
+                    .send_with_scope(&[&push_scope(name)], |client| {
+                        client
+                            .build_reqwest(Method::PUT, url.clone())
+                            .header(header::CONTENT_TYPE, media_type)
+                            .body(body.clone())
+                    })
+                    .await?;
+
+                let status = res.status();
+                trace!("PUT '{}' status: {:?}", res.url(), status);
+
+                match status {
+                    StatusCode::CREATED | StatusCode::OK => {}
+                    _ => return Err(status_error(res).await),
+                }
+
+                let digest = match res.headers().get("docker-content-digest") {
+                    Some(content_digest_value) => content_digest_value.to_str()?.to_string(),
+                    None => {
+                        debug!("cannot find manifestref in headers, computing it locally");
+                        sha256_digest(&body)
+                    }
+                };
+                Ok((digest, res.headers().clone()))
+            })
+            .await
+    }
+
+    /// Delete a tag, leaving the manifest it points to in place.
+    ///
+    /// This requires a registry implementing tag deletion, as specified by OCI
+    /// distribution 1.1; registries which only allow deleting manifests by
+    /// digest make this fail with `Error::TagDeletionUnsupported`.
+    pub async fn delete_tag(&self, name: &str, tag: &str) -> Result<()> {
+        self.deadline()
+            .run(async move {
+                let url = self.build_url(name, tag)?;
+
+                let res = self
+                    .send_with_scope(&[&push_scope(name), &delete_scope(name)], |client| {
+                        client.build_reqwest(Method::DELETE, url.clone())
+                    })
+                    .await?;
+
+                let status = res.status();
+                trace!("DELETE '{}' status: {:?}", res.url(), status);
+
+                match status {
+                    StatusCode::ACCEPTED | StatusCode::OK => Ok(()),
+                    StatusCode::BAD_REQUEST | StatusCode::METHOD_NOT_ALLOWED => {
+                        Err(Error::TagDeletionUnsupported(tag.to_string()))
+                    }
+                    _ => Err(status_error(res).await),
+                }
+            })
+            .await
+    }
+
+    /// Delete a manifest by digest.
+    ///
+    /// This fails with `Error::ManifestNotFound` if the manifest does not
+    /// exist, and with `Error::DeletionUnsupported` if the registry does not
+    /// allow deletions.
+    pub async fn delete_manifest(&self, name: &str, digest: &str) -> Result<()> {
+        self.deadline()
+            .run(async move {
+                ContentDigest::try_new(digest)?;
+                let url = self.build_url(name, digest)?;
+
+                let res = self
+                    .send_with_scope(&[&push_scope(name), &delete_scope(name)], |client| {
+                        client.build_reqwest(Method::DELETE, url.clone())
+                    })
+                    .await?;
+
+                let status = res.status();
+                trace!("DELETE '{}' status: {:?}", res.url(), status);
+
+                match status {
+                    StatusCode::ACCEPTED | StatusCode::OK => Ok(()),
+                    StatusCode::NOT_FOUND => Err(Error::ManifestNotFound(digest.to_string())),
+                    StatusCode::METHOD_NOT_ALLOWED => Err(Error::DeletionUnsupported),
+                    _ => Err(status_error(res).await),
+                }
+            })
+            .await
     }
 
     fn build_url(&self, name: &str, reference: &str) -> Result<Url> {
@@ -105,33 +438,97 @@ impl Client {
 
     /// Fetch content digest for a particular tag.
     pub async fn get_manifestref(&self, name: &str, reference: &str) -> Result<Option<String>> {
-        let url = self.build_url(name, reference)?;
-
-        let accept_headers = build_accept_headers(&self.accepted_types);
-
-        let res = self
-            .build_reqwest(Method::HEAD, url)
-            .headers(accept_headers)
-            .send()
-            .await?;
+        self.deadline()
+            .run(async move {
+                let url = self.build_url(name, reference)?;
+
+                let accept_headers = build_accept_headers(&self.accepted_types);
+
+                let res = self
+                    .send(|client| {
+                        client
+                            .build_reqwest(Method::HEAD, url.clone())
+                            .headers(accept_headers.clone())
+                    })
+                    .await?;
+
+                let status = res.status();
+                trace!("HEAD '{}' status: {:?}", res.url(), status);
+
+                match status {
+                    StatusCode::OK => {}
+                    _ => return Err(Error::UnexpectedHttpStatus(status)),
+                }
 
-        let status = res.status();
-        trace!("HEAD '{}' status: {:?}", res.url(), status);
+                let headers = res.headers();
+                let content_digest = match headers.get("docker-content-digest") {
+                    Some(content_digest_value) => Some(content_digest_value.to_str()?.to_string()),
+                    None => {
+                        debug!("cannot find manifestref in headers");
+                        None
+                    }
+                };
+                Ok(content_digest)
+            })
+            .await
+    }
 
-        match status {
-            StatusCode::OK => {}
-            _ => return Err(Error::UnexpectedHttpStatus(status)),
-        }
+    /// Probe an image manifest without fetching it.
+    ///
+    /// The name and reference parameters identify the image.
+    /// The reference may be either a tag or digest. This is a cheap way of
+    /// detecting whether a tag has changed, by comparing digests.
+    pub async fn head_manifest(&self, name: &str, reference: &str) -> Result<ManifestHead> {
+        self.deadline()
+            .run(async move {
+                let url = self.build_url(name, reference)?;
+
+                let accept_headers = build_accept_headers(&self.accepted_types);
+
+                let res = self
+                    .send(|client| {
+                        client
+                            .build_reqwest(Method::HEAD, url.clone())
+                            .headers(accept_headers.clone())
+                    })
+                    .await?;
+
+                let status = res.status();
+                trace!("HEAD '{}' status: {:?}", res.url(), status);
+
+                match status {
+                    StatusCode::OK => {}
+                    StatusCode::NOT_FOUND => {
+                        return Err(Error::ManifestNotFound(reference.to_string()))
+                    }
+                    _ => return Err(Error::UnexpectedHttpStatus(status)),
+                }
 
-        let headers = res.headers();
-        let content_digest = match headers.get("docker-content-digest") {
-            Some(content_digest_value) => Some(content_digest_value.to_str()?.to_string()),
-            None => {
-                debug!("cannot find manifestref in headers");
-                None
-            }
-        };
-        Ok(content_digest)
+                let headers = res.headers();
+                let digest = match headers.get("docker-content-digest") {
+                    Some(content_digest_value) => Some(content_digest_value.to_str()?.to_string()),
+                    None => {
+                        debug!("cannot find manifestref in headers");
+                        None
+                    }
+                };
+                // `Response::content_length` reports the (empty) body size for HEAD requests.
+                let size = headers
+                    .get(header::CONTENT_LENGTH)
+                    .ok_or(Error::MissingHeader("Content-Length"))?
+                    .to_str()?;
+                let size = size
+                    .parse()
+                    .map_err(|_| Error::InvalidHeader("Content-Length", size.to_string()))?;
+                let media_type = evaluate_media_type(headers.get(header::CONTENT_TYPE), res.url())?;
+
+                Ok(ManifestHead {
+                    digest,
+                    size,
+                    media_type,
+                })
+            })
+            .await
     }
 
     /// Check if an image manifest exists.
@@ -144,52 +541,120 @@ impl Client {
         reference: &str,
         mediatypes: Option<&[&str]>,
     ) -> Result<Option<mediatypes::MediaTypes>> {
-        let url = self.build_url(name, reference)?;
-        let accept_types = match mediatypes {
-            None => {
-                let m = mediatypes::MediaTypes::ManifestV2S2.to_mime();
-                vec![m]
-            }
-            Some(v) => to_mimes(v),
-        };
-
-        let mut accept_headers = header::HeaderMap::with_capacity(accept_types.len());
-        for accept_type in accept_types {
-            let header_value = header::HeaderValue::from_str(accept_type.as_ref())
-                .expect("mime type is always valid header value");
-            accept_headers.insert(header::ACCEPT, header_value);
-        }
-
-        trace!("HEAD {:?}", url);
+        self.deadline()
+            .run(async move {
+                let url = self.build_url(name, reference)?;
+                let accept_types = match mediatypes {
+                    None => {
+                        let m = mediatypes::MediaTypes::ManifestV2S2.to_mime();
+                        vec![m]
+                    }
+                    Some(v) => to_mimes(v),
+                };
+
+                let mut accept_headers = header::HeaderMap::with_capacity(accept_types.len());
+                for accept_type in accept_types {
+                    let header_value = header::HeaderValue::from_str(accept_type.as_ref())
+                        .expect("mime type is always valid header value");
+                    accept_headers.insert(header::ACCEPT, header_value);
+                }
 
-        let r = self
-            .build_reqwest(Method::HEAD, url.clone())
-            .headers(accept_headers)
-            .send()
+                trace!("HEAD {:?}", url);
+
+                let r = self
+                    .send(|client| {
+                        client
+                            .build_reqwest(Method::HEAD, url.clone())
+                            .headers(accept_headers.clone())
+                    })
+                    .await?;
+
+                let status = r.status();
+
+                trace!(
+                    "Manifest check status '{:?}', headers '{:?}",
+                    r.status(),
+                    r.headers(),
+                );
+
+                match status {
+                    StatusCode::MOVED_PERMANENTLY
+                    | StatusCode::TEMPORARY_REDIRECT
+                    | StatusCode::FOUND
+                    | StatusCode::OK => {
+                        let media_type =
+                            evaluate_media_type(r.headers().get(header::CONTENT_TYPE), r.url())?;
+                        trace!("Manifest media-type: {:?}", media_type);
+                        Ok(Some(media_type))
+                    }
+                    StatusCode::NOT_FOUND => Ok(None),
+                    _ => Err(Error::UnexpectedHttpStatus(status)),
+                }
+            })
             .await
-            .map_err(Error::from)?;
-
-        let status = r.status();
+    }
 
-        trace!(
-            "Manifest check status '{:?}', headers '{:?}",
-            r.status(),
-            r.headers(),
-        );
+    /// Fetch the image configuration referenced by `manifest`, from the
+    /// repository `name`.
+    ///
+    /// The configuration is checked against the digest and size of its
+    /// descriptor. Schema 1 manifests and image indexes have none. Schema 2
+    /// manifests already carry theirs, which is returned without fetching
+    /// it again.
+    pub async fn get_image_config(&self, name: &str, manifest: &Manifest) -> Result<ConfigBlob> {
+        self.deadline()
+            .run(async move {
+                let (digest, size) = match manifest {
+                    Manifest::S2(m) => return Ok(m.config_blob.clone()),
+                    Manifest::Artifact(m) => match &m.config {
+                        Some(config) => (&config.digest, config.size),
+                        None => return Err(ManifestError::MissingConfig.into()),
+                    },
+                    Manifest::S1Signed(_) | Manifest::ML(_) | Manifest::Custom(_) => {
+                        return Err(ManifestError::MissingConfig.into())
+                    }
+                };
+                self.fetch_config(name, digest, size).await
+            })
+            .await
+    }
 
-        match status {
-            StatusCode::MOVED_PERMANENTLY
-            | StatusCode::TEMPORARY_REDIRECT
-            | StatusCode::FOUND
-            | StatusCode::OK => {
-                let media_type =
-                    evaluate_media_type(r.headers().get(header::CONTENT_TYPE), r.url())?;
-                trace!("Manifest media-type: {:?}", media_type);
-                Ok(Some(media_type))
-            }
-            StatusCode::NOT_FOUND => Ok(None),
-            _ => Err(Error::UnexpectedHttpStatus(status)),
-        }
+    /// Fetch the labels of an image.
+    ///
+    /// If the reference resolves to an image index, the image for `platform`
+    /// is selected as by `ImageIndex::select`, otherwise `platform` is
+    /// ignored. Labels of schema 1 manifests are read from their history.
+    pub async fn get_image_labels(
+        &self,
+        name: &str,
+        reference: &str,
+        platform: &Platform,
+    ) -> Result<BTreeMap<String, String>> {
+        self.deadline()
+            .run(async move {
+                let raw = self.get_raw_manifest(name, reference).await?;
+                let manifest = match raw.manifest {
+                    Manifest::ML(_) => {
+                        let index = serde_json::from_slice::<ImageIndex>(&raw.raw.body)?;
+                        let entry = index
+                            .select(platform)
+                            .ok_or_else(|| Error::PlatformNotFound(platform.to_string()))?;
+                        self.get_index_entry(name, &entry.digest, Some(&entry.media_type))
+                            .await?
+                            .0
+                    }
+                    manifest => manifest,
+                };
+                let config = match manifest {
+                    Manifest::S1Signed(m) => {
+                        return Ok(m.get_labels(0).into_iter().flatten().collect())
+                    }
+                    Manifest::S2(m) => m.config_blob,
+                    manifest => self.get_image_config(name, &manifest).await?,
+                };
+                Ok(config.config.and_then(|c| c.labels).unwrap_or_default())
+            })
+            .await
     }
 }
 
@@ -197,10 +662,10 @@ fn to_mimes(v: &[&str]) -> Vec<mime::Mime> {
     let res = v
         .iter()
         .filter_map(|x| {
-            let mtype = mediatypes::MediaTypes::from_str(x);
+            let mtype = mediatypes::MediaTypes::parse(x);
             match mtype {
-                Ok(m) => Some(m.to_mime()),
-                _ => None,
+                mediatypes::MediaTypes::Other(_) => None,
+                m => Some(m.to_mime()),
             }
         })
         .collect();
@@ -219,34 +684,51 @@ fn evaluate_media_type(
     let is_pulp_based = url.path().starts_with("/pulp/docker/v2");
 
     match (header_content_type, is_pulp_based) {
-        (Some(header_value), false) => {
-            mediatypes::MediaTypes::from_str(header_value).map_err(Into::into)
-        }
+        (Some(header_value), false) => Ok(mediatypes::MediaTypes::parse(header_value)),
         (None, false) => Err(Error::MediaTypeSniff),
         (Some(header_value), true) => {
             // TODO: remove this workaround once Satellite returns a proper content-type here
             match header_value {
                 "application/x-troff-man" => {
                     trace!("Applying workaround for pulp-based registries, e.g. Satellite");
-                    mediatypes::MediaTypes::from_str(
-                        "application/vnd.docker.distribution.manifest.v1+prettyjws",
-                    )
-                    .map_err(Into::into)
+                    Ok(mediatypes::MediaTypes::ManifestV2S1Signed)
                 }
                 _ => {
                     debug!("Received content-type '{}' from pulp-based registry. Feeling lucky and trying to parse it...", header_value);
-                    mediatypes::MediaTypes::from_str(header_value).map_err(Into::into)
+                    Ok(mediatypes::MediaTypes::parse(header_value))
                 }
             }
         }
         (None, true) => {
             trace!("Applying workaround for pulp-based registries, e.g. Satellite");
-            mediatypes::MediaTypes::from_str(
-                "application/vnd.docker.distribution.manifest.v1+prettyjws",
-            )
-            .map_err(Into::into)
+            Ok(mediatypes::MediaTypes::ManifestV2S1Signed)
+        }
+    }
+}
+
+/// Check the digest reported by the registry for a pushed manifest.
+pub(crate) fn check_pushed_digest(manifest: &ManifestBytes, digest: String) -> Result<String> {
+    if digest != manifest.digest {
+        return Err(Error::DigestMismatch {
+            expected: manifest.digest.clone(),
+            got: digest,
+        });
+    }
+    Ok(digest)
+}
+
+/// Verify a manifest body against the digest it was requested by, if
+/// `reference` is one, and against the digest announced by the registry.
+///
+/// Digests of unsupported algorithms are not checked.
+fn verify_manifest(reference: &str, content_digest: Option<&str>, body: &[u8]) -> Result<()> {
+    for digest in std::iter::once(reference).chain(content_digest) {
+        if let Ok(mut digest) = ContentDigest::try_new(digest) {
+            digest.update(body);
+            digest.verify()?;
         }
     }
+    Ok(())
 }
 
 fn build_accept_headers(accepted_types: &[(MediaTypes, Option<f64>)]) -> header::HeaderMap {
@@ -273,12 +755,70 @@ fn build_accept_headers(accepted_types: &[(MediaTypes, Option<f64>)]) -> header:
     )])
 }
 
+/// Metadata of a manifest, as returned by `Client::head_manifest`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestHead {
+    /// Content digest, if reported by the registry.
+    pub digest: Option<String>,
+    /// Size of the manifest, in bytes.
+    pub size: u64,
+    /// Media type of the manifest.
+    pub media_type: mediatypes::MediaTypes,
+}
+
+/// Outcome of `Client::get_manifest_if_changed`.
+// Short-lived, so not worth boxing the manifest.
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug)]
+pub enum ManifestUpdate {
+    /// The manifest has not changed.
+    NotModified,
+    /// The manifest has changed, or was never seen.
+    ///
+    /// More fields may be added in the future.
+    #[non_exhaustive]
+    Modified {
+        manifest: Manifest,
+        /// Content digest, if reported by the registry.
+        digest: Option<String>,
+        /// Entity tag to pass on the next call, either the `ETag` reported
+        /// by the registry or the digest.
+        etag: Option<String>,
+        /// Media type the manifest was served as.
+        media_type: MediaTypes,
+        /// Bytes of the manifest, as served.
+        body: Vec<u8>,
+    },
+}
+
+/// Manifest along with the exact bytes it was served as, as returned by
+/// `Client::get_raw_manifest`.
+///
+/// Digests are defined over these bytes, which re-serializing `manifest`
+/// does not reproduce: manifests are copied or re-tagged by pushing `raw`.
+#[derive(Debug)]
+pub struct RawManifest {
+    pub manifest: Manifest,
+    /// Bytes, media type and digest of the manifest.
+    ///
+    /// Registries compute the digest of signed schema 1 manifests without
+    /// their signatures, unlike this one.
+    pub raw: ManifestBytes,
+}
+
 /// Umbrella type for common actions on the different manifest schema types
 #[derive(Debug)]
+// Boxing the (large) image manifest variants would break code destructuring
+// them, and manifests are seldom held in bulk, so the size is not an issue.
+#[allow(clippy::large_enum_variant)]
 pub enum Manifest {
     S1Signed(manifest_schema1::ManifestSchema1Signed),
     S2(manifest_schema2::ManifestSchema2),
     ML(manifest_schema2::ManifestList),
+    /// OCI manifest of a non-image artifact (e.g. an SBOM or a signature).
+    Artifact(Box<manifest_oci::OciManifest>),
+    /// Manifest of a media type not supported by this crate.
+    Custom(Box<CustomManifest>),
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -291,6 +831,16 @@ pub enum ManifestError {
     LayerDigestsUnsupported(String),
     #[error("manifest {0} does not support the 'architecture' method")]
     ArchitectureNotSupported(String),
+    #[error("no platform for manifest {0} in manifest list")]
+    MissingPlatform(String),
+    #[error("no blob for layer {0}")]
+    MissingLayer(String),
+    #[error("no history in schema 1 manifest")]
+    NoHistory,
+    #[error("no configuration in manifest")]
+    MissingConfig,
+    #[error("no subject in manifest")]
+    MissingSubject,
 }
 
 impl Manifest {
@@ -332,21 +882,49 @@ impl Manifest {
                 Ok(m.get_layers())
             }
             (Manifest::ML(m), _, _) => Ok(m.get_digests()),
+            (Manifest::Artifact(m), _, None) => Ok(m.get_layers()),
             _ => Err(ManifestError::LayerDigestsUnsupported(format!("{:?}", self)).into()),
         }
     }
 
+    /// The manifest this one refers to, e.g. the image of a signature.
+    ///
+    /// Only OCI manifests have a subject; they are listed by the Referrers
+    /// API of their subject, see `Client::get_referrers`.
+    pub fn subject(&self) -> Option<&Descriptor> {
+        match self {
+            Manifest::S2(m) => m.manifest_spec.subject(),
+            Manifest::Artifact(m) => m.subject.as_ref(),
+            Manifest::S1Signed(_) | Manifest::ML(_) | Manifest::Custom(_) => None,
+        }
+    }
+
+    /// The type of the artifact described by the manifest, if any.
+    ///
+    /// See `OciManifest::artifact_type`.
+    pub fn artifact_type(&self) -> Option<&str> {
+        match self {
+            Manifest::S2(m) => m.manifest_spec.artifact_type(),
+            Manifest::Artifact(m) => m.artifact_type(),
+            Manifest::S1Signed(_) | Manifest::ML(_) | Manifest::Custom(_) => None,
+        }
+    }
+
     /// The architectures of the image the manifest points to, if available.
     pub fn architectures(&self) -> Result<Vec<String>> {
         match self {
             Manifest::S1Signed(m) => Ok([m.architecture.clone()].to_vec()),
             Manifest::S2(m) => Ok([m.architecture()].to_vec()),
             Manifest::ML(m) => Ok(m.architectures()),
+            Manifest::Artifact(_) | Manifest::Custom(_) => {
+                Err(ManifestError::ArchitectureNotSupported(format!("{:?}", self)).into())
+            }
         }
     }
 }
 
 #[cfg(test)]
+#[allow(clippy::needless_borrow)]
 mod tests {
     use super::*;
     use test_case::test_case;
@@ -392,4 +970,32 @@ mod tests {
             .unwrap()
             .to_string()
     }
+
+    #[test]
+    fn custom_media_types_are_least_preferred() {
+        let client = Client::configure()
+            .registry("https://example.com")
+            .accepted_types(Some(vec![
+                (MediaTypes::ManifestV2S2, None),
+                (MediaTypes::ManifestList, Some(0.5)),
+            ]))
+            .media_type_handler("application/vnd.example.b+json", |_| Ok(()))
+            .media_type_handler("application/vnd.example.a+json", |_| Ok(()))
+            .build()
+            .unwrap();
+        let header_map = build_accept_headers(&client.accepted_types);
+        assert_eq!(
+            header_map.get(header::ACCEPT).unwrap(),
+            "application/vnd.docker.distribution.manifest.v2+json,application/vnd.docker.distribution.manifest.list.v2+json; q=0.5,application/vnd.example.a+json; q=0.5,application/vnd.example.b+json; q=0.5"
+        );
+    }
+
+    #[test]
+    fn unknown_media_types_are_not_accepted() {
+        let mimes = to_mimes(&[
+            "application/vnd.oci.image.manifest.v1+json",
+            "application/vnd.example.unknown+json",
+        ]);
+        assert_eq!(mimes.len(), 1);
+    }
 }
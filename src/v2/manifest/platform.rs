@@ -0,0 +1,194 @@
+use super::Platform;
+use std::fmt;
+
+impl Platform {
+    /// Initialize a `Platform` for an OS and architecture.
+    pub fn new(os: &str, architecture: &str) -> Self {
+        Platform {
+            os: os.to_string(),
+            architecture: architecture.to_string(),
+            ..Platform::default()
+        }
+    }
+
+    /// Set the variant of the architecture (e.g. `v7` for `arm`).
+    pub fn with_variant(mut self, variant: &str) -> Self {
+        self.variant = Some(variant.to_string());
+        self
+    }
+
+    /// Set the version of the OS (e.g. `10.0.17763.1879` for Windows).
+    pub fn with_os_version(mut self, os_version: &str) -> Self {
+        self.os_version = Some(os_version.to_string());
+        self
+    }
+
+    /// Whether images of this platform run on `host`.
+    ///
+    /// Architectures are compared after normalization (e.g. `aarch64` is
+    /// `arm64`), and variants are backward compatible: an `arm/v7` host runs
+    /// `arm/v6` images, and `arm64` is `arm64/v8`. Unset variants, OS versions
+    /// and OS features of `host` match any. Windows images require the same
+    /// build as the host, regardless of its revision, and images requiring
+    /// OS features only run on hosts with them.
+    pub fn matches(&self, host: &Platform) -> bool {
+        let (arch, variant) = normalize(&self.architecture, self.variant.as_deref());
+        let (host_arch, host_variant) = normalize(&host.architecture, host.variant.as_deref());
+        self.os == host.os
+            && arch == host_arch
+            && variant_runs_on(variant.as_deref(), host_variant.as_deref())
+            && self.os_matches(host)
+    }
+
+    /// Like `matches`, also accepting images of the 32-bit architectures
+    /// that hosts usually run: `386` on `amd64` and `arm` on `arm64`.
+    pub fn matches_compat(&self, host: &Platform) -> bool {
+        if self.matches(host) {
+            return true;
+        }
+        let (arch, variant) = normalize(&self.architecture, self.variant.as_deref());
+        let (host_arch, host_variant) = normalize(&host.architecture, host.variant.as_deref());
+        let compatible = match (arch.as_str(), host_arch.as_str()) {
+            ("386", "amd64") => true,
+            ("arm", "arm64") => variant_runs_on(variant.as_deref(), host_variant.as_deref()),
+            _ => false,
+        };
+        compatible && self.os == host.os && self.os_matches(host)
+    }
+
+    /// Whether the OS version and features of this platform suit `host`.
+    fn os_matches(&self, host: &Platform) -> bool {
+        let version = match (&self.os_version, &host.os_version) {
+            (Some(version), Some(host_version)) if self.os == "windows" => {
+                // Builds are compatible across revisions, the 4th component.
+                version
+                    .split('.')
+                    .take(3)
+                    .eq(host_version.split('.').take(3))
+            }
+            (Some(version), Some(host_version)) => version == host_version,
+            _ => true,
+        };
+        let features = match (&self.os_features, &host.os_features) {
+            (Some(features), Some(host_features)) => {
+                features.iter().all(|f| host_features.contains(f))
+            }
+            _ => true,
+        };
+        version && features
+    }
+
+    /// Rank of this platform among the ones matching `host`, the higher the
+    /// closer.
+    pub(crate) fn match_rank(&self, host: &Platform) -> (bool, u32) {
+        let (arch, variant) = normalize(&self.architecture, self.variant.as_deref());
+        let (host_arch, host_variant) = normalize(&host.architecture, host.variant.as_deref());
+        // Without a variant to aim for, the first image for the host is fine.
+        let level = match host_variant {
+            Some(_) => variant.as_deref().and_then(variant_level).unwrap_or(0),
+            None => 0,
+        };
+        (arch == host_arch, level)
+    }
+}
+
+/// Formats as `os/architecture[/variant]`, as in `docker --platform`.
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.os, self.architecture)?;
+        match &self.variant {
+            Some(variant) => write!(f, "/{}", variant),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Normalize an architecture and its variant, as done by containerd.
+fn normalize(architecture: &str, variant: Option<&str>) -> (String, Option<String>) {
+    let variant = variant.filter(|v| !v.is_empty()).map(str::to_lowercase);
+    let variant = match (architecture, variant) {
+        ("i386" | "i686", _) => return ("386".into(), None),
+        ("x86_64" | "x86-64" | "amd64", Some(v)) if v == "v1" => return ("amd64".into(), None),
+        ("x86_64" | "x86-64", variant) => return ("amd64".into(), variant),
+        ("aarch64" | "arm64", None) => return ("arm64".into(), Some("v8".into())),
+        ("aarch64" | "arm64", Some(v)) => return ("arm64".into(), Some(normalize_level(v))),
+        ("armhf", _) => return ("arm".into(), Some("v7".into())),
+        ("armel", _) => return ("arm".into(), Some("v6".into())),
+        ("arm", Some(v)) => Some(normalize_level(v)),
+        (_, variant) => variant,
+    };
+    (architecture.to_string(), variant)
+}
+
+/// Spell numeric variants as `vN`.
+fn normalize_level(variant: String) -> String {
+    match variant.parse::<u32>() {
+        Ok(level) => format!("v{}", level),
+        Err(_) => variant,
+    }
+}
+
+fn variant_level(variant: &str) -> Option<u32> {
+    variant.strip_prefix('v')?.parse().ok()
+}
+
+/// Whether an image of architecture variant `variant` runs on a host of
+/// variant `host`.
+fn variant_runs_on(variant: Option<&str>, host: Option<&str>) -> bool {
+    match (variant, host) {
+        (None, _) | (_, None) => true,
+        (Some(variant), Some(host)) => match (variant_level(variant), variant_level(host)) {
+            (Some(level), Some(host_level)) => level <= host_level,
+            _ => variant == host,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn platform_variants_match() {
+        let arm64 = Platform::new("linux", "arm64");
+        assert!(arm64.matches(&Platform::new("linux", "arm64").with_variant("v8")));
+        assert!(Platform::new("linux", "aarch64")
+            .with_variant("8")
+            .matches(&arm64));
+
+        let armv7 = Platform::new("linux", "arm").with_variant("v7");
+        assert!(Platform::new("linux", "arm")
+            .with_variant("v6")
+            .matches(&armv7));
+        assert!(!Platform::new("linux", "arm")
+            .with_variant("v8")
+            .matches(&armv7));
+        assert!(Platform::new("linux", "armhf").matches(&armv7));
+        assert!(!armv7.matches(&Platform::new("windows", "arm").with_variant("v7")));
+
+        let i386 = Platform::new("linux", "i386");
+        let amd64 = Platform::new("linux", "x86_64");
+        assert!(!i386.matches(&amd64));
+        assert!(i386.matches_compat(&amd64));
+        assert!(armv7.matches_compat(&arm64));
+        assert!(!amd64.matches_compat(&i386));
+    }
+
+    #[test]
+    fn platform_os_matches() {
+        let host = Platform::new("windows", "amd64").with_os_version("10.0.17763.1879");
+        let image = |version: &str| Platform::new("windows", "amd64").with_os_version(version);
+        assert!(image("10.0.17763.737").matches(&host));
+        assert!(!image("10.0.20348.587").matches(&host));
+        assert!(Platform::new("windows", "amd64").matches(&host));
+
+        let mut image = Platform::new("windows", "amd64");
+        image.os_features = Some(vec!["win32k".to_string()]);
+        assert!(image.matches(&host));
+        let mut host = host;
+        host.os_features = Some(Vec::new());
+        assert!(!image.matches(&host));
+        host.os_features = Some(vec!["win32k".to_string()]);
+        assert!(image.matches(&host));
+    }
+}
@@ -0,0 +1,248 @@
+use super::{Descriptor, ImageIndex, Manifest, Platform};
+use crate::errors::{Error, Result};
+use crate::mediatypes::MediaTypes;
+use crate::v2::Client;
+use std::collections::{HashMap, HashSet};
+
+/// Sizes of the images of a reference, see `Client::get_image_size`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImageSizes {
+    /// Images of the reference, one per platform for image indexes.
+    pub images: Vec<ImageSize>,
+    /// Size of all the manifests and blobs as stored, counting the ones
+    /// shared by several images once.
+    pub compressed: u64,
+    /// Estimated uncompressed size of the layers of all images, counting
+    /// the ones shared by several images once, if known for each layer.
+    pub uncompressed: Option<u64>,
+}
+
+/// Sizes of an image.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImageSize {
+    /// Digest of the image manifest.
+    pub digest: String,
+    pub platform: Option<Platform>,
+    /// Size of the manifest, configuration and layers as stored.
+    pub compressed: u64,
+    /// Estimated uncompressed size of the layers, if known for each layer.
+    pub uncompressed: Option<u64>,
+    /// Layers of the image, base layer first.
+    pub layers: Vec<LayerSize>,
+}
+
+/// Sizes of an image layer.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LayerSize {
+    pub digest: String,
+    pub media_type: String,
+    /// Size of the layer blob.
+    pub compressed: u64,
+    /// Estimated size of the uncompressed layer, if known.
+    pub uncompressed: Option<u64>,
+}
+
+impl ImageSizes {
+    /// Sum up the sizes of `images`, referenced by an index of `index_size`
+    /// bytes if any.
+    fn new(index_size: u64, images: Vec<ImageSize>) -> Self {
+        let mut seen = HashSet::new();
+        let mut compressed = index_size;
+        let mut uncompressed = Some(0);
+        for image in &images {
+            if seen.insert(image.digest.as_str()) {
+                // Manifests and configurations are not shared across images.
+                compressed +=
+                    image.compressed - image.layers.iter().map(|l| l.compressed).sum::<u64>();
+            }
+            for layer in &image.layers {
+                if seen.insert(layer.digest.as_str()) {
+                    compressed += layer.compressed;
+                    uncompressed = uncompressed.zip(layer.uncompressed).map(|(a, b)| a + b);
+                }
+            }
+        }
+        ImageSizes {
+            images,
+            compressed,
+            uncompressed,
+        }
+    }
+}
+
+impl Client {
+    /// Get the sizes of the image `reference`, without downloading its
+    /// layers.
+    ///
+    /// Sizes as stored are those of the descriptors. Uncompressed sizes are
+    /// known for layers which are not compressed and, with the `render`
+    /// feature, estimated from the annotations of eStargz layers and the
+    /// trailers of gzip layers, fetched with range requests. If the reference
+    /// resolves to an image index, the sizes of the image for each platform
+    /// are returned, leaving out manifests of other artifacts, such as
+    /// attestations.
+    pub async fn get_image_size(&self, name: &str, reference: &str) -> Result<ImageSizes> {
+        self.deadline()
+            .run(async move {
+                let raw = self.get_raw_manifest(name, reference).await?;
+                let mut estimates = HashMap::new();
+                match raw.manifest {
+                    Manifest::ML(_) => {
+                        let index = serde_json::from_slice::<ImageIndex>(&raw.raw.body)?;
+                        let mut images = Vec::new();
+                        for entry in &index.manifests {
+                            let platform = match &entry.platform {
+                                Some(platform) if entry.artifact_type.is_none() => platform,
+                                _ => continue,
+                            };
+                            if platform.os == "unknown" {
+                                continue;
+                            }
+                            let (manifest, _) = self
+                                .get_index_entry(name, &entry.digest, Some(&entry.media_type))
+                                .await?;
+                            let mut image = self
+                                .image_size(
+                                    name,
+                                    &manifest,
+                                    &entry.digest,
+                                    entry.size,
+                                    &mut estimates,
+                                )
+                                .await?;
+                            image.platform = Some(platform.clone());
+                            images.push(image);
+                        }
+                        Ok(ImageSizes::new(raw.raw.body.len() as u64, images))
+                    }
+                    manifest => {
+                        let image = self
+                            .image_size(
+                                name,
+                                &manifest,
+                                &raw.raw.digest,
+                                raw.raw.body.len() as u64,
+                                &mut estimates,
+                            )
+                            .await?;
+                        Ok(ImageSizes::new(0, vec![image]))
+                    }
+                }
+            })
+            .await
+    }
+
+    /// Get the sizes of the image of `manifest`, estimating the uncompressed
+    /// sizes of layers not found in `estimates`.
+    async fn image_size(
+        &self,
+        name: &str,
+        manifest: &Manifest,
+        digest: &str,
+        manifest_size: u64,
+        estimates: &mut HashMap<String, Option<u64>>,
+    ) -> Result<ImageSize> {
+        let (platform, config_size, layers) = match manifest {
+            Manifest::S2(m) => {
+                let config = &m.config_blob;
+                let mut platform = Platform::new(&config.os, &config.architecture);
+                platform.variant = config.variant.clone();
+                let config_size = m.manifest_spec.config().size;
+                (Some(platform), config_size, m.layer_descriptors())
+            }
+            Manifest::Artifact(m) => {
+                let config_size = m.config.as_ref().map_or(0, |c| c.size);
+                (None, config_size, m.layers.clone())
+            }
+            Manifest::S1Signed(_) => {
+                return Err(Error::UnsupportedMediaType(MediaTypes::ManifestV2S1Signed))
+            }
+            Manifest::ML(_) => return Err(Error::UnsupportedMediaType(MediaTypes::ManifestList)),
+            Manifest::Custom(m) => return Err(Error::UnsupportedMediaType(m.media_type.clone())),
+        };
+
+        let mut sizes = Vec::with_capacity(layers.len());
+        for layer in &layers {
+            let uncompressed = match estimates.get(&layer.digest) {
+                Some(uncompressed) => *uncompressed,
+                None => {
+                    let uncompressed = self.estimate_uncompressed(name, layer).await?;
+                    estimates.insert(layer.digest.clone(), uncompressed);
+                    uncompressed
+                }
+            };
+            sizes.push(LayerSize {
+                digest: layer.digest.clone(),
+                media_type: layer.media_type.clone(),
+                compressed: layer.size,
+                uncompressed,
+            });
+        }
+
+        Ok(ImageSize {
+            digest: digest.to_string(),
+            platform,
+            compressed: manifest_size
+                + config_size
+                + sizes.iter().map(|l| l.compressed).sum::<u64>(),
+            uncompressed: sizes.iter().map(|l| l.uncompressed).sum(),
+            layers: sizes,
+        })
+    }
+
+    /// Estimate the uncompressed size of `layer`.
+    #[cfg_attr(not(feature = "render"), allow(unused_variables))]
+    async fn estimate_uncompressed(&self, name: &str, layer: &Descriptor) -> Result<Option<u64>> {
+        if layer.media_type.ends_with("tar") {
+            return Ok(Some(layer.size));
+        }
+        #[cfg(feature = "render")]
+        {
+            if layer.is_estargz() {
+                let size = layer
+                    .annotations
+                    .get(crate::v2::ANNOTATION_UNCOMPRESSED_SIZE)
+                    .and_then(|s| s.parse().ok());
+                return Ok(size);
+            }
+            // Foreign layers are not stored by the registry.
+            if layer.media_type.ends_with("gzip") && !layer.is_foreign() && layer.size >= 4 {
+                let trailer = self
+                    .get_blob_slice(name, &layer.digest, layer.size - 4..layer.size)
+                    .await?;
+                return Ok(gzip_size(&trailer, layer.size));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Uncompressed size of a gzip stream of `compressed` bytes, given its last
+/// 4 bytes, which hold it modulo 2^32.
+#[cfg(feature = "render")]
+fn gzip_size(trailer: &[u8], compressed: u64) -> Option<u64> {
+    use std::convert::TryInto;
+
+    let mut size = u64::from(u32::from_le_bytes(trailer.try_into().ok()?));
+    // Incompressible data grows slightly, smaller sizes are truncated ones.
+    while size + compressed / 1000 + 1024 < compressed {
+        size += 1 << 32;
+    }
+    Some(size)
+}
+
+#[cfg(all(test, feature = "render"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gzip_size_wraps() {
+        assert_eq!(gzip_size(&100u32.to_le_bytes(), 60), Some(100));
+        assert_eq!(gzip_size(&60u32.to_le_bytes(), 61), Some(60));
+        assert_eq!(
+            gzip_size(&100u32.to_le_bytes(), 1 << 31),
+            Some((1 << 32) + 100)
+        );
+        assert_eq!(gzip_size(&[0, 1], 60), None);
+    }
+}
@@ -0,0 +1,537 @@
+//! Structural validation of manifests.
+
+use super::{
+    Descriptor, ImageIndex, Manifest, ManifestList, ManifestSchema1Signed, ManifestSchema2Spec,
+    OciManifest, Platform,
+};
+use crate::mediatypes::MediaTypes;
+use std::fmt;
+
+/// Maximum number of layers of an image, as supported by the overlay
+/// filesystems of container runtimes.
+///
+/// This is no limit of the specifications: images with more layers are
+/// reported with a warning, see `Violation::warning`.
+pub const MAX_LAYERS: usize = 128;
+
+/// Maximum number of manifests of an image index, as supported by common
+/// registries.
+///
+/// As `MAX_LAYERS`, this is only reported with a warning.
+pub const MAX_INDEX_ENTRIES: usize = 1024;
+
+/// Digest of empty content, the only one of a blob of size 0.
+const EMPTY_DIGEST: &str =
+    "sha256:e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+/// Structural problem of a manifest, found by `validate`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    /// Path of the offending field, e.g. `layers[1].digest`.
+    pub field: String,
+    pub message: String,
+    /// Whether this only exceeds a practical limit, such as `MAX_LAYERS`,
+    /// while the manifest follows the specifications.
+    pub warning: bool,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+/// Violations found so far.
+#[derive(Default)]
+struct Violations(Vec<Violation>);
+
+impl Violations {
+    fn push(&mut self, field: &str, message: String) {
+        self.0.push(Violation {
+            field: field.to_string(),
+            message,
+            warning: false,
+        });
+    }
+
+    fn schema_version(&mut self, version: u16, expected: u16) {
+        if version != expected {
+            self.push(
+                "schemaVersion",
+                format!("must be {}, not {}", expected, version),
+            );
+        }
+    }
+
+    fn media_type(&mut self, media_type: &str, allowed: &[MediaTypes]) {
+        // Optional in OCI documents.
+        if !media_type.is_empty() && !allowed.iter().any(|t| t.to_string() == media_type) {
+            self.push("mediaType", format!("unexpected media type {}", media_type));
+        }
+    }
+
+    /// Warn about more than `max` entries in `field`.
+    fn count(&mut self, field: &str, count: usize, max: usize) {
+        if count > max {
+            self.0.push(Violation {
+                field: field.to_string(),
+                message: format!("{} entries, more than {}", count, max),
+                warning: true,
+            });
+        }
+    }
+
+    fn digest(&mut self, field: &str, digest: &str) {
+        if let Err(message) = check_digest(digest) {
+            self.push(field, message);
+        }
+    }
+
+    /// Check the fields of a descriptor, of which `allowed` tells whether
+    /// the media type suits.
+    fn descriptor<F>(&mut self, field: &str, media_type: &str, digest: &str, size: u64, allowed: F)
+    where
+        F: Fn(&str) -> bool,
+    {
+        if media_type.is_empty() {
+            self.push(&format!("{}.mediaType", field), "missing".to_string());
+        } else if media_type.parse::<mime::Mime>().is_err() {
+            self.push(
+                &format!("{}.mediaType", field),
+                format!("invalid media type {}", media_type),
+            );
+        } else if !allowed(media_type) {
+            self.push(
+                &format!("{}.mediaType", field),
+                format!("media type {} not allowed here", media_type),
+            );
+        }
+        self.digest(&format!("{}.digest", field), digest);
+        if size == 0 && digest != EMPTY_DIGEST {
+            self.push(&format!("{}.size", field), "must be positive".to_string());
+        }
+    }
+
+    /// Check the entries of an image index or a manifest list, as their
+    /// media type, digest, size and platform.
+    fn index_entries<'a, I>(&mut self, docker: bool, entries: I)
+    where
+        I: ExactSizeIterator<Item = (&'a str, &'a str, u64, Option<&'a Platform>)>,
+    {
+        let count = entries.len();
+        for (i, (media_type, digest, size, platform)) in entries.enumerate() {
+            let field = format!("manifests[{}]", i);
+            self.descriptor(&field, media_type, digest, size, |t| {
+                is_manifest(t) && !(docker && t.contains(".oci."))
+            });
+            if docker {
+                match platform {
+                    Some(platform) => platform_fields(self, &field, platform),
+                    None => self.push(&format!("{}.platform", field), "missing".to_string()),
+                }
+            }
+        }
+        self.count("manifests", count, MAX_INDEX_ENTRIES);
+    }
+}
+
+/// Check that `digest` is well-formed, as `algorithm:encoded`.
+fn check_digest(digest: &str) -> std::result::Result<(), String> {
+    let (algorithm, encoded) = match digest.split_once(':') {
+        Some(parts) => parts,
+        None if digest.is_empty() => return Err("missing".to_string()),
+        None => return Err(format!("digest {} has no algorithm prefix", digest)),
+    };
+    let valid_algorithm = algorithm.split(|c| "+._-".contains(c)).all(|c| {
+        !c.is_empty()
+            && c.bytes()
+                .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit())
+    });
+    if !valid_algorithm {
+        return Err(format!("invalid digest algorithm {}", algorithm));
+    }
+    let hex = |len: usize| {
+        encoded.len() == len
+            && encoded
+                .bytes()
+                .all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+    };
+    let valid = match algorithm {
+        "sha256" => hex(64),
+        "sha512" => hex(128),
+        _ => {
+            !encoded.is_empty()
+                && encoded
+                    .bytes()
+                    .all(|b| b.is_ascii_alphanumeric() || b"=_-".contains(&b))
+        }
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("invalid {} digest {}", algorithm, digest))
+    }
+}
+
+fn is_manifest(media_type: &str) -> bool {
+    [
+        MediaTypes::ManifestV2S1,
+        MediaTypes::ManifestV2S1Signed,
+        MediaTypes::ManifestV2S2,
+        MediaTypes::ManifestList,
+        MediaTypes::OciImageManifest,
+        MediaTypes::OciImageIndex,
+        MediaTypes::OciArtifactManifest,
+    ]
+    .iter()
+    .any(|t| t.to_string() == media_type)
+}
+
+fn is_docker_layer(media_type: &str) -> bool {
+    media_type.starts_with("application/vnd.docker.image.rootfs.")
+}
+
+impl Manifest {
+    /// Check the structure of the manifest, returning the violations found.
+    ///
+    /// See `OciManifest::validate`. Manifests of custom media types are not
+    /// checked.
+    pub fn validate(&self) -> Vec<Violation> {
+        match self {
+            Manifest::S1Signed(m) => m.validate(),
+            Manifest::S2(m) => m.manifest_spec.validate(),
+            Manifest::ML(m) => m.validate(),
+            Manifest::Artifact(m) => m.validate(),
+            Manifest::Custom(_) => Vec::new(),
+        }
+    }
+}
+
+impl OciManifest {
+    /// Check the structure of the manifest, returning the violations found.
+    ///
+    /// Required fields must be set, digests well-formed and sizes positive,
+    /// except for empty blobs. Neither the config nor the layers may be
+    /// manifests, while the subject must be one. Images with more than
+    /// `MAX_LAYERS` layers get a warning.
+    pub fn validate(&self) -> Vec<Violation> {
+        let mut violations = Violations::default();
+        violations.schema_version(self.schema_version, 2);
+        violations.media_type(
+            self.media_type.as_deref().unwrap_or_default(),
+            &[
+                MediaTypes::OciImageManifest,
+                MediaTypes::OciArtifactManifest,
+            ],
+        );
+        let artifact_manifest = self.media_type.as_deref()
+            == Some(MediaTypes::OciArtifactManifest.to_string().as_str());
+        match &self.config {
+            Some(config) => descriptor(&mut violations, "config", config, |t| !is_manifest(t)),
+            // Artifact manifests have none.
+            None if artifact_manifest => {}
+            None => violations.push("config", "missing".to_string()),
+        }
+        for (i, layer) in self.layers.iter().enumerate() {
+            descriptor(&mut violations, &format!("layers[{}]", i), layer, |t| {
+                !is_manifest(t)
+            });
+        }
+        if self.is_image() {
+            violations.count("layers", self.layers.len(), MAX_LAYERS);
+        }
+        if let Some(subject) = &self.subject {
+            descriptor(&mut violations, "subject", subject, is_manifest);
+        }
+        violations.0
+    }
+}
+
+impl ManifestSchema2Spec {
+    /// Check the structure of the manifest, returning the violations found.
+    ///
+    /// See `OciManifest::validate`. Docker manifests must also describe a
+    /// Docker image, with a container configuration and Docker layers.
+    pub fn validate(&self) -> Vec<Violation> {
+        let mut violations = Violations::default();
+        violations.schema_version(self.schema_version, 2);
+        violations.media_type(
+            &self.media_type,
+            &[MediaTypes::ManifestV2S2, MediaTypes::OciImageManifest],
+        );
+        let docker = self.media_type == MediaTypes::ManifestV2S2.to_string();
+        let config = self.config();
+        violations.descriptor(
+            "config",
+            &config.media_type,
+            &config.digest,
+            config.size,
+            |t| {
+                if docker {
+                    t == MediaTypes::ContainerConfigV1.to_string()
+                } else {
+                    !is_manifest(t)
+                }
+            },
+        );
+        let layers = self.layer_descriptors();
+        for (i, layer) in layers.iter().enumerate() {
+            descriptor(&mut violations, &format!("layers[{}]", i), layer, |t| {
+                if docker {
+                    is_docker_layer(t)
+                } else {
+                    !is_manifest(t)
+                }
+            });
+        }
+        violations.count("layers", layers.len(), MAX_LAYERS);
+        if let Some(subject) = self.subject() {
+            descriptor(&mut violations, "subject", subject, is_manifest);
+        }
+        violations.0
+    }
+}
+
+impl ImageIndex {
+    /// Check the structure of the index, returning the violations found.
+    ///
+    /// Required fields must be set, digests well-formed and sizes positive.
+    /// Entries must be manifests, with a platform in Docker manifest lists,
+    /// and more than `MAX_INDEX_ENTRIES` of them get a warning.
+    pub fn validate(&self) -> Vec<Violation> {
+        let mut violations = Violations::default();
+        violations.schema_version(self.schema_version, 2);
+        let media_type = self.media_type.as_deref().unwrap_or_default();
+        violations.media_type(
+            media_type,
+            &[MediaTypes::ManifestList, MediaTypes::OciImageIndex],
+        );
+        let docker = media_type == MediaTypes::ManifestList.to_string();
+        violations.index_entries(
+            docker,
+            self.manifests.iter().map(|e| {
+                let platform = e.platform.as_ref();
+                (e.media_type.as_str(), e.digest.as_str(), e.size, platform)
+            }),
+        );
+        if let Some(subject) = &self.subject {
+            descriptor(&mut violations, "subject", subject, is_manifest);
+        }
+        violations.0
+    }
+}
+
+impl ManifestList {
+    /// Check the structure of the list, returning the violations found.
+    ///
+    /// See `ImageIndex::validate`.
+    pub fn validate(&self) -> Vec<Violation> {
+        let mut violations = Violations::default();
+        violations.schema_version(self.schema_version, 2);
+        violations.media_type(
+            &self.media_type,
+            &[MediaTypes::ManifestList, MediaTypes::OciImageIndex],
+        );
+        let docker = self.media_type == MediaTypes::ManifestList.to_string();
+        violations.index_entries(
+            docker,
+            self.manifests.iter().map(|e| {
+                let platform = Some(&e.platform);
+                (e.media_type.as_str(), e.digest.as_str(), e.size, platform)
+            }),
+        );
+        violations.0
+    }
+}
+
+impl ManifestSchema1Signed {
+    /// Check the structure of the manifest, returning the violations found.
+    ///
+    /// Besides well-formed layer digests, each layer needs a history entry.
+    pub fn validate(&self) -> Vec<Violation> {
+        let mut violations = Violations::default();
+        violations.schema_version(self.schema_version, 1);
+        if self.name.is_empty() {
+            violations.push("name", "missing".to_string());
+        }
+        if self.fs_layers.is_empty() {
+            violations.push("fsLayers", "missing".to_string());
+        }
+        for (i, layer) in self.fs_layers.iter().enumerate() {
+            violations.digest(&format!("fsLayers[{}].blobSum", i), &layer.blob_sum);
+        }
+        if self.history.len() != self.fs_layers.len() {
+            violations.push(
+                "history",
+                format!(
+                    "{} entries for {} layers",
+                    self.history.len(),
+                    self.fs_layers.len()
+                ),
+            );
+        }
+        violations.count("fsLayers", self.fs_layers.len(), MAX_LAYERS);
+        violations.0
+    }
+}
+
+fn descriptor<F>(violations: &mut Violations, field: &str, descriptor: &Descriptor, allowed: F)
+where
+    F: Fn(&str) -> bool,
+{
+    violations.descriptor(
+        field,
+        &descriptor.media_type,
+        &descriptor.digest,
+        descriptor.size,
+        allowed,
+    );
+}
+
+fn platform_fields(violations: &mut Violations, field: &str, platform: &Platform) {
+    if platform.os.is_empty() {
+        violations.push(&format!("{}.platform.os", field), "missing".to_string());
+    }
+    if platform.architecture.is_empty() {
+        violations.push(
+            &format!("{}.platform.architecture", field),
+            "missing".to_string(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::v2::manifest::{ImageIndexBuilder, ManifestBuilder};
+
+    const DIGEST: &str = "sha256:a3ed95caeb02ffe68cdd9fd84406680ae93d633cb16422d00e8a7c22955b46d4";
+
+    fn fields(violations: Vec<Violation>) -> Vec<String> {
+        violations.into_iter().map(|v| v.field).collect()
+    }
+
+    #[test]
+    fn manifests_validate() {
+        let built = ManifestBuilder::new()
+            .config_blob(MediaTypes::ContainerConfigV1, DIGEST, 1469)
+            .layer_blob(MediaTypes::ImageLayerTgz, DIGEST, 32)
+            .build()
+            .unwrap();
+        let manifest = serde_json::from_slice::<ManifestSchema2Spec>(&built.body).unwrap();
+        assert_eq!(manifest.validate(), Vec::new());
+
+        let manifest = serde_json::json!({
+            "schemaVersion": 2,
+            "mediaType": MediaTypes::ManifestV2S2.to_string(),
+            "config": {
+                "mediaType": MediaTypes::OciImageConfig.to_string(),
+                "digest": DIGEST.to_uppercase(),
+                "size": 1469,
+            },
+            "layers": [
+                {"mediaType": "application/vnd.oci.image.layer.v1.tar+gzip", "digest": DIGEST, "size": 32},
+                {"mediaType": MediaTypes::ImageLayerTgz.to_string(), "digest": "sha256:abc", "size": 0},
+                {"mediaType": MediaTypes::ImageLayerTgz.to_string(), "digest": EMPTY_DIGEST, "size": 0},
+            ],
+        });
+        let manifest = serde_json::from_value::<ManifestSchema2Spec>(manifest).unwrap();
+        assert_eq!(
+            fields(manifest.validate()),
+            vec![
+                "config.mediaType",
+                "config.digest",
+                "layers[0].mediaType",
+                "layers[1].digest",
+                "layers[1].size",
+            ]
+        );
+
+        let manifest = OciManifest {
+            schema_version: 2,
+            layers: vec![Descriptor {
+                media_type: MediaTypes::OciImageManifest.to_string(),
+                digest: DIGEST.to_string(),
+                size: 32,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        assert_eq!(
+            fields(manifest.validate()),
+            vec!["config", "layers[0].mediaType"]
+        );
+    }
+
+    #[test]
+    fn indexes_validate() {
+        let mut index = ImageIndexBuilder::new()
+            .media_type(MediaTypes::ManifestList)
+            .platform_manifest(
+                MediaTypes::ManifestV2S2,
+                DIGEST,
+                528,
+                Platform::new("linux", "amd64"),
+            )
+            .build()
+            .unwrap();
+        assert_eq!(index.validate(), Vec::new());
+
+        index.manifests[0].media_type = MediaTypes::OciImageManifest.to_string();
+        index.manifests[0].platform = None;
+        index.manifests[0].digest = "e3b0c442".to_string();
+        index.schema_version = 1;
+        let violations = index.validate();
+        assert_eq!(
+            fields(violations.clone()),
+            vec![
+                "schemaVersion",
+                "manifests[0].mediaType",
+                "manifests[0].digest",
+                "manifests[0].platform",
+            ]
+        );
+        assert_eq!(
+            violations[2].to_string(),
+            "manifests[0].digest: digest e3b0c442 has no algorithm prefix"
+        );
+    }
+
+    #[test]
+    fn limits_warn() {
+        let manifest = OciManifest {
+            schema_version: 2,
+            config: Some(Descriptor {
+                media_type: MediaTypes::OciImageConfig.to_string(),
+                digest: DIGEST.to_string(),
+                size: 1469,
+                ..Default::default()
+            }),
+            layers: vec![
+                Descriptor {
+                    media_type: "application/vnd.oci.image.layer.v1.tar+gzip".to_string(),
+                    digest: DIGEST.to_string(),
+                    size: 32,
+                    ..Default::default()
+                };
+                MAX_LAYERS + 1
+            ],
+            ..Default::default()
+        };
+        let violations = manifest.validate();
+        assert_eq!(fields(violations.clone()), vec!["layers"]);
+        assert!(violations[0].warning);
+    }
+
+    #[test]
+    fn digests_validate() {
+        assert!(check_digest(DIGEST).is_ok());
+        assert!(
+            check_digest("multihash+base58:QmRZxt2b1FVZPNqd8hsiykDL3TdBDeTSPX9Kv46HmX4Gx8").is_ok()
+        );
+        assert!(check_digest("sha256:").is_err());
+        assert!(check_digest("SHA256:abc").is_err());
+        assert!(check_digest("sha512:abc").is_err());
+        assert!(check_digest("").is_err());
+    }
+}
@@ -0,0 +1,41 @@
+//! Observation of requests sent by a `Client`.
+//!
+//! Implement `MetricsSink` and set it with `Config::metrics_sink` to export
+//! metrics (e.g. to Prometheus), labeled by registry host.
+
+use reqwest::{Method, StatusCode};
+use std::fmt::Debug;
+use std::time::Duration;
+
+/// Outcome of a request sent to a registry or an authorization server.
+#[non_exhaustive]
+#[derive(Debug, Clone)]
+pub struct RequestMetrics<'a> {
+    /// Host the request was sent to, with its port if any.
+    pub host: &'a str,
+    pub method: &'a Method,
+    /// Status of the response, or `None` if no response was received.
+    pub status: Option<StatusCode>,
+    /// Time from sending the request until the response headers arrived,
+    /// including retries.
+    pub latency: Duration,
+    /// Number of times the request was retried.
+    pub retries: u32,
+    /// Size of the request body.
+    pub bytes_sent: u64,
+    /// Size of the response body, as announced by `Content-Length`.
+    pub bytes_received: Option<u64>,
+}
+
+/// Receiver of metrics about the requests sent by a client.
+///
+/// Methods are called inline with requests, so they must not block.
+pub trait MetricsSink: Debug + Send + Sync {
+    /// Record a request, once it got a response or failed.
+    fn record_request(&self, request: &RequestMetrics<'_>);
+
+    /// Record a failed authentication against the registry at `host`.
+    fn record_auth_failure(&self, host: &str) {
+        let _ = host;
+    }
+}
@@ -0,0 +1,90 @@
+use crate::reference::DEFAULT_REGISTRY;
+use crate::v2::*;
+use reqwest::{RequestBuilder, Response};
+
+/// Path segments following the repository name in API endpoints.
+const ENDPOINTS: &[&str] = &["/manifests/", "/blobs/", "/tags/", "/referrers/"];
+
+impl Client {
+    /// Create a client pulling from `mirror` (e.g. `https://mirror.gcr.io`)
+    /// on behalf of this one.
+    pub(crate) fn mirror(&self, mirror: &str) -> Client {
+        let base_url = if mirror.contains("://") {
+            mirror.trim_end_matches('/').to_string()
+        } else {
+            format!("https://{}", mirror.trim_end_matches('/'))
+        };
+        Client {
+            base_url,
+            credentials: None,
+            identity_token: None,
+            provider: None,
+            auth: None,
+            scopes: Vec::new(),
+            tokens: Default::default(),
+            rate_limit: Default::default(),
+            mirrors: Vec::new(),
+            upstream: Some(self.base_url.clone()),
+            ..self.clone()
+        }
+    }
+
+    /// Rewrite an upstream registry URL to this mirror, if it is one.
+    ///
+    /// Official images of Docker Hub get their implicit `library/` prefix,
+    /// which mirrors expect.
+    pub(crate) fn mirror_url(&self, url: Url) -> Url {
+        let upstream = match &self.upstream {
+            Some(upstream) => upstream,
+            None => return url,
+        };
+        let path = match url.as_str().strip_prefix(&format!("{}/v2/", upstream)) {
+            Some(path) => path,
+            None => return url,
+        };
+        let name_end = ENDPOINTS.iter().filter_map(|e| path.find(e)).min();
+        let is_hub = upstream.ends_with(&format!("://{}", DEFAULT_REGISTRY));
+        let path = match name_end {
+            Some(end) if is_hub && !path[..end].contains('/') => format!("library/{}", path),
+            _ => path.to_string(),
+        };
+        let mirrored = format!("{}/v2/{}", self.base_url, path);
+        Url::parse(&mirrored).unwrap_or(url)
+    }
+
+    /// Send a pull request through the configured mirrors, in order.
+    ///
+    /// Returns `None` if no mirror could serve the request, which must then
+    /// be sent to the upstream registry.
+    pub(crate) async fn send_to_mirrors<F>(&self, build: &F) -> Option<Response>
+    where
+        F: Fn(&Client) -> RequestBuilder,
+    {
+        if self.mirrors.is_empty() || !is_pull(build(self)) {
+            return None;
+        }
+        for mirror in &self.mirrors {
+            match mirror.execute(build(mirror)).await {
+                Ok(res) if !needs_fallback(res.status()) => return Some(res),
+                Ok(res) => debug!("mirror {} answered {}", mirror.base_url, res.status()),
+                Err(e) => debug!("mirror {} failed: {}", mirror.base_url, e),
+            }
+        }
+        None
+    }
+}
+
+/// Whether a request only reads from the registry.
+fn is_pull(req: RequestBuilder) -> bool {
+    req.build()
+        .map(|r| *r.method() == Method::GET || *r.method() == Method::HEAD)
+        .unwrap_or(false)
+}
+
+/// Whether a mirror response should be retried on the next mirror, or upstream.
+fn needs_fallback(status: StatusCode) -> bool {
+    status == StatusCode::NOT_FOUND
+        || status == StatusCode::UNAUTHORIZED
+        || status == StatusCode::FORBIDDEN
+        || status.is_server_error()
+}
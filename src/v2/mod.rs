@@ -35,30 +35,108 @@ use reqwest::{Method, StatusCode, Url};
 mod config;
 pub use self::config::Config;
 
+mod api_error;
+pub(crate) use self::api_error::{response_error, status_error};
+pub use self::api_error::{ApiError, ErrorCode};
+
+mod capabilities;
+pub use self::capabilities::ApiCheck;
+
 mod catalog;
 
 mod auth;
-pub use auth::WwwHeaderParseError;
+pub use auth::{AuthMode, TokenFlow, WwwHeaderParseError};
 
 pub mod manifest;
 
 mod tags;
 
 mod blobs;
+pub use self::blobs::BlobResponse;
+
+#[cfg(feature = "render")]
+mod estargz;
+#[cfg(feature = "render")]
+pub use self::estargz::{
+    EstargzToc, TocEntry, ANNOTATION_TOC_DIGEST, ANNOTATION_UNCOMPRESSED_SIZE,
+};
+
+mod foreign;
+pub(crate) use self::foreign::is_foreign;
+pub use self::foreign::ForeignLayerPolicy;
+
+mod upload;
+pub use self::upload::{BlobMount, UploadSession};
+
+mod ratelimit;
+pub use self::ratelimit::RateLimitInfo;
+
+mod redirect;
+
+mod referrers;
+pub use self::referrers::{Referrer, ReferrerFilter};
+
+pub mod metrics;
+
+mod mirror;
+
+mod paginate;
+pub use self::paginate::Paginate;
+
+mod progress;
+pub use self::progress::Progress;
+
+pub mod provider;
+
+mod retry;
+
+mod transport;
+pub(crate) use self::retry::is_connect;
+pub use self::retry::{RateLimit, RetryPolicy};
+pub use self::transport::Transport;
+
+mod deadline;
+pub(crate) use self::deadline::Deadline;
 
 mod content_digest;
 pub(crate) use self::content_digest::ContentDigest;
-pub use self::content_digest::ContentDigestError;
+pub use self::content_digest::{sha256_digest, ContentDigestError};
 
 /// A Client to make outgoing API requests to a registry.
 #[derive(Clone, Debug)]
 pub struct Client {
     base_url: String,
     credentials: Option<(String, String)>,
+    /// OAuth2 refresh token from the Docker configuration.
+    identity_token: Option<String>,
+    provider: Option<std::sync::Arc<dyn provider::CredentialsProvider>>,
     user_agent: Option<String>,
+    default_headers: reqwest::header::HeaderMap,
+    token_flow: TokenFlow,
+    anonymous_fallback: bool,
     auth: Option<auth::Auth>,
     client: reqwest::Client,
     accepted_types: Vec<(MediaTypes, Option<f64>)>,
+    media_type_handlers: manifest::MediaTypeHandlers,
+    #[cfg(feature = "schema1-signatures")]
+    require_schema1_signatures: bool,
+    retry_policy: RetryPolicy,
+    deadline: Option<std::time::Duration>,
+    progress: Option<progress::ProgressHook>,
+    metrics: Option<std::sync::Arc<dyn metrics::MetricsSink>>,
+    /// Transport replacing `client` to send requests, if any.
+    transport: Option<std::sync::Arc<dyn Transport>>,
+    /// Hosts to which redirected requests keep their authorization.
+    redirect_auth_hosts: Vec<String>,
+    foreign_layers: ForeignLayerPolicy,
+    /// Scopes of the last authentication, to renew it.
+    scopes: Vec<String>,
+    /// Bearer tokens issued for this registry, shared by clones.
+    tokens: auth::TokenCache,
+    rate_limit: ratelimit::RateLimitCache,
+    mirrors: Vec<Client>,
+    /// Base URL of the registry mirrored by this client, if any.
+    upstream: Option<String>,
 }
 
 impl Client {
@@ -87,32 +165,37 @@ impl Client {
     /// Check whether remote registry supports v2 API and `self` is authorized.
     /// Authorized means to successfully GET the `/v2` endpoint on the remote registry.
     pub async fn is_v2_supported_and_authorized(&self) -> Result<(bool, bool)> {
-        let api_header = "Docker-Distribution-API-Version";
-        let api_version = "registry/2.0";
-
-        // GET request to bare v2 endpoint.
-        let v2_endpoint = format!("{}/v2/", self.base_url);
-        let request = reqwest::Url::parse(&v2_endpoint).map(|url| {
-            trace!("GET {:?}", url);
-            self.build_reqwest(Method::GET, url)
-        })?;
-
-        let response = request.send().await?;
-
-        let b = match (response.status(), response.headers().get(api_header)) {
-            (StatusCode::OK, Some(x)) => Ok((x == api_version, true)),
-            (StatusCode::UNAUTHORIZED, Some(x)) => Ok((x == api_version, false)),
-            (s, v) => {
-                trace!("Got unexpected status {}, header version {:?}", s, v);
-                return Err(crate::Error::UnexpectedHttpStatus(s));
-            }
-        };
-
-        b
+        self.deadline()
+            .run(async move {
+                let api_header = "Docker-Distribution-API-Version";
+                let api_version = "registry/2.0";
+
+                // GET request to bare v2 endpoint.
+                let v2_endpoint = format!("{}/v2/", self.base_url);
+                let request = reqwest::Url::parse(&v2_endpoint).map(|url| {
+                    trace!("GET {:?}", url);
+                    self.build_reqwest(Method::GET, url)
+                })?;
+
+                let response = self.execute(request).await?;
+
+                let b = match (response.status(), response.headers().get(api_header)) {
+                    (StatusCode::OK, Some(x)) => Ok((x == api_version, true)),
+                    (StatusCode::UNAUTHORIZED, Some(x)) => Ok((x == api_version, false)),
+                    (s, v) => {
+                        trace!("Got unexpected status {}, header version {:?}", s, v);
+                        return Err(crate::Error::UnexpectedHttpStatus(s));
+                    }
+                };
+
+                b
+            })
+            .await
     }
 
     /// Takes reqwest's async RequestBuilder and injects an authentication header if a token is present
     fn build_reqwest(&self, method: Method, url: Url) -> reqwest::RequestBuilder {
+        let url = self.mirror_url(url);
         let mut builder = self.client.request(method, url);
 
         if let Some(auth) = &self.auth {
@@ -125,16 +208,42 @@ impl Client {
 
         builder
     }
-}
 
-#[derive(Debug, Default, Deserialize, Serialize)]
-struct ApiError {
-    code: String,
-    message: String,
-    detail: String,
+    /// Add the default headers which are not set in `headers`, so that those
+    /// of the request take precedence.
+    fn add_default_headers(&self, headers: &mut reqwest::header::HeaderMap) {
+        for key in self.default_headers.keys() {
+            if !headers.contains_key(key) {
+                for value in self.default_headers.get_all(key) {
+                    headers.append(key, value.clone());
+                }
+            }
+        }
+    }
 }
 
-#[derive(Debug, Default, Deserialize, Serialize)]
-struct Errors {
-    errors: Vec<ApiError>,
+/// Parse a `Link` header.
+///
+/// Format is described at https://docs.docker.com/registry/spec/api/#listing-image-tags#pagination.
+pub(crate) fn parse_link(hdr: Option<&reqwest::header::HeaderValue>) -> Option<String> {
+    // TODO(lucab): this a brittle string-matching parser. Investigate
+    // whether there is a a common library to do this, in the future.
+
+    // Raw Header value bytes.
+    let hval = hdr?;
+
+    // Header value string.
+    let sval = match hval.to_str() {
+        Ok(v) => v.to_owned(),
+        _ => return None,
+    };
+
+    // Query parameters for next page URL.
+    let uri = sval.trim_end_matches(">; rel=\"next\"");
+    let query: Vec<&str> = uri.splitn(2, "?").collect();
+    match query.get(1) {
+        //use the entire query param string since some registries have different ways of pagination
+        Some(v) if !v.is_empty() => Some(v.to_string()),
+        _ => None,
+    }
 }
@@ -0,0 +1,104 @@
+use crate::errors::{Error, Result};
+use crate::v2::*;
+use async_stream::try_stream;
+use reqwest::header::{self, HeaderMap};
+use serde::de::DeserializeOwned;
+use std::fmt;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+#[cfg(not(target_arch = "wasm32"))]
+type Pages<'a, T> = futures::stream::BoxStream<'a, Result<T>>;
+// Futures of `fetch` requests are not `Send`.
+#[cfg(target_arch = "wasm32")]
+type Pages<'a, T> = futures::stream::LocalBoxStream<'a, Result<T>>;
+
+/// Stream of the items of an endpoint paginated with `Link` headers, see
+/// `Client::paginate`.
+pub struct Paginate<'a, T> {
+    pages: Pages<'a, T>,
+}
+
+impl<'a, T: Send + 'a> Paginate<'a, T> {
+    /// A stream yielding only `error`.
+    pub(crate) fn failed(error: Error) -> Self {
+        Paginate {
+            pages: Box::pin(stream::once(future::ready(Err(error)))),
+        }
+    }
+}
+
+impl<T> Stream for Paginate<'_, T> {
+    type Item = Result<T>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.pages.as_mut().poll_next(cx)
+    }
+}
+
+impl<T> fmt::Debug for Paginate<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Paginate")
+    }
+}
+
+impl Client {
+    /// Stream the items of a paginated endpoint, starting at `url`.
+    ///
+    /// Pages are fetched with `GET`, accepting `accept`, and deserialized as
+    /// `P`, from which `items` extracts the items given the response headers.
+    /// The next page is fetched from the query of the `Link: rel="next"`
+    /// header, until a page has none.
+    pub fn paginate<'a, P, T, F>(&'a self, url: Url, accept: &'a str, items: F) -> Paginate<'a, T>
+    where
+        P: DeserializeOwned + Send + 'a,
+        T: Send + 'a,
+        F: Fn(&HeaderMap, P) -> Vec<T> + Send + 'a,
+    {
+        let pages = try_stream! {
+            let mut url = url;
+            loop {
+                let (headers, page) = self.fetch_page::<P>(&url, accept).await?;
+                let next = parse_link(headers.get(header::LINK));
+                trace!("next_page {:?}", next);
+                let page_items = items(&headers, page);
+                for item in page_items {
+                    yield item;
+                }
+
+                match next {
+                    Some(query) => url.set_query(Some(&query)),
+                    None => break,
+                }
+            }
+        };
+        Paginate {
+            pages: Box::pin(self.deadline().bound(pages)),
+        }
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(url = %url)))]
+    async fn fetch_page<P: DeserializeOwned>(
+        &self,
+        url: &Url,
+        accept: &str,
+    ) -> Result<(HeaderMap, P)> {
+        let res = self
+            .send(|client| {
+                client
+                    .build_reqwest(Method::GET, url.clone())
+                    .header(header::ACCEPT, accept)
+            })
+            .await?;
+
+        let status = res.status();
+        trace!("GET '{}' status: {:?}", res.url(), status);
+        match status {
+            StatusCode::OK => {}
+            _ => return Err(status_error(res).await),
+        }
+
+        let headers = res.headers().clone();
+        Ok((headers, res.json::<P>().await?))
+    }
+}
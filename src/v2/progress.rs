@@ -0,0 +1,65 @@
+use crate::errors::Result;
+use crate::v2::*;
+use futures::stream::StreamExt;
+use std::fmt;
+use std::sync::Arc;
+
+/// Progress of a blob or manifest download, see `Config::progress`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress<'a> {
+    /// Digest of the blob, or reference of the manifest, being downloaded.
+    pub reference: &'a str,
+    /// Number of bytes received so far.
+    pub transferred: u64,
+    /// Size of the content, if announced by the registry.
+    pub total: Option<u64>,
+}
+
+/// Callback receiving the progress of downloads.
+#[derive(Clone)]
+pub(crate) struct ProgressHook(Arc<dyn Fn(Progress<'_>) + Send + Sync>);
+
+impl ProgressHook {
+    pub(crate) fn new<F>(hook: F) -> Self
+    where
+        F: Fn(Progress<'_>) + Send + Sync + 'static,
+    {
+        Self(Arc::new(hook))
+    }
+
+    pub(crate) fn report(&self, reference: &str, transferred: u64, total: Option<u64>) {
+        (self.0)(Progress {
+            reference,
+            transferred,
+            total,
+        })
+    }
+}
+
+impl fmt::Debug for ProgressHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ProgressHook")
+    }
+}
+
+impl Client {
+    /// Read the body of a response, reporting progress for `reference`.
+    pub(crate) async fn read_body(
+        &self,
+        res: reqwest::Response,
+        reference: &str,
+    ) -> Result<Vec<u8>> {
+        let total = res.content_length();
+        let mut body = Vec::new();
+        let mut stream = res.bytes_stream();
+        while let Some(chunk) = stream.next().await {
+            body.extend_from_slice(&chunk?);
+            if let Some(hook) = &self.progress {
+                hook.report(reference, body.len() as u64, total);
+            }
+        }
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("bytes", body.len());
+        Ok(body)
+    }
+}
@@ -0,0 +1,222 @@
+use super::{CredentialsProvider, ProvidedCredentials};
+use crate::errors::{Error, Result};
+use futures::future::BoxFuture;
+use std::time::Duration;
+use web_time::{SystemTime, UNIX_EPOCH};
+
+/// Default Azure Active Directory endpoint.
+const AUTHORITY_HOST: &str = "https://login.microsoftonline.com";
+
+/// Scope of AAD access tokens exchanged for ACR refresh tokens.
+const AAD_SCOPE: &str = "https://containerregistry.azure.net/.default";
+
+/// Username of basic authentication with ACR refresh tokens.
+const USERNAME: &str = "00000000-0000-0000-0000-000000000000";
+
+/// Validity of ACR refresh tokens, if it cannot be read from them.
+const REFRESH_TOKEN_LIFETIME: Duration = Duration::from_secs(3 * 60 * 60);
+
+/// Provider of credentials for Azure Container Registry, for AAD service
+/// principals.
+///
+/// An AAD access token obtained for the service principal is exchanged for
+/// an ACR refresh token (at `/oauth2/exchange`), which is then used as the
+/// password of the null user to request access tokens (at `/oauth2/token`).
+///
+/// This provider is selected automatically for `azurecr.io` registries,
+/// if a service principal is set in the environment (see `from_env`).
+#[derive(Clone)]
+pub struct AcrProvider {
+    tenant_id: String,
+    client_id: String,
+    client_secret: String,
+    authority_host: String,
+    insecure_registry: bool,
+    client: reqwest::Client,
+}
+
+impl std::fmt::Debug for AcrProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AcrProvider")
+            .field("tenant_id", &self.tenant_id)
+            .field("client_id", &self.client_id)
+            .field("authority_host", &self.authority_host)
+            .field("insecure_registry", &self.insecure_registry)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct AadToken {
+    access_token: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExchangedToken {
+    refresh_token: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Claims {
+    exp: Option<u64>,
+}
+
+impl AcrProvider {
+    /// Initialize an `AcrProvider` for a service principal with a secret.
+    pub fn from_service_principal(tenant_id: &str, client_id: &str, client_secret: &str) -> Self {
+        Self {
+            tenant_id: tenant_id.to_string(),
+            client_id: client_id.to_string(),
+            client_secret: client_secret.to_string(),
+            authority_host: AUTHORITY_HOST.to_string(),
+            insecure_registry: false,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Initialize an `AcrProvider` for the service principal set in the
+    /// environment, by `AZURE_TENANT_ID`, `AZURE_CLIENT_ID` and
+    /// `AZURE_CLIENT_SECRET`.
+    pub fn from_env() -> Result<Self> {
+        let var = |name: &str| {
+            std::env::var(name).map_err(|_| provider_error(format!("{} is not set", name)))
+        };
+        Ok(Self::from_service_principal(
+            &var("AZURE_TENANT_ID")?,
+            &var("AZURE_CLIENT_ID")?,
+            &var("AZURE_CLIENT_SECRET")?,
+        ))
+    }
+
+    /// Set the AAD endpoint, e.g. for national clouds
+    /// (default: `https://login.microsoftonline.com`).
+    pub fn authority_host(mut self, authority_host: &str) -> Self {
+        self.authority_host = authority_host.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Whether to exchange tokens with the registry over an insecure HTTP
+    /// connection.
+    pub fn insecure_registry(mut self, insecure: bool) -> Self {
+        self.insecure_registry = insecure;
+        self
+    }
+
+    /// Set the HTTP client to request tokens with, e.g. to use the proxy
+    /// and TLS settings of the registry client.
+    ///
+    /// When this provider is selected automatically, it uses the client
+    /// built by `Config`.
+    pub fn http_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    async fn get_credentials(&self, registry: &str) -> Result<ProvidedCredentials> {
+        let url = format!(
+            "{}/{}/oauth2/v2.0/token",
+            self.authority_host, self.tenant_id
+        );
+        trace!("Requesting AAD token for client {}", self.client_id);
+        let aad_token: AadToken = self
+            .post(
+                &url,
+                &[
+                    ("grant_type", "client_credentials"),
+                    ("client_id", &self.client_id),
+                    ("client_secret", &self.client_secret),
+                    ("scope", AAD_SCOPE),
+                ],
+            )
+            .await?;
+
+        let scheme = if self.insecure_registry {
+            "http"
+        } else {
+            "https"
+        };
+        let url = format!("{}://{}/oauth2/exchange", scheme, registry);
+        trace!("Exchanging AAD token at {}", url);
+        let exchanged: ExchangedToken = self
+            .post(
+                &url,
+                &[
+                    ("grant_type", "access_token"),
+                    ("service", registry),
+                    ("tenant", &self.tenant_id),
+                    ("access_token", &aad_token.access_token),
+                ],
+            )
+            .await?;
+
+        let expires_at = token_expiry(&exchanged.refresh_token)
+            .unwrap_or_else(|| SystemTime::now() + REFRESH_TOKEN_LIFETIME);
+        Ok(ProvidedCredentials {
+            username: USERNAME.to_string(),
+            password: exchanged.refresh_token,
+            expires_at: Some(expires_at),
+        })
+    }
+
+    async fn post<T: serde::de::DeserializeOwned>(
+        &self,
+        url: &str,
+        form: &[(&str, &str)],
+    ) -> Result<T> {
+        let res = self.client.post(url).form(form).send().await?;
+        let status = res.status();
+        if !status.is_success() {
+            let message = format!("POST {} failed with status {}", url, status);
+            return Err(provider_error(message));
+        }
+        res.json().await.map_err(Into::into)
+    }
+}
+
+impl CredentialsProvider for AcrProvider {
+    fn credentials<'a>(&'a self, registry: &'a str) -> BoxFuture<'a, Result<ProvidedCredentials>> {
+        Box::pin(self.get_credentials(registry))
+    }
+}
+
+/// Whether `registry` is an Azure Container Registry.
+pub(crate) fn is_acr_registry(registry: &str) -> bool {
+    let host = registry.split(':').next().unwrap_or_default();
+    host.ends_with(".azurecr.io")
+}
+
+/// Read the expiry of a JWT, without verifying it.
+fn token_expiry(token: &str) -> Option<SystemTime> {
+    let payload = token.split('.').nth(1)?;
+    let payload = base64::decode_config(payload, base64::URL_SAFE_NO_PAD).ok()?;
+    let claims: Claims = serde_json::from_slice(&payload).ok()?;
+    Some(UNIX_EPOCH + Duration::from_secs(claims.exp?))
+}
+
+fn provider_error(message: String) -> Error {
+    Error::CredentialsProvider("ACR".to_string(), message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn client_secret_is_not_debugged() {
+        let provider = AcrProvider::from_service_principal("tenant", "client", "hunter2");
+        let debugged = format!("{:?}", provider);
+        assert!(debugged.contains("client"));
+        assert!(!debugged.contains("hunter2"));
+    }
+
+    #[test]
+    fn token_expiry_is_read() {
+        let payload = base64::encode_config(r#"{"exp":1700000000}"#, base64::URL_SAFE_NO_PAD);
+        let token = format!("header.{}.signature", payload);
+        assert_eq!(
+            token_expiry(&token),
+            Some(UNIX_EPOCH + Duration::from_secs(1_700_000_000))
+        );
+        assert_eq!(token_expiry("opaque"), None);
+    }
+}
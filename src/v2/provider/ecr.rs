@@ -0,0 +1,128 @@
+use super::{CredentialsProvider, ProvidedCredentials};
+use crate::errors::{Error, Result};
+use futures::future::BoxFuture;
+use once_cell::sync::Lazy;
+use std::convert::TryFrom;
+use std::time::{Duration, SystemTime};
+
+/// Validity of ECR authorization tokens, if not returned.
+const TOKEN_LIFETIME: Duration = Duration::from_secs(12 * 60 * 60);
+
+/// Provider of credentials for Amazon ECR registries.
+///
+/// Authorization tokens are obtained with `GetAuthorizationToken`, in the
+/// region of the registry host (e.g.
+/// `123456789012.dkr.ecr.eu-west-1.amazonaws.com`). They grant access to
+/// all the registries the AWS principal has access to. AWS credentials are
+/// loaded as by the AWS SDK, from the environment, profiles or instance
+/// metadata.
+///
+/// This provider is selected automatically for ECR registries.
+#[derive(Debug, Clone, Default)]
+pub struct EcrProvider {
+    sdk_config: Option<aws_config::SdkConfig>,
+}
+
+impl EcrProvider {
+    /// Initialize an `EcrProvider`, loading the default AWS configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Initialize an `EcrProvider` with an AWS configuration, whose region
+    /// is overridden by the one of registries.
+    pub fn with_config(sdk_config: aws_config::SdkConfig) -> Self {
+        Self {
+            sdk_config: Some(sdk_config),
+        }
+    }
+
+    async fn get_credentials(&self, registry: &str) -> Result<ProvidedCredentials> {
+        let (account, region) = parse_registry(registry)
+            .ok_or_else(|| provider_error(format!("{} is not an ECR registry", registry)))?;
+        let region = aws_config::Region::new(region);
+        let sdk_config = match &self.sdk_config {
+            Some(sdk_config) => sdk_config.to_builder().region(region).build(),
+            None => {
+                aws_config::defaults(aws_config::BehaviorVersion::latest())
+                    .region(region)
+                    .load()
+                    .await
+            }
+        };
+
+        trace!("Requesting ECR authorization token for account {}", account);
+        let output = aws_sdk_ecr::Client::new(&sdk_config)
+            .get_authorization_token()
+            .send()
+            .await
+            .map_err(|e| provider_error(aws_sdk_ecr::error::DisplayErrorContext(e).to_string()))?;
+        let data = output
+            .authorization_data()
+            .first()
+            .ok_or_else(|| provider_error("no authorization data".to_string()))?;
+        let token = data
+            .authorization_token()
+            .ok_or_else(|| provider_error("no authorization token".to_string()))?;
+
+        // Tokens are base64 encoded `AWS:<password>` pairs.
+        let decoded = String::from_utf8(base64::decode(token)?)?;
+        let (username, password) = decoded
+            .split_once(':')
+            .ok_or_else(|| provider_error("invalid authorization token".to_string()))?;
+        let expires_at = data
+            .expires_at()
+            .and_then(|t| SystemTime::try_from(*t).ok())
+            .unwrap_or_else(|| SystemTime::now() + TOKEN_LIFETIME);
+        Ok(ProvidedCredentials {
+            username: username.to_string(),
+            password: password.to_string(),
+            expires_at: Some(expires_at),
+        })
+    }
+}
+
+impl CredentialsProvider for EcrProvider {
+    fn credentials<'a>(&'a self, registry: &'a str) -> BoxFuture<'a, Result<ProvidedCredentials>> {
+        Box::pin(self.get_credentials(registry))
+    }
+}
+
+/// Whether `registry` is an ECR registry.
+pub(crate) fn is_ecr_registry(registry: &str) -> bool {
+    parse_registry(registry).is_some()
+}
+
+/// Split an ECR registry host into its account and region.
+fn parse_registry(registry: &str) -> Option<(String, String)> {
+    static RE: Lazy<regex::Regex> = Lazy::new(|| {
+        regex::Regex::new(
+            r"^(?P<account>[0-9]{12})\.dkr\.ecr(-fips)?\.(?P<region>[a-z0-9-]+)\.amazonaws\.com(\.cn)?(:443)?$",
+        )
+        .expect("this static regex is valid")
+    });
+    let captures = RE.captures(registry)?;
+    Some((
+        captures["account"].to_string(),
+        captures["region"].to_string(),
+    ))
+}
+
+fn provider_error(message: String) -> Error {
+    Error::CredentialsProvider("ECR".to_string(), message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("123456789012.dkr.ecr.eu-west-1.amazonaws.com" => Some(("123456789012".into(), "eu-west-1".into())); "regular")]
+    #[test_case("123456789012.dkr.ecr-fips.us-east-1.amazonaws.com" => Some(("123456789012".into(), "us-east-1".into())); "fips")]
+    #[test_case("123456789012.dkr.ecr.cn-north-1.amazonaws.com.cn" => Some(("123456789012".into(), "cn-north-1".into())); "china")]
+    #[test_case("public.ecr.aws" => None; "public")]
+    #[test_case("quay.io" => None; "other")]
+    fn ecr_registries_are_parsed(registry: &str) -> Option<(String, String)> {
+        parse_registry(registry)
+    }
+}
@@ -0,0 +1,107 @@
+use super::{CredentialsProvider, ProvidedCredentials};
+use crate::errors::{Error, Result};
+use futures::future::BoxFuture;
+use gcp_auth::TokenProvider;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::SystemTime;
+use tokio::sync::OnceCell;
+
+/// OAuth2 scope of access tokens.
+const SCOPE: &str = "https://www.googleapis.com/auth/cloud-platform";
+
+/// Username of basic authentication with access tokens.
+const USERNAME: &str = "oauth2accesstoken";
+
+/// Provider of credentials for Google Artifact Registry and Container
+/// Registry.
+///
+/// Access tokens are used as passwords of the `oauth2accesstoken` user.
+/// They are minted with Application Default Credentials by default, or with
+/// a service account key.
+///
+/// This provider is selected automatically for `gcr.io` and `pkg.dev`
+/// registries.
+#[derive(Default)]
+pub struct GcpProvider {
+    token_provider: OnceCell<Arc<dyn TokenProvider>>,
+}
+
+impl std::fmt::Debug for GcpProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GcpProvider").finish_non_exhaustive()
+    }
+}
+
+impl GcpProvider {
+    /// Initialize a `GcpProvider` with Application Default Credentials.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Initialize a `GcpProvider` with a service account key, in JSON.
+    pub fn from_service_account_key(key: &str) -> Result<Self> {
+        let account = gcp_auth::CustomServiceAccount::from_json(key).map_err(provider_error)?;
+        Ok(Self::with_token_provider(Arc::new(account)))
+    }
+
+    /// Initialize a `GcpProvider` with a service account key file.
+    pub fn from_service_account_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let account = gcp_auth::CustomServiceAccount::from_file(path).map_err(provider_error)?;
+        Ok(Self::with_token_provider(Arc::new(account)))
+    }
+
+    fn with_token_provider(token_provider: Arc<dyn TokenProvider>) -> Self {
+        Self {
+            token_provider: OnceCell::new_with(Some(token_provider)),
+        }
+    }
+
+    async fn get_credentials(&self) -> Result<ProvidedCredentials> {
+        let token_provider = self
+            .token_provider
+            .get_or_try_init(gcp_auth::provider)
+            .await
+            .map_err(provider_error)?;
+        let token = token_provider
+            .token(&[SCOPE])
+            .await
+            .map_err(provider_error)?;
+        Ok(ProvidedCredentials {
+            username: USERNAME.to_string(),
+            password: token.as_str().to_string(),
+            expires_at: Some(SystemTime::from(token.expires_at())),
+        })
+    }
+}
+
+impl CredentialsProvider for GcpProvider {
+    fn credentials<'a>(&'a self, _registry: &'a str) -> BoxFuture<'a, Result<ProvidedCredentials>> {
+        Box::pin(self.get_credentials())
+    }
+}
+
+/// Whether `registry` is a Google Artifact Registry or Container Registry.
+pub(crate) fn is_gcp_registry(registry: &str) -> bool {
+    let host = registry.split(':').next().unwrap_or_default();
+    host == "gcr.io" || host.ends_with(".gcr.io") || host.ends_with(".pkg.dev")
+}
+
+fn provider_error(e: gcp_auth::Error) -> Error {
+    Error::CredentialsProvider("GCP".to_string(), e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("gcr.io" => true; "gcr")]
+    #[test_case("eu.gcr.io" => true; "regional gcr")]
+    #[test_case("europe-west1-docker.pkg.dev" => true; "artifact registry")]
+    #[test_case("quay.io" => false; "other")]
+    #[test_case("notgcr.io" => false; "lookalike")]
+    fn gcp_registries_are_detected(registry: &str) -> bool {
+        is_gcp_registry(registry)
+    }
+}
@@ -0,0 +1,79 @@
+//! Credentials providers, for registries whose credentials are obtained
+//! on demand (e.g. from a cloud provider) rather than configured.
+//!
+//! Providers are set with `Config::credentials_provider`. Some of them are
+//! selected automatically for the registries they serve, when their feature
+//! is enabled.
+
+use crate::errors::Result;
+use futures::future::BoxFuture;
+use std::sync::Arc;
+use std::time::Duration;
+use web_time::SystemTime;
+
+#[cfg(feature = "acr")]
+mod acr;
+#[cfg(feature = "acr")]
+pub use self::acr::AcrProvider;
+
+#[cfg(feature = "ecr")]
+mod ecr;
+#[cfg(feature = "ecr")]
+pub use self::ecr::EcrProvider;
+
+#[cfg(feature = "gcp")]
+mod gcp;
+#[cfg(feature = "gcp")]
+pub use self::gcp::GcpProvider;
+
+/// How long before their expiry provided credentials get renewed.
+const RENEWAL_MARGIN: Duration = Duration::from_secs(60);
+
+/// A source of registry credentials.
+pub trait CredentialsProvider: std::fmt::Debug + Send + Sync {
+    /// Get credentials for `registry` (host, with an optional port).
+    fn credentials<'a>(&'a self, registry: &'a str) -> BoxFuture<'a, Result<ProvidedCredentials>>;
+}
+
+/// Credentials obtained from a `CredentialsProvider`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProvidedCredentials {
+    pub username: String,
+    pub password: String,
+    /// Time after which the credentials are rejected, if any.
+    pub expires_at: Option<SystemTime>,
+}
+
+impl ProvidedCredentials {
+    /// Whether the credentials must be renewed before being used.
+    pub(crate) fn is_expiring(&self) -> bool {
+        match self.expires_at {
+            Some(expires_at) => SystemTime::now() + RENEWAL_MARGIN >= expires_at,
+            None => false,
+        }
+    }
+}
+
+/// Get the provider selected automatically for `registry`, if any, which
+/// sends its own requests with `client`.
+#[allow(unused_variables)]
+pub(crate) fn default_provider(
+    registry: &str,
+    client: &reqwest::Client,
+) -> Option<Arc<dyn CredentialsProvider>> {
+    #[cfg(feature = "acr")]
+    if acr::is_acr_registry(registry) {
+        if let Ok(provider) = AcrProvider::from_env() {
+            return Some(Arc::new(provider.http_client(client.clone())));
+        }
+    }
+    #[cfg(feature = "ecr")]
+    if ecr::is_ecr_registry(registry) {
+        return Some(Arc::new(EcrProvider::new()));
+    }
+    #[cfg(feature = "gcp")]
+    if gcp::is_gcp_registry(registry) {
+        return Some(Arc::new(GcpProvider::new()));
+    }
+    None
+}
@@ -0,0 +1,122 @@
+use crate::errors::{Error, Result};
+use crate::v2::*;
+use reqwest::header::HeaderMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Repository whose manifest can be probed for the rate limit of Docker Hub,
+/// without consuming it.
+const PROBE_REPOSITORY: &str = "ratelimitpreview/test";
+
+/// Last pull rate limit reported by a registry, shared by clones.
+pub(crate) type RateLimitCache = Arc<Mutex<Option<RateLimitInfo>>>;
+
+/// Pull rate limit reported by a registry (e.g. Docker Hub) in the
+/// `RateLimit-Limit` and `RateLimit-Remaining` headers.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RateLimitInfo {
+    /// Number of pulls allowed per time window.
+    pub limit: Option<u64>,
+    /// Number of pulls left in the time window.
+    pub remaining: Option<u64>,
+    /// Duration of the time window.
+    pub window: Option<Duration>,
+    /// What the limit applies to, from `Docker-RateLimit-Source`: the client
+    /// IP address for anonymous pulls, or the account ID.
+    pub source: Option<String>,
+}
+
+impl RateLimitInfo {
+    /// Parse the rate limit headers of a response, if it has any.
+    pub(crate) fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let value = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+        // Quotas look like `100;w=21600`, with the window duration in seconds.
+        let limit = value("ratelimit-limit");
+        let remaining = value("ratelimit-remaining");
+        if limit.is_none() && remaining.is_none() {
+            return None;
+        }
+        let quota = |v: Option<&str>| v?.split(';').next()?.trim().parse().ok();
+        let window = limit.or(remaining).and_then(|v| {
+            v.split(';')
+                .filter_map(|p| p.trim().strip_prefix("w="))
+                .find_map(|w| w.parse().ok())
+                .map(Duration::from_secs)
+        });
+        Some(RateLimitInfo {
+            limit: quota(limit),
+            remaining: quota(remaining),
+            window,
+            source: value("docker-ratelimit-source").map(str::to_string),
+        })
+    }
+}
+
+impl Client {
+    /// Last pull rate limit reported by the registry, if any.
+    ///
+    /// This is updated by every response carrying rate limit headers, to
+    /// requests sent by this client or one of its clones.
+    pub fn rate_limit_info(&self) -> Option<RateLimitInfo> {
+        self.rate_limit.lock().unwrap().clone()
+    }
+
+    /// Query the pull rate limit of Docker Hub for the credentials of the
+    /// client, or anonymous pulls.
+    ///
+    /// This sends a `HEAD` request for a manifest of `ratelimitpreview/test`,
+    /// which does not count as a pull. Registries without rate limit report
+    /// an empty `RateLimitInfo`.
+    pub async fn probe_rate_limit(&self) -> Result<RateLimitInfo> {
+        self.deadline()
+            .run(async move {
+                let url = format!("{}/v2/{}/manifests/latest", self.base_url, PROBE_REPOSITORY);
+                let url = Url::parse(&url)?;
+                let scope = format!("repository:{}:pull", PROBE_REPOSITORY);
+
+                let res = self
+                    .send_with_scope(&[&scope], |client| {
+                        client.build_reqwest(Method::HEAD, url.clone())
+                    })
+                    .await?;
+
+                let status = res.status();
+                trace!("HEAD '{}' status: {:?}", res.url(), status);
+                match status {
+                    s if s.is_success() => {}
+                    s if s.is_client_error() => return Err(Error::Client { status }),
+                    s if s.is_server_error() => return Err(Error::Server { status }),
+                    _ => return Err(Error::UnexpectedHttpStatus(status)),
+                }
+                Ok(RateLimitInfo::from_headers(res.headers()).unwrap_or_default())
+            })
+            .await
+    }
+
+    /// Remember the rate limit reported in the headers of a response.
+    pub(crate) fn record_rate_limit(&self, headers: &HeaderMap) {
+        if let Some(info) = RateLimitInfo::from_headers(headers) {
+            *self.rate_limit.lock().unwrap() = Some(info);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rate_limit_headers_parse() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(RateLimitInfo::from_headers(&headers), None);
+
+        headers.insert("ratelimit-limit", "100;w=21600".parse().unwrap());
+        headers.insert("ratelimit-remaining", "76;w=21600".parse().unwrap());
+        headers.insert("docker-ratelimit-source", "192.0.2.1".parse().unwrap());
+        let info = RateLimitInfo::from_headers(&headers).unwrap();
+        assert_eq!(info.limit, Some(100));
+        assert_eq!(info.remaining, Some(76));
+        assert_eq!(info.window, Some(Duration::from_secs(21600)));
+        assert_eq!(info.source.as_deref(), Some("192.0.2.1"));
+    }
+}
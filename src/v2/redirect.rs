@@ -0,0 +1,118 @@
+use crate::errors::{Error, Result};
+use crate::v2::*;
+use reqwest::{header, Response};
+
+/// Maximum number of redirects followed in a row, as `reqwest` does.
+const MAX_REDIRECTS: usize = 10;
+
+/// Redirect policy of the HTTP client, when `Config::redirect_auth_hosts`
+/// is set.
+///
+/// `reqwest` strips the authorization of requests redirected to another
+/// host, so redirects to `hosts` are not followed here, but by
+/// `Client::follow_redirects`, unless they downgrade to plain HTTP.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn redirect_policy(hosts: Vec<String>) -> reqwest::redirect::Policy {
+    reqwest::redirect::Policy::custom(move |attempt| {
+        let cross_host = attempt.previous().last().is_some_and(|previous| {
+            previous.host_str() != attempt.url().host_str()
+                && keeps_auth(&hosts, previous, attempt.url())
+        });
+        if attempt.previous().len() > MAX_REDIRECTS {
+            attempt.error("too many redirects")
+        } else if cross_host {
+            attempt.stop()
+        } else {
+            attempt.follow()
+        }
+    })
+}
+
+impl Client {
+    /// Follow the redirects of `res` to hosts keeping the authorization,
+    /// sending the method and headers of `request` along.
+    pub(crate) async fn follow_redirects(
+        &self,
+        request: Option<&reqwest::Request>,
+        mut res: Response,
+    ) -> Result<Response> {
+        let request = match request {
+            Some(request) => request,
+            None => return Ok(res),
+        };
+        for _ in 0..MAX_REDIRECTS {
+            if !res.status().is_redirection() {
+                break;
+            }
+            let location = res
+                .headers()
+                .get(header::LOCATION)
+                .and_then(|l| l.to_str().ok())
+                .and_then(|l| res.url().join(l).ok());
+            let location = match location {
+                Some(location) if keeps_auth(&self.redirect_auth_hosts, res.url(), &location) => {
+                    location
+                }
+                _ => break,
+            };
+            // Bodies may be streams, which cannot be sent again.
+            if request.body().is_some() {
+                debug!(
+                    "not following redirect of request with a body to {}",
+                    location
+                );
+                return Err(Error::UnexpectedHttpStatus(res.status()));
+            }
+            trace!("following redirect to {}", location);
+            let mut next = reqwest::Request::new(request.method().clone(), location);
+            *next.headers_mut() = request.headers().clone();
+            self.add_default_headers(next.headers_mut());
+            res = match &self.transport {
+                Some(transport) => transport.execute(next).await?,
+                None => self.client.execute(next).await?,
+            };
+        }
+        Ok(res)
+    }
+}
+
+/// Whether requests redirected from `previous` to `url` keep their
+/// authorization: `url` must be on one of `hosts`, and not downgrade HTTPS
+/// to plain HTTP.
+fn keeps_auth(hosts: &[String], previous: &Url, url: &Url) -> bool {
+    let allowed = url.host_str().is_some_and(|h| hosts.iter().any(|a| a == h));
+    allowed && (url.scheme() == "https" || url.scheme() == previous.scheme())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn auth_is_not_kept_on_downgrades() {
+        let hosts = vec!["cdn.example.com".to_string()];
+        let url = |s: &str| Url::parse(s).unwrap();
+        let registry = url("https://registry.example.com/v2/");
+        assert!(keeps_auth(
+            &hosts,
+            &registry,
+            &url("https://cdn.example.com/blob")
+        ));
+        assert!(!keeps_auth(
+            &hosts,
+            &registry,
+            &url("http://cdn.example.com/blob")
+        ));
+        assert!(!keeps_auth(
+            &hosts,
+            &registry,
+            &url("https://other.example.com/blob")
+        ));
+        let insecure = url("http://registry.example.com/v2/");
+        assert!(keeps_auth(
+            &hosts,
+            &insecure,
+            &url("http://cdn.example.com/blob")
+        ));
+    }
+}
@@ -0,0 +1,283 @@
+use crate::errors::Result;
+use crate::v2::manifest::{
+    canonical_json, check_pushed_digest, ManifestBytes, ManifestError, OciManifest,
+};
+use crate::v2::*;
+use reqwest::{header, StatusCode};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Media type of OCI image indexes, in which referrers are listed.
+const OCI_IMAGE_INDEX: &str = "application/vnd.oci.image.index.v1+json";
+
+/// A manifest referring to another one, e.g. a signature or an SBOM.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Referrer {
+    #[serde(rename = "mediaType")]
+    pub media_type: String,
+    pub digest: String,
+    pub size: u64,
+    #[serde(rename = "artifactType", default)]
+    pub artifact_type: Option<String>,
+    #[serde(default)]
+    pub annotations: HashMap<String, String>,
+}
+
+/// Image index listing referrers, as returned by the Referrers API.
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct ReferrersIndex {
+    #[serde(rename = "schemaVersion", default)]
+    schema_version: u16,
+    #[serde(rename = "mediaType", default)]
+    media_type: String,
+    #[serde(default)]
+    manifests: Vec<Referrer>,
+}
+
+/// Filter of referrers, see `Client::get_referrers_filtered`.
+///
+/// Referrers match if they have the artifact type, when set, and all the
+/// annotations.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReferrerFilter {
+    artifact_type: Option<String>,
+    /// Annotations to look for, with their value if it matters.
+    annotations: Vec<(String, Option<String>)>,
+}
+
+impl ReferrerFilter {
+    /// Initialize a `ReferrerFilter` matching all referrers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only match referrers of this artifact type, e.g.
+    /// `application/vnd.dev.cosign.artifact.sig.v1+json` for signatures of
+    /// cosign.
+    pub fn artifact_type(mut self, artifact_type: &str) -> Self {
+        self.artifact_type = Some(artifact_type.to_string());
+        self
+    }
+
+    /// Only match referrers annotated with `key` set to `value`.
+    pub fn annotation(mut self, key: &str, value: &str) -> Self {
+        self.annotations
+            .push((key.to_string(), Some(value.to_string())));
+        self
+    }
+
+    /// Only match referrers annotated with `key`, whatever its value.
+    pub fn has_annotation(mut self, key: &str) -> Self {
+        self.annotations.push((key.to_string(), None));
+        self
+    }
+
+    /// Whether `referrer` matches the filter.
+    pub fn matches(&self, referrer: &Referrer) -> bool {
+        self.matches_artifact_type(referrer) && self.matches_annotations(referrer)
+    }
+
+    fn matches_artifact_type(&self, referrer: &Referrer) -> bool {
+        match &self.artifact_type {
+            None => true,
+            Some(t) => referrer.artifact_type.as_ref() == Some(t),
+        }
+    }
+
+    fn matches_annotations(&self, referrer: &Referrer) -> bool {
+        self.annotations.iter().all(
+            |(key, value)| match (referrer.annotations.get(key), value) {
+                (Some(got), Some(value)) => got == value,
+                (got, None) => got.is_some(),
+                (None, Some(_)) => false,
+            },
+        )
+    }
+}
+
+impl Client {
+    /// List manifests referring to manifest `digest` (OCI 1.1 Referrers API).
+    ///
+    /// If `artifact_type` is set, only referrers with this artifact type are
+    /// returned. Registries without the Referrers API are handled through the
+    /// tag schema fallback, where referrers are listed in an index tagged
+    /// `<algorithm>-<hex>`.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(registry = %self.base_url, repository = name, digest = %digest))
+    )]
+    pub async fn get_referrers(
+        &self,
+        name: &str,
+        digest: &str,
+        artifact_type: Option<&str>,
+    ) -> Result<Vec<Referrer>> {
+        let filter = match artifact_type {
+            Some(artifact_type) => ReferrerFilter::new().artifact_type(artifact_type),
+            None => ReferrerFilter::new(),
+        };
+        self.get_referrers_filtered(name, digest, &filter).await
+    }
+
+    /// List manifests referring to manifest `digest` which match `filter`.
+    ///
+    /// The artifact type is filtered by the registry, unless it does not
+    /// report so with the `OCI-Filters-Applied` header, and annotations are
+    /// filtered here. See `get_referrers`.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(registry = %self.base_url, repository = name, digest = %digest))
+    )]
+    pub async fn get_referrers_filtered(
+        &self,
+        name: &str,
+        digest: &str,
+        filter: &ReferrerFilter,
+    ) -> Result<Vec<Referrer>> {
+        self.deadline()
+            .run(async move {
+                ContentDigest::try_new(digest)?;
+                let url = format!("{}/v2/{}/referrers/{}", self.base_url, name, digest);
+                let mut url = Url::parse(&url)?;
+                if let Some(artifact_type) = &filter.artifact_type {
+                    url.query_pairs_mut()
+                        .append_pair("artifactType", artifact_type);
+                }
+
+                // Pages may be filtered out entirely, so the fallback depends on
+                // whether any page was fetched rather than on the referrers.
+                let first_page = AtomicBool::new(true);
+                let mut referrers = Vec::new();
+                let mut pages =
+                    self.paginate(url, OCI_IMAGE_INDEX, |headers, index: ReferrersIndex| {
+                        first_page.store(false, Ordering::Relaxed);
+                        // Registries may ignore the filter, which they report via this header.
+                        let filtered = headers
+                            .get("oci-filters-applied")
+                            .and_then(|v| v.to_str().ok())
+                            .is_some_and(|v| v.split(',').any(|f| f.trim() == "artifactType"));
+                        index
+                            .manifests
+                            .into_iter()
+                            .filter(|r| filtered || filter.matches_artifact_type(r))
+                            .filter(|r| filter.matches_annotations(r))
+                            .collect()
+                    });
+                while let Some(referrer) = pages.next().await {
+                    match referrer {
+                        Ok(referrer) => referrers.push(referrer),
+                        Err(e)
+                            if first_page.load(Ordering::Relaxed)
+                                && e.status() == Some(StatusCode::NOT_FOUND) =>
+                        {
+                            debug!("referrers API unavailable, using tag schema fallback");
+                            return self.get_referrers_tag(name, digest, filter).await;
+                        }
+                        Err(e) => return Err(e),
+                    }
+                }
+                Ok(referrers)
+            })
+            .await
+    }
+
+    /// Upload a manifest referring to another one through its `subject`,
+    /// e.g. a signature or an SBOM of an image, by digest.
+    ///
+    /// Registries which do not report handling the subject (with the
+    /// `OCI-Subject` header) are assumed to lack the Referrers API, and the
+    /// manifest is added to the index of the tag schema fallback, so that
+    /// `get_referrers` lists it either way.
+    ///
+    /// Fails with `Error::DigestMismatch` if the digest of `manifest` is not
+    /// the one of its body, before uploading it, or if the registry reports
+    /// another digest.
+    pub async fn put_referrer(&self, name: &str, manifest: &ManifestBytes) -> Result<String> {
+        self.deadline()
+            .run(async move {
+                let oci = serde_json::from_slice::<OciManifest>(&manifest.body)?;
+                let subject = oci.subject.as_ref().ok_or(ManifestError::MissingSubject)?;
+                let computed = sha256_digest(&manifest.body);
+                if computed != manifest.digest {
+                    return Err(Error::DigestMismatch {
+                        expected: manifest.digest.clone(),
+                        got: computed,
+                    });
+                }
+                let (digest, headers) = self
+                    .push_manifest(
+                        name,
+                        &manifest.digest,
+                        &manifest.media_type.to_string(),
+                        manifest.body.clone(),
+                    )
+                    .await?;
+                let digest = check_pushed_digest(manifest, digest)?;
+                if headers.contains_key("oci-subject") {
+                    return Ok(digest);
+                }
+
+                debug!("registry did not handle subject, updating tag schema fallback");
+                let mut manifests = self
+                    .get_referrers_tag(name, &subject.digest, &ReferrerFilter::new())
+                    .await?;
+                if !manifests.iter().any(|r| r.digest == digest) {
+                    manifests.push(Referrer {
+                        media_type: manifest.media_type.to_string(),
+                        digest: digest.clone(),
+                        size: manifest.body.len() as u64,
+                        artifact_type: oci.artifact_type().map(str::to_string),
+                        annotations: oci.annotations.clone(),
+                    });
+                }
+                let index = ReferrersIndex {
+                    schema_version: 2,
+                    media_type: OCI_IMAGE_INDEX.to_string(),
+                    manifests,
+                };
+                let tag = subject.digest.replacen(':', "-", 1);
+                let index =
+                    ManifestBytes::from_raw(MediaTypes::OciImageIndex, canonical_json(&index)?);
+                self.put_manifest_bytes(name, &tag, &index).await?;
+                Ok(digest)
+            })
+            .await
+    }
+
+    /// List referrers through the tag schema fallback.
+    async fn get_referrers_tag(
+        &self,
+        name: &str,
+        digest: &str,
+        filter: &ReferrerFilter,
+    ) -> Result<Vec<Referrer>> {
+        let tag = digest.replacen(':', "-", 1);
+        let url = {
+            let ep = format!("{}/v2/{}/manifests/{}", self.base_url, name, tag);
+            Url::parse(&ep)?
+        };
+
+        let res = self
+            .send(|client| {
+                client
+                    .build_reqwest(Method::GET, url.clone())
+                    .header(header::ACCEPT, OCI_IMAGE_INDEX)
+            })
+            .await?;
+
+        let status = res.status();
+        trace!("GET '{}' status: {:?}", res.url(), status);
+        match status {
+            StatusCode::OK => {}
+            StatusCode::NOT_FOUND => return Ok(Vec::new()),
+            _ => return Err(status_error(res).await),
+        }
+
+        let index = res.json::<ReferrersIndex>().await?;
+        Ok(index
+            .manifests
+            .into_iter()
+            .filter(|r| filter.matches(r))
+            .collect())
+    }
+}
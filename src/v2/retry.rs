@@ -0,0 +1,342 @@
+use crate::errors::{Error, Result};
+use crate::v2::metrics::RequestMetrics;
+use crate::v2::*;
+use reqwest::{header, RequestBuilder, Response};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+use web_time::{Instant, SystemTime, UNIX_EPOCH};
+
+#[cfg(target_arch = "wasm32")]
+use gloo_timers::future::sleep;
+#[cfg(not(target_arch = "wasm32"))]
+use tokio::time::sleep;
+
+/// Policy for retrying requests which failed because of transient errors.
+///
+/// By default, requests are attempted once. See `Config::retry_policy`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    backoff_base: Duration,
+    max_backoff: Duration,
+    jitter: bool,
+    retry_server_errors: bool,
+    retry_connection_errors: bool,
+    retry_timeouts: bool,
+    rate_limit_budget: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 1,
+            backoff_base: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(10),
+            jitter: true,
+            retry_server_errors: true,
+            retry_connection_errors: true,
+            retry_timeouts: true,
+            rate_limit_budget: Duration::ZERO,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Initialize `RetryPolicy` with default values (no retries).
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set how many times a request is attempted in total (default: 1).
+    pub fn max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// Set the delay before the first retry, doubled on each following one
+    /// (default: 100ms).
+    pub fn backoff_base(mut self, backoff_base: Duration) -> Self {
+        self.backoff_base = backoff_base;
+        self
+    }
+
+    /// Set the upper bound of the delay between attempts (default: 10s).
+    pub fn max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    /// Set whether delays are randomized, down to half their duration, so
+    /// that clients do not retry in lockstep (default: true).
+    pub fn jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Set whether `5xx` responses are retried (default: true).
+    pub fn retry_server_errors(mut self, retry: bool) -> Self {
+        self.retry_server_errors = retry;
+        self
+    }
+
+    /// Set whether requests failing to connect to the server are retried
+    /// (default: true).
+    pub fn retry_connection_errors(mut self, retry: bool) -> Self {
+        self.retry_connection_errors = retry;
+        self
+    }
+
+    /// Set whether timed out requests are retried (default: true).
+    pub fn retry_timeouts(mut self, retry: bool) -> Self {
+        self.retry_timeouts = retry;
+        self
+    }
+
+    /// Set how long a request may wait in total for rate limits to be
+    /// lifted, when registries answer `429 Too Many Requests` (default: zero).
+    ///
+    /// Waits follow `Retry-After` headers, or the backoff delay otherwise.
+    /// Requests which cannot wait fail with `Error::RateLimited`.
+    pub fn rate_limit_budget(mut self, budget: Duration) -> Self {
+        self.rate_limit_budget = budget;
+        self
+    }
+
+    fn should_retry(&self, outcome: &Result<Response>) -> bool {
+        match outcome {
+            Ok(res) => self.retry_server_errors && res.status().is_server_error(),
+            Err(Error::Reqwest(e)) if e.is_timeout() => self.retry_timeouts,
+            Err(Error::Reqwest(e)) => self.retry_connection_errors && is_connect(e),
+            Err(_) => false,
+        }
+    }
+
+    /// Delay before attempt `attempt` (starting at 1 for the first retry).
+    fn backoff(&self, attempt: u32) -> Duration {
+        let exp = self
+            .backoff_base
+            .checked_mul(1 << (attempt - 1).min(31))
+            .unwrap_or(self.max_backoff);
+        let delay = exp.min(self.max_backoff);
+        if !self.jitter {
+            return delay;
+        }
+        let random = RandomState::new().build_hasher().finish();
+        let half = delay / 2;
+        half + half.mul_f64((random % 1000) as f64 / 1000.0)
+    }
+}
+
+/// Rate limiting state reported by a registry along a `429` response.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RateLimit {
+    /// Delay after which requests may be sent again, from `Retry-After`.
+    pub retry_after: Option<Duration>,
+    /// Number of requests allowed per time window, from `RateLimit-Limit`.
+    pub limit: Option<u64>,
+    /// Number of requests left in the time window, from `RateLimit-Remaining`.
+    pub remaining: Option<u64>,
+}
+
+impl RateLimit {
+    fn from_headers(headers: &header::HeaderMap) -> Self {
+        let value = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+        // Quotas look like `100;w=21600`, with the window duration.
+        let quota = |name: &str| value(name)?.split(';').next()?.trim().parse().ok();
+        RateLimit {
+            retry_after: value("retry-after").and_then(parse_retry_after),
+            limit: quota("ratelimit-limit"),
+            remaining: quota("ratelimit-remaining"),
+        }
+    }
+}
+
+/// Parse a `Retry-After` header, either a number of seconds or a date.
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    if let Ok(secs) = value.trim().parse() {
+        return Some(Duration::from_secs(secs));
+    }
+    // `httpdate` returns a `std` time, which browsers cannot compare with
+    // the current time.
+    let date = httpdate::parse_http_date(value.trim()).ok()?;
+    let date = date.duration_since(std::time::UNIX_EPOCH).ok()?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?;
+    Some(date.saturating_sub(now))
+}
+
+impl Client {
+    /// Send a request, retrying it according to the client retry policy.
+    ///
+    /// Requests whose body cannot be cloned are attempted only once.
+    pub(crate) async fn execute(&self, req: RequestBuilder) -> Result<Response> {
+        let sink = match &self.metrics {
+            Some(sink) => sink,
+            None => return self.execute_retrying(req, &mut 0).await,
+        };
+        // Bodies of requests sent by this crate can always be cloned.
+        let probe = req.try_clone().and_then(|r| r.build().ok());
+        let started = Instant::now();
+        let mut retries = 0;
+        let outcome = self.execute_retrying(req, &mut retries).await;
+        if let Some(probe) = probe {
+            let url = probe.url();
+            let host = match url.port() {
+                Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+                None => url.host_str().unwrap_or_default().to_string(),
+            };
+            let bytes_sent = probe.body().and_then(|b| b.as_bytes()).map(|b| b.len());
+            sink.record_request(&RequestMetrics {
+                host: &host,
+                method: probe.method(),
+                status: match &outcome {
+                    Ok(res) => Some(res.status()),
+                    Err(Error::RateLimited(_)) => Some(StatusCode::TOO_MANY_REQUESTS),
+                    Err(_) => None,
+                },
+                latency: started.elapsed(),
+                retries,
+                bytes_sent: bytes_sent.unwrap_or(0) as u64,
+                bytes_received: outcome.as_ref().ok().and_then(Response::content_length),
+            });
+        }
+        outcome
+    }
+
+    async fn execute_retrying(&self, req: RequestBuilder, retries: &mut u32) -> Result<Response> {
+        let policy = &self.retry_policy;
+        let mut attempt = 1;
+        let mut rate_limited = Duration::ZERO;
+        let mut req = req;
+        loop {
+            let next = req.try_clone();
+            let redirect = if self.redirect_auth_hosts.is_empty() {
+                None
+            } else {
+                req.try_clone().and_then(|r| r.build().ok())
+            };
+            let outcome = match req.build() {
+                Ok(mut req) => {
+                    self.add_default_headers(req.headers_mut());
+                    match &self.transport {
+                        Some(transport) => transport.execute(req).await,
+                        None => self.client.execute(req).await.map_err(Into::into),
+                    }
+                }
+                Err(e) => Err(e.into()),
+            };
+            let outcome = match outcome {
+                Ok(res) => self.follow_redirects(redirect.as_ref(), res).await,
+                Err(e) => Err(e),
+            };
+            if let Ok(res) = &outcome {
+                self.record_rate_limit(res.headers());
+            }
+            if let Ok(res) = &outcome {
+                if res.status() == StatusCode::TOO_MANY_REQUESTS {
+                    let rate_limit = RateLimit::from_headers(res.headers());
+                    let delay = rate_limit
+                        .retry_after
+                        .unwrap_or_else(|| policy.backoff(attempt));
+                    match next {
+                        Some(next) if rate_limited + delay <= policy.rate_limit_budget => {
+                            debug!("rate limited, retrying in {:?}", delay);
+                            sleep(delay).await;
+                            rate_limited += delay;
+                            *retries += 1;
+                            req = next;
+                            continue;
+                        }
+                        _ => return Err(Error::RateLimited(rate_limit)),
+                    }
+                }
+            }
+            match next {
+                Some(next) if attempt < policy.max_attempts && policy.should_retry(&outcome) => {
+                    let delay = policy.backoff(attempt);
+                    match &outcome {
+                        Ok(res) => debug!("got status {}, retrying in {:?}", res.status(), delay),
+                        Err(e) => debug!("request failed ({}), retrying in {:?}", e, delay),
+                    }
+                    sleep(delay).await;
+                    attempt += 1;
+                    *retries += 1;
+                    req = next;
+                }
+                _ => return outcome,
+            }
+        }
+    }
+
+    /// Send a request built by `build`, see `execute`.
+    ///
+    /// Pull requests are sent to the configured mirrors first. Bearer tokens
+    /// are refreshed shortly before they expire. If an authenticated client
+    /// still gets `401 Unauthorized`, the request is sent again with a client
+    /// authenticated anew for the same scopes, and those required by the
+    /// registry (e.g. for another repository).
+    pub(crate) async fn send<F>(&self, build: F) -> Result<Response>
+    where
+        F: Fn(&Client) -> RequestBuilder,
+    {
+        if let Some(res) = self.send_to_mirrors(&build).await {
+            return Ok(res);
+        }
+        let refreshed = self.refreshed().await?;
+        let this = refreshed.as_ref().unwrap_or(self);
+        let res = this.execute(build(this)).await?;
+        if res.status() != StatusCode::UNAUTHORIZED || this.auth.is_none() {
+            return Ok(res);
+        }
+        let scopes = this.merge_scopes(&[], &res);
+        let scopes: Vec<&str> = scopes.iter().map(String::as_str).collect();
+        trace!("got 401, authenticating again for scopes {:?}", scopes);
+        let client = this.reauthenticate(&scopes).await?;
+        let res = client.execute(build(&client)).await?;
+        if res.status() == StatusCode::UNAUTHORIZED {
+            client.record_auth_failure();
+        }
+        Ok(res)
+    }
+}
+
+/// Whether a request failed to connect to the server.
+pub(crate) fn is_connect(e: &reqwest::Error) -> bool {
+    #[cfg(not(target_arch = "wasm32"))]
+    return e.is_connect();
+    // `fetch` does not tell connection errors apart from other ones.
+    #[cfg(target_arch = "wasm32")]
+    return e.is_request();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test]
+    fn backoff_is_exponential_and_bounded() {
+        let policy = RetryPolicy::new()
+            .backoff_base(Duration::from_millis(100))
+            .max_backoff(Duration::from_secs(1))
+            .jitter(false);
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff(3), Duration::from_millis(400));
+        assert_eq!(policy.backoff(5), Duration::from_secs(1));
+        assert_eq!(policy.backoff(100), Duration::from_secs(1));
+
+        let policy = policy.jitter(true);
+        for attempt in 1..5 {
+            let delay = policy.backoff(attempt);
+            let max = RetryPolicy::backoff(&policy.clone().jitter(false), attempt);
+            assert!(delay >= max / 2 && delay <= max);
+        }
+    }
+
+    #[test_case("120" => Some(Duration::from_secs(120)); "seconds")]
+    #[test_case("Wed, 21 Oct 2015 07:28:00 GMT" => Some(Duration::ZERO); "past date")]
+    #[test_case("soon" => None; "invalid")]
+    fn retry_after(value: &str) -> Option<Duration> {
+        parse_retry_after(value)
+    }
+}
@@ -0,0 +1,24 @@
+use crate::errors::Result;
+use futures::future::BoxFuture;
+use std::fmt::Debug;
+
+/// HTTP transport sending the requests of a `Client`, see `Config::transport`.
+///
+/// Requests are fully built, with their authentication headers. Responses
+/// of other HTTP stacks (e.g. `hyper` over a unix socket, or canned responses
+/// in tests) can be converted from `http::Response` with `Into`.
+pub trait Transport: Debug + Send + Sync {
+    /// Send a request and return its response.
+    fn execute(&self, request: reqwest::Request) -> BoxFuture<'_, Result<reqwest::Response>>;
+}
+
+// Futures of `fetch` requests are not `Send`.
+#[cfg(not(target_arch = "wasm32"))]
+impl Transport for reqwest::Client {
+    fn execute(&self, request: reqwest::Request) -> BoxFuture<'_, Result<reqwest::Response>> {
+        use futures::future::FutureExt;
+        reqwest::Client::execute(self, request)
+            .map(|res| res.map_err(Into::into))
+            .boxed()
+    }
+}
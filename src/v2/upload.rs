@@ -0,0 +1,350 @@
+use crate::errors::{Error, Result};
+use crate::v2::auth::push_scope;
+use crate::v2::*;
+
+use reqwest::{header, Method, StatusCode, Url};
+
+impl Client {
+    /// Upload a blob in a single request (monolithic upload).
+    ///
+    /// `digest` must be the digest of `blob`, which the registry verifies.
+    /// If needed, the client is re-authenticated for the `push` action on
+    /// `name`. Returns the digest of the blob, as computed by the registry.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(registry = %self.base_url, repository = name, digest = %digest, bytes = blob.len()))
+    )]
+    pub async fn push_blob(&self, name: &str, digest: &str, blob: Vec<u8>) -> Result<String> {
+        self.deadline()
+            .run(async move {
+                ContentDigest::try_new(digest)?;
+                let session = self.start_blob_upload(name).await?;
+                session.finish_with(digest, blob).await
+            })
+            .await
+    }
+
+    /// Start a chunked blob upload.
+    ///
+    /// Data is then sent with `UploadSession::upload_chunk`, which allows
+    /// streaming blobs of unknown length, and the upload is committed with
+    /// `UploadSession::finish`.
+    pub async fn start_blob_upload(&self, name: &str) -> Result<UploadSession> {
+        self.deadline()
+            .run(async move {
+                let url = {
+                    let ep = format!("{}/v2/{}/blobs/uploads/", self.base_url, name);
+                    reqwest::Url::parse(&ep)?
+                };
+
+                let (res, client) = self
+                    .send_reauthenticating(&[&push_scope(name)], |client| {
+                        client
+                            .build_reqwest(Method::POST, url.clone())
+                            .header(header::CONTENT_LENGTH, 0)
+                    })
+                    .await?;
+
+                let status = res.status();
+                trace!("POST '{}' status: {:?}", res.url(), status);
+                let res = check_upload_status(res, StatusCode::ACCEPTED).await?;
+                Ok(UploadSession {
+                    client,
+                    name: name.to_string(),
+                    location: upload_location(&url, &res)?,
+                    offset: 0,
+                })
+            })
+            .await
+    }
+
+    /// Mount blob `digest` from repository `from` into repository `name`.
+    ///
+    /// This avoids uploading blobs which already exist on the same registry.
+    /// If the registry does not mount the blob (e.g. because it does not
+    /// support it, or `from` is not accessible), a regular upload session is
+    /// returned instead, to which the blob must be uploaded.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(registry = %self.base_url, repository = name, digest = %digest, from = %from))
+    )]
+    pub async fn mount_blob(&self, name: &str, digest: &str, from: &str) -> Result<BlobMount> {
+        self.deadline()
+            .run(async move {
+                ContentDigest::try_new(digest)?;
+                let mut url = {
+                    let ep = format!("{}/v2/{}/blobs/uploads/", self.base_url, name);
+                    reqwest::Url::parse(&ep)?
+                };
+                url.query_pairs_mut()
+                    .append_pair("mount", digest)
+                    .append_pair("from", from);
+
+                let pull_from = format!("repository:{}:pull", from);
+                let (res, client) = self
+                    .send_reauthenticating(&[&push_scope(name), &pull_from], |client| {
+                        client
+                            .build_reqwest(Method::POST, url.clone())
+                            .header(header::CONTENT_LENGTH, 0)
+                    })
+                    .await?;
+
+                let status = res.status();
+                trace!("POST '{}' status: {:?}", res.url(), status);
+                match status {
+                    StatusCode::CREATED => {
+                        let digest = match res.headers().get("docker-content-digest") {
+                            Some(content_digest_value) => {
+                                content_digest_value.to_str()?.to_string()
+                            }
+                            None => digest.to_string(),
+                        };
+                        Ok(BlobMount::Mounted(digest))
+                    }
+                    StatusCode::ACCEPTED => Ok(BlobMount::Upload(Box::new(UploadSession {
+                        client,
+                        name: name.to_string(),
+                        location: upload_location(&url, &res)?,
+                        offset: 0,
+                    }))),
+                    s if s.is_client_error() => {
+                        debug!("mounting blob from {} failed ({}), uploading it", from, s);
+                        let session = self.start_blob_upload(name).await?;
+                        Ok(BlobMount::Upload(Box::new(session)))
+                    }
+                    _ => Err(status_error(res).await),
+                }
+            })
+            .await
+    }
+
+    /// Resume an interrupted blob upload, given its last upload URL.
+    ///
+    /// The upload offset is fetched from the registry, so that sending can
+    /// resume from the last committed byte.
+    pub async fn resume_blob_upload(&self, name: &str, location: Url) -> Result<UploadSession> {
+        self.deadline()
+            .run(async move {
+                let mut session = UploadSession {
+                    client: self.clone(),
+                    name: name.to_string(),
+                    location,
+                    offset: 0,
+                };
+                session.refresh().await?;
+                Ok(session)
+            })
+            .await
+    }
+}
+
+/// Outcome of a cross-repository blob mount.
+#[derive(Debug)]
+pub enum BlobMount {
+    /// The blob has been mounted, with the given digest.
+    Mounted(String),
+    /// The blob has not been mounted, and must be uploaded to this session.
+    Upload(Box<UploadSession>),
+}
+
+/// An in-progress blob upload.
+///
+/// The current `location` can be persisted, to resume an interrupted upload
+/// later on with `Client::resume_blob_upload`.
+#[derive(Debug, Clone)]
+pub struct UploadSession {
+    client: Client,
+    name: String,
+    location: Url,
+    offset: u64,
+}
+
+impl UploadSession {
+    /// Current upload URL of this session.
+    pub fn location(&self) -> &Url {
+        &self.location
+    }
+
+    /// Number of bytes committed so far.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Upload the next chunk of data.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(repository = %self.name, offset = self.offset, bytes = chunk.len()))
+    )]
+    pub async fn upload_chunk(&mut self, chunk: Vec<u8>) -> Result<()> {
+        let deadline = self.client.deadline();
+        deadline
+            .run(async move {
+                if chunk.is_empty() {
+                    return Ok(());
+                }
+                let len = chunk.len() as u64;
+                let range = format!("{}-{}", self.offset, self.offset + len - 1);
+                let chunk = bytes::Bytes::from(chunk);
+                let url = self.location.clone();
+                let (res, client) = self
+                    .client
+                    .send_reauthenticating(&[&push_scope(&self.name)], |client| {
+                        client
+                            .build_reqwest(Method::PATCH, url.clone())
+                            .header(header::CONTENT_TYPE, "application/octet-stream")
+                            .header(header::CONTENT_RANGE, range.as_str())
+                            .body(chunk.clone())
+                    })
+                    .await?;
+
+                let status = res.status();
+                trace!("PATCH '{}' ({}) status: {:?}", res.url(), range, status);
+                let res = check_upload_status(res, StatusCode::ACCEPTED).await?;
+                self.location = upload_location(&url, &res)?;
+                self.offset += len;
+                self.client = client;
+                Ok(())
+            })
+            .await
+    }
+
+    /// Fetch the upload status from the registry, returning the committed offset.
+    pub async fn refresh(&mut self) -> Result<u64> {
+        let deadline = self.client.deadline();
+        deadline
+            .run(async move {
+                let url = self.location.clone();
+                let (res, client) = self
+                    .client
+                    .send_reauthenticating(&[&push_scope(&self.name)], |client| {
+                        client.build_reqwest(Method::GET, url.clone())
+                    })
+                    .await?;
+
+                let status = res.status();
+                trace!("GET '{}' status: {:?}", res.url(), status);
+                let res = check_upload_status(res, StatusCode::NO_CONTENT).await?;
+                let range = res
+                    .headers()
+                    .get(header::RANGE)
+                    .ok_or(Error::MissingHeader("Range"))?
+                    .to_str()?;
+                self.offset = parse_range_end(range)?;
+                if res.headers().contains_key(header::LOCATION) {
+                    self.location = upload_location(&url, &res)?;
+                }
+                self.client = client;
+                Ok(self.offset)
+            })
+            .await
+    }
+
+    /// Commit the upload, `digest` being the digest of all uploaded data.
+    ///
+    /// Returns the digest of the blob, as computed by the registry.
+    pub async fn finish(self, digest: &str) -> Result<String> {
+        self.finish_with(digest, Vec::new()).await
+    }
+
+    /// Commit the upload, sending a last chunk of data along.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(repository = %self.name, digest = %digest, bytes = last_chunk.len()))
+    )]
+    pub async fn finish_with(self, digest: &str, last_chunk: Vec<u8>) -> Result<String> {
+        let deadline = self.client.deadline();
+        deadline
+            .run(async move {
+                ContentDigest::try_new(digest)?;
+                let mut url = self.location;
+                url.query_pairs_mut().append_pair("digest", digest);
+                let last_chunk = bytes::Bytes::from(last_chunk);
+                let res = self
+                    .client
+                    .send_with_scope(&[&push_scope(&self.name)], |client| {
+                        client
+                            .build_reqwest(Method::PUT, url.clone())
+                            .header(header::CONTENT_TYPE, "application/octet-stream")
+                            .body(last_chunk.clone())
+                    })
+                    .await?;
+
+                let status = res.status();
+                trace!("PUT '{}' status: {:?}", res.url(), status);
+                let res = check_upload_status(res, StatusCode::CREATED).await?;
+
+                match res.headers().get("docker-content-digest") {
+                    Some(content_digest_value) => Ok(content_digest_value.to_str()?.to_string()),
+                    None => Ok(digest.to_string()),
+                }
+            })
+            .await
+    }
+
+    /// Cancel the upload, discarding the data uploaded so far.
+    pub async fn cancel(self) -> Result<()> {
+        let deadline = self.client.deadline();
+        deadline
+            .run(async move {
+                let url = self.location;
+                let res = self
+                    .client
+                    .send_with_scope(&[&push_scope(&self.name)], |client| {
+                        client.build_reqwest(Method::DELETE, url.clone())
+                    })
+                    .await?;
+
+                let status = res.status();
+                trace!("DELETE '{}' status: {:?}", res.url(), status);
+                check_upload_status(res, StatusCode::NO_CONTENT)
+                    .await
+                    .map(|_| ())
+            })
+            .await
+    }
+}
+
+/// Parse a `Range` header of an upload status (`0-<last byte>`) into an offset.
+///
+/// Registries report `0-0` for empty uploads, so this is taken as offset 0.
+fn parse_range_end(range: &str) -> Result<u64> {
+    let invalid = || Error::InvalidHeader("Range", range.to_string());
+    let range = range.trim().trim_start_matches("bytes=");
+    let (_, end) = range.split_once('-').ok_or_else(invalid)?;
+    let end: u64 = end.trim().parse().map_err(|_| invalid())?;
+    Ok(if end == 0 { 0 } else { end + 1 })
+}
+
+/// Resolve the `Location` header of an upload response, which may be relative.
+pub(crate) fn upload_location(request_url: &Url, res: &reqwest::Response) -> Result<Url> {
+    let location = res
+        .headers()
+        .get(header::LOCATION)
+        .ok_or(Error::MissingHeader("Location"))?
+        .to_str()?;
+    request_url.join(location).map_err(Into::into)
+}
+
+/// Map an upload response to an error, unless its status is `expected`.
+pub(crate) async fn check_upload_status(
+    res: reqwest::Response,
+    expected: StatusCode,
+) -> Result<reqwest::Response> {
+    match res.status() {
+        s if s == expected => Ok(res),
+        _ => Err(status_error(res).await),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("0-0" => 0; "empty upload")]
+    #[test_case("0-1023" => 1024; "plain")]
+    #[test_case("bytes=0-99" => 100; "with unit")]
+    fn upload_range_offsets(range: &str) -> u64 {
+        parse_range_end(range).unwrap()
+    }
+}
@@ -69,6 +69,33 @@ fn test_manifest_v2s2() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+#[test]
+fn test_deserialize_config_blob() {
+    let f = fs::File::open("tests/fixtures/container_config_blob.json").expect("Missing fixture");
+    let config: dkregistry::v2::manifest::ConfigBlob = serde_json::from_reader(f).unwrap();
+
+    assert_eq!(config.architecture, "amd64");
+    assert_eq!(config.os, "linux");
+    assert_eq!(config.created.as_deref(), Some("2019-08-16T14:50:54Z"));
+    let container = config.config.unwrap();
+    assert_eq!(container.user.as_deref(), Some("0"));
+    assert_eq!(
+        container.entrypoint,
+        Some(vec!["/usr/bin/cluster-version-operator".to_string()])
+    );
+    assert_eq!(container.cmd, None);
+    assert!(container
+        .env_vars()
+        .any(|var| var == ("BUILD_VERSION", "v4.1.12")));
+    assert_eq!(container.labels.unwrap()["io.openshift.release"], "4.1.12");
+    assert_eq!(config.rootfs.fs_type, "layers");
+    assert_eq!(config.rootfs.diff_ids.len(), 6);
+    assert_eq!(
+        config.history[0].comment.as_deref(),
+        Some("Release image for OpenShift")
+    );
+}
+
 #[test]
 fn test_deserialize_manifest_list_v2() {
     let f = fs::File::open("tests/fixtures/manifest_list_v2.json").expect("Missing fixture");
@@ -76,6 +103,52 @@ fn test_deserialize_manifest_list_v2() {
     let _manif: dkregistry::v2::manifest::ManifestList = serde_json::from_reader(bufrd).unwrap();
 }
 
+#[test]
+fn test_deserialize_oci_artifact_manifest() {
+    let f = fs::File::open("tests/fixtures/oci_artifact_manifest.json").expect("Missing fixture");
+    let bufrd = io::BufReader::new(f);
+    let manif: dkregistry::v2::manifest::OciManifest = serde_json::from_reader(bufrd).unwrap();
+    assert!(!manif.is_image());
+    assert_eq!(manif.artifact_type(), Some("application/spdx+json"));
+    assert_eq!(manif.layers.len(), 1);
+    assert_eq!(
+        manif.subject.map(|s| s.digest).as_deref(),
+        Some("sha256:5b0bcabd1ed22e9fb1310cf6c2dec7cdef19f0ad69efa1f392e94a4333501270")
+    );
+}
+
+#[test]
+fn test_image_index_select_platform() {
+    let f = fs::File::open("tests/fixtures/manifest_list_v2.json").expect("Missing fixture");
+    let bufrd = io::BufReader::new(f);
+    let index: dkregistry::v2::manifest::ImageIndex = serde_json::from_reader(bufrd).unwrap();
+    assert_eq!(
+        index.select_platform("linux", "amd64", None),
+        Some("sha256:5b0bcabd1ed22e9fb1310cf6c2dec7cdef19f0ad69efa1f392e94a4333501270")
+    );
+    assert_eq!(index.select_platform("linux", "s390x", None), None);
+
+    let f = fs::File::open("tests/fixtures/oci_image_index.json").expect("Missing fixture");
+    let bufrd = io::BufReader::new(f);
+    let index: dkregistry::v2::manifest::ImageIndex = serde_json::from_reader(bufrd).unwrap();
+    assert_eq!(index.manifests.len(), 3);
+    assert_eq!(
+        index.select_platform("linux", "arm", Some("v7")),
+        Some("sha256:5b0bcabd1ed22e9fb1310cf6c2dec7cdef19f0ad69efa1f392e94a4333501270")
+    );
+    assert_eq!(
+        index.select_platform("linux", "arm", None),
+        Some("sha256:e692418e4cbaf90ca69d05a66403747baa33ee08806650b51fab815ad7fc331f")
+    );
+    // `arm/v8` hosts run the closest older variant.
+    assert_eq!(
+        index.select_platform("linux", "arm", Some("v8")),
+        Some("sha256:5b0bcabd1ed22e9fb1310cf6c2dec7cdef19f0ad69efa1f392e94a4333501270")
+    );
+    assert_eq!(index.select_platform("linux", "arm", Some("v5")), None);
+    assert_eq!(index.select_platform("linux", "arm64", None), None);
+}
+
 #[test]
 fn test_deserialize_etcd_manifest() {
     let f =
@@ -0,0 +1,59 @@
+extern crate dkregistry;
+extern crate mockito;
+extern crate tokio;
+
+use self::mockito::{mock, Matcher};
+use self::tokio::runtime::Runtime;
+use dkregistry::v2::AuthMode;
+
+fn client(addr: &str, fallback: bool) -> dkregistry::v2::Client {
+    dkregistry::v2::Client::configure()
+        .registry(addr)
+        .insecure_registry(true)
+        .username(Some("user".to_string()))
+        .password(Some("wrong".to_string()))
+        .anonymous_fallback(fallback)
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn test_anonymous_fallback() {
+    let addr = mockito::server_address().to_string();
+    let scope = "repository:library/alpine:pull";
+
+    let _m_auth = mock("GET", "/v2/")
+        .with_status(401)
+        .with_header(
+            "WWW-Authenticate",
+            &format!(r#"Bearer realm="http://{}/token",service="mock""#, addr),
+        )
+        .create();
+    let _m_rejected = mock("GET", "/token")
+        .match_query(Matcher::Any)
+        .match_header("Authorization", Matcher::Any)
+        .with_status(401)
+        .create();
+    let m_anonymous = mock("GET", "/token")
+        .match_query(Matcher::Any)
+        .match_header("Authorization", Matcher::Missing)
+        .with_status(200)
+        .with_body(r#"{"token":"anonymous"}"#)
+        .expect(1)
+        .create();
+
+    let runtime = Runtime::new().unwrap();
+    let res = runtime.block_on(client(&addr, false).authenticate(&[scope]));
+    assert!(matches!(
+        res,
+        Err(dkregistry::errors::Error::UnexpectedHttpStatus(_))
+    ));
+
+    let dclient = runtime
+        .block_on(client(&addr, true).authenticate(&[scope]))
+        .unwrap();
+    assert_eq!(dclient.auth_mode(), AuthMode::Anonymous);
+    m_anonymous.assert();
+
+    mockito::reset();
+}
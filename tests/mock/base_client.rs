@@ -90,3 +90,41 @@ fn test_base_custom_useragent() {
 
     mockito::reset();
 }
+
+#[test]
+fn test_base_default_headers() {
+    let addr = mockito::server_address().to_string();
+    let _m = mock("GET", "/v2/")
+        .match_header("x-tracking-id", "1234")
+        .with_status(401)
+        .with_header(
+            "WWW-Authenticate",
+            &format!(r#"Bearer realm="http://{}/token",service="mock""#, addr),
+        )
+        .create();
+    let m_token = mock("GET", "/token")
+        .match_query(mockito::Matcher::Any)
+        .match_header("x-tracking-id", "1234")
+        .with_status(200)
+        .with_body(r#"{"token":"pulltoken"}"#)
+        .create();
+
+    let mut headers = reqwest::header::HeaderMap::new();
+    headers.insert("x-tracking-id", "1234".parse().unwrap());
+    let runtime = Runtime::new().unwrap();
+    let dclient = dkregistry::v2::Client::configure()
+        .registry(&addr)
+        .insecure_registry(true)
+        .default_headers(headers)
+        .username(None)
+        .password(None)
+        .build()
+        .unwrap();
+
+    runtime
+        .block_on(dclient.authenticate(&["repository:my-repo/my-image:pull"]))
+        .unwrap();
+    m_token.assert();
+
+    mockito::reset();
+}
@@ -6,8 +6,9 @@ extern crate tokio;
 use self::mockito::mock;
 use self::tokio::runtime::Runtime;
 use crate::mock::blobs_download::sha2::Digest;
-use futures::stream::StreamExt;
+use futures::stream::{StreamExt, TryStreamExt};
 use futures::FutureExt;
+use std::sync::{Arc, Mutex};
 
 type Fallible<T> = Result<T, Box<dyn std::error::Error>>;
 
@@ -42,6 +43,41 @@ fn test_blobs_has_layer() {
     mockito::reset();
 }
 
+#[test]
+fn test_blobs_get_size() {
+    let name = "my-repo/my-image";
+    let digest = "fakedigest";
+
+    let ep = format!("/v2/{}/blobs/{}", name, digest);
+    let addr = mockito::server_address().to_string();
+    let _m = mock("HEAD", ep.as_str())
+        .with_status(200)
+        .with_header("Content-Length", "1234")
+        .create();
+    let missing_ep = format!("/v2/{}/blobs/missing", name);
+    let _m_missing = mock("HEAD", missing_ep.as_str()).with_status(404).create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = dkregistry::v2::Client::configure()
+        .registry(&addr)
+        .insecure_registry(true)
+        .username(None)
+        .password(None)
+        .build()
+        .unwrap();
+
+    let res = runtime
+        .block_on(dclient.get_blob_size(name, digest))
+        .unwrap();
+    assert_eq!(res, Some(1234));
+    let res = runtime
+        .block_on(dclient.get_blob_size(name, "missing"))
+        .unwrap();
+    assert_eq!(res, None);
+
+    mockito::reset();
+}
+
 #[test]
 fn test_blobs_hasnot_layer() {
     let name = "my-repo/my-image";
@@ -126,8 +162,11 @@ fn get_blobs_fails_with_inconsistent_layer() -> Fallible<()> {
 
     let futcheck = dclient.get_blob(&name, &digest);
 
-    if runtime.block_on(futcheck).is_ok() {
-        return Err("expected get_blob to fail with an inconsistent blob".into());
+    match runtime.block_on(futcheck) {
+        Err(dkregistry::errors::Error::DigestMismatch { expected, .. }) => {
+            assert_eq!(expected, digest)
+        }
+        _ => return Err("expected get_blob to fail with an inconsistent blob".into()),
     };
 
     mockito::reset();
@@ -168,3 +207,296 @@ fn get_blobs_stream() -> Fallible<()> {
     mockito::reset();
     Ok(())
 }
+
+#[test]
+fn get_blobs_stream_awaits_first_chunk() -> Fallible<()> {
+    let addr = mockito::server_address().to_string();
+
+    let name = "my-repo/my-image";
+    let blob = b"hello";
+    let digest = format!("sha256:{:x}", sha2::Sha256::digest(blob));
+
+    let ep = format!("/v2/{}/blobs/{}", &name, &digest);
+    let _m = mock("GET", ep.as_str())
+        .with_status(200)
+        .with_body(blob)
+        .create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = dkregistry::v2::Client::configure()
+        .registry(&addr)
+        .insecure_registry(true)
+        .username(None)
+        .password(None)
+        .build()
+        .unwrap();
+
+    let futcheck = dclient.get_blob_response(&name, &digest);
+
+    let blob_resp = runtime.block_on(futcheck)?;
+    assert_eq!(blob_resp.size(), Some(5));
+    let stream_output = runtime.block_on(blob_resp.stream().next());
+    let received_blob = stream_output.unwrap_or_else(|| panic!("No blob data"))?;
+    assert_eq!(blob.to_vec(), received_blob);
+    mockito::reset();
+    Ok(())
+}
+
+#[test]
+fn get_blob_stream_with_size() -> Fallible<()> {
+    let addr = mockito::server_address().to_string();
+
+    let name = "my-repo/my-image";
+    let blob = b"hello world";
+    let digest = format!("sha256:{:x}", sha2::Sha256::digest(blob));
+    let wrong_digest = format!("sha256:{:x}", sha2::Sha256::digest(b"hello"));
+
+    let _m = mock("GET", format!("/v2/{}/blobs/{}", &name, &digest).as_str())
+        .with_status(200)
+        .with_body(blob)
+        .create();
+    let _m_wrong = mock(
+        "GET",
+        format!("/v2/{}/blobs/{}", &name, &wrong_digest).as_str(),
+    )
+    .with_status(200)
+    .with_body(blob)
+    .create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = dkregistry::v2::Client::configure()
+        .registry(&addr)
+        .insecure_registry(true)
+        .username(None)
+        .password(None)
+        .build()
+        .unwrap();
+
+    let (stream, size) = runtime.block_on(dclient.get_blob_stream(name, &digest))?;
+    assert_eq!(size, Some(11));
+    let chunks: Vec<_> = runtime.block_on(stream.try_collect::<Vec<_>>())?;
+    assert_eq!(chunks.concat(), blob.to_vec());
+
+    let (stream, _) = runtime.block_on(dclient.get_blob_stream(name, &wrong_digest))?;
+    let chunks: Vec<_> = runtime.block_on(stream.collect());
+    assert!(chunks.last().unwrap().is_err());
+
+    mockito::reset();
+    Ok(())
+}
+
+#[test]
+fn get_blobs_stream_resume() -> Fallible<()> {
+    let addr = mockito::server_address().to_string();
+
+    let name = "my-repo/my-image";
+    let blob = b"hello world";
+    let digest = format!("sha256:{:x}", sha2::Sha256::digest(blob));
+
+    let ep = format!("/v2/{}/blobs/{}", &name, &digest);
+    let _m = mock("GET", ep.as_str())
+        .match_header("Range", "bytes=6-")
+        .with_status(206)
+        .with_header("Content-Range", "bytes 6-10/11")
+        .with_body(&blob[6..])
+        .create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = dkregistry::v2::Client::configure()
+        .registry(&addr)
+        .insecure_registry(true)
+        .username(None)
+        .password(None)
+        .build()
+        .unwrap();
+
+    let stream = dclient.resume_blob_stream(name, &digest, &blob[..6]);
+    let chunks: Vec<_> = runtime.block_on(stream.collect());
+    let received: Vec<u8> = chunks.into_iter().collect::<Result<Vec<_>, _>>()?.concat();
+    assert_eq!(&blob[6..], received.as_slice());
+
+    // A wrong prefix fails the digest verification.
+    let stream = dclient.resume_blob_stream(name, &digest, b"HELLO ");
+    let chunks: Vec<_> = runtime.block_on(stream.collect());
+    assert!(chunks.last().unwrap().is_err());
+
+    mockito::reset();
+    Ok(())
+}
+
+#[test]
+fn get_blobs_stream_resume_range_ignored() -> Fallible<()> {
+    let addr = mockito::server_address().to_string();
+
+    let name = "my-repo/my-image";
+    let blob = b"hello world";
+    let digest = format!("sha256:{:x}", sha2::Sha256::digest(blob));
+
+    let ep = format!("/v2/{}/blobs/{}", &name, &digest);
+    let _m = mock("GET", ep.as_str())
+        .with_status(200)
+        .with_body(blob)
+        .create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = dkregistry::v2::Client::configure()
+        .registry(&addr)
+        .insecure_registry(true)
+        .username(None)
+        .password(None)
+        .build()
+        .unwrap();
+
+    let stream = dclient.resume_blob_stream(name, &digest, &blob[..6]);
+    let chunks: Vec<_> = runtime.block_on(stream.collect());
+    let received: Vec<u8> = chunks.into_iter().collect::<Result<Vec<_>, _>>()?.concat();
+    assert_eq!(&blob[6..], received.as_slice());
+
+    let stream = dclient.get_blob_stream_resumable(name, &digest);
+    let chunks: Vec<_> = runtime.block_on(stream.collect());
+    let received: Vec<u8> = chunks.into_iter().collect::<Result<Vec<_>, _>>()?.concat();
+    assert_eq!(blob.to_vec(), received);
+
+    mockito::reset();
+    Ok(())
+}
+
+#[test]
+fn get_blobs_concurrently() -> Fallible<()> {
+    let addr = mockito::server_address().to_string();
+
+    let name = "my-repo/my-image";
+    let blobs: Vec<Vec<u8>> = (0..5).map(|i| format!("layer{}", i).into_bytes()).collect();
+    let digests: Vec<String> = blobs
+        .iter()
+        .map(|b| format!("sha256:{:x}", sha2::Sha256::digest(b)))
+        .collect();
+    let _mocks: Vec<_> = blobs
+        .iter()
+        .zip(&digests)
+        .map(|(blob, digest)| {
+            let ep = format!("/v2/{}/blobs/{}", &name, digest);
+            mock("GET", ep.as_str())
+                .with_status(200)
+                .with_body(blob)
+                .create()
+        })
+        .collect();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = dkregistry::v2::Client::configure()
+        .registry(&addr)
+        .insecure_registry(true)
+        .username(None)
+        .password(None)
+        .build()
+        .unwrap();
+
+    let digests: Vec<&str> = digests.iter().map(String::as_str).collect();
+    let result = runtime.block_on(dclient.get_blobs(name, &digests, 3))?;
+    assert_eq!(blobs, result);
+
+    mockito::reset();
+    Ok(())
+}
+
+#[test]
+fn get_blobs_reports_progress() -> Fallible<()> {
+    let addr = mockito::server_address().to_string();
+
+    let name = "my-repo/my-image";
+    let blob = b"hello world";
+    let digest = format!("sha256:{:x}", sha2::Sha256::digest(blob));
+
+    let ep = format!("/v2/{}/blobs/{}", &name, &digest);
+    let _m_range = mock("GET", ep.as_str())
+        .match_header("Range", "bytes=6-")
+        .with_status(206)
+        .with_header("Content-Range", "bytes 6-10/11")
+        .with_body(&blob[6..])
+        .create();
+    let _m = mock("GET", ep.as_str())
+        .with_status(200)
+        .with_body(blob)
+        .create();
+
+    let reports = Arc::new(Mutex::new(Vec::new()));
+    let runtime = Runtime::new().unwrap();
+    let dclient = dkregistry::v2::Client::configure()
+        .registry(&addr)
+        .insecure_registry(true)
+        .username(None)
+        .password(None)
+        .progress({
+            let reports = reports.clone();
+            move |p| {
+                let report = (p.reference.to_string(), p.transferred, p.total);
+                reports.lock().unwrap().push(report);
+            }
+        })
+        .build()
+        .unwrap();
+
+    runtime.block_on(dclient.get_blob(name, &digest))?;
+    let last = reports.lock().unwrap().pop();
+    assert_eq!(last, Some((digest.clone(), 11, Some(11))));
+
+    // Resumed downloads account for the content received before.
+    let stream = dclient.resume_blob_stream(name, &digest, &blob[..6]);
+    let chunks: Vec<_> = runtime.block_on(stream.collect());
+    chunks.into_iter().collect::<Result<Vec<_>, _>>()?;
+    let last = reports.lock().unwrap().pop();
+    assert_eq!(last, Some((digest, 11, Some(11))));
+
+    mockito::reset();
+    Ok(())
+}
+
+#[cfg(feature = "render")]
+#[test]
+fn get_layer_media_type() -> Fallible<()> {
+    use dkregistry::render::LayerCompression;
+    use dkregistry::v2::manifest::Descriptor;
+
+    let addr = mockito::server_address().to_string();
+
+    let name = "my-repo/my-image";
+    let blob = b"\x28\xb5\x2f\xfd zstd frame";
+    let digest = format!("sha256:{:x}", sha2::Sha256::digest(blob));
+
+    let ep = format!("/v2/{}/blobs/{}", &name, &digest);
+    let _m = mock("GET", ep.as_str())
+        .with_status(200)
+        .with_header("Content-Type", "application/octet-stream")
+        .with_body(blob)
+        .create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = dkregistry::v2::Client::configure()
+        .registry(&addr)
+        .insecure_registry(true)
+        .username(None)
+        .password(None)
+        .build()
+        .unwrap();
+
+    let res = runtime.block_on(dclient.get_blob_response(name, &digest))?;
+    assert_eq!(res.media_type(), Some("application/octet-stream"));
+    assert_eq!(res.compression(), LayerCompression::Auto);
+
+    let layer = Descriptor {
+        media_type: "application/vnd.oci.image.layer.v1.tar+zstd".to_string(),
+        digest: digest.clone(),
+        size: blob.len() as u64,
+        ..Descriptor::default()
+    };
+    let res = runtime
+        .block_on(dclient.get_layer_response(name, &layer))?
+        .unwrap();
+    assert_eq!(res.media_type(), Some(layer.media_type.as_str()));
+    assert_eq!(res.compression(), LayerCompression::Zstd);
+    assert_eq!(runtime.block_on(res.bytes())?, blob);
+
+    mockito::reset();
+    Ok(())
+}
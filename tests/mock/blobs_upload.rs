@@ -0,0 +1,180 @@
+extern crate dkregistry;
+extern crate mockito;
+extern crate tokio;
+
+use self::mockito::{mock, Matcher};
+use self::tokio::runtime::Runtime;
+use super::client;
+
+#[test]
+fn test_blobs_push_monolithic() {
+    let name = "my-repo/my-image";
+    let blob = b"hello".to_vec();
+    let digest = "sha256:2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+
+    let addr = mockito::server_address().to_string();
+    let ep = format!("/v2/{}/blobs/uploads/", name);
+    let upload_ep = format!("/v2/{}/blobs/uploads/some-uuid", name);
+    let _m_post = mock("POST", ep.as_str())
+        .with_status(202)
+        .with_header("Location", &format!("{}?_state=opaque", upload_ep))
+        .create();
+    let _m_put = mock("PUT", upload_ep.as_str())
+        .match_query(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("_state".into(), "opaque".into()),
+            Matcher::UrlEncoded("digest".into(), digest.into()),
+        ]))
+        .match_body("hello")
+        .with_status(201)
+        .with_header("Docker-Content-Digest", digest)
+        .create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = client(&addr);
+    let futcheck = dclient.push_blob(name, digest, blob);
+
+    let res = runtime.block_on(futcheck).unwrap();
+    assert_eq!(res, digest);
+
+    mockito::reset();
+}
+
+#[test]
+fn test_blobs_push_rejected() {
+    let name = "my-repo/my-image";
+    let digest = "sha256:2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+
+    let addr = mockito::server_address().to_string();
+    let ep = format!("/v2/{}/blobs/uploads/", name);
+    let _m_post = mock("POST", ep.as_str()).with_status(403).create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = client(&addr);
+    let futcheck = dclient.push_blob(name, digest, b"hello".to_vec());
+
+    let res = runtime.block_on(futcheck);
+    assert!(matches!(
+        res,
+        Err(dkregistry::errors::Error::Client { status }) if status == 403
+    ));
+
+    mockito::reset();
+}
+
+#[test]
+fn test_blobs_push_chunked_resume() {
+    let name = "my-repo/my-image";
+    let digest = "sha256:936a185caaa266bb9cbe981e9e05cb78cd732b0b3280eb944412bb6f8f8f07af";
+
+    let addr = mockito::server_address().to_string();
+    let ep = format!("/v2/{}/blobs/uploads/", name);
+    let first = format!("/v2/{}/blobs/uploads/uuid-1", name);
+    let second = format!("/v2/{}/blobs/uploads/uuid-2", name);
+    let _m_post = mock("POST", ep.as_str())
+        .with_status(202)
+        .with_header("Location", &first)
+        .with_header("Range", "0-0")
+        .create();
+    let _m_patch = mock("PATCH", first.as_str())
+        .match_header("Content-Range", "0-4")
+        .match_body("hello")
+        .with_status(202)
+        .with_header("Location", &second)
+        .with_header("Range", "0-4")
+        .create();
+    let _m_status = mock("GET", second.as_str())
+        .with_status(204)
+        .with_header("Location", &second)
+        .with_header("Range", "0-4")
+        .create();
+    let _m_put = mock("PUT", second.as_str())
+        .match_query(Matcher::UrlEncoded("digest".into(), digest.into()))
+        .match_body("world")
+        .with_status(201)
+        .create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = client(&addr);
+
+    let location = runtime.block_on(async {
+        let mut session = dclient.start_blob_upload(name).await.unwrap();
+        session.upload_chunk(b"hello".to_vec()).await.unwrap();
+        assert_eq!(session.offset(), 5);
+        session.location().clone()
+    });
+    assert_eq!(location.path(), second);
+
+    let res = runtime.block_on(async {
+        let session = dclient.resume_blob_upload(name, location).await.unwrap();
+        assert_eq!(session.offset(), 5);
+        session.finish_with(digest, b"world".to_vec()).await
+    });
+    assert_eq!(res.unwrap(), digest);
+
+    mockito::reset();
+}
+
+#[test]
+fn test_blobs_mount() {
+    let name = "my-repo/my-image";
+    let digest = "sha256:2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+
+    let addr = mockito::server_address().to_string();
+    let ep = format!("/v2/{}/blobs/uploads/", name);
+    let _m_mount = mock("POST", ep.as_str())
+        .match_query(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("mount".into(), digest.into()),
+            Matcher::UrlEncoded("from".into(), "other/image".into()),
+        ]))
+        .with_status(201)
+        .with_header("Docker-Content-Digest", digest)
+        .create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = client(&addr);
+    let futcheck = dclient.mount_blob(name, digest, "other/image");
+
+    let res = runtime.block_on(futcheck).unwrap();
+    assert!(matches!(res, dkregistry::v2::BlobMount::Mounted(d) if d == digest));
+
+    mockito::reset();
+}
+
+#[test]
+fn test_blobs_mount_fallback() {
+    let name = "my-repo/my-image";
+    let digest = "sha256:2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+
+    let addr = mockito::server_address().to_string();
+    let ep = format!("/v2/{}/blobs/uploads/", name);
+    let upload_ep = format!("/v2/{}/blobs/uploads/some-uuid", name);
+    let _m_mount = mock("POST", ep.as_str())
+        .match_query(Matcher::UrlEncoded("mount".into(), digest.into()))
+        .with_status(202)
+        .with_header("Location", &upload_ep)
+        .create();
+    let _m_put = mock("PUT", upload_ep.as_str())
+        .match_query(Matcher::UrlEncoded("digest".into(), digest.into()))
+        .match_body("hello")
+        .with_status(201)
+        .create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = client(&addr);
+
+    let res = runtime.block_on(async {
+        match dclient
+            .mount_blob(name, digest, "other/image")
+            .await
+            .unwrap()
+        {
+            dkregistry::v2::BlobMount::Upload(session) => {
+                (*session).finish_with(digest, b"hello".to_vec()).await
+            }
+            dkregistry::v2::BlobMount::Mounted(_) => panic!("blob should not be mounted"),
+        }
+    });
+    assert_eq!(res.unwrap(), digest);
+
+    mockito::reset();
+}
@@ -0,0 +1,41 @@
+extern crate dkregistry;
+extern crate mockito;
+extern crate sha2;
+
+use self::mockito::mock;
+use self::sha2::Digest;
+
+#[test]
+fn test_blocking_client() {
+    let name = "my-repo/my-image";
+    let blob = b"hello";
+    let digest = format!("sha256:{:x}", sha2::Sha256::digest(blob));
+
+    let addr = mockito::server_address().to_string();
+    let _m_tags = mock("GET", format!("/v2/{}/tags/list", name).as_str())
+        .with_status(200)
+        .with_header("Content-Type", "application/json")
+        .with_body(r#"{"name":"my-repo/my-image","tags":["latest"]}"#)
+        .create();
+    let _m_blob = mock("GET", format!("/v2/{}/blobs/{}", name, digest).as_str())
+        .with_status(200)
+        .with_body(blob)
+        .create();
+
+    let config = dkregistry::v2::Client::configure()
+        .registry(&addr)
+        .insecure_registry(true)
+        .username(None)
+        .password(None);
+    let dclient = dkregistry::blocking::Client::new(config).unwrap();
+
+    let tags = dclient.get_all_tags(name, None).unwrap();
+    assert_eq!(tags, vec!["latest"]);
+
+    let mut written = Vec::new();
+    let size = dclient.copy_blob(name, &digest, &mut written).unwrap();
+    assert_eq!(size, 5);
+    assert_eq!(written, blob);
+
+    mockito::reset();
+}
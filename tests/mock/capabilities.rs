@@ -0,0 +1,138 @@
+extern crate dkregistry;
+extern crate mockito;
+extern crate tokio;
+
+use self::mockito::mock;
+use self::tokio::runtime::Runtime;
+
+static EMPTY_DIGEST: &str =
+    "sha256:e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+#[test]
+fn test_check_api_capabilities() {
+    let name = "repo";
+    let addr = mockito::server_address().to_string();
+    let _m_v2 = mock("GET", "/v2/")
+        .with_status(401)
+        .with_header("Docker-Distribution-API-Version", "registry/2.0")
+        .with_header(
+            "WWW-Authenticate",
+            r#"Bearer realm="https://auth.example.com/token",service="registry.example.com""#,
+        )
+        .create();
+    let _m_referrers = mock(
+        "GET",
+        format!("/v2/{}/referrers/{}", name, EMPTY_DIGEST).as_str(),
+    )
+    .with_status(200)
+    .with_body(r#"{"schemaVersion": 2, "manifests": []}"#)
+    .create();
+    let _m_delete = mock(
+        "DELETE",
+        format!("/v2/{}/manifests/{}", name, EMPTY_DIGEST).as_str(),
+    )
+    .with_status(405)
+    .create();
+    let _m_upload = mock("POST", format!("/v2/{}/blobs/uploads/", name).as_str())
+        .with_status(202)
+        .with_header("Location", "/v2/repo/blobs/uploads/probe")
+        .create();
+    let m_cancel = mock("DELETE", "/v2/repo/blobs/uploads/probe")
+        .with_status(204)
+        .expect(1)
+        .create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = dkregistry::v2::Client::configure()
+        .registry(&addr)
+        .insecure_registry(true)
+        .username(None)
+        .password(None)
+        .build()
+        .unwrap();
+
+    let check = runtime.block_on(dclient.check_api(Some(name))).unwrap();
+    assert_eq!(check.api_version.as_deref(), Some("registry/2.0"));
+    assert!(check.auth_required);
+    assert_eq!(check.auth_scheme.as_deref(), Some("bearer"));
+    assert_eq!(
+        check.realm.as_deref(),
+        Some("https://auth.example.com/token")
+    );
+    assert_eq!(check.service.as_deref(), Some("registry.example.com"));
+    assert_eq!(check.referrers, Some(true));
+    assert_eq!(check.delete, Some(false));
+    assert_eq!(check.push, Some(true));
+    m_cancel.assert();
+
+    mockito::reset();
+}
+
+#[test]
+fn test_check_api_anonymous() {
+    let addr = mockito::server_address().to_string();
+    let _m = mock("GET", "/v2/").with_status(200).create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = dkregistry::v2::Client::configure()
+        .registry(&addr)
+        .insecure_registry(true)
+        .username(None)
+        .password(None)
+        .build()
+        .unwrap();
+
+    let check = runtime.block_on(dclient.check_api(None)).unwrap();
+    assert_eq!(check.api_version, None);
+    assert!(!check.auth_required);
+    assert_eq!(check.auth_scheme, None);
+    assert_eq!(check.referrers, None);
+    assert_eq!(check.push, None);
+
+    mockito::reset();
+}
+
+#[test]
+fn test_check_api_denied_with_error_body() {
+    let name = "repo";
+    let addr = mockito::server_address().to_string();
+    let _m_v2 = mock("GET", "/v2/").with_status(200).create();
+    let _m_referrers = mock(
+        "GET",
+        format!("/v2/{}/referrers/{}", name, EMPTY_DIGEST).as_str(),
+    )
+    .with_status(404)
+    .create();
+    let _m_delete = mock(
+        "DELETE",
+        format!("/v2/{}/manifests/{}", name, EMPTY_DIGEST).as_str(),
+    )
+    .with_status(403)
+    .with_header("Content-Type", "application/json")
+    .with_body(r#"{"errors": [{"code": "DENIED", "message": "requested access to the resource is denied"}]}"#)
+    .create();
+    let _m_upload = mock("POST", format!("/v2/{}/blobs/uploads/", name).as_str())
+        .with_status(403)
+        .with_header("Content-Type", "application/json")
+        .with_body(
+            r#"{"errors": [{"code": "UNAUTHORIZED", "message": "authentication required"}]}"#,
+        )
+        .create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = dkregistry::v2::Client::configure()
+        .registry(&addr)
+        .insecure_registry(true)
+        .username(None)
+        .password(None)
+        .build()
+        .unwrap();
+
+    let check = runtime.block_on(dclient.check_api(Some(name))).unwrap();
+    eprintln!("DBG {:?}", check);
+    assert_eq!(check.referrers, Some(false));
+    assert_eq!(check.delete, Some(false));
+    assert_eq!(check.push, Some(false));
+
+    mockito::reset();
+}
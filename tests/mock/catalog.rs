@@ -46,14 +46,14 @@ fn test_catalog_paginate() {
         .with_header(
             "Link",
             &format!(
-                r#"<{}/v2/_catalog?n=21&last=r1/i1>; rel="next""#,
+                r#"<{}/v2/_catalog?n=1&last=r1/i1>; rel="next""#,
                 mockito::server_url()
             ),
         )
         .with_header("Content-Type", "application/json")
         .with_body(repos_p1)
         .create();
-    let _m2 = mock("GET", "/v2/_catalog?n=1&last=r1")
+    let _m2 = mock("GET", "/v2/_catalog?n=1&last=r1/i1")
         .with_status(200)
         .with_header("Content-Type", "application/json")
         .with_body(repos_p2)
@@ -74,10 +74,7 @@ fn test_catalog_paginate() {
     assert_eq!(page1.unwrap().unwrap(), "r1/i1".to_owned());
 
     let (page2, next) = runtime.block_on(next.into_future());
-    // TODO(lucab): implement pagination
-    if page2.is_some() {
-        panic!("end is some: {:?}", page2);
-    }
+    assert_eq!(page2.unwrap().unwrap(), "r2".to_owned());
 
     let (end, _) = runtime.block_on(next.into_future());
     if end.is_some() {
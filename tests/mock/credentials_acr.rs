@@ -0,0 +1,79 @@
+extern crate dkregistry;
+extern crate mockito;
+extern crate tokio;
+
+use self::mockito::{mock, Matcher};
+use self::tokio::runtime::Runtime;
+use dkregistry::v2::provider::AcrProvider;
+use std::sync::Arc;
+
+#[test]
+fn test_acr_token_exchange() {
+    let addr = mockito::server_address().to_string();
+
+    let m_aad = mock("POST", "/my-tenant/oauth2/v2.0/token")
+        .match_body(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("grant_type".into(), "client_credentials".into()),
+            Matcher::UrlEncoded("client_id".into(), "my-client".into()),
+            Matcher::UrlEncoded("client_secret".into(), "my-secret".into()),
+        ]))
+        .with_status(200)
+        .with_body(r#"{"access_token":"aad-token","expires_in":3599}"#)
+        .create();
+    let m_exchange = mock("POST", "/oauth2/exchange")
+        .match_body(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("grant_type".into(), "access_token".into()),
+            Matcher::UrlEncoded("service".into(), addr.clone()),
+            Matcher::UrlEncoded("tenant".into(), "my-tenant".into()),
+            Matcher::UrlEncoded("access_token".into(), "aad-token".into()),
+        ]))
+        .with_status(200)
+        .with_body(r#"{"refresh_token":"acr-refresh"}"#)
+        .create();
+    let _m_auth = mock("GET", "/v2/")
+        .with_status(401)
+        .with_header(
+            "WWW-Authenticate",
+            &format!(
+                r#"Bearer realm="http://{}/oauth2/token",service="{}""#,
+                addr, addr
+            ),
+        )
+        .create();
+    let basic = base64::encode("00000000-0000-0000-0000-000000000000:acr-refresh");
+    let m_token = mock("GET", "/oauth2/token")
+        .match_query(Matcher::Any)
+        .match_header("Authorization", format!("Basic {}", basic).as_str())
+        .with_status(200)
+        .with_body(r#"{"access_token":"acr-access"}"#)
+        .create();
+    let m_blob = mock("HEAD", "/v2/my-repo/my-image/blobs/fakedigest")
+        .match_header("Authorization", "Bearer acr-access")
+        .with_status(200)
+        .with_header("Content-Length", "1234")
+        .create();
+
+    let provider = AcrProvider::from_service_principal("my-tenant", "my-client", "my-secret")
+        .authority_host(&format!("http://{}", addr))
+        .insecure_registry(true);
+    let runtime = Runtime::new().unwrap();
+    let dclient = dkregistry::v2::Client::configure()
+        .registry(&addr)
+        .insecure_registry(true)
+        .credentials_provider(Arc::new(provider))
+        .build()
+        .unwrap();
+    let dclient = runtime
+        .block_on(dclient.authenticate(&["repository:my-repo/my-image:pull"]))
+        .unwrap();
+    let res = runtime
+        .block_on(dclient.get_blob_size("my-repo/my-image", "fakedigest"))
+        .unwrap();
+    assert_eq!(res, Some(1234));
+    m_aad.assert();
+    m_exchange.assert();
+    m_token.assert();
+    m_blob.assert();
+
+    mockito::reset();
+}
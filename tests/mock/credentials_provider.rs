@@ -0,0 +1,71 @@
+extern crate dkregistry;
+extern crate mockito;
+extern crate tokio;
+
+use self::mockito::mock;
+use self::tokio::runtime::Runtime;
+use dkregistry::v2::provider::{CredentialsProvider, ProvidedCredentials};
+use futures::future::BoxFuture;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+/// Provider of short-lived credentials, with a new password on each call.
+#[derive(Debug, Default)]
+struct ExpiringProvider {
+    calls: AtomicUsize,
+}
+
+impl CredentialsProvider for ExpiringProvider {
+    fn credentials<'a>(
+        &'a self,
+        _registry: &'a str,
+    ) -> BoxFuture<'a, dkregistry::errors::Result<ProvidedCredentials>> {
+        let call = self.calls.fetch_add(1, Ordering::SeqCst) + 1;
+        Box::pin(async move {
+            Ok(ProvidedCredentials {
+                username: "user".to_string(),
+                password: format!("password{}", call),
+                expires_at: Some(SystemTime::now() + Duration::from_secs(5)),
+            })
+        })
+    }
+}
+
+#[test]
+fn test_provided_credentials_renewed() {
+    let addr = mockito::server_address().to_string();
+
+    let _m_auth = mock("GET", "/v2/")
+        .with_status(401)
+        .with_header("WWW-Authenticate", r#"Basic realm="mock""#)
+        .create();
+    let m_blob = mock("HEAD", "/v2/my-repo/my-image/blobs/fakedigest")
+        .match_header(
+            "Authorization",
+            format!("Basic {}", base64::encode("user:password2")).as_str(),
+        )
+        .with_status(200)
+        .with_header("Content-Length", "1234")
+        .create();
+
+    let provider = Arc::new(ExpiringProvider::default());
+    let runtime = Runtime::new().unwrap();
+    let dclient = dkregistry::v2::Client::configure()
+        .registry(&addr)
+        .insecure_registry(true)
+        .credentials_provider(provider.clone())
+        .build()
+        .unwrap();
+    let dclient = runtime.block_on(dclient.authenticate(&[])).unwrap();
+    assert_eq!(provider.calls.load(Ordering::SeqCst), 1);
+
+    let res = runtime
+        .block_on(dclient.get_blob_size("my-repo/my-image", "fakedigest"))
+        .unwrap();
+    assert_eq!(res, Some(1234));
+    assert_eq!(provider.calls.load(Ordering::SeqCst), 2);
+    m_blob.assert();
+
+    mockito::reset();
+}
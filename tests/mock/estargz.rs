@@ -0,0 +1,146 @@
+extern crate dkregistry;
+extern crate libflate;
+extern crate mockito;
+extern crate sha2;
+extern crate tar;
+extern crate tokio;
+
+use self::libflate::gzip;
+use self::mockito::mock;
+use self::tokio::runtime::Runtime;
+use crate::mock::estargz::sha2::Digest;
+use dkregistry::v2::manifest::Descriptor;
+use std::collections::HashMap;
+use std::io::Write;
+
+static NAME: &str = "my-repo/my-image";
+
+fn digest(data: &[u8]) -> String {
+    format!("sha256:{:x}", sha2::Sha256::digest(data))
+}
+
+fn member(data: &[u8]) -> Vec<u8> {
+    let mut encoder = gzip::Encoder::new(Vec::new()).unwrap();
+    encoder.write_all(data).unwrap();
+    encoder.finish().into_result().unwrap()
+}
+
+fn tar_header(path: &str, size: usize) -> Vec<u8> {
+    let mut header = tar::Header::new_gnu();
+    header.set_path(path).unwrap();
+    header.set_size(size as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    header.as_bytes().to_vec()
+}
+
+fn padded(data: &[u8]) -> Vec<u8> {
+    let mut data = data.to_vec();
+    data.resize((data.len() + 511) / 512 * 512, 0);
+    data
+}
+
+/// Build an eStargz layer, with `hello.txt` in two chunks and `other.txt`.
+fn estargz_layer() -> (Vec<u8>, Vec<u8>, Vec<u64>) {
+    let mut blob = member(&tar_header("hello.txt", 12));
+    let mut offsets = vec![blob.len() as u64];
+    blob.extend(member(b"hello "));
+    offsets.push(blob.len() as u64);
+    // The last chunk is followed by the tar padding and the next header.
+    let mut tail = padded(b"hello world\n")[6..].to_vec();
+    tail.extend(tar_header("other.txt", 5));
+    blob.extend(member(&tail));
+    offsets.push(blob.len() as u64);
+    blob.extend(member(&padded(b"other")));
+
+    let toc = serde_json::json!({
+        "version": 1,
+        "entries": [
+            {"name": "hello.txt", "type": "reg", "size": 12, "offset": offsets[0],
+             "chunkSize": 6, "digest": digest(b"hello world\n")},
+            {"name": "hello.txt", "type": "chunk", "offset": offsets[1],
+             "chunkOffset": 6, "chunkSize": 6},
+            {"name": "other.txt", "type": "reg", "size": 5, "offset": offsets[2],
+             "digest": digest(b"other")},
+        ],
+    })
+    .to_string()
+    .into_bytes();
+    let toc_offset = blob.len() as u64;
+    offsets.push(toc_offset);
+    let mut toc_tar = tar_header("stargz.index.json", toc.len());
+    toc_tar.extend(padded(&toc));
+    toc_tar.extend([0; 1024]);
+    blob.extend(member(&toc_tar));
+
+    blob.extend(b"\x1f\x8b\x08\x04\0\0\0\0\0\xff\x1a\x00SG\x16\x00");
+    blob.extend(format!("{:016x}STARGZ", toc_offset).into_bytes());
+    blob.extend(b"\x01\x00\x00\xff\xff\0\0\0\0\0\0\0\0");
+    (blob, toc, offsets)
+}
+
+fn range_mock(digest: &str, blob: &[u8], start: u64, end: u64) -> mockito::Mock {
+    mock("GET", format!("/v2/{}/blobs/{}", NAME, digest).as_str())
+        .match_header("range", format!("bytes={}-{}", start, end - 1).as_str())
+        .with_status(206)
+        .with_body(&blob[start as usize..end as usize])
+        .create()
+}
+
+#[test]
+fn test_estargz_read_file() {
+    let (blob, toc, offsets) = estargz_layer();
+    let size = blob.len() as u64;
+    let layer = Descriptor {
+        media_type: "application/vnd.oci.image.layer.v1.tar+gzip".to_string(),
+        digest: digest(&blob),
+        size,
+        annotations: HashMap::from([(
+            dkregistry::v2::ANNOTATION_TOC_DIGEST.to_string(),
+            digest(&toc),
+        )]),
+        ..Descriptor::default()
+    };
+    assert!(layer.is_estargz());
+
+    let m_footer = range_mock(&layer.digest, &blob, size - 51, size);
+    let m_toc = range_mock(&layer.digest, &blob, offsets[3], size - 51);
+    let m_hello = range_mock(&layer.digest, &blob, offsets[0], offsets[2]);
+    let m_other = range_mock(&layer.digest, &blob, offsets[2], offsets[3]);
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = dkregistry::v2::Client::configure()
+        .registry(&mockito::server_address().to_string())
+        .insecure_registry(true)
+        .username(None)
+        .password(None)
+        .build()
+        .unwrap();
+
+    let toc = runtime
+        .block_on(dclient.get_estargz_toc(NAME, &layer))
+        .unwrap();
+    assert_eq!(toc.entries.len(), 3);
+    assert_eq!(toc.entry("/hello.txt").unwrap().size, 12);
+    m_footer.assert();
+    m_toc.assert();
+
+    let hello = runtime
+        .block_on(dclient.read_estargz_file(NAME, &layer, &toc, "hello.txt"))
+        .unwrap();
+    assert_eq!(hello, b"hello world\n");
+    m_hello.assert();
+    let other = runtime
+        .block_on(dclient.read_estargz_file(NAME, &layer, &toc, "./other.txt"))
+        .unwrap();
+    assert_eq!(other, b"other");
+    m_other.assert();
+
+    let res = runtime.block_on(dclient.read_estargz_file(NAME, &layer, &toc, "missing"));
+    assert!(matches!(
+        res,
+        Err(dkregistry::errors::Error::EstargzFileNotFound(_))
+    ));
+
+    mockito::reset();
+}
@@ -0,0 +1,131 @@
+extern crate dkregistry;
+extern crate mockito;
+extern crate sha2;
+extern crate tokio;
+
+use self::mockito::{mock, Matcher};
+use self::tokio::runtime::Runtime;
+use crate::mock::foreign_layers::sha2::Digest;
+use dkregistry::mediatypes::MediaTypes;
+use dkregistry::v2::manifest::Descriptor;
+use dkregistry::v2::ForeignLayerPolicy;
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
+
+static NAME: &str = "my-repo/my-image";
+static BLOB: &[u8] = b"windows base layer";
+
+fn foreign_layer(path: &str) -> Descriptor {
+    Descriptor {
+        media_type: MediaTypes::ImageLayerForeignTgz.to_string(),
+        digest: format!("sha256:{:x}", sha2::Sha256::digest(BLOB)),
+        size: BLOB.len() as u64,
+        urls: Some(vec![format!(
+            "http://{}{}",
+            mockito::server_address(),
+            path
+        )]),
+        ..Descriptor::default()
+    }
+}
+
+fn client(policy: ForeignLayerPolicy) -> dkregistry::v2::Client {
+    let mut headers = HeaderMap::new();
+    headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer secret"));
+    dkregistry::v2::Client::configure()
+        .registry(&mockito::server_address().to_string())
+        .insecure_registry(true)
+        .username(None)
+        .password(None)
+        .default_headers(headers)
+        .foreign_layers(policy)
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn test_foreign_layer_fetch() {
+    let layer = foreign_layer("/foreign/layer.tar.gz");
+    assert!(layer.is_foreign());
+    let m_foreign = mock("GET", "/foreign/layer.tar.gz")
+        .match_header("authorization", Matcher::Missing)
+        .with_status(302)
+        .with_header("Location", "/foreign/redirected.tar.gz")
+        .create();
+    let m_redirected = mock("GET", "/foreign/redirected.tar.gz")
+        .match_header("authorization", Matcher::Missing)
+        .with_status(200)
+        .with_body(BLOB)
+        .create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = client(ForeignLayerPolicy::Fetch);
+    let res = runtime
+        .block_on(dclient.get_layer_response(NAME, &layer))
+        .unwrap()
+        .unwrap();
+    let blob = runtime.block_on(res.bytes()).unwrap();
+    assert_eq!(blob, BLOB);
+    m_foreign.assert();
+    m_redirected.assert();
+
+    mockito::reset();
+}
+
+#[test]
+fn test_foreign_layer_fallback() {
+    let layer = foreign_layer("/foreign/missing.tar.gz");
+    let _m_foreign = mock("GET", "/foreign/missing.tar.gz")
+        .with_status(404)
+        .create();
+    let m_blob = mock(
+        "GET",
+        format!("/v2/{}/blobs/{}", NAME, layer.digest).as_str(),
+    )
+    .with_status(200)
+    .with_body(BLOB)
+    .create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = client(ForeignLayerPolicy::Fetch);
+    let res = runtime
+        .block_on(dclient.get_layer_response(NAME, &layer))
+        .unwrap()
+        .unwrap();
+    assert_eq!(runtime.block_on(res.bytes()).unwrap(), BLOB);
+    m_blob.assert();
+
+    mockito::reset();
+}
+
+#[test]
+fn test_foreign_layer_skip() {
+    let layer = foreign_layer("/foreign/skipped.tar.gz");
+    let m_foreign = mock("GET", "/foreign/skipped.tar.gz").expect(0).create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = client(ForeignLayerPolicy::Skip);
+    let res = runtime
+        .block_on(dclient.get_layer_response(NAME, &layer))
+        .unwrap();
+    assert!(res.is_none());
+    m_foreign.assert();
+
+    mockito::reset();
+}
+
+#[test]
+fn test_foreign_layer_error() {
+    let layer = foreign_layer("/foreign/refused.tar.gz");
+    let m_foreign = mock("GET", "/foreign/refused.tar.gz").expect(0).create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = client(ForeignLayerPolicy::Error);
+    let res = runtime.block_on(dclient.get_layer_response(NAME, &layer));
+    assert!(matches!(
+        res,
+        Err(dkregistry::errors::Error::ForeignLayer(_))
+    ));
+    m_foreign.assert();
+
+    mockito::reset();
+}
@@ -0,0 +1,166 @@
+extern crate dkregistry;
+extern crate mockito;
+extern crate tokio;
+
+use self::mockito::mock;
+use self::tokio::runtime::Runtime;
+use dkregistry::errors::Error;
+use dkregistry::mediatypes::MediaTypes;
+use dkregistry::v2::manifest::{
+    Descriptor, ImageIndexBuilder, Manifest, ManifestBuilder, OciManifest, Platform,
+};
+
+#[test]
+fn test_get_image_config() {
+    let name = "my-repo/my-image";
+    let config = std::fs::read("tests/fixtures/container_config_blob.json").unwrap();
+    let config_digest = dkregistry::v2::sha256_digest(&config);
+    let manifest = ManifestBuilder::new()
+        .config_blob(
+            MediaTypes::ContainerConfigV1,
+            &config_digest,
+            config.len() as u64,
+        )
+        .build()
+        .unwrap();
+
+    let addr = mockito::server_address().to_string();
+    let _m = mock("GET", format!("/v2/{}/manifests/latest", name).as_str())
+        .with_status(200)
+        .with_header("Content-Type", &MediaTypes::ManifestV2S2.to_string())
+        .with_body(&manifest.body)
+        .create();
+    let _b = mock(
+        "GET",
+        format!("/v2/{}/blobs/{}", name, config_digest).as_str(),
+    )
+    .with_status(200)
+    .with_body(&config)
+    .expect(1)
+    .create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = dkregistry::v2::Client::configure()
+        .registry(&addr)
+        .insecure_registry(true)
+        .username(None)
+        .password(None)
+        .build()
+        .unwrap();
+
+    let manifest = runtime
+        .block_on(dclient.get_manifest(name, "latest"))
+        .unwrap();
+    let config_blob = runtime
+        .block_on(dclient.get_image_config(name, &manifest))
+        .unwrap();
+    assert_eq!(config_blob.architecture, "amd64");
+    _b.assert();
+
+    let truncated = Manifest::Artifact(Box::new(OciManifest {
+        config: Some(Descriptor {
+            media_type: MediaTypes::OciImageConfig.to_string(),
+            digest: config_digest.clone(),
+            size: config.len() as u64 - 1,
+            ..Default::default()
+        }),
+        ..Default::default()
+    }));
+    let res = runtime.block_on(dclient.get_image_config(name, &truncated));
+    assert!(matches!(res, Err(Error::SizeMismatch { .. })));
+
+    let configless = Manifest::Artifact(Box::default());
+    let res = runtime.block_on(dclient.get_image_config(name, &configless));
+    assert!(matches!(
+        res,
+        Err(Error::Manifest(
+            dkregistry::v2::manifest::ManifestError::MissingConfig
+        ))
+    ));
+
+    mockito::reset();
+}
+
+#[test]
+fn test_get_image_labels() {
+    let name = "my-repo/my-image";
+    let config = serde_json::json!({
+        "architecture": "arm64",
+        "os": "linux",
+        "config": {"Labels": {"org.opencontainers.image.version": "1.2.3"}},
+        "rootfs": {"type": "layers", "diff_ids": []},
+    })
+    .to_string();
+    let config_digest = dkregistry::v2::sha256_digest(config.as_bytes());
+    let manifest = ManifestBuilder::new()
+        .media_type(MediaTypes::OciImageManifest)
+        .config_blob(
+            MediaTypes::OciImageConfig,
+            &config_digest,
+            config.len() as u64,
+        )
+        .build()
+        .unwrap();
+    let index = ImageIndexBuilder::new()
+        .platform_manifest(
+            MediaTypes::OciImageManifest,
+            &manifest.digest,
+            manifest.body.len() as u64,
+            Platform::new("linux", "arm64"),
+        )
+        .build_bytes()
+        .unwrap();
+
+    let addr = mockito::server_address().to_string();
+    let _i = mock("GET", format!("/v2/{}/manifests/latest", name).as_str())
+        .with_status(200)
+        .with_header("Content-Type", &MediaTypes::OciImageIndex.to_string())
+        .with_body(&index.body)
+        .create();
+    let _m = mock(
+        "GET",
+        format!("/v2/{}/manifests/{}", name, manifest.digest).as_str(),
+    )
+    .with_status(200)
+    .with_header("Content-Type", &MediaTypes::OciImageManifest.to_string())
+    .with_body(&manifest.body)
+    .create();
+    let _b = mock(
+        "GET",
+        format!("/v2/{}/blobs/{}", name, config_digest).as_str(),
+    )
+    .with_status(200)
+    .with_body(&config)
+    .create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = dkregistry::v2::Client::configure()
+        .registry(&addr)
+        .insecure_registry(true)
+        .username(None)
+        .password(None)
+        .build()
+        .unwrap();
+
+    let labels = runtime
+        .block_on(dclient.get_image_labels(name, "latest", &Platform::new("linux", "aarch64")))
+        .unwrap();
+    assert_eq!(
+        labels
+            .get("org.opencontainers.image.version")
+            .map(String::as_str),
+        Some("1.2.3")
+    );
+
+    let res = runtime.block_on(dclient.get_image_labels(
+        name,
+        "latest",
+        &Platform::new("linux", "amd64"),
+    ));
+    match res {
+        Err(Error::PlatformNotFound(platform)) => assert_eq!(platform, "linux/amd64"),
+        res => panic!("unexpected result {:?}", res),
+    }
+
+    mockito::reset();
+}
@@ -0,0 +1,143 @@
+extern crate dkregistry;
+extern crate mockito;
+extern crate tokio;
+
+use self::mockito::mock;
+use self::tokio::runtime::Runtime;
+use dkregistry::mediatypes::MediaTypes;
+use dkregistry::v2::manifest::{ImageIndexBuilder, ManifestBuilder, ManifestBytes, Platform};
+use dkregistry::v2::sha256_digest;
+
+const BASE_LAYER: &str = "sha256:a3ed95caeb02ffe68cdd9fd84406680ae93d633cb16422d00e8a7c22955b46d4";
+const APP_LAYER: &str = "sha256:e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+/// Image manifest for `arch`, with its configuration.
+fn image(arch: &str, top_layer: (MediaTypes, &str, u64)) -> (ManifestBytes, String) {
+    let config = serde_json::json!({
+        "architecture": arch,
+        "os": "linux",
+        "rootfs": {"type": "layers", "diff_ids": []},
+    })
+    .to_string();
+    let manifest = ManifestBuilder::new()
+        .media_type(MediaTypes::OciImageManifest)
+        .config_blob(
+            MediaTypes::OciImageConfig,
+            &sha256_digest(config.as_bytes()),
+            config.len() as u64,
+        )
+        .layer_blob(MediaTypes::ImageLayerTgz, BASE_LAYER, 300)
+        .layer_blob(top_layer.0, top_layer.1, top_layer.2)
+        .build()
+        .unwrap();
+    (manifest, config)
+}
+
+#[test]
+fn test_get_image_size() {
+    let name = "my-repo/my-image";
+    let tar = MediaTypes::Other("application/vnd.oci.image.layer.v1.tar".to_string());
+    let (amd64, amd64_config) = image("amd64", (tar, APP_LAYER, 50));
+    let (arm64, arm64_config) = image("arm64", (MediaTypes::ImageLayerTgz, BASE_LAYER, 300));
+    let index = ImageIndexBuilder::new()
+        .platform_manifest(
+            MediaTypes::OciImageManifest,
+            &amd64.digest,
+            amd64.body.len() as u64,
+            Platform::new("linux", "amd64"),
+        )
+        .platform_manifest(
+            MediaTypes::OciImageManifest,
+            &arm64.digest,
+            arm64.body.len() as u64,
+            Platform::new("linux", "arm64"),
+        )
+        // Attestations of BuildKit, which are not fetched.
+        .platform_manifest(
+            MediaTypes::OciImageManifest,
+            APP_LAYER,
+            500,
+            Platform::new("unknown", "unknown"),
+        )
+        .build_bytes()
+        .unwrap();
+
+    let addr = mockito::server_address().to_string();
+    let mut mocks = vec![
+        mock("GET", format!("/v2/{}/manifests/latest", name).as_str())
+            .with_status(200)
+            .with_header("Content-Type", &MediaTypes::OciImageIndex.to_string())
+            .with_body(&index.body)
+            .create(),
+    ];
+    for (manifest, config) in [(&amd64, &amd64_config), (&arm64, &arm64_config)] {
+        mocks.push(
+            mock(
+                "GET",
+                format!("/v2/{}/manifests/{}", name, manifest.digest).as_str(),
+            )
+            .with_status(200)
+            .with_header("Content-Type", &MediaTypes::OciImageManifest.to_string())
+            .with_body(&manifest.body)
+            .create(),
+        );
+        mocks.push(
+            mock(
+                "GET",
+                format!("/v2/{}/blobs/{}", name, sha256_digest(config.as_bytes())).as_str(),
+            )
+            .with_status(200)
+            .with_body(config)
+            .create(),
+        );
+    }
+    // Shared layers are only looked at once.
+    let trailer = mock("GET", format!("/v2/{}/blobs/{}", name, BASE_LAYER).as_str())
+        .match_header("range", "bytes=296-299")
+        .with_status(206)
+        .with_body(1000u32.to_le_bytes())
+        .expect(1)
+        .create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = dkregistry::v2::Client::configure()
+        .registry(&addr)
+        .insecure_registry(true)
+        .username(None)
+        .password(None)
+        .build()
+        .unwrap();
+
+    let sizes = runtime
+        .block_on(dclient.get_image_size(name, "latest"))
+        .unwrap();
+    trailer.assert();
+    assert_eq!(sizes.images.len(), 2);
+
+    let amd64_size = &sizes.images[0];
+    assert_eq!(amd64_size.digest, amd64.digest);
+    assert_eq!(amd64_size.platform, Some(Platform::new("linux", "amd64")));
+    let layers = amd64_size
+        .layers
+        .iter()
+        .map(|l| (l.compressed, l.uncompressed))
+        .collect::<Vec<_>>();
+    assert_eq!(layers, vec![(300, Some(1000)), (50, Some(50))]);
+    assert_eq!(
+        amd64_size.compressed,
+        amd64.body.len() as u64 + amd64_config.len() as u64 + 350
+    );
+    assert_eq!(amd64_size.uncompressed, Some(1050));
+    assert_eq!(sizes.images[1].uncompressed, Some(2000));
+
+    let compressed = index.body.len()
+        + amd64.body.len()
+        + amd64_config.len()
+        + arm64.body.len()
+        + arm64_config.len()
+        + 350;
+    assert_eq!(sizes.compressed, compressed as u64);
+    assert_eq!(sizes.uncompressed, Some(1050));
+
+    mockito::reset();
+}
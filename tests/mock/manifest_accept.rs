@@ -0,0 +1,48 @@
+extern crate dkregistry;
+extern crate mockito;
+extern crate tokio;
+
+use self::mockito::mock;
+use self::tokio::runtime::Runtime;
+use dkregistry::mediatypes::MediaTypes;
+use dkregistry::v2::manifest::Manifest;
+
+#[test]
+fn test_manifest_accept_media_types() {
+    let name = "my-repo/my-image";
+    let tag = "latest";
+
+    let ep = format!("/v2/{}/manifests/{}", name, tag);
+    let addr = mockito::server_address().to_string();
+    let _m = mock("GET", ep.as_str())
+        .match_header(
+            "Accept",
+            "application/vnd.oci.image.index.v1+json; q=1,\
+             application/vnd.docker.distribution.manifest.list.v2+json; q=0.9",
+        )
+        .with_status(200)
+        .with_header("Content-Type", "application/vnd.oci.image.index.v1+json")
+        .with_body_from_file("tests/fixtures/oci_image_index.json")
+        .create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = dkregistry::v2::Client::configure()
+        .registry(&addr)
+        .insecure_registry(true)
+        .accept_media_types(vec![MediaTypes::OciImageIndex, MediaTypes::ManifestList])
+        .username(None)
+        .password(None)
+        .build()
+        .unwrap();
+
+    let (manifest, media_type) = runtime
+        .block_on(dclient.get_manifest_and_media_type(name, tag))
+        .unwrap();
+    assert_eq!(media_type, MediaTypes::OciImageIndex);
+    match manifest {
+        Manifest::ML(list) => assert_eq!(list.manifests.len(), 3),
+        _ => panic!("expected a manifest list"),
+    }
+
+    mockito::reset();
+}
@@ -0,0 +1,82 @@
+extern crate dkregistry;
+extern crate mockito;
+extern crate tokio;
+
+use self::mockito::mock;
+use self::tokio::runtime::Runtime;
+use dkregistry::errors::Error;
+use dkregistry::mediatypes::MediaTypes;
+use dkregistry::v2::manifest::Manifest;
+
+#[test]
+fn test_manifest_artifact() {
+    let name = "my-repo/my-image";
+    let tag = "sbom";
+
+    let ep = format!("/v2/{}/manifests/{}", name, tag);
+    let addr = mockito::server_address().to_string();
+    let _m = mock("GET", ep.as_str())
+        .with_status(200)
+        .with_header("Content-Type", "application/vnd.oci.image.manifest.v1+json")
+        .with_body_from_file("tests/fixtures/oci_artifact_manifest.json")
+        .create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = dkregistry::v2::Client::configure()
+        .registry(&addr)
+        .insecure_registry(true)
+        .username(None)
+        .password(None)
+        .accepted_types(Some(vec![(MediaTypes::OciImageManifest, None)]))
+        .build()
+        .unwrap();
+
+    let (artifact, _) = runtime
+        .block_on(dclient.get_artifact_manifest(name, tag))
+        .unwrap();
+    assert_eq!(artifact.artifact_type(), Some("application/spdx+json"));
+
+    let manifest = runtime.block_on(dclient.get_manifest(name, tag)).unwrap();
+    assert!(matches!(manifest, Manifest::Artifact(ref m) if **m == artifact));
+    assert_eq!(manifest.layers_digests(None).unwrap().len(), 1);
+
+    mockito::reset();
+}
+
+#[test]
+fn test_manifest_artifact_digest_mismatch() {
+    let name = "my-repo/my-image";
+    let tag = "sbom";
+    let digest = "sha256:2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+
+    let addr = mockito::server_address().to_string();
+    let _m_tag = mock("GET", format!("/v2/{}/manifests/{}", name, tag).as_str())
+        .with_status(200)
+        .with_header("Content-Type", "application/vnd.oci.image.manifest.v1+json")
+        .with_header("Docker-Content-Digest", digest)
+        .with_body_from_file("tests/fixtures/oci_artifact_manifest.json")
+        .create();
+    let _m_digest = mock("GET", format!("/v2/{}/manifests/{}", name, digest).as_str())
+        .with_status(200)
+        .with_header("Content-Type", "application/vnd.oci.image.manifest.v1+json")
+        .with_body_from_file("tests/fixtures/oci_artifact_manifest.json")
+        .create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = dkregistry::v2::Client::configure()
+        .registry(&addr)
+        .insecure_registry(true)
+        .username(None)
+        .password(None)
+        .accepted_types(Some(vec![(MediaTypes::OciImageManifest, None)]))
+        .build()
+        .unwrap();
+
+    // Both the announced and the requested digests are verified.
+    let res = runtime.block_on(dclient.get_artifact_manifest(name, tag));
+    assert!(matches!(res, Err(Error::DigestMismatch { expected, .. }) if expected == digest));
+    let res = runtime.block_on(dclient.get_manifest(name, digest));
+    assert!(matches!(res, Err(Error::DigestMismatch { expected, .. }) if expected == digest));
+
+    mockito::reset();
+}
@@ -0,0 +1,117 @@
+extern crate dkregistry;
+extern crate mockito;
+extern crate tokio;
+
+use self::mockito::{mock, Matcher};
+use self::tokio::runtime::Runtime;
+use dkregistry::mediatypes::MediaTypes;
+use dkregistry::v2::manifest::{Manifest, ManifestUpdate};
+
+static DIGEST: &str = "sha256:891f22d1ce0bed0f4d354ce3de87dc18cacb32318e9c5cd42ebbb8a29c4eee76";
+
+#[test]
+fn test_manifest_if_changed() {
+    let name = "my-repo/my-image";
+    let tag = "latest";
+
+    let ep = format!("/v2/{}/manifests/{}", name, tag);
+    let addr = mockito::server_address().to_string();
+    let etag = format!("\"{}\"", DIGEST);
+    let _m_unchanged = mock("GET", ep.as_str())
+        .match_header("If-None-Match", etag.as_str())
+        .with_status(304)
+        .create();
+    let _m = mock("GET", ep.as_str())
+        .match_header("If-None-Match", Matcher::Missing)
+        .with_status(200)
+        .with_header(
+            "Content-Type",
+            "application/vnd.docker.distribution.manifest.list.v2+json",
+        )
+        .with_header("Docker-Content-Digest", DIGEST)
+        .with_header("ETag", etag.as_str())
+        .with_body_from_file("tests/fixtures/manifest_list_v2.json")
+        .create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = dkregistry::v2::Client::configure()
+        .registry(&addr)
+        .insecure_registry(true)
+        .username(None)
+        .password(None)
+        .build()
+        .unwrap();
+
+    let (manifest, digest) = runtime
+        .block_on(dclient.get_manifest_and_ref(name, tag))
+        .unwrap();
+    assert!(matches!(manifest, Manifest::ML(_)));
+    assert_eq!(digest.as_deref(), Some(DIGEST));
+
+    // Both the entity tag and the digest are accepted.
+    let update = runtime
+        .block_on(dclient.get_manifest_if_changed(name, tag, &etag))
+        .unwrap();
+    assert!(matches!(update, ManifestUpdate::NotModified));
+    let update = runtime
+        .block_on(dclient.get_manifest_if_changed(name, tag, DIGEST))
+        .unwrap();
+    assert!(matches!(update, ManifestUpdate::NotModified));
+
+    mockito::reset();
+}
+
+#[test]
+fn test_manifest_changed() {
+    let name = "my-repo/my-image";
+    let tag = "latest";
+
+    let ep = format!("/v2/{}/manifests/{}", name, tag);
+    let addr = mockito::server_address().to_string();
+    let _m = mock("GET", ep.as_str())
+        .match_header("If-None-Match", "\"sha256:olddigest\"")
+        .with_status(200)
+        .with_header(
+            "Content-Type",
+            "application/vnd.docker.distribution.manifest.list.v2+json",
+        )
+        .with_header("Docker-Content-Digest", DIGEST)
+        .with_body_from_file("tests/fixtures/manifest_list_v2.json")
+        .create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = dkregistry::v2::Client::configure()
+        .registry(&addr)
+        .insecure_registry(true)
+        .username(None)
+        .password(None)
+        .build()
+        .unwrap();
+
+    let update = runtime
+        .block_on(dclient.get_manifest_if_changed(name, tag, "sha256:olddigest"))
+        .unwrap();
+    match update {
+        ManifestUpdate::Modified {
+            manifest,
+            digest,
+            etag,
+            media_type,
+            body,
+            ..
+        } => {
+            assert!(matches!(manifest, Manifest::ML(_)));
+            assert_eq!(
+                body,
+                std::fs::read("tests/fixtures/manifest_list_v2.json").unwrap()
+            );
+            assert_eq!(media_type, MediaTypes::ManifestList);
+            assert_eq!(digest.as_deref(), Some(DIGEST));
+            // Without an `ETag`, the digest is used.
+            assert_eq!(etag.as_deref(), Some(DIGEST));
+        }
+        ManifestUpdate::NotModified => panic!("manifest should have changed"),
+    }
+
+    mockito::reset();
+}
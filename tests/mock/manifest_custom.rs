@@ -0,0 +1,106 @@
+extern crate dkregistry;
+extern crate mockito;
+extern crate tokio;
+
+use self::mockito::{mock, Matcher};
+use self::tokio::runtime::Runtime;
+use dkregistry::errors::Error;
+use dkregistry::mediatypes::MediaTypes;
+use dkregistry::v2::manifest::Manifest;
+
+static NAME: &str = "my-repo/my-image";
+static MEDIA_TYPE: &str = "application/vnd.example.bundle.v1+json";
+static BODY: &str = r#"{"bundle": "example"}"#;
+
+fn serve(tag: &str, media_type: &str) -> mockito::Mock {
+    mock("GET", format!("/v2/{}/manifests/{}", NAME, tag).as_str())
+        .with_status(200)
+        .with_header("Content-Type", media_type)
+        .with_body(BODY)
+        .create()
+}
+
+#[test]
+fn test_manifest_custom_handler() {
+    let m = mock("GET", format!("/v2/{}/manifests/latest", NAME).as_str())
+        .match_header(
+            "Accept",
+            Matcher::Regex(format!(r",{}; q=0\.4$", regex_escape(MEDIA_TYPE))),
+        )
+        .with_status(200)
+        .with_header("Content-Type", MEDIA_TYPE)
+        .with_body(BODY)
+        .create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = dkregistry::v2::Client::configure()
+        .registry(&mockito::server_address().to_string())
+        .insecure_registry(true)
+        .username(None)
+        .password(None)
+        .media_type_handler(MEDIA_TYPE, |body| {
+            serde_json::from_slice::<serde_json::Value>(body).map_err(Error::from)
+        })
+        .build()
+        .unwrap();
+
+    let manifest = runtime
+        .block_on(dclient.get_manifest(NAME, "latest"))
+        .unwrap();
+    let custom = match manifest {
+        Manifest::Custom(custom) => custom,
+        m => panic!("unexpected manifest {:?}", m),
+    };
+    assert_eq!(custom.media_type, MediaTypes::Other(MEDIA_TYPE.to_string()));
+    assert_eq!(custom.body, BODY.as_bytes());
+    let parsed = custom.parsed::<serde_json::Value>().unwrap();
+    assert_eq!(parsed["bundle"], "example");
+    assert!(custom.parsed::<String>().is_none());
+    m.assert();
+
+    mockito::reset();
+}
+
+#[test]
+fn test_manifest_unknown_passthrough() {
+    let media_type = "application/vnd.example.unknown.v1+json";
+    let _m = serve("unknown", media_type);
+
+    let runtime = Runtime::new().unwrap();
+    let configure = || {
+        dkregistry::v2::Client::configure()
+            .registry(&mockito::server_address().to_string())
+            .insecure_registry(true)
+            .username(None)
+            .password(None)
+    };
+
+    let dclient = configure().build().unwrap();
+    let res = runtime.block_on(dclient.get_manifest(NAME, "unknown"));
+    assert!(matches!(
+        res,
+        Err(Error::UnsupportedMediaType(MediaTypes::Other(_)))
+    ));
+
+    let dclient = configure()
+        .passthrough_unknown_media_types(true)
+        .build()
+        .unwrap();
+    let raw = runtime
+        .block_on(dclient.get_raw_manifest(NAME, "unknown"))
+        .unwrap();
+    match raw.manifest {
+        Manifest::Custom(custom) => {
+            assert_eq!(custom.media_type.to_string(), media_type);
+            assert!(custom.parsed::<serde_json::Value>().is_none());
+        }
+        m => panic!("unexpected manifest {:?}", m),
+    }
+    assert_eq!(raw.raw.body, BODY.as_bytes());
+
+    mockito::reset();
+}
+
+fn regex_escape(s: &str) -> String {
+    s.replace('.', r"\.").replace('+', r"\+")
+}
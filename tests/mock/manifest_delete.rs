@@ -0,0 +1,47 @@
+extern crate dkregistry;
+extern crate mockito;
+extern crate tokio;
+
+use self::mockito::mock;
+use self::tokio::runtime::Runtime;
+use dkregistry::errors::Error;
+
+static DIGEST: &str = "sha256:2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+
+fn delete_with_status(status: usize) -> dkregistry::errors::Result<()> {
+    let name = "my-repo/my-image";
+
+    let ep = format!("/v2/{}/manifests/{}", name, DIGEST);
+    let addr = mockito::server_address().to_string();
+    let _m = mock("DELETE", ep.as_str()).with_status(status).create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = dkregistry::v2::Client::configure()
+        .registry(&addr)
+        .insecure_registry(true)
+        .username(None)
+        .password(None)
+        .build()
+        .unwrap();
+    let res = runtime.block_on(dclient.delete_manifest(name, DIGEST));
+
+    mockito::reset();
+    res
+}
+
+#[test]
+fn test_manifest_delete() {
+    delete_with_status(202).unwrap();
+}
+
+#[test]
+fn test_manifest_delete_not_found() {
+    let res = delete_with_status(404);
+    assert!(matches!(res, Err(Error::ManifestNotFound(d)) if d == DIGEST));
+}
+
+#[test]
+fn test_manifest_delete_unsupported() {
+    let res = delete_with_status(405);
+    assert!(matches!(res, Err(Error::DeletionUnsupported)));
+}
@@ -0,0 +1,68 @@
+extern crate dkregistry;
+extern crate mockito;
+extern crate tokio;
+
+use self::mockito::mock;
+use self::tokio::runtime::Runtime;
+use dkregistry::errors::Error;
+use dkregistry::mediatypes::MediaTypes;
+
+static DIGEST: &str = "sha256:2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+
+#[test]
+fn test_manifest_head() {
+    let name = "my-repo/my-image";
+    let tag = "latest";
+
+    let ep = format!("/v2/{}/manifests/{}", name, tag);
+    let addr = mockito::server_address().to_string();
+    let _m = mock("HEAD", ep.as_str())
+        .with_status(200)
+        .with_header("Docker-Content-Digest", DIGEST)
+        .with_header("Content-Length", "527")
+        .with_header(
+            "Content-Type",
+            "application/vnd.docker.distribution.manifest.v2+json",
+        )
+        .create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = dkregistry::v2::Client::configure()
+        .registry(&addr)
+        .insecure_registry(true)
+        .username(None)
+        .password(None)
+        .build()
+        .unwrap();
+
+    let head = runtime.block_on(dclient.head_manifest(name, tag)).unwrap();
+    assert_eq!(head.digest.as_deref(), Some(DIGEST));
+    assert_eq!(head.size, 527);
+    assert_eq!(head.media_type, MediaTypes::ManifestV2S2);
+
+    mockito::reset();
+}
+
+#[test]
+fn test_manifest_head_not_found() {
+    let name = "my-repo/my-image";
+    let tag = "missing";
+
+    let ep = format!("/v2/{}/manifests/{}", name, tag);
+    let addr = mockito::server_address().to_string();
+    let _m = mock("HEAD", ep.as_str()).with_status(404).create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = dkregistry::v2::Client::configure()
+        .registry(&addr)
+        .insecure_registry(true)
+        .username(None)
+        .password(None)
+        .build()
+        .unwrap();
+
+    let res = runtime.block_on(dclient.head_manifest(name, tag));
+    assert!(matches!(res, Err(Error::ManifestNotFound(t)) if t == tag));
+
+    mockito::reset();
+}
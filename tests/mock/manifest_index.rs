@@ -0,0 +1,276 @@
+extern crate dkregistry;
+extern crate mockito;
+extern crate serde_json;
+extern crate sha2;
+extern crate tokio;
+
+use self::mockito::mock;
+use self::sha2::Digest;
+use self::tokio::runtime::Runtime;
+use dkregistry::errors::Error;
+use dkregistry::mediatypes::MediaTypes;
+use dkregistry::v2::manifest::{
+    Descriptor, ImageIndex, ImageIndexBuilder, IndexEntry, Manifest, ManifestError, Platform,
+};
+use std::sync::{Arc, Mutex};
+
+static CHILD_DIGEST: &str =
+    "sha256:5b0bcabd1ed22e9fb1310cf6c2dec7cdef19f0ad69efa1f392e94a4333501270";
+static CONFIG_DIGEST: &str =
+    "sha256:b5b2b2c507a0944348e0303114d8d93aaaa081732b86451d9bce1f432a537bc7";
+
+fn mock_index(name: &str, tag: &str) -> mockito::Mock {
+    let ep = format!("/v2/{}/manifests/{}", name, tag);
+    mock("GET", ep.as_str())
+        .with_status(200)
+        .with_header("Content-Type", "application/vnd.oci.image.index.v1+json")
+        .with_body_from_file("tests/fixtures/oci_image_index.json")
+        .create()
+}
+
+#[test]
+fn test_manifest_index_platform() {
+    let name = "my-repo/my-image";
+    let tag = "latest";
+
+    let addr = mockito::server_address().to_string();
+    let config = r#"{"architecture": "arm"}"#;
+    let config_digest = format!("sha256:{:x}", sha2::Sha256::digest(config));
+    let child = format!(
+        r#"{{
+            "schemaVersion": 2,
+            "config": {{
+                "mediaType": "application/vnd.oci.image.config.v1+json",
+                "size": {},
+                "digest": "{}"
+            }},
+            "layers": []
+        }}"#,
+        config.len(),
+        config_digest
+    );
+    let child_digest = format!("sha256:{:x}", sha2::Sha256::digest(&child));
+
+    // Point the index to the child manifest, so that digests are consistent.
+    let index = std::fs::read_to_string("tests/fixtures/oci_image_index.json")
+        .unwrap()
+        .replace(CHILD_DIGEST, &child_digest);
+    let index_ep = format!("/v2/{}/manifests/{}", name, tag);
+    let _m_index = mock("GET", index_ep.as_str())
+        .with_status(200)
+        .with_header("Content-Type", "application/vnd.oci.image.index.v1+json")
+        .with_body(index)
+        .create();
+    let child_ep = format!("/v2/{}/manifests/{}", name, child_digest);
+    let _m_child = mock("GET", child_ep.as_str())
+        .match_header(
+            "Accept",
+            mockito::Matcher::Regex("application/vnd.oci.image.manifest.v1\\+json".to_string()),
+        )
+        .with_status(200)
+        .with_header("Content-Type", "application/vnd.oci.image.manifest.v1+json")
+        .with_body(child)
+        .create();
+    let config_ep = format!("/v2/{}/blobs/{}", name, config_digest);
+    let _m_config = mock("GET", config_ep.as_str())
+        .with_status(200)
+        .with_body(config)
+        .create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = dkregistry::v2::Client::configure()
+        .registry(&addr)
+        .insecure_registry(true)
+        .username(None)
+        .password(None)
+        .build()
+        .unwrap();
+
+    let index = runtime
+        .block_on(dclient.get_image_index(name, tag))
+        .unwrap();
+    assert_eq!(index.manifests.len(), 3);
+
+    let (manifest, digest) = runtime
+        .block_on(dclient.get_platform_manifest(name, tag, "linux", "arm", Some("v7")))
+        .unwrap();
+    assert!(matches!(manifest, Manifest::S2(ref m) if m.architecture() == "arm"));
+    assert_eq!(digest.as_deref(), Some(child_digest.as_str()));
+
+    mockito::reset();
+}
+
+#[test]
+fn test_manifest_index_platform_not_found() {
+    let name = "my-repo/my-image";
+    let tag = "latest";
+
+    let addr = mockito::server_address().to_string();
+    let _m_index = mock_index(name, tag);
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = dkregistry::v2::Client::configure()
+        .registry(&addr)
+        .insecure_registry(true)
+        .username(None)
+        .password(None)
+        .build()
+        .unwrap();
+
+    let res = runtime.block_on(dclient.get_platform_manifest(name, tag, "windows", "amd64", None));
+    assert!(matches!(res, Err(Error::PlatformNotFound(p)) if p == "windows/amd64"));
+
+    mockito::reset();
+}
+
+#[test]
+fn test_manifest_index_push() {
+    let name = "my-repo/my-image";
+    let tag = "latest";
+
+    let platform = Platform {
+        architecture: "arm".to_string(),
+        os: "linux".to_string(),
+        variant: Some("v7".to_string()),
+        ..Platform::default()
+    };
+    let index = ImageIndexBuilder::new()
+        .platform_manifest(MediaTypes::ManifestV2S2, CHILD_DIGEST, 7682, platform)
+        .build()
+        .unwrap();
+    let bytes = index.to_bytes().unwrap();
+    let body = String::from_utf8(bytes.body).unwrap();
+    assert!(!body.contains("null"));
+
+    let ep = format!("/v2/{}/manifests/{}", name, tag);
+    let addr = mockito::server_address().to_string();
+    let _m = mock("PUT", ep.as_str())
+        .match_header(
+            "Content-Type",
+            "application/vnd.docker.distribution.manifest.list.v2+json",
+        )
+        .match_body(mockito::Matcher::Exact(body))
+        .with_status(201)
+        .with_header("Docker-Content-Digest", &bytes.digest)
+        .create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = dkregistry::v2::Client::configure()
+        .registry(&addr)
+        .insecure_registry(true)
+        .username(None)
+        .password(None)
+        .build()
+        .unwrap();
+
+    let digest = runtime
+        .block_on(dclient.put_image_index(name, tag, &index))
+        .unwrap();
+    assert_eq!(digest, bytes.digest);
+
+    mockito::reset();
+}
+
+#[test]
+fn test_manifest_index_missing_platform() {
+    let entry = IndexEntry {
+        media_type: MediaTypes::ManifestV2S2.to_string(),
+        digest: CHILD_DIGEST.to_string(),
+        size: 7682,
+        ..IndexEntry::default()
+    };
+    let res = ImageIndexBuilder::new().manifest(entry.clone()).build();
+    assert!(matches!(
+        res,
+        Err(Error::Manifest(ManifestError::MissingPlatform(_)))
+    ));
+
+    let index = ImageIndexBuilder::new()
+        .media_type(MediaTypes::OciImageIndex)
+        .manifest(entry)
+        .build()
+        .unwrap();
+    assert_eq!(index.manifests.len(), 1);
+}
+
+#[test]
+fn test_manifest_index_oci_extensions() {
+    let subject = Descriptor {
+        media_type: MediaTypes::OciImageManifest.to_string(),
+        digest: CHILD_DIGEST.to_string(),
+        size: 7682,
+        ..Descriptor::default()
+    };
+    let builder = ImageIndexBuilder::new()
+        .artifact_manifest(
+            MediaTypes::OciImageManifest,
+            CONFIG_DIGEST,
+            566,
+            "application/vnd.example.sbom",
+        )
+        .artifact_type("application/vnd.example.sbom.set")
+        .subject(subject.clone())
+        .annotation("org.opencontainers.image.ref.name", "latest");
+
+    let res = builder.clone().build();
+    assert!(matches!(res, Err(Error::UnsupportedMediaType(_))));
+
+    let bytes = builder
+        .media_type(MediaTypes::OciImageIndex)
+        .build_bytes()
+        .unwrap();
+    assert_eq!(bytes.media_type, MediaTypes::OciImageIndex);
+    assert_eq!(
+        bytes.digest,
+        format!("sha256:{:x}", sha2::Sha256::digest(&bytes.body))
+    );
+    let body = String::from_utf8(bytes.body.clone()).unwrap();
+    assert!(body.starts_with(
+        r#"{"annotations":{"org.opencontainers.image.ref.name":"latest"},"artifactType":"#
+    ));
+
+    let index = serde_json::from_slice::<ImageIndex>(&bytes.body).unwrap();
+    assert_eq!(index.subject, Some(subject));
+    assert_eq!(
+        index.manifests[0].artifact_type.as_deref(),
+        Some("application/vnd.example.sbom")
+    );
+    assert_eq!(index.to_bytes().unwrap(), bytes);
+}
+
+#[test]
+fn test_manifest_index_progress() {
+    let name = "my-repo/my-image";
+    let tag = "latest";
+
+    let addr = mockito::server_address().to_string();
+    let _m_index = mock_index(name, tag);
+    let size = std::fs::metadata("tests/fixtures/oci_image_index.json")
+        .unwrap()
+        .len();
+
+    let reports = Arc::new(Mutex::new(Vec::new()));
+    let runtime = Runtime::new().unwrap();
+    let dclient = dkregistry::v2::Client::configure()
+        .registry(&addr)
+        .insecure_registry(true)
+        .username(None)
+        .password(None)
+        .progress({
+            let reports = reports.clone();
+            move |p| {
+                let report = (p.reference.to_string(), p.transferred, p.total);
+                reports.lock().unwrap().push(report);
+            }
+        })
+        .build()
+        .unwrap();
+
+    runtime
+        .block_on(dclient.get_image_index(name, tag))
+        .unwrap();
+    let last = reports.lock().unwrap().pop();
+    assert_eq!(last, Some((tag.to_string(), size, Some(size))));
+
+    mockito::reset();
+}
@@ -0,0 +1,47 @@
+extern crate dkregistry;
+extern crate mockito;
+extern crate tokio;
+
+use self::mockito::mock;
+use self::tokio::runtime::Runtime;
+use dkregistry::mediatypes::MediaTypes;
+use dkregistry::v2::manifest::Manifest;
+
+#[test]
+fn test_manifest_raw() {
+    let name = "my-repo/my-image";
+    let tag = "latest";
+    let body = std::fs::read("tests/fixtures/manifest_list_v2.json").unwrap();
+    let digest = dkregistry::v2::sha256_digest(&body);
+
+    let ep = format!("/v2/{}/manifests/{}", name, tag);
+    let addr = mockito::server_address().to_string();
+    let _m = mock("GET", ep.as_str())
+        .with_status(200)
+        .with_header(
+            "Content-Type",
+            "application/vnd.docker.distribution.manifest.list.v2+json",
+        )
+        .with_header("Docker-Content-Digest", &digest)
+        .with_body(&body)
+        .create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = dkregistry::v2::Client::configure()
+        .registry(&addr)
+        .insecure_registry(true)
+        .username(None)
+        .password(None)
+        .build()
+        .unwrap();
+
+    let raw = runtime
+        .block_on(dclient.get_raw_manifest(name, tag))
+        .unwrap();
+    assert!(matches!(raw.manifest, Manifest::ML(_)));
+    assert_eq!(raw.raw.media_type, MediaTypes::ManifestList);
+    assert_eq!(raw.raw.body, body);
+    assert_eq!(raw.raw.digest, digest);
+
+    mockito::reset();
+}
@@ -0,0 +1,136 @@
+extern crate dkregistry;
+extern crate mockito;
+extern crate tokio;
+
+use self::mockito::{mock, Matcher};
+use self::tokio::runtime::Runtime;
+use super::client;
+use dkregistry::mediatypes::MediaTypes;
+use dkregistry::v2::manifest::ManifestBytes;
+
+static MEDIA_TYPE: &str = "application/vnd.docker.distribution.manifest.v2+json";
+
+#[test]
+fn test_manifest_put() {
+    let name = "my-repo/my-image";
+    let body = br#"{"schemaVersion":2}"#.to_vec();
+
+    let ep = format!("/v2/{}/manifests/latest", name);
+    let addr = mockito::server_address().to_string();
+    let _m = mock("PUT", ep.as_str())
+        .match_header("Content-Type", MEDIA_TYPE)
+        .match_body(Matcher::Exact(String::from_utf8(body.clone()).unwrap()))
+        .with_status(201)
+        .with_header("Docker-Content-Digest", "sha256:registrydigest")
+        .create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = client(&addr);
+    let futcheck = dclient.put_manifest(name, "latest", MEDIA_TYPE, body);
+
+    let digest = runtime.block_on(futcheck).unwrap();
+    assert_eq!(digest, "sha256:registrydigest");
+
+    mockito::reset();
+}
+
+#[test]
+fn test_manifest_put_local_digest() {
+    let name = "my-repo/my-image";
+    let body = b"{}".to_vec();
+
+    let ep = format!("/v2/{}/manifests/latest", name);
+    let addr = mockito::server_address().to_string();
+    let _m = mock("PUT", ep.as_str()).with_status(201).create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = client(&addr);
+    let futcheck = dclient.put_manifest(name, "latest", MEDIA_TYPE, body);
+
+    let digest = runtime.block_on(futcheck).unwrap();
+    assert_eq!(
+        digest,
+        "sha256:44136fa355b3678a1146ad16f7e8649e94fb4fc21fe77e8310c060f61caaff8a"
+    );
+
+    mockito::reset();
+}
+
+#[test]
+fn test_manifest_put_authenticates_for_push() {
+    let name = "my-repo/my-image";
+    let addr = mockito::server_address().to_string();
+
+    let ep = format!("/v2/{}/manifests/latest", name);
+    let _m_denied = mock("PUT", ep.as_str())
+        .match_header("Authorization", Matcher::Missing)
+        .with_status(401)
+        .create();
+    let _m_auth = mock("GET", "/v2/")
+        .with_status(401)
+        .with_header(
+            "WWW-Authenticate",
+            &format!(r#"Bearer realm="http://{}/token",service="mock""#, addr),
+        )
+        .create();
+    let _m_token = mock("GET", "/token")
+        .match_query(Matcher::UrlEncoded(
+            "scope".into(),
+            format!("repository:{}:pull,push", name),
+        ))
+        .with_status(200)
+        .with_body(r#"{"token":"pushtoken"}"#)
+        .create();
+    let _m_put = mock("PUT", ep.as_str())
+        .match_header("Authorization", "Bearer pushtoken")
+        .with_status(201)
+        .with_header("Docker-Content-Digest", "sha256:registrydigest")
+        .create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = client(&addr);
+    let futcheck = dclient.put_manifest(name, "latest", MEDIA_TYPE, b"{}".to_vec());
+
+    let digest = runtime.block_on(futcheck).unwrap();
+    assert_eq!(digest, "sha256:registrydigest");
+
+    mockito::reset();
+}
+
+#[test]
+fn test_manifest_put_bytes() {
+    let name = "my-repo/my-image";
+    // Not canonical, which must not matter for existing manifests.
+    let body = b"{ \"schemaVersion\": 2 }".to_vec();
+    let manifest = ManifestBytes::from_raw(MediaTypes::ManifestV2S2, body.clone());
+    assert_eq!(manifest.digest, dkregistry::v2::sha256_digest(&body));
+
+    let ep = format!("/v2/{}/manifests/latest", name);
+    let addr = mockito::server_address().to_string();
+    let _m = mock("PUT", ep.as_str())
+        .match_header("Content-Type", MEDIA_TYPE)
+        .match_body(Matcher::Exact(String::from_utf8(body).unwrap()))
+        .with_status(201)
+        .with_header("Docker-Content-Digest", &manifest.digest)
+        .create();
+    let other_ep = format!("/v2/{}/manifests/other", name);
+    let _m_other = mock("PUT", other_ep.as_str())
+        .with_status(201)
+        .with_header("Docker-Content-Digest", "sha256:registrydigest")
+        .create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = client(&addr);
+    let digest = runtime
+        .block_on(dclient.put_manifest_bytes(name, "latest", &manifest))
+        .unwrap();
+    assert_eq!(digest, manifest.digest);
+
+    let res = runtime.block_on(dclient.put_manifest_bytes(name, "other", &manifest));
+    assert!(matches!(
+        res,
+        Err(dkregistry::errors::Error::DigestMismatch { .. })
+    ));
+
+    mockito::reset();
+}
@@ -0,0 +1,110 @@
+extern crate dkregistry;
+extern crate mockito;
+extern crate tokio;
+
+use self::mockito::{mock, Matcher};
+use self::tokio::runtime::Runtime;
+use dkregistry::v2::metrics::{MetricsSink, RequestMetrics};
+use dkregistry::v2::RetryPolicy;
+use reqwest::{Method, StatusCode};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Request metrics, as (host, method, status, retries, bytes received).
+type Request = (String, Method, Option<StatusCode>, u32, Option<u64>);
+
+#[derive(Debug, Default)]
+struct Recorder {
+    requests: Mutex<Vec<Request>>,
+    auth_failures: Mutex<Vec<String>>,
+}
+
+impl MetricsSink for Recorder {
+    fn record_request(&self, request: &RequestMetrics<'_>) {
+        self.requests.lock().unwrap().push((
+            request.host.to_string(),
+            request.method.clone(),
+            request.status,
+            request.retries,
+            request.bytes_received,
+        ));
+    }
+
+    fn record_auth_failure(&self, host: &str) {
+        self.auth_failures.lock().unwrap().push(host.to_string());
+    }
+}
+
+fn client(addr: &str, recorder: &Arc<Recorder>) -> dkregistry::v2::Client {
+    dkregistry::v2::Client::configure()
+        .registry(addr)
+        .insecure_registry(true)
+        .username(Some("user".to_string()))
+        .password(Some("wrong".to_string()))
+        .retry_policy(
+            RetryPolicy::new()
+                .max_attempts(2)
+                .backoff_base(Duration::from_millis(1)),
+        )
+        .metrics_sink(recorder.clone())
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn test_metrics_requests() {
+    let name = "my-repo/my-image";
+    let digest = "sha256:2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+
+    let ep = format!("/v2/{}/blobs/{}", name, digest);
+    let addr = mockito::server_address().to_string();
+    let _m_failing = mock("GET", ep.as_str()).with_status(503).expect(1).create();
+    let _m = mock("GET", ep.as_str())
+        .with_status(200)
+        .with_body("hello")
+        .create();
+
+    let recorder = Arc::new(Recorder::default());
+    let runtime = Runtime::new().unwrap();
+    let dclient = client(&addr, &recorder);
+    let blob = runtime.block_on(dclient.get_blob(name, digest)).unwrap();
+    assert_eq!(blob, b"hello");
+
+    let requests = recorder.requests.lock().unwrap();
+    assert_eq!(
+        *requests,
+        vec![(addr, Method::GET, Some(StatusCode::OK), 1, Some(5))]
+    );
+
+    mockito::reset();
+}
+
+#[test]
+fn test_metrics_auth_failure() {
+    let addr = mockito::server_address().to_string();
+    let _m_auth = mock("GET", "/v2/")
+        .with_status(401)
+        .with_header(
+            "WWW-Authenticate",
+            &format!(r#"Bearer realm="http://{}/token",service="mock""#, addr),
+        )
+        .create();
+    let _m_token = mock("GET", "/token")
+        .match_query(Matcher::Any)
+        .with_status(401)
+        .create();
+
+    let recorder = Arc::new(Recorder::default());
+    let runtime = Runtime::new().unwrap();
+    let dclient = client(&addr, &recorder);
+    runtime
+        .block_on(dclient.authenticate(&["repository:my-repo/my-image:pull"]))
+        .unwrap_err();
+
+    assert_eq!(*recorder.auth_failures.lock().unwrap(), vec![addr]);
+    let requests = recorder.requests.lock().unwrap();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[1].2, Some(StatusCode::UNAUTHORIZED));
+
+    mockito::reset();
+}
@@ -0,0 +1,89 @@
+extern crate dkregistry;
+extern crate mockito;
+extern crate tokio;
+
+use self::mockito::mock;
+use self::tokio::runtime::Runtime;
+
+static DIGEST: &str = "sha256:2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+
+fn client(registry: &str) -> dkregistry::v2::Client {
+    let mirror = format!("http://{}/mirror", mockito::server_address());
+    dkregistry::v2::Client::configure()
+        .registry(registry)
+        .insecure_registry(true)
+        .username(None)
+        .password(None)
+        .mirrors(vec![mirror])
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn test_mirror_pull() {
+    let name = "my-repo/my-image";
+
+    let ep = format!("/v2/{}/blobs/{}", name, DIGEST);
+    let mirror_ep = format!("/mirror{}", ep);
+    let addr = mockito::server_address().to_string();
+    let m_mirror = mock("HEAD", mirror_ep.as_str())
+        .with_status(200)
+        .with_header("Content-Length", "1234")
+        .create();
+    let m = mock("HEAD", ep.as_str()).expect(0).create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = client(&addr);
+    let res = runtime
+        .block_on(dclient.get_blob_size(name, DIGEST))
+        .unwrap();
+    assert_eq!(res, Some(1234));
+    m_mirror.assert();
+    m.assert();
+
+    mockito::reset();
+}
+
+#[test]
+fn test_mirror_fallback() {
+    let name = "my-repo/my-image";
+
+    let ep = format!("/v2/{}/blobs/{}", name, DIGEST);
+    let mirror_ep = format!("/mirror{}", ep);
+    let addr = mockito::server_address().to_string();
+    let m_mirror = mock("HEAD", mirror_ep.as_str()).with_status(404).create();
+    let m = mock("HEAD", ep.as_str())
+        .with_status(200)
+        .with_header("Content-Length", "1234")
+        .create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = client(&addr);
+    let res = runtime
+        .block_on(dclient.get_blob_size(name, DIGEST))
+        .unwrap();
+    assert_eq!(res, Some(1234));
+    m_mirror.assert();
+    m.assert();
+
+    mockito::reset();
+}
+
+#[test]
+fn test_mirror_docker_hub_library() {
+    let mirror_ep = format!("/mirror/v2/library/alpine/blobs/{}", DIGEST);
+    let m_mirror = mock("HEAD", mirror_ep.as_str())
+        .with_status(200)
+        .with_header("Content-Length", "1234")
+        .create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = client("registry-1.docker.io");
+    let res = runtime
+        .block_on(dclient.get_blob_size("alpine", DIGEST))
+        .unwrap();
+    assert_eq!(res, Some(1234));
+    m_mirror.assert();
+
+    mockito::reset();
+}
@@ -1,6 +1,55 @@
 mod api_version;
+mod auth_fallback;
 mod base_client;
 mod blobs_download;
+mod blobs_upload;
+#[cfg(feature = "blocking")]
+mod blocking;
+mod capabilities;
 mod catalog;
+#[cfg(feature = "acr")]
+mod credentials_acr;
+mod credentials_provider;
+#[cfg(feature = "render")]
+mod estargz;
+mod foreign_layers;
+mod image_config;
+#[cfg(feature = "render")]
+mod image_size;
+mod manifest_accept;
+mod manifest_artifact;
+mod manifest_conditional;
+mod manifest_custom;
+mod manifest_delete;
+mod manifest_head;
+mod manifest_index;
+mod manifest_raw;
+mod manifest_upload;
+mod metrics;
+mod mirror;
+mod paginate;
+mod proxy;
+mod rate_limit;
+mod redirect;
+mod referrers;
+mod registry_errors;
+mod retry;
+mod tags_delete;
 mod tags_dockerv2;
 mod tags_quay;
+mod token_cache;
+mod token_oauth2;
+#[cfg(feature = "tracing")]
+mod tracing_spans;
+mod transport;
+
+/// Client of the mock registry at `addr`, without credentials.
+fn client(addr: &str) -> dkregistry::v2::Client {
+    dkregistry::v2::Client::configure()
+        .registry(addr)
+        .insecure_registry(true)
+        .username(None)
+        .password(None)
+        .build()
+        .unwrap()
+}
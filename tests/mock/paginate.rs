@@ -0,0 +1,87 @@
+extern crate dkregistry;
+extern crate futures;
+extern crate mockito;
+extern crate serde_json;
+extern crate tokio;
+
+use self::futures::TryStreamExt;
+use self::mockito::mock;
+use self::tokio::runtime::Runtime;
+
+#[test]
+fn test_paginate_extension_endpoint() {
+    let page1 = r#"{"items": ["a", "b"]}"#;
+    let page2 = r#"{"items": ["c"]}"#;
+
+    let addr = mockito::server_address().to_string();
+    let _m1 = mock("GET", "/v2/_ext/items?n=2")
+        .match_header("Accept", "application/vnd.example+json")
+        .with_status(200)
+        .with_header("Link", r#"</v2/_ext/items?n=2&last=b>; rel="next""#)
+        .with_header("X-Page", "1")
+        .with_body(page1)
+        .create();
+    let _m2 = mock("GET", "/v2/_ext/items?n=2&last=b")
+        .match_header("Accept", "application/vnd.example+json")
+        .with_status(200)
+        .with_header("X-Page", "2")
+        .with_body(page2)
+        .create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = dkregistry::v2::Client::configure()
+        .registry(&addr)
+        .insecure_registry(true)
+        .username(None)
+        .password(None)
+        .build()
+        .unwrap();
+
+    let url = format!("{}/v2/_ext/items?n=2", mockito::server_url());
+    let items = dclient.paginate(
+        url.parse().unwrap(),
+        "application/vnd.example+json",
+        |headers, page: serde_json::Value| {
+            let number = headers["X-Page"].to_str().unwrap().to_string();
+            page["items"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|item| format!("{}{}", item.as_str().unwrap(), number))
+                .collect()
+        },
+    );
+    let items: Vec<String> = runtime.block_on(items.try_collect()).unwrap();
+    assert_eq!(items, vec!["a1", "b1", "c2"]);
+
+    mockito::reset();
+}
+
+#[test]
+fn test_paginate_error_status() {
+    let addr = mockito::server_address().to_string();
+    let _m = mock("GET", "/v2/_ext/items").with_status(404).create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = dkregistry::v2::Client::configure()
+        .registry(&addr)
+        .insecure_registry(true)
+        .username(None)
+        .password(None)
+        .build()
+        .unwrap();
+
+    let url = format!("{}/v2/_ext/items", mockito::server_url());
+    let items = dclient.paginate(
+        url.parse().unwrap(),
+        "application/json",
+        |_, page: Vec<String>| page,
+    );
+    let res: dkregistry::errors::Result<Vec<String>> = runtime.block_on(items.try_collect());
+    match res {
+        Err(dkregistry::errors::Error::Client { status }) => assert_eq!(status, 404),
+        res => panic!("unexpected result: {:?}", res),
+    }
+
+    mockito::reset();
+}
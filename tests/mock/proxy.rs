@@ -0,0 +1,37 @@
+extern crate dkregistry;
+extern crate mockito;
+extern crate tokio;
+
+use self::mockito::{mock, Matcher};
+use self::tokio::runtime::Runtime;
+
+#[test]
+fn test_proxy() {
+    let name = "my-repo/my-image";
+    let digest = "fakedigest";
+
+    let proxy = format!("http://{}", mockito::server_address());
+    let ep = format!("http://registry.invalid/v2/{}/blobs/{}", name, digest);
+    let _m = mock("HEAD", ep.as_str())
+        .match_header("Proxy-Authorization", Matcher::Regex("^Basic ".to_string()))
+        .with_status(200)
+        .with_header("Content-Length", "1234")
+        .create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = dkregistry::v2::Client::configure()
+        .registry("registry.invalid")
+        .insecure_registry(true)
+        .proxy(&proxy)
+        .proxy_credentials("user", "secret")
+        .no_proxy("")
+        .build()
+        .unwrap();
+
+    let res = runtime
+        .block_on(dclient.get_blob_size(name, digest))
+        .unwrap();
+    assert_eq!(res, Some(1234));
+
+    mockito::reset();
+}
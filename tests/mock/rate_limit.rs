@@ -0,0 +1,65 @@
+extern crate dkregistry;
+extern crate mockito;
+extern crate tokio;
+
+use self::mockito::mock;
+use self::tokio::runtime::Runtime;
+use std::time::Duration;
+
+#[test]
+fn test_probe_rate_limit() {
+    let addr = mockito::server_address().to_string();
+    let _m = mock("HEAD", "/v2/ratelimitpreview/test/manifests/latest")
+        .with_status(200)
+        .with_header("RateLimit-Limit", "100;w=21600")
+        .with_header("RateLimit-Remaining", "98;w=21600")
+        .with_header("Docker-RateLimit-Source", "192.0.2.1")
+        .create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = dkregistry::v2::Client::configure()
+        .registry(&addr)
+        .insecure_registry(true)
+        .username(None)
+        .password(None)
+        .build()
+        .unwrap();
+    assert_eq!(dclient.rate_limit_info(), None);
+
+    let info = runtime.block_on(dclient.probe_rate_limit()).unwrap();
+    assert_eq!(info.limit, Some(100));
+    assert_eq!(info.remaining, Some(98));
+    assert_eq!(info.window, Some(Duration::from_secs(21600)));
+    assert_eq!(info.source.as_deref(), Some("192.0.2.1"));
+    assert_eq!(dclient.clone().rate_limit_info(), Some(info));
+
+    mockito::reset();
+}
+
+#[test]
+fn test_rate_limit_info_from_pulls() {
+    let name = "repo";
+    let addr = mockito::server_address().to_string();
+    let _m = mock("HEAD", format!("/v2/{}/blobs/sha256:abc", name).as_str())
+        .with_status(200)
+        .with_header("RateLimit-Remaining", "12;w=21600")
+        .create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = dkregistry::v2::Client::configure()
+        .registry(&addr)
+        .insecure_registry(true)
+        .username(None)
+        .password(None)
+        .build()
+        .unwrap();
+
+    runtime
+        .block_on(dclient.has_blob(name, "sha256:abc"))
+        .unwrap();
+    let info = dclient.rate_limit_info().unwrap();
+    assert_eq!(info.limit, None);
+    assert_eq!(info.remaining, Some(12));
+
+    mockito::reset();
+}
@@ -0,0 +1,100 @@
+extern crate dkregistry;
+extern crate mockito;
+extern crate sha2;
+extern crate tokio;
+
+use self::mockito::{mock, Matcher};
+use self::tokio::runtime::Runtime;
+use crate::mock::redirect::sha2::Digest;
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
+
+/// Get a blob redirected to another host name of the mock server.
+fn get_redirected_blob(redirect_auth_hosts: Vec<String>, authorization: Matcher) {
+    let name = "my-repo/my-image";
+    let blob = b"hello";
+    let digest = format!("sha256:{:x}", sha2::Sha256::digest(blob));
+    let cdn_url = format!(
+        "http://localhost:{}/cdn/blob?sig=abc",
+        mockito::server_address().port()
+    );
+
+    let addr = mockito::server_address().to_string();
+    let _m_blob = mock("GET", format!("/v2/{}/blobs/{}", name, digest).as_str())
+        .with_status(307)
+        .with_header("Location", &cdn_url)
+        .create();
+    let m_cdn = mock("GET", "/cdn/blob?sig=abc")
+        .match_header("authorization", authorization)
+        .with_status(200)
+        .with_body(blob)
+        .create();
+
+    let mut headers = HeaderMap::new();
+    headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer secret"));
+    let runtime = Runtime::new().unwrap();
+    let dclient = dkregistry::v2::Client::configure()
+        .registry(&addr)
+        .insecure_registry(true)
+        .username(None)
+        .password(None)
+        .default_headers(headers)
+        .redirect_auth_hosts(redirect_auth_hosts)
+        .build()
+        .unwrap();
+
+    let res = runtime.block_on(dclient.get_blob(name, &digest)).unwrap();
+    assert_eq!(res, blob);
+    m_cdn.assert();
+
+    mockito::reset();
+}
+
+#[test]
+fn test_redirect_strips_auth() {
+    get_redirected_blob(Vec::new(), Matcher::Missing);
+}
+
+#[test]
+fn test_redirect_keeps_auth_for_allowed_hosts() {
+    get_redirected_blob(
+        vec!["localhost".to_string()],
+        Matcher::Exact("Bearer secret".to_string()),
+    );
+}
+
+#[test]
+fn test_redirect_with_body_fails() {
+    let name = "my-repo/my-image";
+    let storage_url = format!(
+        "http://localhost:{}/storage/manifest",
+        mockito::server_address().port()
+    );
+
+    let addr = mockito::server_address().to_string();
+    let _m_put = mock("PUT", format!("/v2/{}/manifests/latest", name).as_str())
+        .with_status(307)
+        .with_header("Location", &storage_url)
+        .create();
+    let m_storage = mock("PUT", "/storage/manifest").expect(0).create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = dkregistry::v2::Client::configure()
+        .registry(&addr)
+        .insecure_registry(true)
+        .username(None)
+        .password(None)
+        .redirect_auth_hosts(vec!["localhost".to_string()])
+        .build()
+        .unwrap();
+
+    let res = runtime.block_on(dclient.put_manifest(
+        name,
+        "latest",
+        "application/vnd.oci.image.manifest.v1+json",
+        b"{}".to_vec(),
+    ));
+    assert!(res.is_err());
+    m_storage.assert();
+
+    mockito::reset();
+}
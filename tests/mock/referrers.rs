@@ -0,0 +1,330 @@
+extern crate dkregistry;
+extern crate mockito;
+extern crate tokio;
+
+use self::mockito::{mock, Matcher};
+use self::tokio::runtime::Runtime;
+use super::client;
+use dkregistry::mediatypes::MediaTypes;
+use dkregistry::v2::manifest::{Descriptor, ManifestBuilder, ManifestBytes};
+use dkregistry::v2::ReferrerFilter;
+
+static DIGEST: &str = "sha256:2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+static INDEX: &str = r#"{
+    "schemaVersion": 2,
+    "mediaType": "application/vnd.oci.image.index.v1+json",
+    "manifests": [
+        {
+            "mediaType": "application/vnd.oci.image.manifest.v1+json",
+            "digest": "sha256:1111111111111111111111111111111111111111111111111111111111111111",
+            "size": 100,
+            "artifactType": "application/vnd.dev.cosign.artifact.sig.v1+json"
+        },
+        {
+            "mediaType": "application/vnd.oci.image.manifest.v1+json",
+            "digest": "sha256:2222222222222222222222222222222222222222222222222222222222222222",
+            "size": 200,
+            "artifactType": "application/spdx+json",
+            "annotations": {"org.opencontainers.image.created": "2023-01-01T00:00:00Z"}
+        }
+    ]
+}"#;
+
+#[test]
+fn test_referrers() {
+    let name = "my-repo/my-image";
+
+    let ep = format!("/v2/{}/referrers/{}", name, DIGEST);
+    let addr = mockito::server_address().to_string();
+    let _m = mock("GET", ep.as_str())
+        .with_status(200)
+        .with_header("Content-Type", "application/vnd.oci.image.index.v1+json")
+        .with_body(INDEX)
+        .create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = client(&addr);
+    let referrers = runtime
+        .block_on(dclient.get_referrers(name, DIGEST, None))
+        .unwrap();
+    assert_eq!(referrers.len(), 2);
+    assert_eq!(referrers[1].size, 200);
+    assert_eq!(referrers[1].annotations.len(), 1);
+
+    mockito::reset();
+}
+
+#[test]
+fn test_referrers_filter_unapplied() {
+    let name = "my-repo/my-image";
+
+    let ep = format!(
+        "/v2/{}/referrers/{}?artifactType=application%2Fspdx%2Bjson",
+        name, DIGEST
+    );
+    let addr = mockito::server_address().to_string();
+    let _m = mock("GET", ep.as_str())
+        .with_status(200)
+        .with_header("Content-Type", "application/vnd.oci.image.index.v1+json")
+        .with_body(INDEX)
+        .create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = client(&addr);
+    let referrers = runtime
+        .block_on(dclient.get_referrers(name, DIGEST, Some("application/spdx+json")))
+        .unwrap();
+    assert_eq!(referrers.len(), 1);
+    assert_eq!(
+        referrers[0].artifact_type.as_deref(),
+        Some("application/spdx+json")
+    );
+
+    mockito::reset();
+}
+
+#[test]
+fn test_referrers_filter_annotations() {
+    let name = "my-repo/my-image";
+
+    let ep = format!("/v2/{}/referrers/{}", name, DIGEST);
+    let addr = mockito::server_address().to_string();
+    // Filtered by artifact type as reported, which only annotations narrow down.
+    let _m = mock("GET", ep.as_str())
+        .match_query(Matcher::UrlEncoded(
+            "artifactType".into(),
+            "application/spdx+json".into(),
+        ))
+        .with_status(200)
+        .with_header("Content-Type", "application/vnd.oci.image.index.v1+json")
+        .with_header("OCI-Filters-Applied", "artifactType")
+        .with_body(INDEX)
+        .create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = client(&addr);
+    let filter = ReferrerFilter::new().artifact_type("application/spdx+json");
+    let referrers = runtime
+        .block_on(dclient.get_referrers_filtered(name, DIGEST, &filter))
+        .unwrap();
+    assert_eq!(referrers.len(), 2);
+
+    let filter = filter.has_annotation("org.opencontainers.image.created");
+    let referrers = runtime
+        .block_on(dclient.get_referrers_filtered(name, DIGEST, &filter))
+        .unwrap();
+    assert_eq!(referrers.len(), 1);
+    assert_eq!(referrers[0].size, 200);
+
+    let filter = filter.annotation("org.opencontainers.image.created", "2024-01-01T00:00:00Z");
+    let referrers = runtime
+        .block_on(dclient.get_referrers_filtered(name, DIGEST, &filter))
+        .unwrap();
+    assert!(referrers.is_empty());
+
+    mockito::reset();
+}
+
+#[test]
+fn test_referrers_filtered_page_before_error() {
+    let name = "my-repo/my-image";
+
+    let ep = format!("/v2/{}/referrers/{}", name, DIGEST);
+    let tag_ep = format!("/v2/{}/manifests/{}", name, DIGEST.replace(':', "-"));
+    let addr = mockito::server_address().to_string();
+    let _m = mock("GET", ep.as_str())
+        .match_query(Matcher::Missing)
+        .with_status(200)
+        .with_header("Content-Type", "application/vnd.oci.image.index.v1+json")
+        .with_header("Link", &format!(r#"<{}?last=b>; rel="next""#, ep))
+        .with_body(INDEX)
+        .create();
+    let _m_next = mock("GET", ep.as_str())
+        .match_query(Matcher::UrlEncoded("last".into(), "b".into()))
+        .with_status(404)
+        .create();
+    let m_tag = mock("GET", tag_ep.as_str()).expect(0).create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = client(&addr);
+    let filter = ReferrerFilter::new().has_annotation("org.example.missing");
+    let res = runtime.block_on(dclient.get_referrers_filtered(name, DIGEST, &filter));
+    assert!(res.is_err());
+    m_tag.assert();
+
+    mockito::reset();
+}
+
+#[test]
+fn test_referrers_tag_fallback() {
+    let name = "my-repo/my-image";
+
+    let ep = format!("/v2/{}/referrers/{}", name, DIGEST);
+    let tag_ep = format!("/v2/{}/manifests/{}", name, DIGEST.replace(':', "-"));
+    let addr = mockito::server_address().to_string();
+    let _m = mock("GET", ep.as_str()).with_status(404).create();
+    let _m_tag = mock("GET", tag_ep.as_str())
+        .with_status(200)
+        .with_header("Content-Type", "application/vnd.oci.image.index.v1+json")
+        .with_body(INDEX)
+        .create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = client(&addr);
+    let referrers = runtime
+        .block_on(dclient.get_referrers(name, DIGEST, None))
+        .unwrap();
+    assert_eq!(referrers.len(), 2);
+
+    mockito::reset();
+}
+
+#[test]
+fn test_referrers_tag_fallback_with_error_body() {
+    let name = "my-repo/my-image";
+
+    let ep = format!("/v2/{}/referrers/{}", name, DIGEST);
+    let tag_ep = format!("/v2/{}/manifests/{}", name, DIGEST.replace(':', "-"));
+    let addr = mockito::server_address().to_string();
+    let _m = mock("GET", ep.as_str())
+        .with_status(404)
+        .with_header("Content-Type", "application/json")
+        .with_body(r#"{"errors": [{"code": "NAME_UNKNOWN", "message": "repository name not known to registry"}]}"#)
+        .create();
+    let _m_tag = mock("GET", tag_ep.as_str())
+        .with_status(200)
+        .with_header("Content-Type", "application/vnd.oci.image.index.v1+json")
+        .with_body(INDEX)
+        .create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = client(&addr);
+    let referrers = runtime
+        .block_on(dclient.get_referrers(name, DIGEST, None))
+        .unwrap();
+    assert_eq!(referrers.len(), 2);
+
+    mockito::reset();
+}
+
+#[test]
+fn test_referrers_none() {
+    let name = "my-repo/my-image";
+
+    let ep = format!("/v2/{}/referrers/{}", name, DIGEST);
+    let tag_ep = format!("/v2/{}/manifests/{}", name, DIGEST.replace(':', "-"));
+    let addr = mockito::server_address().to_string();
+    let _m = mock("GET", ep.as_str()).with_status(404).create();
+    let _m_tag = mock("GET", tag_ep.as_str()).with_status(404).create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = client(&addr);
+    let referrers = runtime
+        .block_on(dclient.get_referrers(name, DIGEST, None))
+        .unwrap();
+    assert!(referrers.is_empty());
+
+    mockito::reset();
+}
+
+fn signature() -> ManifestBytes {
+    ManifestBuilder::new()
+        .media_type(MediaTypes::OciImageManifest)
+        .artifact_type("application/vnd.dev.cosign.artifact.sig.v1+json")
+        .empty_config()
+        .subject(Descriptor {
+            media_type: MediaTypes::OciImageManifest.to_string(),
+            digest: DIGEST.to_string(),
+            size: 300,
+            ..Descriptor::default()
+        })
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn test_put_referrer() {
+    let name = "my-repo/my-image";
+    let manifest = signature();
+
+    let ep = format!("/v2/{}/manifests/{}", name, manifest.digest);
+    let tag_ep = format!("/v2/{}/manifests/{}", name, DIGEST.replace(':', "-"));
+    let addr = mockito::server_address().to_string();
+    let m_put = mock("PUT", ep.as_str())
+        .match_header("content-type", "application/vnd.oci.image.manifest.v1+json")
+        .with_status(201)
+        .with_header("Docker-Content-Digest", &manifest.digest)
+        .with_header("OCI-Subject", DIGEST)
+        .create();
+    let m_tag = mock("PUT", tag_ep.as_str()).expect(0).create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = client(&addr);
+    let digest = runtime
+        .block_on(dclient.put_referrer(name, &manifest))
+        .unwrap();
+    assert_eq!(digest, manifest.digest);
+    m_put.assert();
+    m_tag.assert();
+
+    mockito::reset();
+}
+
+#[test]
+fn test_put_referrer_digest_mismatch() {
+    let name = "my-repo/my-image";
+    let mut manifest = signature();
+    manifest.digest = DIGEST.to_string();
+
+    let ep = format!("/v2/{}/manifests/{}", name, manifest.digest);
+    let addr = mockito::server_address().to_string();
+    let m_put = mock("PUT", ep.as_str()).expect(0).create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = client(&addr);
+    let res = runtime.block_on(dclient.put_referrer(name, &manifest));
+    assert!(matches!(
+        res,
+        Err(dkregistry::errors::Error::DigestMismatch { .. })
+    ));
+    m_put.assert();
+
+    mockito::reset();
+}
+
+#[test]
+fn test_put_referrer_tag_fallback() {
+    let name = "my-repo/my-image";
+    let manifest = signature();
+
+    let ep = format!("/v2/{}/manifests/{}", name, manifest.digest);
+    let tag_ep = format!("/v2/{}/manifests/{}", name, DIGEST.replace(':', "-"));
+    let addr = mockito::server_address().to_string();
+    let _m_put = mock("PUT", ep.as_str())
+        .with_status(201)
+        .with_header("Docker-Content-Digest", &manifest.digest)
+        .create();
+    let _m_get_tag = mock("GET", tag_ep.as_str())
+        .with_status(200)
+        .with_header("Content-Type", "application/vnd.oci.image.index.v1+json")
+        .with_body(INDEX)
+        .create();
+    let m_put_tag = mock("PUT", tag_ep.as_str())
+        .match_header("content-type", "application/vnd.oci.image.index.v1+json")
+        .match_body(Matcher::AllOf(vec![
+            Matcher::Regex("sha256:1111".to_string()),
+            Matcher::Regex("sha256:2222".to_string()),
+            Matcher::Regex(manifest.digest.clone()),
+        ]))
+        .with_status(201)
+        .create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = client(&addr);
+    runtime
+        .block_on(dclient.put_referrer(name, &manifest))
+        .unwrap();
+    m_put_tag.assert();
+
+    mockito::reset();
+}
@@ -0,0 +1,74 @@
+extern crate dkregistry;
+extern crate mockito;
+extern crate tokio;
+
+use self::dkregistry::errors::Error;
+use self::dkregistry::v2::ErrorCode;
+use self::mockito::mock;
+use self::tokio::runtime::Runtime;
+
+#[test]
+fn test_manifest_unknown_error() {
+    let addr = mockito::server_address().to_string();
+    let _m = mock("GET", "/v2/repo/manifests/missing")
+        .with_status(404)
+        .with_header("Content-Type", "application/json")
+        .with_body(
+            r#"{"errors": [{"code": "MANIFEST_UNKNOWN", "message": "manifest unknown", "detail": {"Tag": "missing"}}]}"#,
+        )
+        .create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = dkregistry::v2::Client::configure()
+        .registry(&addr)
+        .insecure_registry(true)
+        .username(None)
+        .password(None)
+        .build()
+        .unwrap();
+
+    let res = runtime.block_on(dclient.get_manifest("repo", "missing"));
+    match res {
+        Err(Error::Registry { status, errors }) => {
+            assert_eq!(status, 404);
+            assert_eq!(errors.len(), 1);
+            assert_eq!(errors[0].code, ErrorCode::ManifestUnknown);
+            assert_eq!(errors[0].message, "manifest unknown");
+        }
+        res => panic!("unexpected result: {:?}", res),
+    }
+
+    mockito::reset();
+}
+
+#[test]
+fn test_upload_denied_error() {
+    let addr = mockito::server_address().to_string();
+    let _m = mock("POST", "/v2/repo/blobs/uploads/")
+        .with_status(403)
+        .with_header("Content-Type", "application/json")
+        .with_body(r#"{"errors": [{"code": "DENIED", "message": "requested access to the resource is denied"}]}"#)
+        .create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = dkregistry::v2::Client::configure()
+        .registry(&addr)
+        .insecure_registry(true)
+        .username(None)
+        .password(None)
+        .build()
+        .unwrap();
+
+    let res = runtime.block_on(dclient.start_blob_upload("repo"));
+    let err = res.unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "request failed with status 403 Forbidden: DENIED: requested access to the resource is denied"
+    );
+    match err {
+        Error::Registry { errors, .. } => assert_eq!(errors[0].code, ErrorCode::Denied),
+        err => panic!("unexpected error: {:?}", err),
+    }
+
+    mockito::reset();
+}
@@ -0,0 +1,217 @@
+extern crate dkregistry;
+extern crate mockito;
+extern crate tokio;
+
+use self::mockito::mock;
+use self::tokio::runtime::Runtime;
+use dkregistry::v2::RetryPolicy;
+use futures::StreamExt;
+use std::io::{Read, Write};
+use std::time::Duration;
+
+fn client(addr: &str, attempts: u32) -> dkregistry::v2::Client {
+    dkregistry::v2::Client::configure()
+        .registry(addr)
+        .insecure_registry(true)
+        .username(None)
+        .password(None)
+        .retry_policy(
+            RetryPolicy::new()
+                .max_attempts(attempts)
+                .backoff_base(Duration::from_millis(1)),
+        )
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn test_retry_server_errors() {
+    let name = "my-repo/my-image";
+    let digest = "fakedigest";
+
+    let ep = format!("/v2/{}/blobs/{}", name, digest);
+    let addr = mockito::server_address().to_string();
+    let m_failing = mock("HEAD", ep.as_str())
+        .with_status(503)
+        .expect(2)
+        .create();
+    let m = mock("HEAD", ep.as_str())
+        .with_status(200)
+        .with_header("Content-Length", "1234")
+        .create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = client(&addr, 3);
+    let res = runtime
+        .block_on(dclient.get_blob_size(name, digest))
+        .unwrap();
+    assert_eq!(res, Some(1234));
+    m_failing.assert();
+    m.assert();
+
+    mockito::reset();
+}
+
+#[test]
+fn test_retry_exhausted() {
+    let name = "my-repo/my-image";
+    let digest = "fakedigest";
+
+    let ep = format!("/v2/{}/blobs/{}", name, digest);
+    let addr = mockito::server_address().to_string();
+    let m = mock("HEAD", ep.as_str())
+        .with_status(503)
+        .expect(2)
+        .create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = client(&addr, 2);
+    let res = runtime.block_on(dclient.get_blob_size(name, digest));
+    assert!(matches!(res, Err(dkregistry::errors::Error::Server { .. })));
+    m.assert();
+
+    mockito::reset();
+}
+
+#[test]
+fn test_deadline_exceeded() {
+    // Connections are accepted by the OS, but never answered.
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = dkregistry::v2::Client::configure()
+        .registry(&addr)
+        .insecure_registry(true)
+        .deadline(Duration::from_millis(100))
+        .build()
+        .unwrap();
+    let res = runtime.block_on(dclient.get_blob_size("my-repo/my-image", "fakedigest"));
+    assert!(matches!(
+        res,
+        Err(dkregistry::errors::Error::DeadlineExceeded(_))
+    ));
+
+    let dclient = dkregistry::v2::Client::configure()
+        .registry(&addr)
+        .insecure_registry(true)
+        .timeout(Duration::from_millis(100))
+        .build()
+        .unwrap();
+    let res = runtime.block_on(dclient.get_blob_size("my-repo/my-image", "fakedigest"));
+    assert!(matches!(res, Err(dkregistry::errors::Error::Reqwest(e)) if e.is_timeout()));
+}
+
+#[test]
+fn test_deadline_exceeded_while_reading_body() {
+    // Headers are sent right away, but the body never completes.
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            std::thread::spawn(move || {
+                let mut request = [0; 4096];
+                let _ = stream.read(&mut request);
+                let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 1024\r\n\r\npartial");
+                std::thread::sleep(Duration::from_secs(5));
+            });
+        }
+    });
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = dkregistry::v2::Client::configure()
+        .registry(&addr)
+        .insecure_registry(true)
+        .deadline(Duration::from_millis(200))
+        .build()
+        .unwrap();
+    let digest = "sha256:2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+    let res = runtime.block_on(dclient.get_blob("my-repo/my-image", digest));
+    assert!(matches!(
+        res,
+        Err(dkregistry::errors::Error::DeadlineExceeded(_))
+    ));
+
+    let chunks = runtime.block_on(async {
+        let (stream, size) = dclient
+            .get_blob_stream("my-repo/my-image", digest)
+            .await
+            .unwrap();
+        assert_eq!(size, Some(1024));
+        stream.collect::<Vec<_>>().await
+    });
+    assert!(matches!(
+        chunks.last(),
+        Some(Err(dkregistry::errors::Error::DeadlineExceeded(_)))
+    ));
+}
+
+#[test]
+fn test_rate_limit_retry_after() {
+    let name = "my-repo/my-image";
+    let digest = "fakedigest";
+
+    let ep = format!("/v2/{}/blobs/{}", name, digest);
+    let addr = mockito::server_address().to_string();
+    let m_limited = mock("HEAD", ep.as_str())
+        .with_status(429)
+        .with_header("Retry-After", "0")
+        .expect(2)
+        .create();
+    let m = mock("HEAD", ep.as_str())
+        .with_status(200)
+        .with_header("Content-Length", "1234")
+        .create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = dkregistry::v2::Client::configure()
+        .registry(&addr)
+        .insecure_registry(true)
+        .retry_policy(RetryPolicy::new().rate_limit_budget(Duration::from_secs(1)))
+        .build()
+        .unwrap();
+    let res = runtime
+        .block_on(dclient.get_blob_size(name, digest))
+        .unwrap();
+    assert_eq!(res, Some(1234));
+    m_limited.assert();
+    m.assert();
+
+    mockito::reset();
+}
+
+#[test]
+fn test_rate_limited() {
+    let name = "my-repo/my-image";
+    let digest = "fakedigest";
+
+    let ep = format!("/v2/{}/blobs/{}", name, digest);
+    let addr = mockito::server_address().to_string();
+    let m = mock("HEAD", ep.as_str())
+        .with_status(429)
+        .with_header("Retry-After", "3600")
+        .with_header("RateLimit-Limit", "100;w=21600")
+        .with_header("RateLimit-Remaining", "0;w=21600")
+        .expect(1)
+        .create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = dkregistry::v2::Client::configure()
+        .registry(&addr)
+        .insecure_registry(true)
+        .retry_policy(RetryPolicy::new().rate_limit_budget(Duration::from_secs(1)))
+        .build()
+        .unwrap();
+    let res = runtime.block_on(dclient.get_blob_size(name, digest));
+    let rate_limit = match res {
+        Err(dkregistry::errors::Error::RateLimited(rate_limit)) => rate_limit,
+        other => panic!("unexpected result: {:?}", other),
+    };
+    assert_eq!(rate_limit.retry_after, Some(Duration::from_secs(3600)));
+    assert_eq!(rate_limit.limit, Some(100));
+    assert_eq!(rate_limit.remaining, Some(0));
+    m.assert();
+
+    mockito::reset();
+}
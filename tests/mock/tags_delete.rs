@@ -0,0 +1,45 @@
+extern crate dkregistry;
+extern crate mockito;
+extern crate tokio;
+
+use self::mockito::mock;
+use self::tokio::runtime::Runtime;
+use super::client;
+
+#[test]
+fn test_tags_delete() {
+    let name = "my-repo/my-image";
+
+    let ep = format!("/v2/{}/manifests/old", name);
+    let addr = mockito::server_address().to_string();
+    let _m = mock("DELETE", ep.as_str()).with_status(202).create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = client(&addr);
+    let futcheck = dclient.delete_tag(name, "old");
+
+    runtime.block_on(futcheck).unwrap();
+
+    mockito::reset();
+}
+
+#[test]
+fn test_tags_delete_unsupported() {
+    let name = "my-repo/my-image";
+
+    let ep = format!("/v2/{}/manifests/old", name);
+    let addr = mockito::server_address().to_string();
+    let _m = mock("DELETE", ep.as_str()).with_status(405).create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = client(&addr);
+    let futcheck = dclient.delete_tag(name, "old");
+
+    let res = runtime.block_on(futcheck);
+    assert!(matches!(
+        res,
+        Err(dkregistry::errors::Error::TagDeletionUnsupported(tag)) if tag == "old"
+    ));
+
+    mockito::reset();
+}
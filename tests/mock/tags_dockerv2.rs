@@ -90,6 +90,44 @@ fn test_dockerv2_tags_paginate() {
     mockito::reset();
 }
 
+#[test]
+fn test_dockerv2_tags_all() {
+    let name = "repo";
+    let tags_p1 = r#"{"name": "repo", "tags": [ "t1" ]}"#;
+    let tags_p2 = r#"{"name": "repo", "tags": [ "t2" ]}"#;
+
+    let ep1 = format!("/v2/{}/tags/list?n=1", name);
+    let ep2 = format!("/v2/{}/tags/list?n=1&last=t1", name);
+    let addr = mockito::server_address().to_string();
+    let _m1 = mock("GET", ep1.as_str())
+        .with_status(200)
+        .with_header("Link", r#"</v2/repo/tags/list?n=1&last=t1>; rel="next""#)
+        .with_header("Content-Type", "application/json")
+        .with_body(tags_p1)
+        .create();
+    let _m2 = mock("GET", ep2.as_str())
+        .with_status(200)
+        .with_header("Content-Type", "application/json")
+        .with_body(tags_p2)
+        .create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = dkregistry::v2::Client::configure()
+        .registry(&addr)
+        .insecure_registry(true)
+        .username(None)
+        .password(None)
+        .build()
+        .unwrap();
+
+    let tags = runtime
+        .block_on(dclient.get_all_tags(name, Some(1)))
+        .unwrap();
+    assert_eq!(tags, vec!["t1", "t2"]);
+
+    mockito::reset();
+}
+
 #[test]
 fn test_dockerv2_tags_404() {
     let name = "repo";
@@ -0,0 +1,153 @@
+extern crate dkregistry;
+extern crate mockito;
+extern crate tokio;
+
+use self::mockito::{mock, Matcher};
+use self::tokio::runtime::Runtime;
+use super::client;
+
+#[test]
+fn test_token_cached() {
+    let addr = mockito::server_address().to_string();
+    let scope = "repository:my-repo/my-image:pull";
+
+    let m_auth = mock("GET", "/v2/")
+        .with_status(401)
+        .with_header(
+            "WWW-Authenticate",
+            &format!(r#"Bearer realm="http://{}/token",service="mock""#, addr),
+        )
+        .expect(1)
+        .create();
+    let m_token = mock("GET", "/token")
+        .match_query(Matcher::UrlEncoded("scope".into(), scope.into()))
+        .with_status(200)
+        .with_body(r#"{"token":"pulltoken","expires_in":300}"#)
+        .expect(1)
+        .create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = runtime
+        .block_on(client(&addr).authenticate(&[scope]))
+        .unwrap();
+    runtime.block_on(dclient.authenticate(&[scope])).unwrap();
+    m_auth.assert();
+    m_token.assert();
+
+    mockito::reset();
+}
+
+#[test]
+fn test_token_refreshed_before_expiry() {
+    let name = "my-repo/my-image";
+    let digest = "fakedigest";
+    let addr = mockito::server_address().to_string();
+    let scope = "repository:my-repo/my-image:pull";
+
+    let _m_auth = mock("GET", "/v2/")
+        .with_status(401)
+        .with_header(
+            "WWW-Authenticate",
+            &format!(r#"Bearer realm="http://{}/token",service="mock""#, addr),
+        )
+        .create();
+    let m_expiring = mock("GET", "/token")
+        .match_query(Matcher::Any)
+        .with_status(200)
+        .with_body(r#"{"token":"expiring","expires_in":1}"#)
+        .expect(1)
+        .create();
+    let m_renewed = mock("GET", "/token")
+        .match_query(Matcher::Any)
+        .with_status(200)
+        .with_body(r#"{"token":"renewed","expires_in":300}"#)
+        .expect(1)
+        .create();
+    let m_blob = mock("HEAD", format!("/v2/{}/blobs/{}", name, digest).as_str())
+        .match_header("Authorization", "Bearer renewed")
+        .with_status(200)
+        .with_header("Content-Length", "1234")
+        .create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = runtime
+        .block_on(client(&addr).authenticate(&[scope]))
+        .unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(600));
+    let res = runtime
+        .block_on(dclient.get_blob_size(name, digest))
+        .unwrap();
+    assert_eq!(res, Some(1234));
+    m_expiring.assert();
+    m_renewed.assert();
+    m_blob.assert();
+
+    mockito::reset();
+}
+
+#[test]
+fn test_token_scopes_merged() {
+    let addr = mockito::server_address().to_string();
+    let scope_a = "repository:repo/a:pull";
+    let scope_b = "repository:repo/b:pull";
+
+    let _m_auth = mock("GET", "/v2/")
+        .with_status(401)
+        .with_header(
+            "WWW-Authenticate",
+            &format!(r#"Bearer realm="http://{}/token",service="mock""#, addr),
+        )
+        .create();
+    let _m_token_a = mock("GET", "/token")
+        .match_query(Matcher::UrlEncoded("scope".into(), scope_a.into()))
+        .with_status(200)
+        .with_body(r#"{"token":"token-a","expires_in":300}"#)
+        .expect(1)
+        .create();
+    let m_token_ab = mock("GET", "/token")
+        .match_query(Matcher::Regex(format!(
+            "scope={}&scope={}",
+            scope_a, scope_b
+        )))
+        .with_status(200)
+        .with_body(r#"{"token":"token-ab","expires_in":300}"#)
+        .expect(1)
+        .create();
+    let m_denied = mock("HEAD", "/v2/repo/b/blobs/fakedigest")
+        .match_header("Authorization", "Bearer token-a")
+        .with_status(401)
+        .with_header(
+            "WWW-Authenticate",
+            &format!(
+                r#"Bearer realm="http://{}/token",service="mock",scope="{}",error="insufficient_scope""#,
+                addr, scope_b
+            ),
+        )
+        .expect(1)
+        .create();
+    let m_blob = mock(
+        "HEAD",
+        Matcher::Regex("^/v2/repo/[ab]/blobs/fakedigest$".into()),
+    )
+    .match_header("Authorization", "Bearer token-ab")
+    .with_status(200)
+    .with_header("Content-Length", "1234")
+    .expect(3)
+    .create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = runtime
+        .block_on(client(&addr).authenticate(&[scope_a]))
+        .unwrap();
+    for name in &["repo/b", "repo/b", "repo/a"] {
+        let res = runtime
+            .block_on(dclient.get_blob_size(name, "fakedigest"))
+            .unwrap();
+        assert_eq!(res, Some(1234));
+    }
+    m_token_ab.assert();
+    m_denied.assert();
+    m_blob.assert();
+
+    mockito::reset();
+}
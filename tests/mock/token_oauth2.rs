@@ -0,0 +1,275 @@
+extern crate dkregistry;
+extern crate mockito;
+extern crate tokio;
+
+use self::mockito::{mock, Matcher};
+use self::tokio::runtime::Runtime;
+use dkregistry::v2::TokenFlow;
+
+fn client(addr: &str, token_flow: TokenFlow) -> dkregistry::v2::Client {
+    dkregistry::v2::Client::configure()
+        .registry(addr)
+        .insecure_registry(true)
+        .username(Some("user".to_string()))
+        .password(Some("secret".to_string()))
+        .token_flow(token_flow)
+        .build()
+        .unwrap()
+}
+
+fn mock_challenge(addr: &str) -> mockito::Mock {
+    mock("GET", "/v2/")
+        .with_status(401)
+        .with_header(
+            "WWW-Authenticate",
+            &format!(r#"Bearer realm="http://{}/token",service="mock""#, addr),
+        )
+        .create()
+}
+
+#[test]
+fn test_oauth2_password_grant() {
+    let addr = mockito::server_address().to_string();
+    let scope = "repository:my-repo/my-image:pull";
+
+    let _m_auth = mock_challenge(&addr);
+    let m_token = mock("POST", "/token")
+        .match_header("Content-Type", "application/x-www-form-urlencoded")
+        .match_body(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("grant_type".into(), "password".into()),
+            Matcher::UrlEncoded("username".into(), "user".into()),
+            Matcher::UrlEncoded("password".into(), "secret".into()),
+            Matcher::UrlEncoded("service".into(), "mock".into()),
+            Matcher::UrlEncoded("scope".into(), scope.into()),
+        ]))
+        .with_status(200)
+        .with_body(r#"{"access_token":"oauthtoken","expires_in":300}"#)
+        .create();
+    let m_blob = mock("HEAD", "/v2/my-repo/my-image/blobs/fakedigest")
+        .match_header("Authorization", "Bearer oauthtoken")
+        .with_status(200)
+        .with_header("Content-Length", "1234")
+        .create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = runtime
+        .block_on(client(&addr, TokenFlow::OAuth2).authenticate(&[scope]))
+        .unwrap();
+    let res = runtime
+        .block_on(dclient.get_blob_size("my-repo/my-image", "fakedigest"))
+        .unwrap();
+    assert_eq!(res, Some(1234));
+    m_token.assert();
+    m_blob.assert();
+
+    mockito::reset();
+}
+
+#[test]
+fn test_oauth2_fallback() {
+    let addr = mockito::server_address().to_string();
+    let scope = "repository:my-repo/my-image:pull";
+
+    let _m_auth = mock_challenge(&addr);
+    let m_get = mock("GET", "/token")
+        .match_query(Matcher::Any)
+        .with_status(405)
+        .create();
+    let m_post = mock("POST", "/token")
+        .match_body(Matcher::UrlEncoded("grant_type".into(), "password".into()))
+        .with_status(200)
+        .with_body(r#"{"access_token":"oauthtoken"}"#)
+        .create();
+
+    let runtime = Runtime::new().unwrap();
+    runtime
+        .block_on(client(&addr, TokenFlow::Auto).authenticate(&[scope]))
+        .unwrap();
+    m_get.assert();
+    m_post.assert();
+
+    mockito::reset();
+}
+
+#[test]
+fn test_oauth2_realm_prefers_post() {
+    let addr = mockito::server_address().to_string();
+    let scope = "repository:my-repo/my-image:pull";
+
+    let _m_auth = mock("GET", "/v2/")
+        .with_status(401)
+        .with_header(
+            "WWW-Authenticate",
+            &format!(
+                r#"Bearer realm="http://{}/oauth2/token",service="mock""#,
+                addr
+            ),
+        )
+        .create();
+    let m_get = mock("GET", "/oauth2/token")
+        .match_query(Matcher::Any)
+        .expect(0)
+        .create();
+    let m_post = mock("POST", "/oauth2/token")
+        .match_body(Matcher::UrlEncoded("grant_type".into(), "password".into()))
+        .with_status(200)
+        .with_body(r#"{"access_token":"oauthtoken"}"#)
+        .create();
+
+    let runtime = Runtime::new().unwrap();
+    runtime
+        .block_on(client(&addr, TokenFlow::Auto).authenticate(&[scope]))
+        .unwrap();
+    m_get.assert();
+    m_post.assert();
+
+    mockito::reset();
+}
+
+#[test]
+fn test_oauth2_realm_falls_back_on_client_error() {
+    let addr = mockito::server_address().to_string();
+    let scope = "repository:my-repo/my-image:pull";
+
+    let _m_auth = mock("GET", "/v2/")
+        .with_status(401)
+        .with_header(
+            "WWW-Authenticate",
+            &format!(
+                r#"Bearer realm="http://{}/oauth2/token",service="mock""#,
+                addr
+            ),
+        )
+        .create();
+    let m_post = mock("POST", "/oauth2/token").with_status(400).create();
+    let m_get = mock("GET", "/oauth2/token")
+        .match_query(Matcher::Any)
+        .with_status(200)
+        .with_body(r#"{"token":"gettoken"}"#)
+        .create();
+
+    let runtime = Runtime::new().unwrap();
+    runtime
+        .block_on(client(&addr, TokenFlow::Auto).authenticate(&[scope]))
+        .unwrap();
+    m_post.assert();
+    m_get.assert();
+
+    mockito::reset();
+}
+
+#[test]
+fn test_oauth2_refresh_token() {
+    let addr = mockito::server_address().to_string();
+    let scope = "repository:my-repo/my-image:pull";
+
+    let _m_auth = mock_challenge(&addr);
+    let m_password = mock("POST", "/token")
+        .match_body(Matcher::UrlEncoded("grant_type".into(), "password".into()))
+        .with_status(200)
+        .with_body(r#"{"access_token":"expiring","expires_in":1,"refresh_token":"refresh"}"#)
+        .expect(1)
+        .create();
+    let m_refresh = mock("POST", "/token")
+        .match_body(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("grant_type".into(), "refresh_token".into()),
+            Matcher::UrlEncoded("refresh_token".into(), "refresh".into()),
+        ]))
+        .with_status(200)
+        .with_body(r#"{"access_token":"renewed","expires_in":300}"#)
+        .expect(1)
+        .create();
+    let m_blob = mock("HEAD", "/v2/my-repo/my-image/blobs/fakedigest")
+        .match_header("Authorization", "Bearer renewed")
+        .with_status(200)
+        .with_header("Content-Length", "1234")
+        .create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = runtime
+        .block_on(client(&addr, TokenFlow::OAuth2).authenticate(&[scope]))
+        .unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(600));
+    let res = runtime
+        .block_on(dclient.get_blob_size("my-repo/my-image", "fakedigest"))
+        .unwrap();
+    assert_eq!(res, Some(1234));
+    m_password.assert();
+    m_refresh.assert();
+    m_blob.assert();
+
+    mockito::reset();
+}
+
+#[test]
+fn test_oauth2_identity_token() {
+    let addr = mockito::server_address().to_string();
+    let scope = "repository:my-repo/my-image:pull";
+
+    let mut docker_config = tempfile::NamedTempFile::new().unwrap();
+    let auths = format!(
+        r#"{{"auths": {{"http://{}": {{"identitytoken": "idtoken"}}}}}}"#,
+        addr
+    );
+    std::io::Write::write_all(&mut docker_config, auths.as_bytes()).unwrap();
+
+    let _m_auth = mock_challenge(&addr);
+    let m_refresh = mock("POST", "/token")
+        .match_body(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("grant_type".into(), "refresh_token".into()),
+            Matcher::UrlEncoded("refresh_token".into(), "idtoken".into()),
+        ]))
+        .with_status(200)
+        .with_body(r#"{"access_token":"oauthtoken"}"#)
+        .create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = dkregistry::v2::Config::from_docker_config_path(docker_config.path())
+        .unwrap()
+        .registry(&addr)
+        .insecure_registry(true)
+        .build()
+        .unwrap();
+    runtime.block_on(dclient.authenticate(&[scope])).unwrap();
+    m_refresh.assert();
+
+    mockito::reset();
+}
+
+#[test]
+fn test_identity_token_rejected() {
+    let addr = mockito::server_address().to_string();
+    let scope = "repository:my-repo/my-image:pull";
+
+    let _m_auth = mock_challenge(&addr);
+    let m_refresh = mock("POST", "/token")
+        .match_body(Matcher::UrlEncoded(
+            "refresh_token".into(),
+            "idtoken".into(),
+        ))
+        .with_status(401)
+        .create();
+    let m_anonymous = mock("GET", "/token")
+        .match_query(Matcher::Any)
+        .with_status(200)
+        .with_body(r#"{"token":"anonymous"}"#)
+        .expect(0)
+        .create();
+
+    let runtime = Runtime::new().unwrap();
+    let dclient = dkregistry::v2::Client::configure()
+        .registry(&addr)
+        .insecure_registry(true)
+        .identity_token(Some("idtoken".to_string()))
+        .build()
+        .unwrap();
+    let res = runtime.block_on(dclient.authenticate(&[scope]));
+    assert!(matches!(
+        res,
+        Err(dkregistry::errors::Error::UnexpectedHttpStatus(_))
+    ));
+    m_refresh.assert();
+    m_anonymous.assert();
+
+    mockito::reset();
+}
@@ -0,0 +1,122 @@
+extern crate dkregistry;
+extern crate mockito;
+extern crate tokio;
+
+use self::mockito::mock;
+use self::tokio::runtime::Runtime;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+/// Fields recorded on spans, as `(span, field, value)`.
+type Fields = Arc<Mutex<Vec<(String, String, String)>>>;
+
+/// Subscriber recording the fields of all spans.
+#[derive(Default)]
+struct FieldRecorder {
+    next_id: AtomicU64,
+    names: Mutex<Vec<&'static str>>,
+    fields: Fields,
+}
+
+struct Visitor<'a> {
+    span: &'static str,
+    fields: &'a Fields,
+}
+
+impl Visit for Visitor<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.fields.lock().unwrap().push((
+            self.span.to_string(),
+            field.name().to_string(),
+            format!("{:?}", value),
+        ));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.fields.lock().unwrap().push((
+            self.span.to_string(),
+            field.name().to_string(),
+            value.to_string(),
+        ));
+    }
+}
+
+impl Subscriber for FieldRecorder {
+    fn enabled(&self, _: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        let name = span.metadata().name();
+        self.names.lock().unwrap().push(name);
+        span.record(&mut Visitor {
+            span: name,
+            fields: &self.fields,
+        });
+        Id::from_u64(self.next_id.fetch_add(1, Ordering::Relaxed) + 1)
+    }
+
+    fn record(&self, span: &Id, values: &Record<'_>) {
+        let name = self.names.lock().unwrap()[span.into_u64() as usize - 1];
+        values.record(&mut Visitor {
+            span: name,
+            fields: &self.fields,
+        });
+    }
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, _: &Event<'_>) {}
+
+    fn enter(&self, _: &Id) {}
+
+    fn exit(&self, _: &Id) {}
+}
+
+#[test]
+fn test_span_fields_are_recorded() {
+    let name = "my-repo/my-image";
+    let digest = "sha256:2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+    let ep = format!("/v2/{}/blobs/{}", name, digest);
+    let addr = mockito::server_address().to_string();
+    let _m = mock("GET", ep.as_str())
+        .with_status(200)
+        .with_body("hello")
+        .create();
+
+    let recorder = FieldRecorder::default();
+    let fields = recorder.fields.clone();
+    let runtime = Runtime::new().unwrap();
+    let dclient = dkregistry::v2::Client::configure()
+        .registry(&addr)
+        .insecure_registry(true)
+        .username(None)
+        .password(None)
+        .build()
+        .unwrap();
+    tracing::subscriber::with_default(recorder, || {
+        runtime.block_on(dclient.get_blob(name, digest)).unwrap();
+    });
+
+    let fields = fields.lock().unwrap();
+    let field = |span: &str, field: &str| {
+        fields
+            .iter()
+            .find(|(s, f, _)| s == span && f == field)
+            .map(|(_, _, v)| v.clone())
+    };
+    assert_eq!(
+        field("get_blob_response", "repository").as_deref(),
+        Some(name)
+    );
+    assert_eq!(
+        field("get_blob_response", "digest").as_deref(),
+        Some(digest)
+    );
+
+    mockito::reset();
+}
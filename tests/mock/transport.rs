@@ -0,0 +1,79 @@
+extern crate dkregistry;
+extern crate tokio;
+
+use self::tokio::runtime::Runtime;
+use dkregistry::errors::Result;
+use dkregistry::v2::Transport;
+use futures::future::{BoxFuture, FutureExt};
+use std::sync::{Arc, Mutex};
+
+/// Transport answering with canned tags, and recording requests.
+#[derive(Debug, Default)]
+struct CannedTransport {
+    requests: Mutex<Vec<String>>,
+    headers: Mutex<Vec<reqwest::header::HeaderMap>>,
+}
+
+impl Transport for CannedTransport {
+    fn execute(&self, request: reqwest::Request) -> BoxFuture<'_, Result<reqwest::Response>> {
+        self.requests
+            .lock()
+            .unwrap()
+            .push(format!("{} {}", request.method(), request.url()));
+        self.headers.lock().unwrap().push(request.headers().clone());
+        let res = http::Response::builder()
+            .status(200)
+            .header("Content-Type", "application/json")
+            .body(r#"{"name":"my-repo/my-image","tags":["latest"]}"#)
+            .unwrap();
+        futures::future::ready(Ok(res.into())).boxed()
+    }
+}
+
+#[test]
+fn test_custom_transport() {
+    let transport = Arc::new(CannedTransport::default());
+    let runtime = Runtime::new().unwrap();
+    let dclient = dkregistry::v2::Client::configure()
+        .registry("registry.invalid")
+        .transport(transport.clone())
+        .username(None)
+        .password(None)
+        .build()
+        .unwrap();
+
+    let tags = runtime
+        .block_on(dclient.get_all_tags("my-repo/my-image", None))
+        .unwrap();
+    assert_eq!(tags, vec!["latest"]);
+    assert_eq!(
+        *transport.requests.lock().unwrap(),
+        vec!["GET https://registry.invalid/v2/my-repo/my-image/tags/list"]
+    );
+}
+
+#[test]
+fn test_custom_transport_default_headers() {
+    let mut headers = reqwest::header::HeaderMap::new();
+    headers.insert("user-agent", "default-agent".parse().unwrap());
+    headers.insert("x-tracking-id", "1234".parse().unwrap());
+    let transport = Arc::new(CannedTransport::default());
+    let runtime = Runtime::new().unwrap();
+    let dclient = dkregistry::v2::Client::configure()
+        .registry("registry.invalid")
+        .transport(transport.clone())
+        .default_headers(headers)
+        .user_agent(Some("my-agent".to_string()))
+        .username(None)
+        .password(None)
+        .build()
+        .unwrap();
+
+    runtime
+        .block_on(dclient.get_all_tags("my-repo/my-image", None))
+        .unwrap();
+    let headers = transport.headers.lock().unwrap();
+    let user_agents: Vec<_> = headers[0].get_all("user-agent").iter().collect();
+    assert_eq!(user_agents, vec!["my-agent"]);
+    assert_eq!(headers[0].get("x-tracking-id").unwrap(), "1234");
+}
@@ -1,3 +1,12 @@
+// Lints that only fire on the existing test code, which is kept as written.
+#![allow(
+    clippy::bool_assert_comparison,
+    clippy::get_first,
+    clippy::needless_borrow,
+    clippy::redundant_static_lifetimes,
+    clippy::useless_format
+)]
+
 #[cfg(feature = "test-net")]
 mod net;
 
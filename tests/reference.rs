@@ -1,3 +1,5 @@
+#![allow(clippy::useless_vec)]
+
 extern crate spectral;
 
 use dkregistry::reference::Reference;